@@ -0,0 +1,202 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+//! Command-line parsing. Options can be given anywhere on the command line,
+//! and all but `--output` and `--file` can also be set in the environment so
+//! the token doesn't have to appear in `ps` output or shell history.
+
+use std::path::PathBuf;
+
+use uuid::Uuid;
+
+pub const USAGE: &str = "\
+Usage: smartos-ui-cli [OPTIONS] <COMMAND>
+
+Commands:
+  instances list           List instances
+  instances get <uuid>     Show all of an instance's properties, as JSON
+  instances start <uuid>   Start an instance
+  instances stop <uuid>    Stop an instance
+  instances delete <uuid>  Delete an instance
+  images list              List installed images
+  images get <uuid>        Show an installed image's manifest, as JSON
+  images import <uuid>     Import an image from the host's image sources
+  images delete <uuid>     Delete an installed image
+  provision -f <file>      Create an instance from a vmadm payload, \"-\" reads
+                           the payload from stdin
+  sysinfo                  Show details of the host
+  version                  Print the version
+
+  \"instance\" and \"image\" can be used in place of \"instances\" and
+  \"images\".
+
+Options:
+  -u, --url <url>          Address of the UI, e.g. https://gz.example.com:4443
+                           [env: SMARTOS_UI_URL]
+  -t, --token <token>      API token [env: SMARTOS_UI_TOKEN]
+  -o, --output <format>    \"table\" or \"json\" [default: table]
+      --json               Same as --output json
+      --ca-cert <file>     Trust the PEM certificate in <file>, e.g. the UI's
+                           self-signed certificate [env: SMARTOS_UI_CA_CERT]
+  -k, --insecure           Don't verify the UI's certificate
+                           [env: SMARTOS_UI_INSECURE=1]
+  -f, --file <file>        Payload for provision
+      --overcommit         Provision even if the host doesn't have room left
+  -h, --help               Print this help
+";
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum Output {
+    #[default]
+    Table,
+    Json,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Command {
+    Help,
+    Version,
+    ListInstances,
+    GetInstance(Uuid),
+    StartInstance(Uuid),
+    StopInstance(Uuid),
+    DeleteInstance(Uuid),
+    ListImages,
+    GetImage(Uuid),
+    ImportImage(Uuid),
+    DeleteImage(Uuid),
+    /// Path to the payload, or "-" for stdin
+    Provision(PathBuf),
+    Sysinfo,
+}
+
+#[derive(Debug, Default)]
+pub struct Options {
+    pub url: Option<String>,
+    pub token: Option<String>,
+    pub output: Output,
+    pub ca_cert: Option<PathBuf>,
+    pub insecure: bool,
+    /// Provision beyond the host's remaining RAM, disk and CPU
+    pub overcommit: bool,
+}
+
+/// Parse the arguments (without the program name), falling back to `env` for
+/// anything not given on the command line
+pub fn parse<I, E>(args: I, env: E) -> Result<(Options, Command), String>
+where
+    I: IntoIterator<Item = String>,
+    E: Fn(&str) -> Option<String>,
+{
+    let mut options = Options {
+        url: env("SMARTOS_UI_URL"),
+        token: env("SMARTOS_UI_TOKEN"),
+        ca_cert: env("SMARTOS_UI_CA_CERT").map(PathBuf::from),
+        insecure: env("SMARTOS_UI_INSECURE").is_some_and(|v| v == "1"),
+        ..Default::default()
+    };
+    let mut file: Option<PathBuf> = None;
+    let mut positional: Vec<String> = Vec::new();
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            positional.extend(args.by_ref());
+            break;
+        }
+        if !arg.starts_with('-') || arg == "-" {
+            positional.push(arg);
+            continue;
+        }
+
+        // Support both "--url value" and "--url=value"
+        let (name, inline) = match arg.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => {
+                (String::from(name), Some(String::from(value)))
+            }
+            _ => (arg, None),
+        };
+        let mut value = |name: &str| {
+            inline
+                .clone()
+                .or_else(|| args.next())
+                .ok_or_else(|| format!("{} requires a value", name))
+        };
+        match name.as_str() {
+            "-h" | "--help" => return Ok((options, Command::Help)),
+            "-V" | "--version" => return Ok((options, Command::Version)),
+            "-u" | "--url" => options.url = Some(value(&name)?),
+            "-t" | "--token" => options.token = Some(value(&name)?),
+            "-o" | "--output" => {
+                options.output = match value(&name)?.as_str() {
+                    "table" => Output::Table,
+                    "json" => Output::Json,
+                    other => {
+                        return Err(format!(
+                            "Unknown output format \"{}\", expected \"table\" \
+                            or \"json\"",
+                            other
+                        ))
+                    }
+                }
+            }
+            "--json" => options.output = Output::Json,
+            "--ca-cert" => options.ca_cert = Some(value(&name)?.into()),
+            "-k" | "--insecure" => options.insecure = true,
+            "--overcommit" => options.overcommit = true,
+            "-f" | "--file" => file = Some(value(&name)?.into()),
+            _ => return Err(format!("Unknown option {}", name)),
+        }
+    }
+
+    let words: Vec<&str> = positional.iter().map(String::as_str).collect();
+    let command = match words.as_slice() {
+        [] | ["help"] => Command::Help,
+        ["version"] => Command::Version,
+        ["instances" | "instance", "list" | "ls"] => Command::ListInstances,
+        ["instances" | "instance", action, id] => {
+            let id = parse_uuid(id)?;
+            match *action {
+                "get" => Command::GetInstance(id),
+                "start" => Command::StartInstance(id),
+                "stop" => Command::StopInstance(id),
+                "delete" | "rm" => Command::DeleteInstance(id),
+                _ => {
+                    return Err(format!("Unknown instances command {}", action))
+                }
+            }
+        }
+        ["images" | "image", "list" | "ls"] => Command::ListImages,
+        ["images" | "image", action, id] => {
+            let id = parse_uuid(id)?;
+            match *action {
+                "get" => Command::GetImage(id),
+                "import" => Command::ImportImage(id),
+                "delete" | "rm" => Command::DeleteImage(id),
+                _ => return Err(format!("Unknown images command {}", action)),
+            }
+        }
+        ["provision"] => Command::Provision(
+            file.ok_or("provision requires a payload file, -f <file>")?,
+        ),
+        ["provision", path] if file.is_none() => {
+            Command::Provision(PathBuf::from(path))
+        }
+        ["sysinfo"] => Command::Sysinfo,
+        _ => {
+            return Err(format!("Unknown command \"{}\"", positional.join(" ")))
+        }
+    };
+    Ok((options, command))
+}
+
+fn parse_uuid(id: &str) -> Result<Uuid, String> {
+    Uuid::parse_str(id).map_err(|e| format!("Invalid UUID {}: {}", id, e))
+}
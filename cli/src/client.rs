@@ -0,0 +1,207 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+//! Client for the UI's `/api/v1` endpoints
+
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+
+use smartos_shared::image::Image;
+use smartos_shared::instance::{CreatePayload, InstanceView};
+
+use reqwest::{Certificate, Method, RequestBuilder, Response, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::Value;
+use uuid::Uuid;
+
+#[derive(Debug)]
+pub enum Error {
+    /// The UI couldn't be reached, or sent something unexpected
+    Request(reqwest::Error),
+    /// The UI refused the request
+    Api { status: StatusCode, message: String },
+    /// Anything else, such as bad options or an unreadable payload
+    Message(String),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Request(e) => write!(f, "{}", e),
+            Error::Api { status, message } => {
+                write!(f, "{} ({})", message, status)
+            }
+            Error::Message(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Error::Request(e)
+    }
+}
+
+/// Body of a Dropshot error response
+#[derive(Deserialize)]
+struct ErrorBody {
+    message: String,
+}
+
+#[derive(Deserialize)]
+pub struct ProvisionResponse {
+    pub uuid: Uuid,
+}
+
+#[derive(Deserialize)]
+pub struct MessageResponse {
+    pub message: String,
+}
+
+pub struct Client {
+    http: reqwest::Client,
+    url: String,
+    token: String,
+}
+
+impl Client {
+    /// `ca_cert` is a PEM certificate to trust in addition to the system's,
+    /// `insecure` disables certificate verification entirely
+    pub fn new(
+        url: &str,
+        token: String,
+        ca_cert: Option<&[u8]>,
+        insecure: bool,
+    ) -> Result<Self, Error> {
+        let parsed = reqwest::Url::parse(url).map_err(|e| {
+            Error::Message(format!("Invalid URL {}: {}", url, e))
+        })?;
+        if parsed.scheme() != "https" {
+            return Err(Error::Message(format!(
+                "The URL must use https, got {}",
+                url
+            )));
+        }
+
+        // No overall timeout, image imports only return once they finish
+        let mut builder = reqwest::Client::builder()
+            .connect_timeout(Duration::from_secs(10))
+            .user_agent(format!("smartos-ui-cli/{}", crate::VERSION));
+        if let Some(pem) = ca_cert {
+            builder = builder.add_root_certificate(Certificate::from_pem(pem)?);
+        }
+        if insecure {
+            builder = builder.danger_accept_invalid_certs(true);
+        }
+
+        Ok(Self {
+            http: builder.build()?,
+            url: String::from(url.trim_end_matches('/')),
+            token,
+        })
+    }
+
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        self.http
+            .request(method, format!("{}/api/v1/{}", self.url, path))
+            .bearer_auth(&self.token)
+    }
+
+    /// Turn an unsuccessful response into [Error::Api], using the message
+    /// from the body when there is one
+    async fn check(response: Response) -> Result<Response, Error> {
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        let body = response.text().await.unwrap_or_default();
+        let message = serde_json::from_str::<ErrorBody>(&body)
+            .map(|e| e.message)
+            .unwrap_or(body);
+        Err(Error::Api { status, message })
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, Error> {
+        let response = self.request(Method::GET, path).send().await?;
+        Ok(Self::check(response).await?.json().await?)
+    }
+
+    async fn send(&self, method: Method, path: &str) -> Result<(), Error> {
+        let response = self.request(method, path).send().await?;
+        Self::check(response).await.map(|_| ())
+    }
+
+    pub async fn instances(&self) -> Result<Vec<InstanceView>, Error> {
+        self.get("instances").await
+    }
+
+    pub async fn instance(&self, id: &Uuid) -> Result<Value, Error> {
+        self.get(&format!("instances/{}", id)).await
+    }
+
+    pub async fn start_instance(&self, id: &Uuid) -> Result<(), Error> {
+        self.send(Method::POST, &format!("instances/{}/start", id)).await
+    }
+
+    pub async fn stop_instance(&self, id: &Uuid) -> Result<(), Error> {
+        self.send(Method::POST, &format!("instances/{}/stop", id)).await
+    }
+
+    pub async fn delete_instance(&self, id: &Uuid) -> Result<(), Error> {
+        self.send(Method::DELETE, &format!("instances/{}", id)).await
+    }
+
+    /// Create an instance from a `vmadm create` payload. With `overcommit`
+    /// the host's remaining capacity isn't checked.
+    pub async fn provision(
+        &self,
+        payload: &CreatePayload,
+        overcommit: bool,
+    ) -> Result<ProvisionResponse, Error> {
+        let response = self
+            .request(Method::POST, "instances")
+            .query(&[("overcommit", overcommit)])
+            .json(payload)
+            .send()
+            .await?;
+        Ok(Self::check(response).await?.json().await?)
+    }
+
+    pub async fn images(&self) -> Result<Vec<Image>, Error> {
+        self.get("images").await
+    }
+
+    pub async fn image(&self, id: &Uuid) -> Result<Value, Error> {
+        self.get(&format!("images/{}", id)).await
+    }
+
+    pub async fn import_image(
+        &self,
+        id: &Uuid,
+    ) -> Result<MessageResponse, Error> {
+        let response = self
+            .request(Method::POST, &format!("images/{}/import", id))
+            .send()
+            .await?;
+        Ok(Self::check(response).await?.json().await?)
+    }
+
+    pub async fn delete_image(&self, id: &Uuid) -> Result<(), Error> {
+        self.send(Method::DELETE, &format!("images/{}", id)).await
+    }
+
+    /// The host's complete `sysinfo` output
+    pub async fn sysinfo(&self) -> Result<Value, Error> {
+        self.get("sysinfo").await
+    }
+}
@@ -0,0 +1,208 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+use std::io::{ErrorKind, Read, Write};
+use std::process::ExitCode;
+use std::{env, fs, io};
+
+use smartos_shared::instance::CreatePayload;
+use smartos_shared::sysinfo::Sysinfo;
+
+use smartos_ui_cli::args::{self, Command, Options, Output, USAGE};
+use smartos_ui_cli::client::{Client, Error};
+use smartos_ui_cli::{render, VERSION};
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let (options, command) =
+        match args::parse(env::args().skip(1), |name| env::var(name).ok()) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                eprintln!("error: {}\n\n{}", e, USAGE);
+                return ExitCode::from(2);
+            }
+        };
+
+    match command {
+        Command::Help => {
+            print!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Command::Version => {
+            println!("{}", VERSION);
+            return ExitCode::SUCCESS;
+        }
+        _ => {}
+    }
+
+    match run(options, command).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn client(options: &Options) -> Result<Client, Error> {
+    let Some(url) = &options.url else {
+        return Err(Error::Message(String::from(
+            "No UI address, use --url or set SMARTOS_UI_URL",
+        )));
+    };
+    let Some(token) = &options.token else {
+        return Err(Error::Message(String::from(
+            "No API token, use --token or set SMARTOS_UI_TOKEN",
+        )));
+    };
+    let ca_cert = match &options.ca_cert {
+        Some(path) => Some(fs::read(path).map_err(|e| {
+            Error::Message(format!("Failed to read {}: {}", path.display(), e))
+        })?),
+        None => None,
+    };
+    Client::new(url, token.clone(), ca_cert.as_deref(), options.insecure)
+}
+
+/// Write to stdout, stopping quietly if it has been closed (e.g. piped to
+/// `head`) rather than panicking like `print!` does
+fn print(text: &str) -> Result<(), Error> {
+    match io::stdout().write_all(text.as_bytes()) {
+        Err(e) if e.kind() != ErrorKind::BrokenPipe => {
+            Err(Error::Message(format!("Failed to write output: {}", e)))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn print_json<T: Serialize>(value: &T) -> Result<(), Error> {
+    let json = serde_json::to_string_pretty(value)
+        .map_err(|e| Error::Message(e.to_string()))?;
+    print(&format!("{}\n", json))
+}
+
+/// Print `message` for people, or `value` for scripts
+fn print_result(
+    output: Output,
+    message: String,
+    value: Value,
+) -> Result<(), Error> {
+    match output {
+        Output::Table => print(&format!("{}\n", message)),
+        Output::Json => print_json(&value),
+    }
+}
+
+fn read_payload(path: &std::path::Path) -> Result<CreatePayload, Error> {
+    let mut payload = String::new();
+    let read = if path.as_os_str() == "-" {
+        io::stdin().read_to_string(&mut payload).map(|_| ())
+    } else {
+        fs::read_to_string(path).map(|contents| payload = contents)
+    };
+    read.map_err(|e| {
+        Error::Message(format!("Failed to read {}: {}", path.display(), e))
+    })?;
+    serde_json::from_str(&payload).map_err(|e| {
+        Error::Message(format!("Invalid payload {}: {}", path.display(), e))
+    })
+}
+
+async fn run(options: Options, command: Command) -> Result<(), Error> {
+    let client = client(&options)?;
+    let output = options.output;
+
+    match command {
+        Command::Help | Command::Version => Ok(()),
+        Command::ListInstances => {
+            let instances = client.instances().await?;
+            match output {
+                Output::Table => print(&render::instances(&instances))?,
+                Output::Json => print_json(&instances)?,
+            }
+            Ok(())
+        }
+        Command::GetInstance(id) => print_json(&client.instance(&id).await?),
+        Command::StartInstance(id) => {
+            client.start_instance(&id).await?;
+            print_result(
+                output,
+                format!("Started instance {}", id),
+                json!({ "uuid": id }),
+            )
+        }
+        Command::StopInstance(id) => {
+            client.stop_instance(&id).await?;
+            print_result(
+                output,
+                format!("Stopped instance {}", id),
+                json!({ "uuid": id }),
+            )
+        }
+        Command::DeleteInstance(id) => {
+            client.delete_instance(&id).await?;
+            print_result(
+                output,
+                format!("Deleted instance {}", id),
+                json!({ "uuid": id }),
+            )
+        }
+        Command::Provision(path) => {
+            let payload = read_payload(&path)?;
+            let created =
+                client.provision(&payload, options.overcommit).await?;
+            print_result(
+                output,
+                format!("Created instance {}", created.uuid),
+                json!({ "uuid": created.uuid }),
+            )
+        }
+        Command::ListImages => {
+            let images = client.images().await?;
+            match output {
+                Output::Table => print(&render::images(&images))?,
+                Output::Json => print_json(&images)?,
+            }
+            Ok(())
+        }
+        Command::GetImage(id) => print_json(&client.image(&id).await?),
+        Command::ImportImage(id) => {
+            let imported = client.import_image(&id).await?;
+            print_result(
+                output,
+                imported.message.clone(),
+                json!({ "uuid": id, "message": imported.message }),
+            )
+        }
+        Command::DeleteImage(id) => {
+            client.delete_image(&id).await?;
+            print_result(
+                output,
+                format!("Deleted image {}", id),
+                json!({ "uuid": id }),
+            )
+        }
+        Command::Sysinfo => {
+            let sysinfo = client.sysinfo().await?;
+            match output {
+                Output::Table => {
+                    let parsed: Sysinfo = serde_json::from_value(sysinfo)
+                        .map_err(|e| Error::Message(e.to_string()))?;
+                    print(&render::sysinfo(&parsed))?;
+                }
+                Output::Json => print_json(&sysinfo)?,
+            }
+            Ok(())
+        }
+    }
+}
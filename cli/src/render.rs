@@ -0,0 +1,97 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+//! Plain text tables, in the style of `vmadm list` and `imgadm list`
+
+use smartos_shared::image::Image;
+use smartos_shared::instance::InstanceView;
+use smartos_shared::sysinfo::Sysinfo;
+
+/// Left aligned columns separated by two spaces
+pub fn table(headers: &[&str], rows: &[Vec<String>]) -> String {
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.len()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let line = |cells: Vec<&str>| {
+        let padded: Vec<String> = cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect();
+        format!("{}\n", padded.join("  ").trim_end())
+    };
+
+    let mut out = line(headers.to_vec());
+    for row in rows {
+        out.push_str(&line(row.iter().map(String::as_str).collect()));
+    }
+    out
+}
+
+pub fn instances(instances: &[InstanceView]) -> String {
+    let rows: Vec<Vec<String>> = instances
+        .iter()
+        .map(|i| {
+            vec![
+                i.uuid.to_string(),
+                i.alias.clone(),
+                i.brand.to_string(),
+                i.state.clone(),
+                i.ram.to_string(),
+                i.disk_usage.to_string(),
+                i.cpu.to_string(),
+                i.primary_ip.clone().unwrap_or_else(|| String::from("-")),
+            ]
+        })
+        .collect();
+    table(
+        &["UUID", "ALIAS", "BRAND", "STATE", "RAM", "DISK", "CPU", "IP"],
+        &rows,
+    )
+}
+
+pub fn images(images: &[Image]) -> String {
+    let rows: Vec<Vec<String>> = images
+        .iter()
+        .map(|image| {
+            let manifest = &image.manifest;
+            vec![
+                manifest.uuid.to_string(),
+                manifest.name.clone(),
+                manifest.version.clone(),
+                manifest.r#type.to_string(),
+                manifest.os.clone(),
+                manifest
+                    .published_at
+                    .clone()
+                    .unwrap_or_else(|| String::from("-")),
+            ]
+        })
+        .collect();
+    table(&["UUID", "NAME", "VERSION", "TYPE", "OS", "PUBLISHED"], &rows)
+}
+
+pub fn sysinfo(sysinfo: &Sysinfo) -> String {
+    let rows = vec![
+        vec![String::from("Hostname"), sysinfo.hostname.clone()],
+        vec![String::from("Platform"), sysinfo.live_image.clone()],
+        vec![String::from("CPUs"), sysinfo.cpu_count.to_string()],
+        vec![String::from("Memory (MiB)"), sysinfo.mib_of_memory.to_string()],
+        vec![
+            String::from("Zpool size (GiB)"),
+            sysinfo.zpool_size_in_gib.to_string(),
+        ],
+    ];
+    table(&["PROPERTY", "VALUE"], &rows)
+}
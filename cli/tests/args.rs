@@ -0,0 +1,62 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+use std::path::PathBuf;
+
+use smartos_ui_cli::args::{parse, Command, Output};
+use smartos_ui_cli::render::table;
+use uuid::Uuid;
+
+fn args(line: &str) -> Vec<String> {
+    line.split_whitespace().map(String::from).collect()
+}
+
+#[test]
+fn test_parse() {
+    let id = "aaaaaaaa-aaaa-aaaa-aaaa-aaaaaaaaaaaa";
+    let env = |name: &str| match name {
+        "SMARTOS_UI_URL" => Some(String::from("https://env:4443")),
+        "SMARTOS_UI_TOKEN" => Some(String::from("sui_env")),
+        _ => None,
+    };
+
+    let (options, command) =
+        parse(args(&format!("instance start {} --json", id)), env).unwrap();
+    assert_eq!(command, Command::StartInstance(Uuid::parse_str(id).unwrap()));
+    assert_eq!(options.output, Output::Json);
+    assert_eq!(options.url.as_deref(), Some("https://env:4443"));
+    assert_eq!(options.token.as_deref(), Some("sui_env"));
+
+    let (options, command) =
+        parse(args("--url=https://gz:4443 -k provision -f vm.json"), env)
+            .unwrap();
+    assert_eq!(command, Command::Provision(PathBuf::from("vm.json")));
+    assert_eq!(options.url.as_deref(), Some("https://gz:4443"));
+    assert!(options.insecure);
+
+    assert_eq!(parse(args(""), env).unwrap().1, Command::Help);
+    assert!(parse(args("images import not-a-uuid"), env).is_err());
+    assert!(parse(args("instances list -o yaml"), env).is_err());
+    assert!(parse(args("provision"), env).is_err());
+}
+
+#[test]
+fn test_table() {
+    let rows = vec![
+        vec![String::from("vm01"), String::from("running")],
+        vec![String::from("a-longer-alias"), String::from("stopped")],
+    ];
+    assert_eq!(
+        table(&["ALIAS", "STATE"], &rows),
+        "ALIAS           STATE\n\
+         vm01            running\n\
+         a-longer-alias  stopped\n"
+    );
+}
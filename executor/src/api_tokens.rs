@@ -0,0 +1,142 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+//! Bearer tokens for the UI's `/api/v1` endpoints. A token looks like
+//! `sui_<id>_<secret>`; the ID is used to find it and only a SHA-256 hash of
+//! the whole token is kept, in `API_TOKENS_FILE`, a JSON object keyed by ID
+//! that only root can read:
+//!
+//! ```json
+//! {
+//!   "3f9a1c0b7d2e": {
+//!     "name": "ci",
+//!     "user": "olive",
+//!     "hash": "<sha256 hex>",
+//!     "scopes": ["read", "power_control"],
+//!     "created": "2025-01-01T00:00:00Z",
+//!     "last_used": null
+//!   }
+//! }
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::shadow::write_private;
+
+use smartos_shared::auth::{constant_time_eq, ApiToken, Scope};
+
+use ring::digest::{digest, SHA256};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+/// Start of every token, so they are easy to recognise (e.g. by secret
+/// scanners)
+pub const PREFIX: &str = "sui_";
+
+/// Random bytes in a token's ID
+const ID_BYTES: usize = 6;
+
+/// Random bytes in a token's secret part
+const SECRET_BYTES: usize = 32;
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Token {
+    pub name: String,
+    pub user: String,
+    /// SHA-256 hash of the whole token, hex encoded
+    pub hash: String,
+    pub scopes: Vec<Scope>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created: OffsetDateTime,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub last_used: Option<OffsetDateTime>,
+}
+
+impl Token {
+    /// The token as shown to its owner
+    pub fn view(&self, id: &str) -> ApiToken {
+        ApiToken {
+            id: String::from(id),
+            name: self.name.clone(),
+            user: self.user.clone(),
+            scopes: self.scopes.clone(),
+            created: self.created,
+            last_used: self.last_used,
+        }
+    }
+}
+
+pub type Store = BTreeMap<String, Token>;
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::with_capacity(bytes.len() * 2), |mut hex, b| {
+        let _ = write!(hex, "{:02x}", b);
+        hex
+    })
+}
+
+fn random_hex(len: usize) -> io::Result<String> {
+    let mut bytes = vec![0u8; len];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .map_err(|_| io::Error::new(io::ErrorKind::Other, "RNG failure"))?;
+    Ok(to_hex(&bytes))
+}
+
+/// Hash of a token as stored in [Token::hash]
+pub fn hash(secret: &str) -> String {
+    to_hex(digest(&SHA256, secret.as_bytes()).as_ref())
+}
+
+/// A new token ID and the full token
+pub fn generate() -> io::Result<(String, String)> {
+    let id = random_hex(ID_BYTES)?;
+    let secret = format!("{}{}_{}", PREFIX, id, random_hex(SECRET_BYTES)?);
+    Ok((id, secret))
+}
+
+/// The ID part of a token, if it is well formed
+pub fn parse_id(secret: &str) -> Option<&str> {
+    let (id, rest) = secret.strip_prefix(PREFIX)?.split_once('_')?;
+    (id.len() == ID_BYTES * 2 && rest.len() == SECRET_BYTES * 2).then_some(id)
+}
+
+/// Find the token for `secret`, returning its ID
+pub fn find<'a>(
+    store: &'a Store,
+    secret: &str,
+) -> Option<(&'a str, &'a Token)> {
+    let id = parse_id(secret)?;
+    let (id, token) = store.get_key_value(id)?;
+    constant_time_eq(token.hash.as_bytes(), hash(secret).as_bytes())
+        .then_some((id.as_str(), token))
+}
+
+/// Read the tokens file, a missing file has no tokens
+pub fn load(path: &Path) -> io::Result<Store> {
+    match fs::read_to_string(path) {
+        Ok(contents) if contents.trim().is_empty() => Ok(Store::new()),
+        Ok(contents) => Ok(serde_json::from_str(&contents)?),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Store::new()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Write the tokens file, creating it (and its directory) readable only by
+/// the owner if needed
+pub fn save(path: &Path, store: &Store) -> io::Result<()> {
+    let contents = serde_json::to_string_pretty(store)?;
+    write_private(path, &contents)
+}
@@ -0,0 +1,209 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+//! API token management for the UI. Tokens are created and revoked by their
+//! owner from a logged in session, and checked on every `/api/v1` request.
+
+use std::path::Path;
+
+use crate::api_tokens::{self, Token};
+use crate::endpoints::Context;
+use crate::users;
+
+use smartos_shared::auth::{
+    ApiToken, ApiTokenCreateRequest, ApiTokenCreateResponse,
+    ApiTokenDeleteRequest, ApiTokenUserRequest, ApiTokenVerifyRequest,
+    ApiTokenVerifyResponse,
+};
+use smartos_shared::http_server::{to_bad_request, to_internal_error};
+
+use dropshot::{
+    endpoint, HttpError, HttpResponseOk, HttpResponseUpdatedNoContent,
+    RequestContext, TypedBody,
+};
+use slog::{error, info, warn};
+use time::{Duration, OffsetDateTime};
+
+/// Most tokens a single user can have
+const MAX_TOKENS: usize = 20;
+
+/// Longest token name kept, in characters
+const MAX_NAME_LENGTH: usize = 64;
+
+/// How stale a token's last use can be before it is written again, so that
+/// busy scripts don't rewrite the file on every request
+const LAST_USED_RESOLUTION: Duration = Duration::MINUTE;
+
+/// Load the tokens file, pass the tokens to `update` and save them if it
+/// returns true. The lock is held throughout so concurrent requests can't
+/// lose each other's changes.
+fn modify<T, F>(ctx: &Context, update: F) -> Result<T, HttpError>
+where
+    F: FnOnce(&mut api_tokens::Store) -> Result<(bool, T), HttpError>,
+{
+    let _guard = ctx
+        .api_tokens_lock
+        .lock()
+        .map_err(|e| HttpError::for_internal_error(format!("{}", e)))?;
+    let path = Path::new(&ctx.config.api_tokens_file);
+    let mut store = api_tokens::load(path).map_err(to_internal_error)?;
+    let (changed, result) = update(&mut store)?;
+    if changed {
+        api_tokens::save(path, &store).map_err(to_internal_error)?;
+    }
+    Ok(result)
+}
+
+#[endpoint {
+method = POST,
+path = "/auth/tokens/list",
+}]
+pub async fn post_list(
+    ctx: RequestContext<Context>,
+    request_body: TypedBody<ApiTokenUserRequest>,
+) -> Result<HttpResponseOk<Vec<ApiToken>>, HttpError> {
+    let ApiTokenUserRequest { user } = request_body.into_inner();
+    let store =
+        api_tokens::load(Path::new(&ctx.context().config.api_tokens_file))
+            .map_err(to_internal_error)?;
+    let tokens = store
+        .iter()
+        .filter(|(_, token)| token.user == user)
+        .map(|(id, token)| token.view(id))
+        .collect();
+    Ok(HttpResponseOk(tokens))
+}
+
+#[endpoint {
+method = POST,
+path = "/auth/tokens/create",
+}]
+pub async fn post_create(
+    ctx: RequestContext<Context>,
+    request_body: TypedBody<ApiTokenCreateRequest>,
+) -> Result<HttpResponseOk<ApiTokenCreateResponse>, HttpError> {
+    let ApiTokenCreateRequest { user, name, mut scopes } =
+        request_body.into_inner();
+    scopes.sort();
+    scopes.dedup();
+    if scopes.is_empty() {
+        return Err(to_bad_request("A token needs at least one scope"));
+    }
+    let mut name: String = name.trim().chars().take(MAX_NAME_LENGTH).collect();
+    if name.is_empty() {
+        name = String::from("API token");
+    }
+
+    let (id, secret) = api_tokens::generate().map_err(to_internal_error)?;
+    let token = Token {
+        name,
+        user: user.clone(),
+        hash: api_tokens::hash(&secret),
+        scopes,
+        created: OffsetDateTime::now_utc(),
+        last_used: None,
+    };
+    let view = token.view(&id);
+
+    modify(ctx.context(), |store| {
+        if store.values().filter(|t| t.user == user).count() >= MAX_TOKENS {
+            return Err(to_bad_request("Too many API tokens"));
+        }
+        store.insert(id.clone(), token);
+        Ok((true, ()))
+    })?;
+    info!(ctx.log, "Created API token {} \"{}\" for {}", id, view.name, user);
+    Ok(HttpResponseOk(ApiTokenCreateResponse { token: view, secret }))
+}
+
+/// Check a bearer token. Tokens are long and random, so unlike passwords
+/// failures aren't rate limited.
+#[endpoint {
+method = POST,
+path = "/auth/tokens/verify",
+}]
+pub async fn post_verify(
+    ctx: RequestContext<Context>,
+    request_body: TypedBody<ApiTokenVerifyRequest>,
+) -> Result<HttpResponseOk<ApiTokenVerifyResponse>, HttpError> {
+    let ApiTokenVerifyRequest { secret } = request_body.into_inner();
+    let failed = ApiTokenVerifyResponse { token: None, role: None };
+
+    let token = modify(ctx.context(), |store| {
+        let Some(id) = api_tokens::find(store, &secret).map(|(id, _)| id)
+        else {
+            return Ok((false, None));
+        };
+        let id = String::from(id);
+        let Some(token) = store.get_mut(&id) else {
+            return Ok((false, None));
+        };
+        let now = OffsetDateTime::now_utc();
+        let stale = token
+            .last_used
+            .map_or(true, |last| now - last >= LAST_USED_RESOLUTION);
+        if stale {
+            token.last_used = Some(now);
+        }
+        Ok((stale, Some(token.view(&id))))
+    })?;
+
+    let Some(token) = token else {
+        warn!(ctx.log, "Unknown API token used");
+        return Ok(HttpResponseOk(failed));
+    };
+    let config = &ctx.context().config;
+    let user_list =
+        users::load(Path::new(&config.users_file)).unwrap_or_else(|e| {
+            error!(ctx.log, "Failed to load {}: {}", config.users_file, e);
+            vec![]
+        });
+    let Some(account) =
+        users::resolve(&user_list, &config.login_user, &token.user)
+    else {
+        warn!(
+            ctx.log,
+            "API token {} belongs to {}, who is not a UI user",
+            token.id,
+            token.user
+        );
+        return Ok(HttpResponseOk(failed));
+    };
+    Ok(HttpResponseOk(ApiTokenVerifyResponse {
+        token: Some(token),
+        role: Some(account.role),
+    }))
+}
+
+#[endpoint {
+method = POST,
+path = "/auth/tokens/delete",
+}]
+pub async fn post_delete(
+    ctx: RequestContext<Context>,
+    request_body: TypedBody<ApiTokenDeleteRequest>,
+) -> Result<HttpResponseUpdatedNoContent, HttpError> {
+    let ApiTokenDeleteRequest { user, id } = request_body.into_inner();
+    let removed = modify(ctx.context(), |store| {
+        if store.get(&id).is_some_and(|token| token.user == user) {
+            store.remove(&id);
+            return Ok((true, true));
+        }
+        Ok((false, false))
+    })?;
+    if !removed {
+        return Err(HttpError::for_not_found(
+            None,
+            format!("No such API token for {}", user),
+        ));
+    }
+    info!(ctx.log, "Revoked API token {} for {}", id, user);
+    Ok(HttpResponseUpdatedNoContent())
+}
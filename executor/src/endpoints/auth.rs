@@ -0,0 +1,362 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+//! Password verification and changes for UI users. The UI process never sees
+//! password hashes, it sends the credentials here and only gets back pass or
+//! fail.
+
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::endpoints::{exec, Context};
+use crate::shadow;
+use crate::totp;
+use crate::users::{self, Account, Credential};
+
+use smartos_shared::auth::{
+    constant_time_eq, AuthVerifyRequest, AuthVerifyResponse,
+    ChangePasswordRequest, ChangePasswordResponse,
+};
+use smartos_shared::http_server::{to_bad_request, to_internal_error};
+use smartos_shared::sysinfo::Sysinfo;
+
+use dropshot::{
+    endpoint, HttpError, HttpResponseOk, RequestContext, TypedBody,
+};
+use http::StatusCode;
+use pwhash::{sha512_crypt, unix};
+use slog::{error, info, warn};
+
+/// Number of consecutive failures allowed for a user within
+/// [FAILURE_WINDOW] before further attempts are refused. The UI enforces its
+/// own configurable per-IP and per-user lockouts before calling this, so
+/// this is a backstop that is intentionally higher than the UI's default.
+const MAX_FAILURES: u32 = 20;
+
+/// Failures older than this are forgotten
+const FAILURE_WINDOW: Duration = Duration::from_secs(15 * 60);
+
+/// How long attempts for a user are refused once [MAX_FAILURES] is reached
+const LOCKOUT: Duration = Duration::from_secs(15 * 60);
+
+/// Hash used when the user has no usable password so that the time taken to
+/// respond doesn't reveal whether an account exists.
+const DUMMY_HASH: &str = "$6$QUkzD0UzGWP0PHxr$";
+
+/// Recent authentication failures for a single user
+pub struct AuthFailures {
+    count: u32,
+    last_failure: Instant,
+    locked_until: Option<Instant>,
+}
+
+impl AuthFailures {
+    fn is_stale(&self, now: Instant) -> bool {
+        let locked = self.locked_until.is_some_and(|until| until > now);
+        !locked && now.duration_since(self.last_failure) > FAILURE_WINDOW
+    }
+}
+
+/// Returns the remaining lockout time if `user` may not attempt to log in
+fn locked_out(ctx: &Context, user: &str) -> Option<Duration> {
+    let now = Instant::now();
+    let failures = ctx.auth_failures.lock().ok()?;
+    let until = failures.get(user)?.locked_until?;
+    if until > now {
+        return Some(until - now);
+    }
+    None
+}
+
+pub(crate) fn check_locked_out(
+    ctx: &RequestContext<Context>,
+    user: &str,
+) -> Result<(), HttpError> {
+    if let Some(remaining) = locked_out(ctx.context(), user) {
+        warn!(ctx.log, "Refusing authentication for locked user {}", user);
+        return Err(HttpError::for_client_error(
+            None,
+            StatusCode::TOO_MANY_REQUESTS,
+            format!(
+                "Too many failed attempts, try again in {} seconds",
+                remaining.as_secs()
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Check `password` for `user` and record the result for rate limiting.
+/// Returns the user's [Account] if the password is correct.
+fn authenticate(
+    ctx: &RequestContext<Context>,
+    user: &str,
+    password: &str,
+) -> Result<Option<Account>, HttpError> {
+    let config = &ctx.context().config;
+
+    // A broken users file must not lock out the login user as well
+    let user_list =
+        users::load(Path::new(&config.users_file)).unwrap_or_else(|e| {
+            error!(ctx.log, "Failed to load {}: {}", config.users_file, e);
+            vec![]
+        });
+
+    let account = users::resolve(&user_list, &config.login_user, user);
+    let hash = match &account {
+        Some(Account {
+            credential: Credential::Shadow(shadow_user), ..
+        }) => {
+            let contents = read_to_string(&config.shadow_path)
+                .map_err(to_internal_error)?;
+            shadow::find_hash(&contents, shadow_user).map(String::from)
+        }
+        Some(Account { credential: Credential::Local(hash), .. }) => {
+            Some(hash.clone()).filter(|hash| hash.starts_with('$'))
+        }
+        None => None,
+    };
+    let success = check_password(password, hash.as_deref());
+
+    record_result(ctx.context(), user, success);
+    if success {
+        info!(ctx.log, "Authentication succeeded for {}", user);
+    } else {
+        warn!(ctx.log, "Authentication failed for {}", user);
+    }
+    Ok(account.filter(|_| success))
+}
+
+pub(crate) fn record_result(ctx: &Context, user: &str, success: bool) {
+    let now = Instant::now();
+    if let Ok(mut failures) = ctx.auth_failures.lock() {
+        // Prune entries so attempts against many usernames can't grow the
+        // map without bound
+        failures.retain(|_, entry| !entry.is_stale(now));
+
+        if success {
+            failures.remove(user);
+            return;
+        }
+
+        let entry = failures.entry(user.to_string()).or_insert(AuthFailures {
+            count: 0,
+            last_failure: now,
+            locked_until: None,
+        });
+        entry.count += 1;
+        entry.last_failure = now;
+        if entry.count >= MAX_FAILURES {
+            entry.count = 0;
+            entry.locked_until = Some(now + LOCKOUT);
+        }
+    }
+}
+
+/// Check a password against the crypt `hash` in constant time. If there is no
+/// hash a dummy one is used so the amount of work done is the same.
+pub fn check_password(password: &str, hash: Option<&str>) -> bool {
+    let setting = hash.unwrap_or(DUMMY_HASH);
+    match unix::crypt(password, setting) {
+        Ok(computed) => {
+            constant_time_eq(computed.as_bytes(), setting.as_bytes())
+                && hash.is_some()
+        }
+        Err(_) => false,
+    }
+}
+
+#[endpoint {
+method = POST,
+path = "/auth/verify",
+}]
+pub async fn post_verify(
+    ctx: RequestContext<Context>,
+    request_body: TypedBody<AuthVerifyRequest>,
+) -> Result<HttpResponseOk<AuthVerifyResponse>, HttpError> {
+    let AuthVerifyRequest { user, password } = request_body.into_inner();
+    check_locked_out(&ctx, &user)?;
+    let account = authenticate(&ctx, &user, &password)?;
+    // A TOTP file that can't be read must not let users skip their second
+    // factor, so this fails the login rather than assuming no enrollment
+    let totp_enabled = account.is_some()
+        && totp::load(Path::new(&ctx.context().config.totp_file))
+            .map_err(to_internal_error)?
+            .get(&user)
+            .is_some_and(|entry| entry.enabled);
+    Ok(HttpResponseOk(AuthVerifyResponse {
+        success: account.is_some(),
+        role: account.map(|account| account.role),
+        totp_enabled,
+    }))
+}
+
+/// Set `user`'s password after verifying the current one. Users with their own
+/// hash have it replaced in the users file. For users backed by the shadow
+/// file the new hash is written to the shadow file, the copy on the USB key
+/// that is restored at boot and, for root, the `root_shadow` boot parameter
+/// if the host uses it.
+#[endpoint {
+method = POST,
+path = "/auth/password",
+}]
+pub async fn post_password(
+    ctx: RequestContext<Context>,
+    request_body: TypedBody<ChangePasswordRequest>,
+) -> Result<HttpResponseOk<ChangePasswordResponse>, HttpError> {
+    let ChangePasswordRequest { user, current_password, new_password } =
+        request_body.into_inner();
+
+    if new_password.is_empty() {
+        return Err(to_bad_request("New password must not be empty"));
+    }
+
+    check_locked_out(&ctx, &user)?;
+    let Some(account) = authenticate(&ctx, &user, &current_password)? else {
+        return Ok(HttpResponseOk(ChangePasswordResponse {
+            success: false,
+            warnings: vec![],
+        }));
+    };
+
+    let hash = sha512_crypt::hash(&new_password).map_err(to_internal_error)?;
+    let config = &ctx.context().config;
+
+    let user = match account.credential {
+        Credential::Shadow(shadow_user) => shadow_user,
+        Credential::Local(_) => {
+            let users_file = PathBuf::from(&config.users_file);
+            tokio::task::spawn_blocking(move || {
+                users::set_password_hash(&users_file, &user, &hash)
+            })
+            .await
+            .map_err(to_internal_error)?
+            .map_err(to_internal_error)?;
+            info!(ctx.log, "Password changed in {}", config.users_file);
+            return Ok(HttpResponseOk(ChangePasswordResponse {
+                success: true,
+                warnings: vec![],
+            }));
+        }
+    };
+
+    let lastchg = shadow::days_since_epoch();
+    let mut warnings = Vec::new();
+
+    let shadow_path = PathBuf::from(&config.shadow_path);
+    let (hash_user, hash_value) = (user.clone(), hash.clone());
+    tokio::task::spawn_blocking(move || {
+        shadow::update_hash(&shadow_path, &hash_user, &hash_value, lastchg)
+    })
+    .await
+    .map_err(to_internal_error)?
+    .map_err(to_internal_error)?;
+    info!(ctx.log, "Password changed for {} in {}", user, config.shadow_path);
+
+    let usbkey_shadow_path = PathBuf::from(&config.usbkey_shadow_path);
+    if usbkey_shadow_path.exists() {
+        let (hash_user, hash_value) = (user.clone(), hash.clone());
+        let result = tokio::task::spawn_blocking(move || {
+            shadow::update_hash(
+                &usbkey_shadow_path,
+                &hash_user,
+                &hash_value,
+                lastchg,
+            )
+        })
+        .await
+        .map_err(to_internal_error)?;
+        if let Err(e) = result {
+            error!(
+                ctx.log,
+                "Failed to update {}: {}", config.usbkey_shadow_path, e
+            );
+            warnings.push(format!(
+                "{} could not be updated ({}), the previous password may be \
+                restored on reboot",
+                config.usbkey_shadow_path, e
+            ));
+        }
+    }
+
+    if user == "root" {
+        if let Some(warning) = update_root_shadow(&ctx, &hash).await {
+            warnings.push(warning);
+        }
+    }
+
+    Ok(HttpResponseOk(ChangePasswordResponse { success: true, warnings }))
+}
+
+/// If the host was booted with the `root_shadow` boot parameter, that value
+/// replaces root's shadow entry on every boot, so loader.conf on the USB key
+/// must be updated as well. Returns a warning if that wasn't possible.
+async fn update_root_shadow(
+    ctx: &RequestContext<Context>,
+    hash: &str,
+) -> Option<String> {
+    let unknown =
+        || Some(String::from("Unable to check the root_shadow boot parameter"));
+
+    let Ok((stdout, _)) = exec(ctx, "sysinfo", []).await else {
+        return unknown();
+    };
+    let sysinfo: Sysinfo = match serde_json::from_str(&stdout) {
+        Ok(sysinfo) => sysinfo,
+        Err(e) => {
+            error!(ctx.log, "Failed to parse sysinfo: {}", e);
+            return unknown();
+        }
+    };
+
+    if sysinfo.boot_parameters.root_shadow.is_empty() {
+        return None;
+    }
+
+    let loader_conf = PathBuf::from(&ctx.context().config.loader_conf_path);
+    if !loader_conf.exists() {
+        warn!(
+            ctx.log,
+            "root_shadow boot parameter is set but {} does not exist",
+            loader_conf.display()
+        );
+        return Some(format!(
+            "This host sets root's password with the root_shadow boot \
+            parameter but {} was not found. Mount the USB key (sdc-usbkey \
+            mount) and change the password again, otherwise the previous \
+            password will be restored on reboot.",
+            loader_conf.display()
+        ));
+    }
+
+    let hash = hash.to_string();
+    let path = loader_conf.clone();
+    let result = tokio::task::spawn_blocking(move || {
+        let contents = read_to_string(&path)?;
+        shadow::write_atomic(&path, &shadow::set_root_shadow(&contents, &hash))
+    })
+    .await
+    .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))
+    .and_then(|result| result);
+
+    if let Err(e) = result {
+        error!(ctx.log, "Failed to update {}: {}", loader_conf.display(), e);
+        return Some(format!(
+            "Failed to update root_shadow in {} ({}), the previous password \
+            will be restored on reboot",
+            loader_conf.display(),
+            e
+        ));
+    }
+
+    info!(ctx.log, "Updated root_shadow in {}", loader_conf.display());
+    None
+}
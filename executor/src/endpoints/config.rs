@@ -9,12 +9,21 @@
  */
 
 use crate::endpoints::Context;
+use crate::shadow::{write_atomic, write_private};
 
 use smartos_shared::http_server::to_internal_error;
+use smartos_shared::tls::TlsInstallRequest;
 
-use std::fs::read_to_string;
+use std::fs::{read_to_string, OpenOptions};
+use std::io::{self, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::Path;
 
-use dropshot::{endpoint, HttpError, HttpResponseOk, RequestContext};
+use dropshot::{
+    endpoint, HttpError, HttpResponseOk, HttpResponseUpdatedNoContent,
+    RequestContext, TypedBody,
+};
+use slog::info;
 
 type ConfigEntries = Vec<(String, String)>;
 
@@ -46,3 +55,37 @@ pub async fn get_gz_index(
     }
     Ok(HttpResponseOk(entries))
 }
+
+/// Write a public file, atomically if it already exists
+fn write_public(path: &Path, contents: &str) -> io::Result<()> {
+    if path.exists() {
+        return write_atomic(path, contents);
+    }
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o644)
+        .open(path)?
+        .write_all(contents.as_bytes())
+}
+
+/// Replace the UI's TLS certificate and key. The UI has already checked them
+/// and starts using them itself, this only makes the change survive restarts.
+#[endpoint {
+method = POST,
+path = "/config/tls",
+}]
+pub async fn post_tls(
+    ctx: RequestContext<Context>,
+    request_body: TypedBody<TlsInstallRequest>,
+) -> Result<HttpResponseUpdatedNoContent, HttpError> {
+    let config = &ctx.context().config;
+    let request = request_body.into_inner();
+
+    write_private(Path::new(&config.key_file), &request.key)
+        .map_err(to_internal_error)?;
+    write_public(Path::new(&config.cert_file), &request.cert)
+        .map_err(to_internal_error)?;
+    info!(ctx.log, "Installed a new TLS certificate at {}", config.cert_file);
+    Ok(HttpResponseUpdatedNoContent())
+}
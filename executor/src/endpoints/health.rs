@@ -0,0 +1,89 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+use std::fs::File;
+use std::time::{Duration, Instant};
+
+use crate::endpoints::{exec, Context};
+
+use smartos_shared::health::{self, Check, ExecutorHealth};
+use smartos_shared::sysinfo::Sysinfo;
+
+use dropshot::{endpoint, HttpError, HttpResponseOk, RequestContext};
+use tokio::time::timeout;
+
+/// Programs that take longer than this are reported as down
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Run `cmd`, returning a check named after it and its stdout
+async fn run(
+    ctx: &RequestContext<Context>,
+    cmd: &str,
+    args: &[&str],
+) -> (Check, Option<String>) {
+    let started = Instant::now();
+    match timeout(TIMEOUT, exec(ctx, cmd, args.iter().copied())).await {
+        Ok(Ok((stdout, _))) => (Check::timed(cmd, started), Some(stdout)),
+        Ok(Err(e)) => (Check::down(cmd, e.internal_message.trim()), None),
+        Err(_) => (
+            Check::down(
+                cmd,
+                format!("No response after {}s", TIMEOUT.as_secs()),
+            ),
+            None,
+        ),
+    }
+}
+
+/// Check the programs the executor runs, and the disk its log is on
+#[endpoint {
+method = GET,
+path = "/health"
+}]
+pub async fn get_index(
+    ctx: RequestContext<Context>,
+) -> Result<HttpResponseOk<ExecutorHealth>, HttpError> {
+    let (vmadm, imgadm, sysinfo) = tokio::join!(
+        run(&ctx, "vmadm", &["list", "-p", "-o", "uuid"]),
+        run(&ctx, "imgadm", &["--version"]),
+        run(&ctx, "sysinfo", &[]),
+    );
+    let (mut vmadm, _) = vmadm;
+    let (mut imgadm, version) = imgadm;
+    let (mut sysinfo, output) = sysinfo;
+
+    imgadm.version =
+        version.map(|v| String::from(v.trim().trim_start_matches("imgadm ")));
+
+    // vmadm is part of the platform, so has the same version as it
+    if let Some(output) = output {
+        match serde_json::from_str::<Sysinfo>(&output) {
+            Ok(info) => {
+                sysinfo.version = Some(info.live_image.clone());
+                vmadm.version = Some(info.live_image);
+            }
+            Err(e) => {
+                sysinfo =
+                    Check::down("sysinfo", format!("Invalid output: {}", e))
+            }
+        }
+    }
+
+    let name = "executor_log_disk";
+    let log_disk = match File::open(&ctx.context().config.log_file) {
+        Ok(file) => health::disk_space(name, &file),
+        Err(e) => Check::down(name, e),
+    };
+
+    Ok(HttpResponseOk(ExecutorHealth {
+        version: String::from(env!("CARGO_PKG_VERSION")),
+        checks: vec![vmadm, imgadm, sysinfo, log_disk],
+    }))
+}
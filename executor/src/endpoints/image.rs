@@ -9,6 +9,7 @@
  */
 
 use std::process::Stdio;
+use std::time::Instant;
 
 use crate::endpoints::{exec, exec_and_cache, Context, PathParams};
 
@@ -69,7 +70,11 @@ pub async fn get_index(
 
             if !cached {
                 debug!(ctx.log, "Caching output");
-                ctx.context().set_cache(key, stdout);
+                ctx.context().set_cache(
+                    key,
+                    stdout,
+                    ctx.context().config.exec_cache_seconds,
+                );
             }
 
             return response
@@ -79,7 +84,11 @@ pub async fn get_index(
 
         if !cached {
             debug!(ctx.log, "Caching output");
-            ctx.context().set_cache("imgadm list -j", stdout.clone());
+            ctx.context().set_cache(
+                key,
+                stdout.clone(),
+                ctx.context().config.exec_cache_seconds,
+            );
         }
 
         // happy path, just return the raw output from imgadm
@@ -101,7 +110,8 @@ pub async fn get_by_id(
     path_params: Path<PathParams>,
 ) -> Result<Response<Body>, HttpError> {
     let id = path_params.into_inner().id;
-    exec_and_cache(ctx, "imgadm", ["get", &id.to_string()]).await
+    let seconds = ctx.context().config.exec_cache_seconds;
+    exec_and_cache(ctx, "imgadm", ["get", &id.to_string()], seconds).await
 }
 
 #[endpoint {
@@ -188,6 +198,7 @@ pub async fn post_import_by_id(
 
     let args = ["import", "-q", "-S", &req.url.as_ref(), &id.to_string()];
     debug!(ctx.log, "Executing imgadm {:?}", &args);
+    let started = Instant::now();
     let out = Command::new("imgadm")
         .args(args)
         .stdin(Stdio::null())
@@ -199,6 +210,12 @@ pub async fn post_import_by_id(
         .await
         .map_err(to_internal_error)?;
 
+    ctx.context().observe_command(
+        "imgadm import",
+        started,
+        out.status.success(),
+    );
+
     if let Ok(mut queue) = image_import_queue.lock() {
         if out.status.success() {
             let stdout =
@@ -253,7 +270,8 @@ path = "/source",
 pub async fn get_source_index(
     ctx: RequestContext<Context>,
 ) -> Result<Response<Body>, HttpError> {
-    exec_and_cache(ctx, "imgadm", ["sources", "-j"]).await
+    let seconds = ctx.context().config.exec_cache_seconds;
+    exec_and_cache(ctx, "imgadm", ["sources", "-j"], seconds).await
 }
 
 #[endpoint {
@@ -263,5 +281,6 @@ path = "/avail",
 pub async fn get_avail(
     ctx: RequestContext<Context>,
 ) -> Result<Response<Body>, HttpError> {
-    exec_and_cache(ctx, "imgadm", ["avail", "-j"]).await
+    let seconds = ctx.context().config.avail_cache_seconds;
+    exec_and_cache(ctx, "imgadm", ["avail", "-j"], seconds).await
 }
@@ -8,12 +8,17 @@
  * Copyright 2024 MNX Cloud, Inc.
  */
 
+use std::collections::BTreeMap;
 use std::process::Stdio;
+use std::time::Instant;
 
 use crate::endpoints::{exec, Context, PathParams};
+use smartos_shared::disk::DiskUpdate;
 use smartos_shared::instance::{
     InstancePayload, InstanceValidateResponse, PayloadContainer,
 };
+use smartos_shared::nic::NicUpdate;
+use smartos_shared::tag::TagUpdate;
 
 use dropshot::{
     endpoint, HttpError, HttpResponseOk, Path, RequestContext, TypedBody,
@@ -43,6 +48,7 @@ pub async fn post_provision_index(
     let args = ["create"];
     debug!(ctx.log, "Executing vmadm {:?}", &args);
 
+    let started = Instant::now();
     let mut process = Command::new("vmadm")
         .args(args)
         .stdin(Stdio::piped())
@@ -63,6 +69,12 @@ pub async fn post_provision_index(
 
     let out = process.wait_with_output().await.map_err(to_internal_error)?;
 
+    ctx.context().observe_command(
+        "vmadm create",
+        started,
+        out.status.success(),
+    );
+
     if out.status.success() {
         let stdout =
             String::from_utf8(out.stdout).map_err(to_internal_error)?;
@@ -81,11 +93,12 @@ method = POST,
 path = "/validate/create",
 }]
 pub async fn post_validate_create(
-    _: RequestContext<Context>,
+    ctx: RequestContext<Context>,
     request_body: TypedBody<InstancePayload>,
 ) -> Result<HttpResponseOk<InstanceValidateResponse>, HttpError> {
     let InstancePayload { payload } = request_body.into_inner();
 
+    let started = Instant::now();
     let mut process = Command::new("vmadm")
         .args(["validate", "create"])
         .stdin(Stdio::piped())
@@ -102,6 +115,11 @@ pub async fn post_validate_create(
 
         let out =
             process.wait_with_output().await.map_err(to_internal_error)?;
+        ctx.context().observe_command(
+            "vmadm validate",
+            started,
+            out.status.success(),
+        );
         let stderr = String::from_utf8(out.stderr).unwrap_or_default();
 
         let response = InstanceValidateResponse {
@@ -166,6 +184,23 @@ pub async fn start_by_id(
         .map_err(to_internal_error)
 }
 
+#[endpoint {
+method = POST,
+path = "/instance/{id}/reboot",
+}]
+pub async fn reboot_by_id(
+    ctx: RequestContext<Context>,
+    path_params: Path<PathParams>,
+) -> Result<Response<Body>, HttpError> {
+    let req = path_params.into_inner();
+    let (_, stderr) =
+        exec(&ctx, "vmadm", ["reboot", &req.id.to_string()]).await?;
+    Response::builder()
+        .status(StatusCode::OK)
+        .body(stderr.into())
+        .map_err(to_internal_error)
+}
+
 #[endpoint {
 method = GET,
 path = "/info/{id}",
@@ -176,6 +211,7 @@ pub async fn info_by_id(
 ) -> Result<Response<Body>, HttpError> {
     let req = path_params.into_inner();
 
+    let started = Instant::now();
     let out = Command::new("vmadm")
         .args(["info", &req.id.to_string(), "vnc"])
         .stdin(Stdio::null())
@@ -187,6 +223,8 @@ pub async fn info_by_id(
         .await
         .map_err(to_internal_error)?;
 
+    ctx.context().observe_command("vmadm info", started, out.status.success());
+
     if !out.status.success() {
         let stderr =
             String::from_utf8(out.stderr).map_err(to_internal_error)?;
@@ -208,3 +246,139 @@ pub async fn info_by_id(
         .body(stdout.into())
         .map_err(to_internal_error)
 }
+
+/// Run `vmadm update` on instance `id` with `payload` on stdin, returning
+/// vmadm's output
+async fn vmadm_update(
+    ctx: &RequestContext<Context>,
+    id: &str,
+    payload: &str,
+) -> Result<String, HttpError> {
+    debug!(ctx.log, "Executing vmadm update {}: {}", id, payload);
+
+    let started = Instant::now();
+    let mut process = Command::new("vmadm")
+        .args(["update", id])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(to_internal_error)?;
+
+    if let Some(mut stdin) = process.stdin.take() {
+        stdin.write_all(payload.as_bytes()).await.map_err(to_internal_error)?;
+        drop(stdin);
+    } else {
+        return Err(to_internal_error("Failed to acquire stdin pipe"));
+    }
+
+    let out = process.wait_with_output().await.map_err(to_internal_error)?;
+    ctx.context().observe_command(
+        "vmadm update",
+        started,
+        out.status.success(),
+    );
+
+    let stderr = String::from_utf8(out.stderr).map_err(to_internal_error)?;
+    if !out.status.success() {
+        error!(ctx.log, "Instance {} update failed: {}", id, stderr);
+        return Err(to_bad_request(stderr));
+    }
+    info!(ctx.log, "Instance {} updated: {}", id, stderr);
+    Ok(stderr)
+}
+
+/// Add, change or remove disks of a bhyve or KVM instance
+#[endpoint {
+method = POST,
+path = "/instance/{id}/disks",
+}]
+pub async fn post_disks_by_id(
+    ctx: RequestContext<Context>,
+    path_params: Path<PathParams>,
+    request_body: TypedBody<DiskUpdate>,
+) -> Result<Response<Body>, HttpError> {
+    let id = path_params.into_inner().id.to_string();
+    let update = request_body.into_inner();
+    if update.is_empty() {
+        return Err(to_bad_request("No disk changes"));
+    }
+    let payload = serde_json::to_string(&update).map_err(to_internal_error)?;
+    let stderr = vmadm_update(&ctx, &id, &payload).await?;
+    Response::builder()
+        .status(StatusCode::OK)
+        .body(stderr.into())
+        .map_err(to_internal_error)
+}
+
+/// Add, change or remove NICs of an instance
+#[endpoint {
+method = POST,
+path = "/instance/{id}/nics",
+}]
+pub async fn post_nics_by_id(
+    ctx: RequestContext<Context>,
+    path_params: Path<PathParams>,
+    request_body: TypedBody<NicUpdate>,
+) -> Result<Response<Body>, HttpError> {
+    let id = path_params.into_inner().id.to_string();
+    let update = request_body.into_inner();
+    if update.is_empty() {
+        return Err(to_bad_request("No NIC changes"));
+    }
+    let payload = serde_json::to_string(&update).map_err(to_internal_error)?;
+    let stderr = vmadm_update(&ctx, &id, &payload).await?;
+    Response::builder()
+        .status(StatusCode::OK)
+        .body(stderr.into())
+        .map_err(to_internal_error)
+}
+
+/// Set or remove tags of an instance
+#[endpoint {
+method = POST,
+path = "/instance/{id}/tags",
+}]
+pub async fn post_tags_by_id(
+    ctx: RequestContext<Context>,
+    path_params: Path<PathParams>,
+    request_body: TypedBody<TagUpdate>,
+) -> Result<Response<Body>, HttpError> {
+    let id = path_params.into_inner().id.to_string();
+    let update = request_body.into_inner();
+    if update.is_empty() {
+        return Err(to_bad_request("No tag changes"));
+    }
+    let payload = serde_json::to_string(&update).map_err(to_internal_error)?;
+    let stderr = vmadm_update(&ctx, &id, &payload).await?;
+    Response::builder()
+        .status(StatusCode::OK)
+        .body(stderr.into())
+        .map_err(to_internal_error)
+}
+
+/// Bytes used by each of an instance's disks, by zvol. bhyve disks are under
+/// the instance's dataset, KVM disks are next to it, so every volume with the
+/// instance's UUID in its name is included.
+#[endpoint {
+method = GET,
+path = "/instance/{id}/disk_usage",
+}]
+pub async fn get_disk_usage_by_id(
+    ctx: RequestContext<Context>,
+    path_params: Path<PathParams>,
+) -> Result<HttpResponseOk<BTreeMap<String, u64>>, HttpError> {
+    let id = path_params.into_inner().id.to_string();
+    let (stdout, _) =
+        exec(&ctx, "zfs", ["list", "-Hp", "-t", "volume", "-o", "name,used"])
+            .await?;
+    let usage = stdout
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .filter(|(name, _)| name.contains(&id))
+        .filter_map(|(name, used)| {
+            used.trim().parse().ok().map(|used| (String::from(name), used))
+        })
+        .collect();
+    Ok(HttpResponseOk(usage))
+}
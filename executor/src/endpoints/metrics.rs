@@ -0,0 +1,37 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+use crate::endpoints::Context;
+
+use smartos_shared::http_server::to_internal_error;
+use smartos_shared::metrics::CONTENT_TYPE;
+
+use dropshot::{endpoint, HttpError, RequestContext};
+use hyper::{Body, Response, StatusCode};
+
+/// Executor metrics in the Prometheus text format. The UI appends this to its
+/// own `/metrics` output, so it is only registered when metrics are enabled.
+#[endpoint {
+method = GET,
+path = "/metrics",
+}]
+pub async fn get_index(
+    ctx: RequestContext<Context>,
+) -> Result<Response<Body>, HttpError> {
+    let cache_entries = match ctx.context().cache.lock() {
+        Ok(cache) => cache.len(),
+        Err(_) => 0,
+    };
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", CONTENT_TYPE)
+        .body(ctx.context().metrics.render(cache_entries).into())
+        .map_err(to_internal_error)
+}
@@ -8,10 +8,12 @@
  * Copyright 2024 MNX Cloud, Inc.
  */
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::ffi::OsStr;
 use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
 use smartos_shared::config::Config;
 use time::{Duration, OffsetDateTime};
@@ -23,15 +25,22 @@ use serde::Deserialize;
 use slog::{debug, error};
 use smartos_shared::http_server::{empty_ok, to_internal_error};
 use smartos_shared::image::Image;
+use smartos_shared::metrics::{header, sample, Histogram};
 use tokio::process::Command;
 use uuid::Uuid;
 
+pub mod api_tokens;
+pub mod auth;
 pub mod config;
+pub mod health;
 pub mod image;
 pub mod instance;
+pub mod metrics;
 pub mod nictag;
-pub mod pwhash;
+pub mod presets;
 pub mod sysinfo;
+pub mod totp;
+pub mod webauthn;
 
 #[derive(Debug)]
 pub struct CacheEntry {
@@ -39,10 +48,68 @@ pub struct CacheEntry {
     pub content: String,
 }
 
+/// Service level metrics exposed by `GET /metrics` when enabled
+#[derive(Default)]
+pub struct Metrics {
+    /// Execution time of each program run, keyed by program and subcommand
+    pub command_latency: Mutex<BTreeMap<String, Histogram>>,
+    /// Number of non-zero exits, keyed the same as `command_latency`
+    pub command_failures: Mutex<BTreeMap<String, u64>>,
+    pub cache_hits: AtomicU64,
+    pub cache_misses: AtomicU64,
+}
+
+impl Metrics {
+    /// Render all metrics in the Prometheus text format
+    pub fn render(&self, cache_entries: usize) -> String {
+        let mut out = String::new();
+
+        let name = "smartos_executor_command_duration_seconds";
+        header(&mut out, name, "histogram", "Time spent executing programs");
+        if let Ok(latency) = self.command_latency.lock() {
+            for (command, histogram) in latency.iter() {
+                histogram.write(&mut out, name, &[("command", command)]);
+            }
+        }
+
+        let name = "smartos_executor_command_failures_total";
+        header(&mut out, name, "counter", "Programs that exited non-zero");
+        if let Ok(failures) = self.command_failures.lock() {
+            for (command, count) in failures.iter() {
+                sample(&mut out, name, &[("command", command)], count);
+            }
+        }
+
+        let name = "smartos_executor_cache_hits_total";
+        header(&mut out, name, "counter", "Command output cache hits");
+        sample(&mut out, name, &[], self.cache_hits.load(Ordering::Relaxed));
+
+        let name = "smartos_executor_cache_misses_total";
+        header(&mut out, name, "counter", "Command output cache misses");
+        sample(&mut out, name, &[], self.cache_misses.load(Ordering::Relaxed));
+
+        let name = "smartos_executor_cache_entries";
+        header(&mut out, name, "gauge", "Entries in the command output cache");
+        sample(&mut out, name, &[], cache_entries);
+
+        out
+    }
+}
+
 pub struct Context {
     pub config: Config,
     pub cache: Arc<Mutex<HashMap<String, CacheEntry>>>,
     pub import_queue: Arc<Mutex<HashMap<Uuid, Image>>>,
+    pub metrics: Arc<Metrics>,
+    pub auth_failures: Mutex<HashMap<String, auth::AuthFailures>>,
+    /// Held while the TOTP file is read, modified and written
+    pub totp_lock: Mutex<()>,
+    /// Held while the WebAuthn credentials file is read, modified and written
+    pub webauthn_lock: Mutex<()>,
+    /// Held while the API tokens file is read, modified and written
+    pub api_tokens_lock: Mutex<()>,
+    /// Held while the presets file is read, modified and written
+    pub presets_lock: Mutex<()>,
 }
 
 impl Context {
@@ -52,6 +119,12 @@ impl Context {
             config,
             cache: Arc::new(Mutex::new(HashMap::<String, CacheEntry>::new())),
             import_queue: Arc::new(Mutex::new(HashMap::<Uuid, Image>::new())),
+            metrics: Arc::new(Metrics::default()),
+            auth_failures: Mutex::new(HashMap::new()),
+            totp_lock: Mutex::new(()),
+            webauthn_lock: Mutex::new(()),
+            api_tokens_lock: Mutex::new(()),
+            presets_lock: Mutex::new(()),
         }
     }
 
@@ -59,22 +132,40 @@ impl Context {
         if let Ok(cache) = self.cache.clone().lock() {
             if let Some(entry) = cache.get(&key.into()) {
                 if entry.expiry > OffsetDateTime::now_utc() {
+                    self.metrics.cache_hits.fetch_add(1, Ordering::Relaxed);
                     return Some(entry.content.clone());
                 }
             }
         }
+        self.metrics.cache_misses.fetch_add(1, Ordering::Relaxed);
         None
     }
 
+    /// Record how long a program took to run and whether it succeeded.
+    /// `command` should be the program name and subcommand (e.g.
+    /// "vmadm create") so that the number of label values stays bounded.
+    pub fn observe_command(&self, command: &str, started: Instant, ok: bool) {
+        let elapsed = started.elapsed().as_secs_f64();
+        if let Ok(mut latency) = self.metrics.command_latency.lock() {
+            latency.entry(command.to_string()).or_default().observe(elapsed);
+        }
+        if !ok {
+            if let Ok(mut failures) = self.metrics.command_failures.lock() {
+                *failures.entry(command.to_string()).or_default() += 1;
+            }
+        }
+    }
+
+    /// Cache `content` for `seconds`, usually one of the `*_cache_seconds`
+    /// values from [Config]
     pub fn set_cache<S: Into<String>>(
         &self,
         key: S,
         content: String,
+        seconds: i64,
     ) -> Option<CacheEntry> {
         if let Ok(mut cache) = self.cache.clone().lock() {
-            let cache_duration = self.config.exec_cache_seconds;
-            let expiry =
-                OffsetDateTime::now_utc() + Duration::new(cache_duration, 0);
+            let expiry = OffsetDateTime::now_utc() + Duration::new(seconds, 0);
             return cache.insert(key.into(), CacheEntry { expiry, content });
         }
         None
@@ -93,6 +184,15 @@ pub struct PathParams {
     id: Uuid,
 }
 
+/// Build the metrics label for a program invocation from the program name and
+/// its first argument (the subcommand for vmadm and imgadm)
+fn command_label<S: std::fmt::Display>(cmd: &S, args: &[S]) -> String {
+    match args.first() {
+        Some(subcommand) => format!("{} {}", cmd, subcommand),
+        None => cmd.to_string(),
+    }
+}
+
 pub async fn exec<I, S>(
     ctx: &RequestContext<Context>,
     cmd: S,
@@ -102,6 +202,9 @@ where
     I: IntoIterator<Item = S> + std::fmt::Debug,
     S: AsRef<OsStr> + std::fmt::Display,
 {
+    let args: Vec<S> = args.into_iter().collect();
+    let label = command_label(&cmd, &args);
+    let started = Instant::now();
     let out = Command::new(&cmd)
         .args(args)
         .stdin(Stdio::null())
@@ -113,13 +216,18 @@ where
         .await
         .map_err(to_internal_error)?;
 
+    ctx.context().observe_command(&label, started, out.status.success());
+
     if !out.status.success() {
         let stderr =
             String::from_utf8(out.stderr).map_err(to_internal_error)?;
 
         error!(ctx.log, "Exec failed {}: {}", cmd, stderr);
 
-        return Err(HttpError::for_internal_error(stderr));
+        // The UI shows the command's own reason for failing
+        let mut error = HttpError::for_internal_error(stderr.clone());
+        error.external_message = stderr;
+        return Err(error);
     }
 
     Ok((
@@ -132,6 +240,7 @@ pub async fn exec_and_cache<I, S>(
     ctx: RequestContext<Context>,
     cmd: S,
     args: I,
+    cache_seconds: i64,
 ) -> Result<Response<Body>, HttpError>
 where
     I: IntoIterator<Item = S> + std::fmt::Debug,
@@ -151,6 +260,9 @@ where
             .map_err(to_internal_error);
     }
 
+    let args: Vec<S> = args.into_iter().collect();
+    let label = command_label(&cmd, &args);
+    let started = Instant::now();
     let out = Command::new(&cmd)
         .args(args)
         .stdin(Stdio::null())
@@ -162,6 +274,8 @@ where
         .await
         .map_err(to_internal_error)?;
 
+    ctx.context().observe_command(&label, started, out.status.success());
+
     if !out.status.success() {
         let stderr =
             String::from_utf8(out.stderr).map_err(to_internal_error)?;
@@ -176,7 +290,7 @@ where
 
     let stdout = String::from_utf8(out.stdout).map_err(to_internal_error)?;
 
-    ctx.context().set_cache(key, stdout.clone());
+    ctx.context().set_cache(key, stdout.clone(), cache_seconds);
 
     response
         .status(StatusCode::OK)
@@ -0,0 +1,115 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+//! Provisioning presets. The UI checks a preset against the host's capacity
+//! before saving it, here only its name is checked.
+
+use std::path::Path;
+
+use crate::endpoints::Context;
+use crate::presets;
+
+use smartos_shared::http_server::{to_bad_request, to_internal_error};
+use smartos_shared::preset::{valid_name, Preset};
+
+use dropshot::{
+    endpoint, HttpError, HttpResponseOk, HttpResponseUpdatedNoContent,
+    Path as PathParam, RequestContext, TypedBody,
+};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use slog::info;
+
+/// Most presets kept
+const MAX_PRESETS: usize = 100;
+
+#[derive(Deserialize, JsonSchema)]
+pub struct PresetPathParams {
+    name: String,
+}
+
+/// Load the presets file, pass the presets to `update` and save them if it
+/// returns true, holding the lock throughout
+fn modify<T, F>(ctx: &Context, update: F) -> Result<T, HttpError>
+where
+    F: FnOnce(&mut presets::Store) -> Result<(bool, T), HttpError>,
+{
+    let _guard = ctx
+        .presets_lock
+        .lock()
+        .map_err(|e| HttpError::for_internal_error(format!("{}", e)))?;
+    let path = Path::new(&ctx.config.presets_file);
+    let mut store = presets::load(path).map_err(to_internal_error)?;
+    let (changed, result) = update(&mut store)?;
+    if changed {
+        presets::save(path, &store).map_err(to_internal_error)?;
+    }
+    Ok(result)
+}
+
+#[endpoint {
+method = GET,
+path = "/preset",
+}]
+pub async fn get_index(
+    ctx: RequestContext<Context>,
+) -> Result<HttpResponseOk<Vec<Preset>>, HttpError> {
+    let store = presets::load(Path::new(&ctx.context().config.presets_file))
+        .map_err(to_internal_error)?;
+    Ok(HttpResponseOk(store.into_values().collect()))
+}
+
+/// Create a preset, or replace the one with the same name
+#[endpoint {
+method = POST,
+path = "/preset",
+}]
+pub async fn post_index(
+    ctx: RequestContext<Context>,
+    request_body: TypedBody<Preset>,
+) -> Result<HttpResponseUpdatedNoContent, HttpError> {
+    let preset = request_body.into_inner();
+    if !valid_name(&preset.name) {
+        return Err(to_bad_request("Invalid preset name"));
+    }
+    let name = preset.name.clone();
+    modify(ctx.context(), |store| {
+        if !store.contains_key(&name) && store.len() >= MAX_PRESETS {
+            return Err(to_bad_request("Too many presets"));
+        }
+        store.insert(name.clone(), preset);
+        Ok((true, ()))
+    })?;
+    info!(ctx.log, "Saved preset {}", name);
+    Ok(HttpResponseUpdatedNoContent())
+}
+
+#[endpoint {
+method = DELETE,
+path = "/preset/{name}",
+}]
+pub async fn delete_by_name(
+    ctx: RequestContext<Context>,
+    path_params: PathParam<PresetPathParams>,
+) -> Result<HttpResponseUpdatedNoContent, HttpError> {
+    let name = path_params.into_inner().name;
+    let removed = modify(ctx.context(), |store| {
+        let removed = store.remove(&name).is_some();
+        Ok((removed, removed))
+    })?;
+    if !removed {
+        return Err(HttpError::for_not_found(
+            None,
+            format!("No such preset {}", name),
+        ));
+    }
+    info!(ctx.log, "Deleted preset {}", name);
+    Ok(HttpResponseUpdatedNoContent())
+}
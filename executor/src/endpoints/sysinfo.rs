@@ -20,5 +20,6 @@ path = "/sysinfo",
 pub async fn get_index(
     ctx: RequestContext<Context>,
 ) -> Result<Response<Body>, HttpError> {
-    exec_and_cache(ctx, "sysinfo", []).await
+    let seconds = ctx.context().config.sysinfo_cache_seconds;
+    exec_and_cache(ctx, "sysinfo", [], seconds).await
 }
@@ -0,0 +1,227 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+//! TOTP enrollment and verification for UI users. The UI only calls these
+//! for a user that has already authenticated with a password, the secret is
+//! only sent back once, when it is generated.
+
+use std::path::Path;
+
+use crate::endpoints::auth::{check_locked_out, record_result};
+use crate::endpoints::Context;
+use crate::totp::{self, Accepted, Entry};
+
+use smartos_shared::auth::{
+    TotpCodeRequest, TotpCodeResponse, TotpEnrollResponse, TotpStatusResponse,
+    TotpUserRequest,
+};
+use smartos_shared::http_server::{to_bad_request, to_internal_error};
+
+use dropshot::{
+    endpoint, HttpError, HttpResponseOk, RequestContext, TypedBody,
+};
+use slog::{info, warn};
+
+/// Key used for rate limiting code attempts, kept separate from password
+/// failures
+fn failure_key(user: &str) -> String {
+    format!("totp:{}", user)
+}
+
+/// Load the TOTP file, pass the entries to `update` and save them if it
+/// returns true. The lock is held throughout so concurrent requests can't
+/// lose each other's changes.
+fn modify<T, F>(ctx: &Context, update: F) -> Result<T, HttpError>
+where
+    F: FnOnce(&mut totp::Entries) -> Result<(bool, T), HttpError>,
+{
+    let _guard = ctx
+        .totp_lock
+        .lock()
+        .map_err(|e| HttpError::for_internal_error(format!("{}", e)))?;
+    let path = Path::new(&ctx.config.totp_file);
+    let mut entries = totp::load(path).map_err(to_internal_error)?;
+    let (changed, result) = update(&mut entries)?;
+    if changed {
+        totp::save(path, &entries).map_err(to_internal_error)?;
+    }
+    Ok(result)
+}
+
+#[endpoint {
+method = POST,
+path = "/auth/totp/status",
+}]
+pub async fn post_status(
+    ctx: RequestContext<Context>,
+    request_body: TypedBody<TotpUserRequest>,
+) -> Result<HttpResponseOk<TotpStatusResponse>, HttpError> {
+    let TotpUserRequest { user } = request_body.into_inner();
+    let entries = totp::load(Path::new(&ctx.context().config.totp_file))
+        .map_err(to_internal_error)?;
+    let entry = entries.get(&user).filter(|entry| entry.enabled);
+    Ok(HttpResponseOk(TotpStatusResponse {
+        enabled: entry.is_some(),
+        recovery_codes: entry.map_or(0, |entry| entry.recovery_codes.len()),
+    }))
+}
+
+/// Generate a new secret for `user`. It isn't used for logins until it is
+/// confirmed with `POST /auth/totp/confirm`.
+#[endpoint {
+method = POST,
+path = "/auth/totp/enroll",
+}]
+pub async fn post_enroll(
+    ctx: RequestContext<Context>,
+    request_body: TypedBody<TotpUserRequest>,
+) -> Result<HttpResponseOk<TotpEnrollResponse>, HttpError> {
+    let TotpUserRequest { user } = request_body.into_inner();
+    let secret = modify(ctx.context(), |entries| {
+        if entries.get(&user).is_some_and(|entry| entry.enabled) {
+            return Err(to_bad_request(format!(
+                "Two-factor authentication is already enabled for {}",
+                user
+            )));
+        }
+        let secret = totp::generate_secret().map_err(to_internal_error)?;
+        entries.insert(
+            user.clone(),
+            Entry { secret: secret.clone(), ..Default::default() },
+        );
+        Ok((true, secret))
+    })?;
+    info!(ctx.log, "Started TOTP enrollment for {}", user);
+    Ok(HttpResponseOk(TotpEnrollResponse { secret }))
+}
+
+/// Enable TOTP for `user` once they have proven their authenticator app has
+/// the secret, returning newly generated recovery codes
+#[endpoint {
+method = POST,
+path = "/auth/totp/confirm",
+}]
+pub async fn post_confirm(
+    ctx: RequestContext<Context>,
+    request_body: TypedBody<TotpCodeRequest>,
+) -> Result<HttpResponseOk<TotpCodeResponse>, HttpError> {
+    let TotpCodeRequest { user, code } = request_body.into_inner();
+    let key = failure_key(&user);
+    check_locked_out(&ctx, &key)?;
+
+    let recovery_codes = modify(ctx.context(), |entries| {
+        let Some(entry) = entries.get_mut(&user).filter(|e| !e.enabled) else {
+            return Err(to_bad_request(format!(
+                "No pending two-factor enrollment for {}",
+                user
+            )));
+        };
+        if totp::check(entry, &code, totp::now(), false).is_none() {
+            return Ok((false, None));
+        }
+        let codes =
+            totp::generate_recovery_codes().map_err(to_internal_error)?;
+        entry.recovery_codes =
+            codes.iter().map(|c| totp::hash_recovery_code(c)).collect();
+        entry.enabled = true;
+        Ok((true, Some(codes)))
+    })?;
+
+    record_result(ctx.context(), &key, recovery_codes.is_some());
+    if recovery_codes.is_some() {
+        info!(ctx.log, "Enabled TOTP for {}", user);
+    } else {
+        warn!(ctx.log, "Invalid TOTP code confirming enrollment for {}", user);
+    }
+    Ok(HttpResponseOk(TotpCodeResponse {
+        success: recovery_codes.is_some(),
+        recovery_codes: recovery_codes.unwrap_or_default(),
+        recovery_codes_left: None,
+    }))
+}
+
+/// Check `code` for `user`, calling `action` with the entries (which are
+/// then saved) if it is accepted
+fn check_code<F>(
+    ctx: &RequestContext<Context>,
+    user: &str,
+    code: &str,
+    action: F,
+) -> Result<TotpCodeResponse, HttpError>
+where
+    F: FnOnce(&mut totp::Entries),
+{
+    let key = failure_key(user);
+    check_locked_out(ctx, &key)?;
+
+    let accepted = modify(ctx.context(), |entries| {
+        let Some(entry) = entries.get_mut(user).filter(|e| e.enabled) else {
+            return Ok((false, None));
+        };
+        let accepted = totp::check(entry, code, totp::now(), true);
+        let left = entry.recovery_codes.len();
+        if accepted.is_some() {
+            action(entries);
+        }
+        Ok((accepted.is_some(), accepted.map(|accepted| (accepted, left))))
+    })?;
+
+    record_result(ctx.context(), &key, accepted.is_some());
+    match &accepted {
+        Some((Accepted::RecoveryCode, left)) => {
+            warn!(
+                ctx.log,
+                "Recovery code used by {}, {} remaining", user, left
+            );
+        }
+        Some((Accepted::Totp, _)) => {}
+        None => warn!(ctx.log, "Invalid TOTP code for {}", user),
+    }
+    Ok(TotpCodeResponse {
+        success: accepted.is_some(),
+        recovery_codes: vec![],
+        recovery_codes_left: accepted.and_then(|(accepted, left)| {
+            (accepted == Accepted::RecoveryCode).then_some(left)
+        }),
+    })
+}
+
+/// Verify the second factor for a login. Accepts a code from the user's
+/// authenticator app or one of their recovery codes.
+#[endpoint {
+method = POST,
+path = "/auth/totp/verify",
+}]
+pub async fn post_verify(
+    ctx: RequestContext<Context>,
+    request_body: TypedBody<TotpCodeRequest>,
+) -> Result<HttpResponseOk<TotpCodeResponse>, HttpError> {
+    let TotpCodeRequest { user, code } = request_body.into_inner();
+    Ok(HttpResponseOk(check_code(&ctx, &user, &code, |_| {})?))
+}
+
+/// Turn off TOTP for `user`, which requires a valid code (or recovery code)
+#[endpoint {
+method = POST,
+path = "/auth/totp/disable",
+}]
+pub async fn post_disable(
+    ctx: RequestContext<Context>,
+    request_body: TypedBody<TotpCodeRequest>,
+) -> Result<HttpResponseOk<TotpCodeResponse>, HttpError> {
+    let TotpCodeRequest { user, code } = request_body.into_inner();
+    let response = check_code(&ctx, &user, &code, |entries| {
+        entries.remove(&user);
+    })?;
+    if response.success {
+        info!(ctx.log, "Disabled TOTP for {}", user);
+    }
+    Ok(HttpResponseOk(response))
+}
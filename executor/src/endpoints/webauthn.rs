@@ -0,0 +1,312 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+//! WebAuthn credential registration and login assertions. The UI generates
+//! the challenges and works out the relying party from the host it was
+//! reached by, the signatures are checked and the credentials stored here.
+
+use std::path::Path;
+
+use crate::endpoints::auth::{check_locked_out, record_result};
+use crate::endpoints::Context;
+use crate::users;
+use crate::webauthn::{self, Credential, Expected, UserCredentials};
+
+use smartos_shared::auth::{
+    WebauthnAssertRequest, WebauthnAssertResponse, WebauthnCredential,
+    WebauthnCredentialsResponse, WebauthnDeleteRequest,
+    WebauthnRegisterRequest, WebauthnRegisterResponse, WebauthnUserRequest,
+};
+use smartos_shared::http_server::to_internal_error;
+
+use dropshot::{
+    endpoint, HttpError, HttpResponseOk, HttpResponseUpdatedNoContent,
+    RequestContext, TypedBody,
+};
+use slog::{error, info, warn};
+use time::OffsetDateTime;
+
+/// Most credentials a single user can register
+const MAX_CREDENTIALS: usize = 20;
+
+/// Longest credential name kept, in characters
+const MAX_NAME_LENGTH: usize = 64;
+
+/// Key used for rate limiting assertions, kept separate from password
+/// failures
+fn failure_key(user: &str) -> String {
+    format!("webauthn:{}", user)
+}
+
+/// Load the credentials file, pass the credentials to `update` and save them
+/// if it returns true. The lock is held throughout so concurrent requests
+/// can't lose each other's changes.
+fn modify<T, F>(ctx: &Context, update: F) -> Result<T, HttpError>
+where
+    F: FnOnce(&mut webauthn::Store) -> Result<(bool, T), HttpError>,
+{
+    let _guard = ctx
+        .webauthn_lock
+        .lock()
+        .map_err(|e| HttpError::for_internal_error(format!("{}", e)))?;
+    let path = Path::new(&ctx.config.webauthn_file);
+    let mut store = webauthn::load(path).map_err(to_internal_error)?;
+    let (changed, result) = update(&mut store)?;
+    if changed {
+        webauthn::save(path, &store).map_err(to_internal_error)?;
+    }
+    Ok(result)
+}
+
+#[endpoint {
+method = POST,
+path = "/auth/webauthn/credentials",
+}]
+pub async fn post_credentials(
+    ctx: RequestContext<Context>,
+    request_body: TypedBody<WebauthnUserRequest>,
+) -> Result<HttpResponseOk<WebauthnCredentialsResponse>, HttpError> {
+    let WebauthnUserRequest { user } = request_body.into_inner();
+    let store = webauthn::load(Path::new(&ctx.context().config.webauthn_file))
+        .map_err(to_internal_error)?;
+    let response = match store.get(&user) {
+        Some(entry) => WebauthnCredentialsResponse {
+            handle: entry.handle.clone(),
+            credentials: entry.credentials.iter().map(Into::into).collect(),
+        },
+        None => WebauthnCredentialsResponse {
+            handle: webauthn::generate_handle().map_err(to_internal_error)?,
+            credentials: vec![],
+        },
+    };
+    Ok(HttpResponseOk(response))
+}
+
+/// Verify and store a newly created credential
+#[endpoint {
+method = POST,
+path = "/auth/webauthn/register",
+}]
+pub async fn post_register(
+    ctx: RequestContext<Context>,
+    request_body: TypedBody<WebauthnRegisterRequest>,
+) -> Result<HttpResponseOk<WebauthnRegisterResponse>, HttpError> {
+    let request = request_body.into_inner();
+    let expected = Expected {
+        rp_id: &request.rp_id,
+        origin: &request.origin,
+        challenge: &request.challenge,
+    };
+    let verified =
+        webauthn::decode(&request.client_data_json).and_then(|client_data| {
+            let attestation = webauthn::decode(&request.attestation_object)?;
+            webauthn::verify_registration(&expected, &client_data, &attestation)
+        });
+    let registration = match verified {
+        Ok(registration) => registration,
+        Err(e) => {
+            warn!(ctx.log, "Refused credential for {}: {}", request.user, e);
+            return Ok(HttpResponseOk(WebauthnRegisterResponse {
+                credential: None,
+                error: Some(e),
+            }));
+        }
+    };
+
+    let mut name: String =
+        request.name.trim().chars().take(MAX_NAME_LENGTH).collect();
+    if name.is_empty() {
+        name = String::from("Passkey");
+    }
+    let credential = Credential {
+        id: webauthn::encode(&registration.id),
+        name,
+        public_key: webauthn::encode(&registration.public_key),
+        rp_id: request.rp_id.clone(),
+        sign_count: registration.sign_count,
+        created: OffsetDateTime::now_utc(),
+        last_used: None,
+    };
+
+    let result = modify(ctx.context(), |store| {
+        if webauthn::find(store, &credential.id).is_some() {
+            return Ok((false, Err("This credential is already registered")));
+        }
+        let entry = store.entry(request.user.clone()).or_insert_with(|| {
+            UserCredentials {
+                handle: request.handle.clone(),
+                credentials: vec![],
+            }
+        });
+        // Another registration gave the user a handle first, the
+        // authenticator has stored the wrong one
+        if entry.handle != request.handle {
+            return Ok((false, Err("The registration was interrupted")));
+        }
+        if entry.credentials.len() >= MAX_CREDENTIALS {
+            return Ok((false, Err("Too many passkeys are registered")));
+        }
+        entry.credentials.push(credential.clone());
+        Ok((true, Ok(())))
+    })?;
+
+    if let Err(e) = result {
+        warn!(ctx.log, "Refused credential for {}: {}", request.user, e);
+        return Ok(HttpResponseOk(WebauthnRegisterResponse {
+            credential: None,
+            error: Some(String::from(e)),
+        }));
+    }
+    info!(
+        ctx.log,
+        "Registered WebAuthn credential \"{}\" for {}",
+        credential.name,
+        request.user
+    );
+    Ok(HttpResponseOk(WebauthnRegisterResponse {
+        credential: Some(WebauthnCredential::from(&credential)),
+        error: None,
+    }))
+}
+
+/// Check a login assertion. User verification (PIN or biometrics) is
+/// required as the credential replaces both the password and TOTP.
+#[endpoint {
+method = POST,
+path = "/auth/webauthn/assert",
+}]
+pub async fn post_assert(
+    ctx: RequestContext<Context>,
+    request_body: TypedBody<WebauthnAssertRequest>,
+) -> Result<HttpResponseOk<WebauthnAssertResponse>, HttpError> {
+    let request = request_body.into_inner();
+    let failed =
+        WebauthnAssertResponse { success: false, user: None, role: None };
+    let expected = Expected {
+        rp_id: &request.rp_id,
+        origin: &request.origin,
+        challenge: &request.challenge,
+    };
+
+    let login = modify(ctx.context(), |store| {
+        let Some((login, _)) = webauthn::find(store, &request.credential_id)
+        else {
+            warn!(ctx.log, "Assertion for unknown WebAuthn credential");
+            return Ok((false, None));
+        };
+        let login = login.clone();
+        if request.user.as_ref().is_some_and(|user| *user != login) {
+            warn!(ctx.log, "WebAuthn credential does not belong to user");
+            return Ok((false, None));
+        }
+        let key = failure_key(&login);
+        check_locked_out(&ctx, &key)?;
+
+        let Some(entry) = store.get_mut(&login) else {
+            return Ok((false, None));
+        };
+        let handle_matches = request
+            .user_handle
+            .as_ref()
+            .map_or(true, |handle| *handle == entry.handle);
+        let Some(credential) = entry
+            .credentials
+            .iter_mut()
+            .find(|c| c.id == request.credential_id)
+        else {
+            return Ok((false, None));
+        };
+
+        let verified = (|| {
+            if !handle_matches {
+                return Err(String::from("user handle does not match"));
+            }
+            if credential.rp_id != request.rp_id {
+                return Err(format!(
+                    "credential is registered for {}",
+                    credential.rp_id
+                ));
+            }
+            webauthn::verify_assertion(
+                &expected,
+                credential,
+                &webauthn::decode(&request.client_data_json)?,
+                &webauthn::decode(&request.authenticator_data)?,
+                &webauthn::decode(&request.signature)?,
+                true,
+            )
+        })();
+
+        record_result(ctx.context(), &key, verified.is_ok());
+        match verified {
+            Ok(sign_count) => {
+                credential.sign_count = sign_count;
+                credential.last_used = Some(OffsetDateTime::now_utc());
+                Ok((true, Some(login)))
+            }
+            Err(e) => {
+                warn!(ctx.log, "WebAuthn login failed for {}: {}", login, e);
+                Ok((false, None))
+            }
+        }
+    })?;
+
+    let Some(login) = login else {
+        return Ok(HttpResponseOk(failed));
+    };
+    let config = &ctx.context().config;
+    let user_list =
+        users::load(Path::new(&config.users_file)).unwrap_or_else(|e| {
+            error!(ctx.log, "Failed to load {}: {}", config.users_file, e);
+            vec![]
+        });
+    let Some(account) = users::resolve(&user_list, &config.login_user, &login)
+    else {
+        warn!(ctx.log, "WebAuthn login for {}, who is not a UI user", login);
+        return Ok(HttpResponseOk(failed));
+    };
+    info!(ctx.log, "WebAuthn authentication succeeded for {}", login);
+    Ok(HttpResponseOk(WebauthnAssertResponse {
+        success: true,
+        user: Some(login),
+        role: Some(account.role),
+    }))
+}
+
+#[endpoint {
+method = POST,
+path = "/auth/webauthn/delete",
+}]
+pub async fn post_delete(
+    ctx: RequestContext<Context>,
+    request_body: TypedBody<WebauthnDeleteRequest>,
+) -> Result<HttpResponseUpdatedNoContent, HttpError> {
+    let WebauthnDeleteRequest { user, id } = request_body.into_inner();
+    let removed = modify(ctx.context(), |store| {
+        let Some(entry) = store.get_mut(&user) else {
+            return Ok((false, false));
+        };
+        let count = entry.credentials.len();
+        entry.credentials.retain(|c| c.id != id);
+        let removed = entry.credentials.len() != count;
+        if entry.credentials.is_empty() {
+            store.remove(&user);
+        }
+        Ok((removed, removed))
+    })?;
+    if !removed {
+        return Err(HttpError::for_not_found(
+            None,
+            format!("No such credential for {}", user),
+        ));
+    }
+    info!(ctx.log, "Removed WebAuthn credential for {}", user);
+    Ok(HttpResponseUpdatedNoContent())
+}
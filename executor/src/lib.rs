@@ -8,4 +8,10 @@
  * Copyright 2024 MNX Cloud, Inc.
  */
 
+pub mod api_tokens;
 pub mod endpoints;
+pub mod presets;
+pub mod shadow;
+pub mod totp;
+pub mod users;
+pub mod webauthn;
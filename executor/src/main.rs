@@ -11,7 +11,9 @@
 #[macro_use]
 extern crate slog;
 
-use smartos_shared::config::Config;
+use std::env;
+
+use smartos_shared::config::{self, Config};
 use smartos_ui_executor::{endpoints, endpoints::Context};
 
 use dropshot::{
@@ -23,10 +25,17 @@ use dropshot::{
 async fn main() -> Result<(), String> {
     let name = option_env!("CARGO_PKG_NAME").unwrap_or("?");
     let version = option_env!("CARGO_PKG_VERSION").unwrap_or("v?");
-    let config = Config::new(name);
+
+    // "config check" and "config dump"
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Some(command) = config::Command::parse(&args) {
+        return command.run(name);
+    }
+
+    let config = Config::load_or_report(name)?;
 
     let config_logging = ConfigLogging::File {
-        level: dropshot::ConfigLoggingLevel::Debug,
+        level: config.log_level.clone(),
         path: config.log_file.clone().into(),
         if_exists: dropshot::ConfigLoggingIfExists::Append,
     };
@@ -37,7 +46,23 @@ async fn main() -> Result<(), String> {
 
     let mut api = ApiDescription::new();
     api.register(endpoints::sysinfo::get_index)?;
-    api.register(endpoints::pwhash::get_index)?;
+
+    // /auth
+    api.register(endpoints::auth::post_verify)?;
+    api.register(endpoints::auth::post_password)?;
+    api.register(endpoints::totp::post_status)?;
+    api.register(endpoints::totp::post_enroll)?;
+    api.register(endpoints::totp::post_confirm)?;
+    api.register(endpoints::totp::post_verify)?;
+    api.register(endpoints::totp::post_disable)?;
+    api.register(endpoints::webauthn::post_credentials)?;
+    api.register(endpoints::webauthn::post_register)?;
+    api.register(endpoints::webauthn::post_assert)?;
+    api.register(endpoints::webauthn::post_delete)?;
+    api.register(endpoints::api_tokens::post_list)?;
+    api.register(endpoints::api_tokens::post_create)?;
+    api.register(endpoints::api_tokens::post_verify)?;
+    api.register(endpoints::api_tokens::post_delete)?;
 
     // /image
     api.register(endpoints::image::get_index)?;
@@ -70,17 +95,45 @@ async fn main() -> Result<(), String> {
     // /instance/{id}/start
     api.register(endpoints::instance::start_by_id)?;
 
+    // /instance/{id}/reboot
+    api.register(endpoints::instance::reboot_by_id)?;
+
+    // /instance/{id}/disks
+    api.register(endpoints::instance::post_disks_by_id)?;
+
+    // /instance/{id}/nics
+    api.register(endpoints::instance::post_nics_by_id)?;
+    // /instance/{id}/tags
+    api.register(endpoints::instance::post_tags_by_id)?;
+
+    // /instance/{id}/disk_usage
+    api.register(endpoints::instance::get_disk_usage_by_id)?;
+
     // /info/{id}
     api.register(endpoints::instance::info_by_id)?;
 
     // /nictag
     api.register(endpoints::nictag::get_index)?;
 
+    // /preset
+    api.register(endpoints::presets::get_index)?;
+    api.register(endpoints::presets::post_index)?;
+    api.register(endpoints::presets::delete_by_name)?;
+
     // /ping
     api.register(endpoints::get_ping)?;
 
+    // /health
+    api.register(endpoints::health::get_index)?;
+
     // /config
     api.register(endpoints::config::get_gz_index)?;
+    api.register(endpoints::config::post_tls)?;
+
+    // /metrics
+    if config.metrics_enabled {
+        api.register(endpoints::metrics::get_index)?;
+    }
 
     info!(log, "{} v{}", name, version);
 
@@ -0,0 +1,52 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+//! Provisioning presets, kept in `PRESETS_FILE` as a JSON object keyed by
+//! name:
+//!
+//! ```json
+//! {
+//!   "small-zone": {
+//!     "name": "small-zone",
+//!     "ram": 1024,
+//!     "quota": 10,
+//!     "cpu_cap": 100,
+//!     "nic_tag": null,
+//!     "resolvers": []
+//!   }
+//! }
+//! ```
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::shadow::write_private;
+
+use smartos_shared::preset::Preset;
+
+pub type Store = BTreeMap<String, Preset>;
+
+/// Read the presets file, a missing file has no presets
+pub fn load(path: &Path) -> io::Result<Store> {
+    match fs::read_to_string(path) {
+        Ok(contents) if contents.trim().is_empty() => Ok(Store::new()),
+        Ok(contents) => Ok(serde_json::from_str(&contents)?),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Store::new()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Write the presets file, creating it (and its directory) if needed
+pub fn save(path: &Path, store: &Store) -> io::Result<()> {
+    let contents = serde_json::to_string_pretty(store)?;
+    write_private(path, &contents)
+}
@@ -0,0 +1,229 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+//! Reading and updating the shadow(5) password file. Each line consists of
+//! nine colon separated fields, the first being the login name and the second
+//! the crypt hash (or a marker such as `*LK*` or `NP` for locked accounts).
+
+use std::fs::{self, DirBuilder, File, OpenOptions};
+use std::io::{self, Write};
+use std::os::fd::AsRawFd;
+use std::os::unix::fs::{chown, DirBuilderExt, MetadataExt, OpenOptionsExt};
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Return the password hash for `user`, or [None] if the user doesn't exist or
+/// the account has no usable password.
+pub fn find_hash<'a>(contents: &'a str, user: &str) -> Option<&'a str> {
+    for line in contents.lines() {
+        let mut fields = line.split(':');
+        if fields.next() != Some(user) {
+            continue;
+        }
+        return match fields.next() {
+            Some(hash) if hash.starts_with('$') => Some(hash),
+            _ => None,
+        };
+    }
+    None
+}
+
+/// Replace the password hash for `user` and set the date of the last change
+/// (in days since the epoch). All other lines and fields are left untouched.
+/// Returns [None] if the user has no entry.
+pub fn set_hash(
+    contents: &str,
+    user: &str,
+    hash: &str,
+    lastchg: u64,
+) -> Option<String> {
+    let mut found = false;
+    let mut output = String::with_capacity(contents.len() + hash.len());
+    for line in contents.split_inclusive('\n') {
+        let (entry, ending) = match line.strip_suffix('\n') {
+            Some(entry) => (entry, "\n"),
+            None => (line, ""),
+        };
+        let mut fields: Vec<&str> = entry.split(':').collect();
+        if found || fields.len() < 3 || fields[0] != user {
+            output.push_str(line);
+            continue;
+        }
+        let lastchg = lastchg.to_string();
+        fields[1] = hash;
+        fields[2] = &lastchg;
+        output.push_str(&fields.join(":"));
+        output.push_str(ending);
+        found = true;
+    }
+    found.then_some(output)
+}
+
+/// Days since the epoch, the unit shadow(5) uses for `lastchg`
+pub fn days_since_epoch() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() / 86400)
+        .unwrap_or_default()
+}
+
+/// Set `root_shadow` in a loader.conf(5) file, replacing an existing
+/// definition or appending one.
+pub fn set_root_shadow(contents: &str, hash: &str) -> String {
+    let setting = format!("root_shadow=\"{}\"", hash);
+    let mut replaced = false;
+    let mut lines: Vec<String> = contents
+        .lines()
+        .map(|line| {
+            let key = line.trim_start().split('=').next().unwrap_or_default();
+            if key.trim_end() == "root_shadow" {
+                replaced = true;
+                setting.clone()
+            } else {
+                line.to_string()
+            }
+        })
+        .collect();
+    if !replaced {
+        lines.push(setting);
+    }
+    let mut output = lines.join("\n");
+    output.push('\n');
+    output
+}
+
+/// An exclusive lock on the `.pwd.lock` file next to the shadow file, the same
+/// lock taken by lckpwdf(3C) so that passwd(1) and friends are excluded while
+/// the file is being rewritten. Released when dropped.
+pub struct ShadowLock {
+    _file: File,
+}
+
+impl ShadowLock {
+    /// Give up waiting for the lock after this long, as lckpwdf(3C) does
+    const TIMEOUT: Duration = Duration::from_secs(15);
+
+    pub fn acquire(shadow_path: &Path) -> io::Result<Self> {
+        let dir = shadow_path.parent().unwrap_or(Path::new("/"));
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .mode(0o600)
+            .open(dir.join(".pwd.lock"))?;
+
+        // SAFETY: flock is a plain C struct for which all zeroes is valid
+        let mut lock: libc::flock = unsafe { std::mem::zeroed() };
+        // F_WRLCK is a c_int on some platforms and a c_short on others
+        #[allow(clippy::unnecessary_cast)]
+        {
+            lock.l_type = libc::F_WRLCK as libc::c_short;
+            lock.l_whence = libc::SEEK_SET as libc::c_short;
+        }
+
+        let started = Instant::now();
+        loop {
+            // SAFETY: the descriptor is open for the duration of the call and
+            // `lock` is a valid flock struct
+            let ret =
+                unsafe { libc::fcntl(file.as_raw_fd(), libc::F_SETLK, &lock) };
+            if ret == 0 {
+                return Ok(Self { _file: file });
+            }
+            let error = io::Error::last_os_error();
+            if !matches!(
+                error.raw_os_error(),
+                Some(libc::EAGAIN | libc::EACCES)
+            ) || started.elapsed() > Self::TIMEOUT
+            {
+                return Err(error);
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        }
+    }
+}
+
+/// Replace the file at `path` with `contents` without ever leaving a partially
+/// written file in place. The new file is written alongside the original with
+/// the same mode and ownership, flushed to disk and then renamed over it.
+pub fn write_atomic(path: &Path, contents: &str) -> io::Result<()> {
+    let metadata = fs::metadata(path)?;
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".smartos_ui.tmp");
+    let tmp_path = path.with_file_name(tmp_name);
+
+    let result = (|| {
+        let mut tmp = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(metadata.mode() & 0o7777)
+            .open(&tmp_path)?;
+        tmp.set_permissions(metadata.permissions())?;
+        // Ownership can only be changed by root, which is what the executor
+        // runs as in production. In development keep going as the current
+        // user.
+        if let Err(e) =
+            chown(&tmp_path, Some(metadata.uid()), Some(metadata.gid()))
+        {
+            if e.kind() != io::ErrorKind::PermissionDenied {
+                return Err(e);
+            }
+        }
+        tmp.write_all(contents.as_bytes())?;
+        tmp.sync_all()?;
+        fs::rename(&tmp_path, path)?;
+        if let Some(dir) = path.parent() {
+            File::open(dir)?.sync_all()?;
+        }
+        Ok(())
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    result
+}
+
+/// Like [write_atomic], but if the file doesn't exist yet it is created (along
+/// with its directory) readable only by the owner.
+pub fn write_private(path: &Path, contents: &str) -> io::Result<()> {
+    if !path.exists() {
+        if let Some(dir) = path.parent() {
+            DirBuilder::new().recursive(true).mode(0o700).create(dir)?;
+        }
+        OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(path)?;
+    }
+    write_atomic(path, contents)
+}
+
+/// Set the password hash for `user` in the shadow file at `path` while holding
+/// the password file lock.
+pub fn update_hash(
+    path: &Path,
+    user: &str,
+    hash: &str,
+    lastchg: u64,
+) -> io::Result<()> {
+    let _lock = ShadowLock::acquire(path)?;
+    let contents = fs::read_to_string(path)?;
+    let updated =
+        set_hash(&contents, user, hash, lastchg).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("No entry for {} in {}", user, path.display()),
+            )
+        })?;
+    write_atomic(path, &updated)
+}
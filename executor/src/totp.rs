@@ -0,0 +1,291 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+//! RFC 6238 time-based one time passwords (TOTP) for UI logins, using the
+//! defaults authenticator apps expect: HMAC-SHA1, 6 digits and a 30 second
+//! step.
+//!
+//! Secrets and hashed recovery codes are kept in `TOTP_FILE`, a JSON object
+//! keyed by login that only root can read:
+//!
+//! ```json
+//! {
+//!   "root": {
+//!     "secret": "JBSWY3DPEHPK3PXP...",
+//!     "enabled": true,
+//!     "recovery_codes": ["<sha256 hex>", "..."],
+//!     "last_step": 58000000
+//!   }
+//! }
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::shadow::write_private;
+
+use smartos_shared::auth::constant_time_eq;
+
+use ring::digest::{digest, SHA256};
+use ring::hmac;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+
+/// Length of each time step in seconds
+pub const STEP: u64 = 30;
+
+/// Number of digits in a code
+pub const DIGITS: u32 = 6;
+
+/// Steps either side of the current one that are also accepted, to allow for
+/// clock drift and slow typing
+const SKEW: u64 = 1;
+
+/// Bytes of randomness in a secret (160 bits, as recommended by RFC 4226)
+const SECRET_BYTES: usize = 20;
+
+/// Number of recovery codes generated at enrollment
+pub const RECOVERY_CODES: usize = 10;
+
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// Alphabet for recovery codes, without characters that are easily confused
+/// (0, i, l and o)
+const RECOVERY_ALPHABET: &[u8; 32] = b"abcdefghjkmnpqrstuvwxyz123456789";
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct Entry {
+    /// Base32 encoded shared secret
+    pub secret: String,
+    /// False until the user has confirmed enrollment with a valid code
+    #[serde(default)]
+    pub enabled: bool,
+    /// SHA-256 hashes of unused recovery codes
+    #[serde(default)]
+    pub recovery_codes: Vec<String>,
+    /// Step of the last accepted code, codes can't be used twice
+    #[serde(default)]
+    pub last_step: u64,
+}
+
+pub type Entries = BTreeMap<String, Entry>;
+
+/// Unpadded RFC 4648 base32
+pub fn base32_encode(data: &[u8]) -> String {
+    let mut out = String::with_capacity((data.len() * 8 + 4) / 5);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for byte in data {
+        buffer = (buffer << 8) | u32::from(*byte);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(BASE32_ALPHABET[(buffer >> bits) as usize & 31] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(BASE32_ALPHABET[(buffer << (5 - bits)) as usize & 31] as char);
+    }
+    out
+}
+
+/// Decode base32, ignoring case, padding, spaces and dashes
+pub fn base32_decode(encoded: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(encoded.len() * 5 / 8);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for c in encoded.bytes() {
+        if matches!(c, b'=' | b' ' | b'-') {
+            continue;
+        }
+        let c = c.to_ascii_uppercase();
+        let value = BASE32_ALPHABET.iter().position(|a| *a == c)? as u32;
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+    }
+    Some(out)
+}
+
+/// RFC 4226 HOTP value for `counter`, truncated to `digits` digits
+pub fn hotp(secret: &[u8], counter: u64, digits: u32) -> u32 {
+    let key = hmac::Key::new(hmac::HMAC_SHA1_FOR_LEGACY_USE_ONLY, secret);
+    let tag = hmac::sign(&key, &counter.to_be_bytes());
+    let hash = tag.as_ref();
+    let offset = (hash[hash.len() - 1] & 0xf) as usize;
+    let binary = u32::from_be_bytes([
+        hash[offset] & 0x7f,
+        hash[offset + 1],
+        hash[offset + 2],
+        hash[offset + 3],
+    ]);
+    binary % 10u32.pow(digits)
+}
+
+/// The code for the step containing `time` (seconds since the epoch)
+pub fn code_at(secret: &[u8], time: u64) -> String {
+    format!(
+        "{:0width$}",
+        hotp(secret, time / STEP, DIGITS),
+        width = DIGITS as usize
+    )
+}
+
+/// Check `code` against the steps around `time`. Steps at or before
+/// `last_step` have already been used and are refused. Returns the matching
+/// step.
+pub fn verify(
+    secret: &[u8],
+    code: &str,
+    time: u64,
+    last_step: u64,
+) -> Option<u64> {
+    let code = code.trim();
+    if code.len() != DIGITS as usize
+        || !code.bytes().all(|b| b.is_ascii_digit())
+    {
+        return None;
+    }
+    let current = time / STEP;
+    let mut matched = None;
+    // Check every step in the window so the time taken doesn't reveal which
+    // one matched
+    for step in current.saturating_sub(SKEW)..=current + SKEW {
+        let expected = format!(
+            "{:0width$}",
+            hotp(secret, step, DIGITS),
+            width = DIGITS as usize
+        );
+        if constant_time_eq(expected.as_bytes(), code.as_bytes())
+            && step > last_step
+        {
+            matched = Some(step);
+        }
+    }
+    matched
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// A new base32 encoded secret
+pub fn generate_secret() -> io::Result<String> {
+    let mut secret = [0u8; SECRET_BYTES];
+    SystemRandom::new()
+        .fill(&mut secret)
+        .map_err(|_| io::Error::new(io::ErrorKind::Other, "RNG failure"))?;
+    Ok(base32_encode(&secret))
+}
+
+/// New recovery codes, formatted as `xxxxx-xxxxx`
+pub fn generate_recovery_codes() -> io::Result<Vec<String>> {
+    let rng = SystemRandom::new();
+    let mut codes = Vec::with_capacity(RECOVERY_CODES);
+    for _ in 0..RECOVERY_CODES {
+        let mut bytes = [0u8; 10];
+        rng.fill(&mut bytes)
+            .map_err(|_| io::Error::new(io::ErrorKind::Other, "RNG failure"))?;
+        let mut code: String = bytes
+            .iter()
+            .map(|b| RECOVERY_ALPHABET[(*b & 31) as usize] as char)
+            .collect();
+        code.insert(5, '-');
+        codes.push(code);
+    }
+    Ok(codes)
+}
+
+/// Hash of a recovery code as stored in [Entry::recovery_codes]. Case, spaces
+/// and dashes are ignored.
+pub fn hash_recovery_code(code: &str) -> String {
+    let normalized: String = code
+        .chars()
+        .filter(|c| !matches!(c, ' ' | '-'))
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    digest(&SHA256, normalized.as_bytes()).as_ref().iter().fold(
+        String::with_capacity(64),
+        |mut hex, b| {
+            let _ = write!(hex, "{:02x}", b);
+            hex
+        },
+    )
+}
+
+/// Remove `code` from the entry's unused recovery codes, returns false if it
+/// isn't one of them
+pub fn use_recovery_code(entry: &mut Entry, code: &str) -> bool {
+    let hash = hash_recovery_code(code);
+    let position = entry
+        .recovery_codes
+        .iter()
+        .position(|h| constant_time_eq(h.as_bytes(), hash.as_bytes()));
+    if let Some(position) = position {
+        entry.recovery_codes.remove(position);
+        return true;
+    }
+    false
+}
+
+/// How a code passed to [check] was accepted
+#[derive(Debug, PartialEq)]
+pub enum Accepted {
+    Totp,
+    /// A recovery code, which has now been used up
+    RecoveryCode,
+}
+
+/// Check a TOTP code, or a recovery code if `allow_recovery` is set, against
+/// `entry`. The entry is updated so the same code can't be used again and
+/// must be saved if this returns [Some].
+pub fn check(
+    entry: &mut Entry,
+    code: &str,
+    time: u64,
+    allow_recovery: bool,
+) -> Option<Accepted> {
+    let secret = base32_decode(&entry.secret)?;
+    if let Some(step) = verify(&secret, code, time, entry.last_step) {
+        entry.last_step = step;
+        return Some(Accepted::Totp);
+    }
+    if allow_recovery && use_recovery_code(entry, code) {
+        return Some(Accepted::RecoveryCode);
+    }
+    None
+}
+
+/// Read the TOTP file, a missing file has no entries
+pub fn load(path: &Path) -> io::Result<Entries> {
+    match fs::read_to_string(path) {
+        Ok(contents) if contents.trim().is_empty() => Ok(Entries::new()),
+        Ok(contents) => Ok(serde_json::from_str(&contents)?),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Entries::new()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Write the TOTP file, creating it (and its directory) readable only by the
+/// owner if needed
+pub fn save(path: &Path, entries: &Entries) -> io::Result<()> {
+    let contents = serde_json::to_string_pretty(entries)?;
+    write_private(path, &contents)
+}
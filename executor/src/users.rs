@@ -0,0 +1,120 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+//! The UI user database, a JSON array of users in `USERS_FILE`. Each user
+//! either authenticates against an account in the shadow file
+//! (`shadow_user`) or has its own crypt hash (`password_hash`), e.g.
+//!
+//! ```json
+//! [
+//!   { "login": "alice", "role": "operator", "shadow_user": "alice" },
+//!   { "login": "bob", "role": "viewer", "password_hash": "$6$..." }
+//! ]
+//! ```
+//!
+//! The configured `LOGIN_USER` is always an admin that authenticates against
+//! its own shadow entry, whether or not it is listed here.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::shadow::write_atomic;
+
+use smartos_shared::auth::Role;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct User {
+    pub login: String,
+    pub role: Role,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shadow_user: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password_hash: Option<String>,
+}
+
+/// Where a user's password hash lives
+#[derive(Debug, PartialEq)]
+pub enum Credential {
+    /// The named account in the shadow file
+    Shadow(String),
+    /// A hash stored in the users file
+    Local(String),
+}
+
+/// A user that is able to log in
+#[derive(Debug, PartialEq)]
+pub struct Account {
+    pub role: Role,
+    pub credential: Credential,
+}
+
+pub fn parse(contents: &str) -> Result<Vec<User>, serde_json::Error> {
+    serde_json::from_str(contents)
+}
+
+/// Read the users file, a missing file is the same as an empty one
+pub fn load(path: &Path) -> io::Result<Vec<User>> {
+    match fs::read_to_string(path) {
+        Ok(contents) => parse(&contents).map_err(io::Error::from),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(vec![]),
+        Err(e) => Err(e),
+    }
+}
+
+/// Find the [Account] for `login`, the `login_user` is always an admin backed
+/// by the shadow file. Users without a credential can't log in.
+pub fn resolve(
+    users: &[User],
+    login_user: &str,
+    login: &str,
+) -> Option<Account> {
+    if login == login_user {
+        return Some(Account {
+            role: Role::Admin,
+            credential: Credential::Shadow(login_user.to_string()),
+        });
+    }
+    let user = users.iter().find(|user| user.login == login)?;
+    let credential = match (&user.shadow_user, &user.password_hash) {
+        (Some(shadow_user), _) => Credential::Shadow(shadow_user.clone()),
+        (None, Some(hash)) => Credential::Local(hash.clone()),
+        (None, None) => return None,
+    };
+    Some(Account { role: user.role, credential })
+}
+
+/// Replace the stored hash for a user with a [Credential::Local] password
+pub fn set_password_hash(
+    path: &Path,
+    login: &str,
+    hash: &str,
+) -> io::Result<()> {
+    let mut users = load(path)?;
+    let user = users
+        .iter_mut()
+        .find(|user| user.login == login && user.password_hash.is_some())
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!(
+                    "No local password for {} in {}",
+                    login,
+                    path.display()
+                ),
+            )
+        })?;
+    user.password_hash = Some(hash.to_string());
+    let mut contents = serde_json::to_string_pretty(&users)?;
+    contents.push('\n');
+    write_atomic(path, &contents)
+}
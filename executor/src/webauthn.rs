@@ -0,0 +1,513 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+//! WebAuthn (passkeys and security keys) for UI logins. Only what is needed
+//! to verify registrations and assertions is implemented: attestation
+//! statements aren't checked, the UI asks for `"none"`, and credentials must
+//! use ES256, EdDSA (Ed25519) or RS256 keys.
+//!
+//! Credentials are kept in `WEBAUTHN_FILE`, a JSON object keyed by login:
+//!
+//! ```json
+//! {
+//!   "root": {
+//!     "handle": "<base64url user handle>",
+//!     "credentials": [{
+//!       "id": "<base64url credential ID>",
+//!       "name": "YubiKey",
+//!       "public_key": "<base64url COSE_Key>",
+//!       "rp_id": "gz.example.com",
+//!       "sign_count": 12,
+//!       "created": "2025-01-01T00:00:00Z",
+//!       "last_used": "2025-01-02T00:00:00Z"
+//!     }]
+//!   }
+//! }
+//! ```
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::shadow::write_private;
+
+use smartos_shared::auth::WebauthnCredential;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use ring::digest::{digest, SHA256};
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::signature::{
+    RsaPublicKeyComponents, UnparsedPublicKey, ECDSA_P256_SHA256_ASN1, ED25519,
+    RSA_PKCS1_2048_8192_SHA256,
+};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+/// COSE algorithm identifiers, in the order they are offered to browsers
+pub const ES256: i64 = -7;
+pub const EDDSA: i64 = -8;
+pub const RS256: i64 = -257;
+
+/// User present
+const FLAG_UP: u8 = 0x01;
+/// User verified, by PIN or biometrics
+const FLAG_UV: u8 = 0x04;
+/// Attested credential data included
+const FLAG_AT: u8 = 0x40;
+
+/// Deepest nesting accepted when decoding CBOR, authenticators only need 2
+const MAX_DEPTH: usize = 16;
+
+/// Bytes of randomness in a user handle
+const HANDLE_BYTES: usize = 16;
+
+pub fn encode(data: &[u8]) -> String {
+    URL_SAFE_NO_PAD.encode(data)
+}
+
+/// Decode base64url, with or without padding
+pub fn decode(encoded: &str) -> Result<Vec<u8>, String> {
+    URL_SAFE_NO_PAD
+        .decode(encoded.trim_end_matches('='))
+        .map_err(|e| format!("invalid base64url: {}", e))
+}
+
+/// A decoded CBOR data item. Floats, indefinite lengths and integers outside
+/// the range of an [i64] aren't supported as authenticators don't use them.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Cbor {
+    Int(i64),
+    Bytes(Vec<u8>),
+    Text(String),
+    Array(Vec<Cbor>),
+    Map(Vec<(Cbor, Cbor)>),
+    Bool(bool),
+    Null,
+}
+
+impl Cbor {
+    /// The value for `key` if this is a map
+    pub fn get(&self, key: &Cbor) -> Option<&Cbor> {
+        let Cbor::Map(entries) = self else {
+            return None;
+        };
+        entries.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Cbor::Int(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Cbor::Bytes(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+/// Decode the CBOR data item at the start of `data`, returning it and the
+/// number of bytes it took up
+pub fn decode_cbor(data: &[u8]) -> Result<(Cbor, usize), String> {
+    let mut reader = Reader { data, pos: 0 };
+    let item = reader.item(0)?;
+    Ok((item, reader.pos))
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.data.len())
+            .ok_or("truncated CBOR")?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn argument(&mut self, info: u8) -> Result<u64, String> {
+        let len = match info {
+            0..=23 => return Ok(u64::from(info)),
+            24 => 1,
+            25 => 2,
+            26 => 4,
+            27 => 8,
+            _ => return Err(String::from("unsupported CBOR length")),
+        };
+        Ok(self
+            .take(len)?
+            .iter()
+            .fold(0, |value, byte| value << 8 | u64::from(*byte)))
+    }
+
+    /// A length or item count. Every item takes at least a byte, so anything
+    /// longer than what's left can be refused before allocating for it.
+    fn length(&mut self, info: u8) -> Result<usize, String> {
+        usize::try_from(self.argument(info)?)
+            .ok()
+            .filter(|len| *len <= self.data.len() - self.pos)
+            .ok_or_else(|| String::from("truncated CBOR"))
+    }
+
+    fn item(&mut self, depth: usize) -> Result<Cbor, String> {
+        if depth > MAX_DEPTH {
+            return Err(String::from("CBOR nested too deeply"));
+        }
+        let initial = self.take(1)?[0];
+        let (major, info) = (initial >> 5, initial & 0x1f);
+        let int = |value: u64| {
+            i64::try_from(value).map_err(|_| String::from("CBOR int too large"))
+        };
+        Ok(match major {
+            0 => Cbor::Int(int(self.argument(info)?)?),
+            1 => Cbor::Int(-1 - int(self.argument(info)?)?),
+            2 => {
+                let len = self.length(info)?;
+                Cbor::Bytes(self.take(len)?.to_vec())
+            }
+            3 => {
+                let len = self.length(info)?;
+                let text = std::str::from_utf8(self.take(len)?)
+                    .map_err(|_| String::from("invalid UTF-8 in CBOR"))?;
+                Cbor::Text(String::from(text))
+            }
+            4 => {
+                let len = self.length(info)?;
+                let mut items = Vec::with_capacity(len);
+                for _ in 0..len {
+                    items.push(self.item(depth + 1)?);
+                }
+                Cbor::Array(items)
+            }
+            5 => {
+                let len = self.length(info)?;
+                let mut entries = Vec::with_capacity(len);
+                for _ in 0..len {
+                    let key = self.item(depth + 1)?;
+                    entries.push((key, self.item(depth + 1)?));
+                }
+                Cbor::Map(entries)
+            }
+            // Tags don't change how anything here is interpreted
+            6 => {
+                self.argument(info)?;
+                self.item(depth + 1)?
+            }
+            _ => match info {
+                20 => Cbor::Bool(false),
+                21 => Cbor::Bool(true),
+                22 => Cbor::Null,
+                _ => return Err(String::from("unsupported CBOR simple value")),
+            },
+        })
+    }
+}
+
+/// A credential's public key
+#[derive(Debug, PartialEq)]
+pub enum PublicKey {
+    /// Uncompressed P-256 point
+    Es256(Vec<u8>),
+    Ed25519(Vec<u8>),
+    Rs256 {
+        n: Vec<u8>,
+        e: Vec<u8>,
+    },
+}
+
+impl PublicKey {
+    /// Parse a COSE_Key (RFC 9053)
+    pub fn from_cose(encoded: &[u8]) -> Result<Self, String> {
+        let (key, _) = decode_cbor(encoded)?;
+        let int =
+            |label: i64| key.get(&Cbor::Int(label)).and_then(Cbor::as_int);
+        let bytes = |label: i64, len: Option<usize>| {
+            key.get(&Cbor::Int(label))
+                .and_then(Cbor::as_bytes)
+                .filter(|b| len.map_or(!b.is_empty(), |len| b.len() == len))
+                .map(<[u8]>::to_vec)
+                .ok_or_else(|| format!("invalid COSE key parameter {}", label))
+        };
+        let kty = int(1).ok_or("COSE key has no key type")?;
+        let alg = int(3).ok_or("COSE key has no algorithm")?;
+        match (kty, alg, int(-1)) {
+            // EC2 on curve P-256
+            (2, ES256, Some(1)) => {
+                let mut point = vec![0x04];
+                point.extend(bytes(-2, Some(32))?);
+                point.extend(bytes(-3, Some(32))?);
+                Ok(PublicKey::Es256(point))
+            }
+            // OKP on curve Ed25519
+            (1, EDDSA, Some(6)) => Ok(PublicKey::Ed25519(bytes(-2, Some(32))?)),
+            (3, RS256, _) => Ok(PublicKey::Rs256 {
+                n: bytes(-1, None)?,
+                e: bytes(-2, None)?,
+            }),
+            _ => Err(format!(
+                "unsupported key type {} with algorithm {}",
+                kty, alg
+            )),
+        }
+    }
+
+    pub fn verify(&self, message: &[u8], signature: &[u8]) -> bool {
+        match self {
+            PublicKey::Es256(point) => {
+                UnparsedPublicKey::new(&ECDSA_P256_SHA256_ASN1, point)
+                    .verify(message, signature)
+                    .is_ok()
+            }
+            PublicKey::Ed25519(key) => UnparsedPublicKey::new(&ED25519, key)
+                .verify(message, signature)
+                .is_ok(),
+            PublicKey::Rs256 { n, e } => RsaPublicKeyComponents { n, e }
+                .verify(&RSA_PKCS1_2048_8192_SHA256, message, signature)
+                .is_ok(),
+        }
+    }
+}
+
+/// The parts of the authenticator data that are checked
+#[derive(Debug)]
+pub struct AuthenticatorData {
+    pub rp_id_hash: Vec<u8>,
+    pub flags: u8,
+    pub sign_count: u32,
+    /// Credential ID and COSE_Key, included at registration
+    pub credential: Option<(Vec<u8>, Vec<u8>)>,
+}
+
+impl AuthenticatorData {
+    pub fn parse(data: &[u8]) -> Result<Self, String> {
+        let mut reader = Reader { data, pos: 0 };
+        let rp_id_hash = reader.take(32)?.to_vec();
+        let flags = reader.take(1)?[0];
+        let sign_count = reader
+            .take(4)?
+            .iter()
+            .fold(0, |value, byte| value << 8 | u32::from(*byte));
+        let credential = if flags & FLAG_AT != 0 {
+            // AAGUID, which identifies the model of authenticator
+            reader.take(16)?;
+            let len = reader.take(2)?;
+            let len = usize::from(len[0]) << 8 | usize::from(len[1]);
+            let id = reader.take(len)?.to_vec();
+            let (_, key_len) = decode_cbor(&data[reader.pos..])?;
+            Some((id, reader.take(key_len)?.to_vec()))
+        } else {
+            None
+        };
+        Ok(Self { rp_id_hash, flags, sign_count, credential })
+    }
+}
+
+/// What the UI expects a ceremony to have been performed for
+pub struct Expected<'a> {
+    pub rp_id: &'a str,
+    /// e.g. `https://gz.example.com:4443`
+    pub origin: &'a str,
+    /// Base64url challenge as sent to the browser
+    pub challenge: &'a str,
+}
+
+#[derive(Deserialize)]
+struct ClientData {
+    #[serde(rename = "type")]
+    kind: String,
+    challenge: String,
+    origin: String,
+}
+
+impl Expected<'_> {
+    fn check_client_data(&self, raw: &[u8], kind: &str) -> Result<(), String> {
+        let client_data: ClientData = serde_json::from_slice(raw)
+            .map_err(|e| format!("invalid client data: {}", e))?;
+        if client_data.kind != kind {
+            return Err(format!("unexpected ceremony {}", client_data.kind));
+        }
+        if client_data.challenge.trim_end_matches('=') != self.challenge {
+            return Err(String::from("challenge does not match"));
+        }
+        if client_data.origin != self.origin {
+            return Err(format!(
+                "origin {} does not match {}",
+                client_data.origin, self.origin
+            ));
+        }
+        Ok(())
+    }
+
+    fn check_authenticator_data(
+        &self,
+        auth_data: &AuthenticatorData,
+        require_uv: bool,
+    ) -> Result<(), String> {
+        if auth_data.rp_id_hash
+            != digest(&SHA256, self.rp_id.as_bytes()).as_ref()
+        {
+            return Err(format!("credential is not for {}", self.rp_id));
+        }
+        if auth_data.flags & FLAG_UP == 0 {
+            return Err(String::from("user presence was not confirmed"));
+        }
+        if require_uv && auth_data.flags & FLAG_UV == 0 {
+            return Err(String::from("user was not verified"));
+        }
+        Ok(())
+    }
+}
+
+/// A credential that has passed registration checks
+#[derive(Debug)]
+pub struct Registration {
+    pub id: Vec<u8>,
+    /// COSE_Key
+    pub public_key: Vec<u8>,
+    pub sign_count: u32,
+}
+
+/// Check the result of `navigator.credentials.create()`
+pub fn verify_registration(
+    expected: &Expected,
+    client_data_json: &[u8],
+    attestation_object: &[u8],
+) -> Result<Registration, String> {
+    expected.check_client_data(client_data_json, "webauthn.create")?;
+    let (attestation, _) = decode_cbor(attestation_object)?;
+    let auth_data = attestation
+        .get(&Cbor::Text(String::from("authData")))
+        .and_then(Cbor::as_bytes)
+        .ok_or("attestation has no authenticator data")?;
+    let auth_data = AuthenticatorData::parse(auth_data)?;
+    expected.check_authenticator_data(&auth_data, false)?;
+    let (id, public_key) =
+        auth_data.credential.ok_or("attestation has no credential")?;
+    PublicKey::from_cose(&public_key)?;
+    Ok(Registration { id, public_key, sign_count: auth_data.sign_count })
+}
+
+/// Check the result of `navigator.credentials.get()` against a stored
+/// `credential`. Returns the new signature counter.
+pub fn verify_assertion(
+    expected: &Expected,
+    credential: &Credential,
+    client_data_json: &[u8],
+    authenticator_data: &[u8],
+    signature: &[u8],
+    require_uv: bool,
+) -> Result<u32, String> {
+    expected.check_client_data(client_data_json, "webauthn.get")?;
+    let auth_data = AuthenticatorData::parse(authenticator_data)?;
+    expected.check_authenticator_data(&auth_data, require_uv)?;
+
+    let key = PublicKey::from_cose(&decode(&credential.public_key)?)?;
+    let mut message = authenticator_data.to_vec();
+    message.extend_from_slice(digest(&SHA256, client_data_json).as_ref());
+    if !key.verify(&message, signature) {
+        return Err(String::from("invalid signature"));
+    }
+
+    // Authenticators without a counter always send 0, otherwise it must go
+    // up or the credential may have been copied
+    if (auth_data.sign_count != 0 || credential.sign_count != 0)
+        && auth_data.sign_count <= credential.sign_count
+    {
+        return Err(String::from(
+            "signature counter did not increase, the authenticator may have \
+             been cloned",
+        ));
+    }
+    Ok(auth_data.sign_count)
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Credential {
+    /// Base64url credential ID
+    pub id: String,
+    pub name: String,
+    /// Base64url COSE_Key
+    pub public_key: String,
+    pub rp_id: String,
+    #[serde(default)]
+    pub sign_count: u32,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created: OffsetDateTime,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub last_used: Option<OffsetDateTime>,
+}
+
+impl From<&Credential> for WebauthnCredential {
+    fn from(credential: &Credential) -> Self {
+        Self {
+            id: credential.id.clone(),
+            name: credential.name.clone(),
+            rp_id: credential.rp_id.clone(),
+            created: credential.created,
+            last_used: credential.last_used,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct UserCredentials {
+    /// Base64url user handle, returned by authenticators with discoverable
+    /// credentials
+    pub handle: String,
+    #[serde(default)]
+    pub credentials: Vec<Credential>,
+}
+
+pub type Store = BTreeMap<String, UserCredentials>;
+
+/// The login a credential ID is registered to, and the credential
+pub fn find<'a>(
+    store: &'a Store,
+    id: &str,
+) -> Option<(&'a String, &'a Credential)> {
+    store.iter().find_map(|(login, user)| {
+        user.credentials.iter().find(|c| c.id == id).map(|c| (login, c))
+    })
+}
+
+/// A new random user handle
+pub fn generate_handle() -> io::Result<String> {
+    let mut handle = [0u8; HANDLE_BYTES];
+    SystemRandom::new()
+        .fill(&mut handle)
+        .map_err(|_| io::Error::new(io::ErrorKind::Other, "RNG failure"))?;
+    Ok(encode(&handle))
+}
+
+/// Read the credentials file, a missing file has no credentials
+pub fn load(path: &Path) -> io::Result<Store> {
+    match fs::read_to_string(path) {
+        Ok(contents) if contents.trim().is_empty() => Ok(Store::new()),
+        Ok(contents) => Ok(serde_json::from_str(&contents)?),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Store::new()),
+        Err(e) => Err(e),
+    }
+}
+
+pub fn save(path: &Path, store: &Store) -> io::Result<()> {
+    let contents = serde_json::to_string_pretty(store)?;
+    write_private(path, &contents)
+}
@@ -0,0 +1,51 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+use smartos_ui_executor::api_tokens::{
+    find, generate, hash, parse_id, Store, Token, PREFIX,
+};
+
+use smartos_shared::auth::Scope;
+use time::OffsetDateTime;
+
+#[test]
+fn test_generate_and_find() {
+    let (id, secret) = generate().expect("generate");
+    assert!(secret.starts_with(PREFIX));
+    assert_eq!(parse_id(&secret), Some(id.as_str()));
+    assert_ne!(generate().expect("generate").1, secret);
+
+    let mut store = Store::new();
+    store.insert(
+        id.clone(),
+        Token {
+            name: String::from("ci"),
+            user: String::from("olive"),
+            hash: hash(&secret),
+            scopes: vec![Scope::Read],
+            created: OffsetDateTime::now_utc(),
+            last_used: None,
+        },
+    );
+
+    let (found, token) = find(&store, &secret).expect("find");
+    assert_eq!(found, id);
+    assert_eq!(token.user, "olive");
+
+    // Right ID, wrong secret
+    let mut wrong = secret.clone();
+    let last = if wrong.ends_with('0') { "1" } else { "0" };
+    wrong.replace_range(wrong.len() - 1.., last);
+    assert!(find(&store, &wrong).is_none());
+
+    assert!(find(&store, "").is_none());
+    assert!(parse_id("sui_abc_def").is_none());
+    assert!(parse_id(&secret[PREFIX.len()..]).is_none());
+}
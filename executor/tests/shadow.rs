@@ -0,0 +1,77 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+use smartos_ui_executor::endpoints::auth::check_password;
+use smartos_ui_executor::shadow::{
+    find_hash, set_hash, set_root_shadow, update_hash,
+};
+
+const SHADOW: &str = include_str!("../../test/data/shadow");
+
+#[test]
+fn test_find_hash() {
+    assert!(find_hash(SHADOW, "root").unwrap().starts_with("$5$"));
+    assert_eq!(find_hash(SHADOW, "nobody"), None);
+    assert_eq!(find_hash(SHADOW, "doesnotexist"), None);
+}
+
+#[test]
+fn test_check_password() {
+    let hash = find_hash(SHADOW, "root");
+    assert!(check_password("root", hash));
+    assert!(!check_password("wrong", hash));
+    assert!(!check_password("root", None));
+}
+
+#[test]
+fn test_set_hash() {
+    let updated = set_hash(SHADOW, "root", "$6$salt$hash", 20000).unwrap();
+    assert_eq!(updated.lines().next(), Some("root:$6$salt$hash:20000::::::"));
+    // Every other line is untouched
+    assert_eq!(
+        updated.lines().skip(1).collect::<Vec<_>>(),
+        SHADOW.lines().skip(1).collect::<Vec<_>>()
+    );
+    assert_eq!(updated.ends_with('\n'), SHADOW.ends_with('\n'));
+    assert_eq!(set_hash(SHADOW, "doesnotexist", "$6$x$y", 1), None);
+}
+
+#[test]
+fn test_set_root_shadow() {
+    let conf =
+        "console=\"text\"\nroot_shadow=\"$5$old\"\nos_console=\"ttya\"\n";
+    assert_eq!(
+        set_root_shadow(conf, "$6$new"),
+        "console=\"text\"\nroot_shadow=\"$6$new\"\nos_console=\"ttya\"\n"
+    );
+    assert_eq!(
+        set_root_shadow("console=\"text\"\n", "$6$new"),
+        "console=\"text\"\nroot_shadow=\"$6$new\"\n"
+    );
+}
+
+#[test]
+fn test_update_hash() {
+    let dir = std::env::temp_dir()
+        .join(format!("smartos-ui-shadow-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("shadow");
+    std::fs::write(&path, SHADOW).unwrap();
+
+    let hash = pwhash::sha512_crypt::hash("new password").unwrap();
+    update_hash(&path, "root", &hash, 20000).unwrap();
+
+    let contents = std::fs::read_to_string(&path).unwrap();
+    assert!(check_password("new password", find_hash(&contents, "root")));
+    assert!(!check_password("root", find_hash(&contents, "root")));
+    assert!(update_hash(&path, "doesnotexist", &hash, 20000).is_err());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,76 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+use smartos_ui_executor::totp::{
+    base32_decode, base32_encode, check, code_at, generate_recovery_codes,
+    hash_recovery_code, hotp, verify, Accepted, Entry,
+};
+
+/// The SHA-1 secret from RFC 6238 Appendix B
+const SECRET: &[u8] = b"12345678901234567890";
+
+#[test]
+fn test_base32() {
+    assert_eq!(base32_encode(b"foobar"), "MZXW6YTBOI");
+    assert_eq!(base32_decode("MZXW6YTBOI").unwrap(), b"foobar");
+    assert_eq!(base32_decode("mzxw-6ytb oi======").unwrap(), b"foobar");
+    assert_eq!(base32_decode(&base32_encode(SECRET)).unwrap(), SECRET);
+    assert_eq!(base32_decode("not base32!"), None);
+}
+
+#[test]
+fn test_rfc6238_vectors() {
+    assert_eq!(hotp(SECRET, 59 / 30, 8), 94287082);
+    assert_eq!(hotp(SECRET, 1111111109 / 30, 8), 7081804);
+    assert_eq!(hotp(SECRET, 1234567890 / 30, 8), 89005924);
+    assert_eq!(hotp(SECRET, 20000000000 / 30, 8), 65353130);
+    assert_eq!(code_at(SECRET, 1111111109), "081804");
+}
+
+#[test]
+fn test_verify() {
+    let time = 1234567890;
+    let step = time / 30;
+    let code = code_at(SECRET, time);
+    assert_eq!(verify(SECRET, &code, time, 0), Some(step));
+    // Codes from adjacent steps are accepted to allow for clock drift
+    assert_eq!(verify(SECRET, &code, time + 30, 0), Some(step));
+    assert_eq!(verify(SECRET, &code, time + 90, 0), None);
+    // A code can't be used twice
+    assert_eq!(verify(SECRET, &code, time, step), None);
+    assert_eq!(verify(SECRET, "12345", time, 0), None);
+    assert_eq!(verify(SECRET, "abcdef", time, 0), None);
+}
+
+#[test]
+fn test_check() {
+    let codes = generate_recovery_codes().unwrap();
+    assert_eq!(codes.len(), 10);
+    let mut entry = Entry {
+        secret: base32_encode(SECRET),
+        enabled: true,
+        recovery_codes: codes.iter().map(|c| hash_recovery_code(c)).collect(),
+        last_step: 0,
+    };
+    let time = 1234567890;
+
+    let code = code_at(SECRET, time);
+    assert_eq!(check(&mut entry, &code, time, true), Some(Accepted::Totp));
+    assert_eq!(check(&mut entry, &code, time, true), None);
+
+    assert_eq!(check(&mut entry, &codes[0], time, false), None);
+    let upper = codes[0].to_uppercase();
+    assert_eq!(
+        check(&mut entry, &upper, time, true),
+        Some(Accepted::RecoveryCode)
+    );
+    assert_eq!(check(&mut entry, &codes[0], time, true), None);
+    assert_eq!(entry.recovery_codes.len(), 9);
+}
@@ -0,0 +1,52 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+use smartos_shared::auth::Role;
+use smartos_ui_executor::users::{parse, resolve, Account, Credential};
+
+const USERS: &str = r#"[
+  { "login": "alice", "role": "operator", "shadow_user": "alice" },
+  { "login": "bob", "role": "viewer", "password_hash": "$6$salt$hash" },
+  { "login": "carol", "role": "admin" },
+  { "login": "root", "role": "viewer", "password_hash": "$6$salt$hash" }
+]"#;
+
+#[test]
+fn test_resolve() {
+    let users = parse(USERS).unwrap();
+
+    assert_eq!(
+        resolve(&users, "root", "alice"),
+        Some(Account {
+            role: Role::Operator,
+            credential: Credential::Shadow(String::from("alice")),
+        })
+    );
+    assert_eq!(
+        resolve(&users, "root", "bob"),
+        Some(Account {
+            role: Role::Viewer,
+            credential: Credential::Local(String::from("$6$salt$hash")),
+        })
+    );
+
+    // No credential
+    assert_eq!(resolve(&users, "root", "carol"), None);
+    assert_eq!(resolve(&users, "root", "dave"), None);
+
+    // The login user is always an admin backed by the shadow file
+    assert_eq!(
+        resolve(&users, "root", "root"),
+        Some(Account {
+            role: Role::Admin,
+            credential: Credential::Shadow(String::from("root")),
+        })
+    );
+}
@@ -0,0 +1,195 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+use smartos_ui_executor::webauthn::{
+    decode_cbor, encode, verify_assertion, verify_registration, Cbor,
+    Credential, Expected, PublicKey,
+};
+
+use ring::digest::{digest, SHA256};
+use ring::rand::SystemRandom;
+use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_ASN1_SIGNING};
+use time::OffsetDateTime;
+
+const RP_ID: &str = "gz.example.com";
+const ORIGIN: &str = "https://gz.example.com:4443";
+const CHALLENGE: &str = "c2VydmVyIGNoYWxsZW5nZQ";
+
+fn head(major: u8, value: u64) -> Vec<u8> {
+    if value < 24 {
+        vec![major << 5 | value as u8]
+    } else if value < 256 {
+        vec![major << 5 | 24, value as u8]
+    } else {
+        let mut out = vec![major << 5 | 25];
+        out.extend((value as u16).to_be_bytes());
+        out
+    }
+}
+
+fn int(value: i64) -> Vec<u8> {
+    if value >= 0 {
+        head(0, value as u64)
+    } else {
+        head(1, (-1 - value) as u64)
+    }
+}
+
+fn bytes(value: &[u8]) -> Vec<u8> {
+    let mut out = head(2, value.len() as u64);
+    out.extend(value);
+    out
+}
+
+fn text(value: &str) -> Vec<u8> {
+    let mut out = head(3, value.len() as u64);
+    out.extend(value.as_bytes());
+    out
+}
+
+fn map(entries: &[(Vec<u8>, Vec<u8>)]) -> Vec<u8> {
+    let mut out = head(5, entries.len() as u64);
+    for (key, value) in entries {
+        out.extend(key);
+        out.extend(value);
+    }
+    out
+}
+
+fn client_data(kind: &str, challenge: &str, origin: &str) -> Vec<u8> {
+    format!(
+        r#"{{"type":"{}","challenge":"{}","origin":"{}","crossOrigin":false}}"#,
+        kind, challenge, origin
+    )
+    .into_bytes()
+}
+
+fn authenticator_data(flags: u8, sign_count: u32) -> Vec<u8> {
+    let mut out = digest(&SHA256, RP_ID.as_bytes()).as_ref().to_vec();
+    out.push(flags);
+    out.extend(sign_count.to_be_bytes());
+    out
+}
+
+fn expected() -> Expected<'static> {
+    Expected { rp_id: RP_ID, origin: ORIGIN, challenge: CHALLENGE }
+}
+
+#[test]
+fn test_cbor() {
+    let encoded = map(&[
+        (int(1), int(-7)),
+        (text("a"), bytes(b"xyz")),
+        (int(-300), head(4, 2).into_iter().chain([0xf5, 0xf6]).collect()),
+    ]);
+    let (value, len) =
+        decode_cbor(&[encoded.clone(), vec![0xff]].concat()).expect("decode");
+    assert_eq!(len, encoded.len());
+    assert_eq!(value.get(&Cbor::Int(1)), Some(&Cbor::Int(-7)));
+    assert_eq!(
+        value.get(&Cbor::Text(String::from("a"))).and_then(Cbor::as_bytes),
+        Some(&b"xyz"[..])
+    );
+    assert_eq!(
+        value.get(&Cbor::Int(-300)),
+        Some(&Cbor::Array(vec![Cbor::Bool(true), Cbor::Null]))
+    );
+
+    assert!(decode_cbor(&encoded[..encoded.len() - 1]).is_err());
+    // A byte string claiming to be longer than the input
+    assert!(decode_cbor(&[0x5a, 0xff, 0xff, 0xff, 0xff]).is_err());
+    assert!(decode_cbor(&[0x81; 64]).is_err());
+}
+
+#[test]
+fn test_register_and_assert() {
+    let rng = SystemRandom::new();
+    let pkcs8 =
+        EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &rng)
+            .expect("generate key");
+    let key = EcdsaKeyPair::from_pkcs8(
+        &ECDSA_P256_SHA256_ASN1_SIGNING,
+        pkcs8.as_ref(),
+        &rng,
+    )
+    .expect("load key");
+    let point = key.public_key().as_ref();
+    let cose_key = map(&[
+        (int(1), int(2)),
+        (int(3), int(-7)),
+        (int(-1), int(1)),
+        (int(-2), bytes(&point[1..33])),
+        (int(-3), bytes(&point[33..])),
+    ]);
+    assert_eq!(
+        PublicKey::from_cose(&cose_key),
+        Ok(PublicKey::Es256(point.to_vec()))
+    );
+
+    let credential_id = b"credential-id";
+    let mut auth_data = authenticator_data(0x45, 0);
+    auth_data.extend([0; 16]);
+    auth_data.extend((credential_id.len() as u16).to_be_bytes());
+    auth_data.extend(credential_id);
+    auth_data.extend(&cose_key);
+    let attestation = map(&[
+        (text("fmt"), text("none")),
+        (text("attStmt"), map(&[])),
+        (text("authData"), bytes(&auth_data)),
+    ]);
+
+    let create = client_data("webauthn.create", CHALLENGE, ORIGIN);
+    let registration = verify_registration(&expected(), &create, &attestation)
+        .expect("registration");
+    assert_eq!(registration.id, credential_id);
+    assert_eq!(registration.public_key, cose_key);
+    let wrong_origin =
+        client_data("webauthn.create", CHALLENGE, "https://evil.example.com");
+    assert!(
+        verify_registration(&expected(), &wrong_origin, &attestation).is_err()
+    );
+    let other_rp = Expected { rp_id: "other.example.com", ..expected() };
+    assert!(verify_registration(&other_rp, &create, &attestation).is_err());
+
+    let mut credential = Credential {
+        id: encode(&registration.id),
+        name: String::from("Test"),
+        public_key: encode(&registration.public_key),
+        rp_id: String::from(RP_ID),
+        sign_count: registration.sign_count,
+        created: OffsetDateTime::now_utc(),
+        last_used: None,
+    };
+    let assert = |credential: &Credential, flags, count, client_data: &[u8]| {
+        let auth_data = authenticator_data(flags, count);
+        let mut message = auth_data.clone();
+        message.extend(digest(&SHA256, client_data).as_ref());
+        let signature = key.sign(&rng, &message).expect("sign");
+        verify_assertion(
+            &expected(),
+            credential,
+            client_data,
+            &auth_data,
+            signature.as_ref(),
+            true,
+        )
+    };
+
+    let get = client_data("webauthn.get", CHALLENGE, ORIGIN);
+    assert_eq!(assert(&credential, 0x05, 1, &get), Ok(1));
+    credential.sign_count = 1;
+    // The counter must go up
+    assert!(assert(&credential, 0x05, 1, &get).is_err());
+    // User verification is required
+    assert!(assert(&credential, 0x01, 2, &get).is_err());
+    let stale = client_data("webauthn.get", "b2xk", ORIGIN);
+    assert!(assert(&credential, 0x05, 2, &stale).is_err());
+    assert!(assert(&credential, 0x05, 2, &create).is_err());
+}
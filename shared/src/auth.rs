@@ -0,0 +1,427 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+use std::fmt;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+/// What a UI user is allowed to do. Each role includes everything allowed for
+/// the roles before it.
+#[derive(
+    Deserialize,
+    Serialize,
+    JsonSchema,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Read-only access to instances, images and the host
+    Viewer,
+    /// Viewer, plus starting and stopping instances
+    Operator,
+    /// Full access, including provisioning, deleting and configuration
+    Admin,
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Role::Viewer => write!(f, "viewer"),
+            Role::Operator => write!(f, "operator"),
+            Role::Admin => write!(f, "admin"),
+        }
+    }
+}
+
+/// An action that is checked against a user's [Role] before it is performed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    /// View instances, images and host details
+    View,
+    /// Start and stop instances
+    PowerControl,
+    /// Create instances
+    Provision,
+    /// Change an existing instance's disks, NICs and tags
+    Modify,
+    /// Delete instances
+    Delete,
+    /// Import and delete images
+    ManageImages,
+    /// View and change host configuration
+    Configure,
+    /// View and revoke other users' sessions
+    ManageSessions,
+    /// View and clear login lockouts
+    ManageLockouts,
+}
+
+impl Permission {
+    /// The least privileged role that holds this permission
+    pub fn minimum_role(&self) -> Role {
+        match self {
+            Permission::View => Role::Viewer,
+            Permission::PowerControl => Role::Operator,
+            Permission::Provision
+            | Permission::Modify
+            | Permission::Delete
+            | Permission::ManageImages
+            | Permission::Configure
+            | Permission::ManageSessions
+            | Permission::ManageLockouts => Role::Admin,
+        }
+    }
+}
+
+impl Role {
+    pub fn can(&self, permission: Permission) -> bool {
+        *self >= permission.minimum_role()
+    }
+}
+
+/// Credentials sent by the UI to the executor's `POST /auth/verify`
+#[derive(Deserialize, Serialize, JsonSchema)]
+pub struct AuthVerifyRequest {
+    pub user: String,
+    pub password: String,
+}
+
+/// Result of checking credentials. The password hash itself never leaves the
+/// executor.
+#[derive(Deserialize, Serialize, Debug, JsonSchema)]
+pub struct AuthVerifyResponse {
+    pub success: bool,
+    /// The user's role, set when `success` is true
+    #[serde(default)]
+    pub role: Option<Role>,
+    /// Whether the user has enrolled in TOTP two-factor authentication, set
+    /// when `success` is true
+    #[serde(default)]
+    pub totp_enabled: bool,
+}
+
+/// Sent to the executor's `POST /auth/password` to replace a user's password
+#[derive(Deserialize, Serialize, JsonSchema)]
+pub struct ChangePasswordRequest {
+    pub user: String,
+    pub current_password: String,
+    pub new_password: String,
+}
+
+/// Result of a password change. `warnings` describes anything that was
+/// changed but may not persist, e.g. when the `root_shadow` boot parameter
+/// could not be updated.
+#[derive(Deserialize, Serialize, Debug, JsonSchema)]
+pub struct ChangePasswordResponse {
+    pub success: bool,
+    pub warnings: Vec<String>,
+}
+
+/// Identifies the user for the executor's `POST /auth/totp/status` and
+/// `POST /auth/totp/enroll`
+#[derive(Deserialize, Serialize, JsonSchema)]
+pub struct TotpUserRequest {
+    pub user: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, JsonSchema)]
+pub struct TotpStatusResponse {
+    pub enabled: bool,
+    /// Number of unused recovery codes
+    pub recovery_codes: usize,
+}
+
+/// A newly generated, not yet confirmed, TOTP secret. This is the only time
+/// the secret leaves the executor.
+#[derive(Deserialize, Serialize, Debug, JsonSchema)]
+pub struct TotpEnrollResponse {
+    /// Base32 encoded secret, as used in `otpauth://` URIs
+    pub secret: String,
+}
+
+/// A code from the user's authenticator app, or a recovery code where
+/// accepted, for the executor's `POST /auth/totp/{confirm,verify,disable}`
+#[derive(Deserialize, Serialize, JsonSchema)]
+pub struct TotpCodeRequest {
+    pub user: String,
+    pub code: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, JsonSchema)]
+pub struct TotpCodeResponse {
+    pub success: bool,
+    /// Recovery codes generated when enrollment is confirmed, they are not
+    /// stored in plain text and can't be retrieved again
+    #[serde(default)]
+    pub recovery_codes: Vec<String>,
+    /// Set when a recovery code was used, the number of codes left
+    #[serde(default)]
+    pub recovery_codes_left: Option<usize>,
+}
+
+/// Identifies the user for the executor's `POST /auth/webauthn/credentials`
+#[derive(Deserialize, Serialize, JsonSchema)]
+pub struct WebauthnUserRequest {
+    pub user: String,
+}
+
+/// A registered WebAuthn credential (passkey or security key), without its
+/// public key
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema)]
+pub struct WebauthnCredential {
+    /// Base64url credential ID, as used in `allowCredentials`
+    pub id: String,
+    pub name: String,
+    /// The relying party (host name) the credential was registered for
+    pub rp_id: String,
+    #[serde(with = "time::serde::rfc3339")]
+    #[schemars(with = "String")]
+    pub created: OffsetDateTime,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    #[schemars(with = "Option<String>")]
+    pub last_used: Option<OffsetDateTime>,
+}
+
+#[derive(Deserialize, Serialize, Debug, JsonSchema)]
+pub struct WebauthnCredentialsResponse {
+    /// Base64url WebAuthn user handle. Generated, but not stored, for users
+    /// without any credentials yet.
+    pub handle: String,
+    pub credentials: Vec<WebauthnCredential>,
+}
+
+/// The response to `navigator.credentials.create()`, along with what the UI
+/// expects it to have been created for. Binary fields are base64url.
+#[derive(Deserialize, Serialize, JsonSchema)]
+pub struct WebauthnRegisterRequest {
+    pub user: String,
+    /// The user handle the credential was created with
+    pub handle: String,
+    pub name: String,
+    pub rp_id: String,
+    pub origin: String,
+    pub challenge: String,
+    pub client_data_json: String,
+    pub attestation_object: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, JsonSchema)]
+pub struct WebauthnRegisterResponse {
+    /// Set when the credential was verified and stored
+    #[serde(default)]
+    pub credential: Option<WebauthnCredential>,
+    /// Why the credential was refused
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+/// The response to `navigator.credentials.get()`, along with what the UI
+/// expects it to have been created for. Binary fields are base64url.
+#[derive(Deserialize, Serialize, JsonSchema)]
+pub struct WebauthnAssertRequest {
+    /// Set when the login form had a user name, the credential must then
+    /// belong to that user
+    #[serde(default)]
+    pub user: Option<String>,
+    pub rp_id: String,
+    pub origin: String,
+    pub challenge: String,
+    pub credential_id: String,
+    pub client_data_json: String,
+    pub authenticator_data: String,
+    pub signature: String,
+    #[serde(default)]
+    pub user_handle: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, JsonSchema)]
+pub struct WebauthnAssertResponse {
+    pub success: bool,
+    /// The user the credential belongs to, set when `success` is true
+    #[serde(default)]
+    pub user: Option<String>,
+    #[serde(default)]
+    pub role: Option<Role>,
+}
+
+/// Removes one of `user`'s credentials with the executor's
+/// `POST /auth/webauthn/delete`
+#[derive(Deserialize, Serialize, JsonSchema)]
+pub struct WebauthnDeleteRequest {
+    pub user: String,
+    pub id: String,
+}
+
+/// What an API token may be used for. A token can never do more than its
+/// owner's [Role] allows, whatever its scopes.
+#[derive(
+    Deserialize,
+    Serialize,
+    JsonSchema,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope {
+    /// List and view instances, images and host details
+    Read,
+    /// Start and stop instances
+    PowerControl,
+    /// Create instances
+    Provision,
+    /// Delete instances
+    Delete,
+    /// Import and delete images
+    ManageImages,
+}
+
+impl Scope {
+    pub const ALL: [Scope; 5] = [
+        Scope::Read,
+        Scope::PowerControl,
+        Scope::Provision,
+        Scope::Delete,
+        Scope::ManageImages,
+    ];
+
+    /// The [Permission] the scope grants
+    pub fn permission(&self) -> Permission {
+        match self {
+            Scope::Read => Permission::View,
+            Scope::PowerControl => Permission::PowerControl,
+            Scope::Provision => Permission::Provision,
+            Scope::Delete => Permission::Delete,
+            Scope::ManageImages => Permission::ManageImages,
+        }
+    }
+
+    /// The scope a token needs for `permission`, [None] for permissions
+    /// tokens can't be given
+    pub fn for_permission(permission: Permission) -> Option<Scope> {
+        Scope::ALL.into_iter().find(|s| s.permission() == permission)
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Scope::Read => write!(f, "read"),
+            Scope::PowerControl => write!(f, "power_control"),
+            Scope::Provision => write!(f, "provision"),
+            Scope::Delete => write!(f, "delete"),
+            Scope::ManageImages => write!(f, "manage_images"),
+        }
+    }
+}
+
+impl std::str::FromStr for Scope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Scope::ALL
+            .into_iter()
+            .find(|scope| scope.to_string() == s)
+            .ok_or_else(|| format!("Unknown scope: {}", s))
+    }
+}
+
+/// An API token as listed to its owner. The secret is only returned once,
+/// when the token is created.
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema)]
+pub struct ApiToken {
+    pub id: String,
+    pub name: String,
+    pub user: String,
+    pub scopes: Vec<Scope>,
+    #[serde(with = "time::serde::rfc3339")]
+    #[schemars(with = "String")]
+    pub created: OffsetDateTime,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    #[schemars(with = "Option<String>")]
+    pub last_used: Option<OffsetDateTime>,
+}
+
+impl ApiToken {
+    /// Whether the token, used by someone with `role`, allows `permission`
+    pub fn allows(&self, role: Role, permission: Permission) -> bool {
+        role.can(permission)
+            && Scope::for_permission(permission)
+                .is_some_and(|scope| self.scopes.contains(&scope))
+    }
+}
+
+/// Identifies the user for the executor's `POST /auth/tokens/list`
+#[derive(Deserialize, Serialize, JsonSchema)]
+pub struct ApiTokenUserRequest {
+    pub user: String,
+}
+
+/// Sent to the executor's `POST /auth/tokens/create`
+#[derive(Deserialize, Serialize, JsonSchema)]
+pub struct ApiTokenCreateRequest {
+    pub user: String,
+    pub name: String,
+    pub scopes: Vec<Scope>,
+}
+
+#[derive(Deserialize, Serialize, Debug, JsonSchema)]
+pub struct ApiTokenCreateResponse {
+    pub token: ApiToken,
+    /// The bearer token, which isn't stored and can't be shown again
+    pub secret: String,
+}
+
+/// Sent to the executor's `POST /auth/tokens/verify` with the bearer token
+/// from a request to the UI's API
+#[derive(Deserialize, Serialize, JsonSchema)]
+pub struct ApiTokenVerifyRequest {
+    pub secret: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, JsonSchema)]
+pub struct ApiTokenVerifyResponse {
+    /// Set when the secret belongs to a token
+    #[serde(default)]
+    pub token: Option<ApiToken>,
+    /// The owner's current role, tokens of users who no longer exist are
+    /// refused
+    #[serde(default)]
+    pub role: Option<Role>,
+}
+
+/// Revokes one of `user`'s tokens with the executor's
+/// `POST /auth/tokens/delete`
+#[derive(Deserialize, Serialize, JsonSchema)]
+pub struct ApiTokenDeleteRequest {
+    pub user: String,
+    pub id: String,
+}
+
+/// Compare two byte strings in time that depends only on their length, not on
+/// where the first difference is.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b.iter()).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
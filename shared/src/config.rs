@@ -8,13 +8,38 @@
  * Copyright 2025 MNX Cloud, Inc.
  */
 
+//! Configuration shared by the UI and executor. Values are read from an
+//! optional TOML file and can be overridden by environment variables, e.g.
+//! `exec_cache_seconds` in the file or `EXEC_CACHE` in the environment.
+//! Loading fails, listing every problem, rather than silently falling back
+//! to defaults when a value is invalid.
+
 use std::env;
+use std::fmt;
+use std::fs;
+use std::io::ErrorKind;
+use std::net::SocketAddr;
+use std::str::FromStr;
+
+use dropshot::ConfigLoggingLevel;
+use serde::{Deserialize, Serialize, Serializer};
 
 const REQ_MAX_BYTES: usize = 1024 * 1024 * 8;
 
-#[derive(Debug)]
+/// Longest login lockout that can be configured, a week
+const LOGIN_LOCKOUT_LIMIT_SECONDS: u64 = 60 * 60 * 24 * 7;
+
+/// Read when `CONFIG_FILE` isn't set. Unlike a file named by
+/// `CONFIG_FILE`, it doesn't have to exist.
+pub const CONFIG_FILE: &str = "/usbkey/smartos_ui/config.toml";
+
+#[derive(Debug, Serialize)]
 pub struct Config {
+    /// The file the configuration was read from, if any
+    #[serde(skip)]
+    pub config_file: Option<String>,
     pub log_file: String,
+    pub log_level: ConfigLoggingLevel,
     pub ui_bind_http_address: String,
     pub ui_bind_https_address: String,
     pub exec_bind_address: String,
@@ -22,62 +47,532 @@ pub struct Config {
     pub request_body_max_bytes: usize,
     pub chroot: String,
     pub shadow_path: String,
+    pub usbkey_shadow_path: String,
+    pub loader_conf_path: String,
     pub gz_config_path: String,
     pub login_user: String,
+    pub users_file: String,
+    /// How long the output of `imgadm list`, `imgadm get` and
+    /// `imgadm sources` is cached
     pub exec_cache_seconds: i64,
+    pub sysinfo_cache_seconds: i64,
+    /// `imgadm avail` queries remote image servers, so is slow enough to
+    /// be worth caching for longer
+    pub avail_cache_seconds: i64,
     pub skip_privilege_drop: bool,
     pub cert_file: String,
     pub key_file: String,
+    #[serde(skip)]
     pub builder_brand: bool,
+    pub metrics_enabled: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metrics_token: Option<Secret>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_file: Option<String>,
+    pub session_hours: u32,
+    pub login_max_failures: u32,
+    pub login_lockout_seconds: u64,
+    pub login_lockout_max_seconds: u64,
+    pub totp_file: String,
+    pub totp_required: bool,
+    pub webauthn_file: String,
+    pub api_tokens_file: String,
+    pub presets_file: String,
+}
+
+/// A configuration value that must not end up in log output
+#[derive(Clone)]
+pub struct Secret(pub String);
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<redacted>")
+    }
+}
+
+impl Serialize for Secret {
+    fn serialize<S: Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str("<redacted>")
+    }
+}
+
+/// Settings that differ between the UI and executor, in the `[ui]` and
+/// `[executor]` tables of the config file
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ServiceFile {
+    log_file: Option<String>,
+    log_level: Option<ConfigLoggingLevel>,
+}
+
+/// The config file. Every field is optional, and unknown fields are
+/// rejected so that typos don't go unnoticed.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    log_file: Option<String>,
+    log_level: Option<ConfigLoggingLevel>,
+    ui_bind_http_address: Option<String>,
+    ui_bind_https_address: Option<String>,
+    exec_bind_address: Option<String>,
+    vminfo_bind_address: Option<String>,
+    request_body_max_bytes: Option<usize>,
+    chroot: Option<String>,
+    shadow_path: Option<String>,
+    usbkey_shadow_path: Option<String>,
+    loader_conf_path: Option<String>,
+    gz_config_path: Option<String>,
+    login_user: Option<String>,
+    users_file: Option<String>,
+    exec_cache_seconds: Option<i64>,
+    sysinfo_cache_seconds: Option<i64>,
+    avail_cache_seconds: Option<i64>,
+    skip_privilege_drop: Option<bool>,
+    cert_file: Option<String>,
+    key_file: Option<String>,
+    metrics_enabled: Option<bool>,
+    metrics_token: Option<String>,
+    session_file: Option<String>,
+    session_hours: Option<u32>,
+    login_max_failures: Option<u32>,
+    login_lockout_seconds: Option<u64>,
+    login_lockout_max_seconds: Option<u64>,
+    totp_file: Option<String>,
+    totp_required: Option<bool>,
+    webauthn_file: Option<String>,
+    api_tokens_file: Option<String>,
+    presets_file: Option<String>,
+    ui: Option<ServiceFile>,
+    executor: Option<ServiceFile>,
+}
+
+/// Every problem found while loading the configuration
+#[derive(Debug)]
+pub struct ConfigError(pub Vec<String>);
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid configuration: {}", self.0.join("; "))
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Collects values from the environment and config file, and the problems
+/// with them
+struct Sources<E> {
+    env: E,
+    problems: Vec<String>,
+}
+
+impl<E: Fn(&str) -> Option<String>> Sources<E> {
+    /// The value of the environment variable `var`, otherwise the value from
+    /// the config file, otherwise `default`
+    fn get<T>(&mut self, var: &str, file: Option<T>, default: T) -> T
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        self.get_with(var, file, default, |value| {
+            value.parse().map_err(|e: T::Err| e.to_string())
+        })
+    }
+
+    fn get_with<T, P>(
+        &mut self,
+        var: &str,
+        file: Option<T>,
+        default: T,
+        parse: P,
+    ) -> T
+    where
+        P: Fn(&str) -> Result<T, String>,
+    {
+        let Some(value) = (self.env)(var) else {
+            return file.unwrap_or(default);
+        };
+        parse(&value).unwrap_or_else(|e| {
+            self.problems.push(format!("{}=\"{}\": {}", var, value, e));
+            default
+        })
+    }
+
+    /// Flags are set in the environment by any non-empty value, so an empty
+    /// value turns off a flag set in the config file
+    fn flag(&mut self, var: &str, file: Option<bool>) -> bool {
+        match (self.env)(var) {
+            Some(value) => !value.is_empty(),
+            None => file.unwrap_or(false),
+        }
+    }
+
+    /// An optional value, where an empty environment variable overrides the
+    /// config file with nothing
+    fn optional(&mut self, var: &str, file: Option<String>) -> Option<String> {
+        match (self.env)(var) {
+            Some(value) => Some(value),
+            None => file,
+        }
+        .filter(|value| !value.is_empty())
+    }
+
+    fn check(&mut self, ok: bool, key: &str, var: &str, problem: &str) {
+        if !ok {
+            self.problems.push(format!("{} ({}) {}", key, var, problem));
+        }
+    }
+}
+
+fn parse_log_level(value: &str) -> Result<ConfigLoggingLevel, String> {
+    match value.to_lowercase().as_str() {
+        "trace" => Ok(ConfigLoggingLevel::Trace),
+        "debug" => Ok(ConfigLoggingLevel::Debug),
+        "info" => Ok(ConfigLoggingLevel::Info),
+        "warn" => Ok(ConfigLoggingLevel::Warn),
+        "error" => Ok(ConfigLoggingLevel::Error),
+        "critical" => Ok(ConfigLoggingLevel::Critical),
+        _ => Err(String::from(
+            "expected trace, debug, info, warn, error or critical",
+        )),
+    }
 }
 
 impl Config {
-    #[must_use]
-    pub fn new(log_file: &str) -> Self {
-        let skip_privilege_drop =
-            if let Ok(priv_drop) = env::var("SKIP_PRIVILEGE_DROP") {
-                !priv_drop.is_empty()
-            } else {
-                false
-            };
-        Self {
-            log_file: env::var("LOG_FILE")
-                .unwrap_or(format!("/var/log/{}.log", log_file)),
-            ui_bind_http_address: env::var("UI_BIND_HTTP_ADDRESS")
-                .unwrap_or_else(|_| String::from("127.0.0.1:8880")),
-            ui_bind_https_address: env::var("UI_BIND_HTTPS_ADDRESS")
-                .unwrap_or_else(|_| String::from("127.0.0.1:4443")),
-            exec_bind_address: env::var("EXEC_BIND_ADDRESS")
-                .unwrap_or_else(|_| String::from("127.0.0.1:8881")),
-            vminfo_bind_address: env::var("VMINFO_BIND_ADDRESS")
-                .unwrap_or_else(|_| String::from("127.0.0.1:9090")),
-            chroot: env::var("CHROOT")
-                .unwrap_or_else(|_| String::from("/var/run/smartos_ui")),
-            shadow_path: env::var("SHADOW_PATH")
-                .unwrap_or_else(|_| String::from("/etc/shadow")),
-            gz_config_path: env::var("GZ_CONFIG_PATH")
-                .unwrap_or_else(|_| String::from("/usbkey/config")),
-            login_user: env::var("LOGIN_USER")
-                .unwrap_or_else(|_| String::from("root")),
-            request_body_max_bytes: env::var("REQ_MAX_BYTES")
-                .unwrap_or_else(|_| REQ_MAX_BYTES.to_string())
-                .parse()
-                .unwrap_or(REQ_MAX_BYTES),
-            exec_cache_seconds: env::var("EXEC_CACHE")
-                .unwrap_or_else(|_| String::from("300"))
-                .parse()
-                .unwrap_or(300),
-            skip_privilege_drop,
-            cert_file: env::var("CERT_FILE").unwrap_or_else(|_| {
-                String::from("/usbkey/tls/smartos_ui_cert.pem")
-            }),
-            key_file: env::var("KEY_FILE").unwrap_or_else(|_| {
-                String::from("/usbkey/tls/smartos_ui_key.pem")
-            }),
+    /// Load the configuration for the service `name` (e.g. "smartos_ui") from
+    /// the config file and environment
+    pub fn load(name: &str) -> Result<Self, ConfigError> {
+        let (path, required) = match env::var("CONFIG_FILE") {
+            Ok(path) => (path, true),
+            Err(_) => (String::from(CONFIG_FILE), false),
+        };
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => Some(contents),
+            Err(e) if e.kind() == ErrorKind::NotFound && !required => None,
+            Err(e) => {
+                return Err(ConfigError(vec![format!(
+                    "Failed to read {}: {}",
+                    path, e
+                )]))
+            }
+        };
+        let file =
+            contents.as_deref().map(|contents| (path.as_str(), contents));
+        Self::from_sources(name, file, |var| env::var(var).ok())
+    }
+
+    /// [Config::load], printing each problem to stderr rather than returning
+    /// them, since errors from the TOML parser span several lines
+    pub fn load_or_report(name: &str) -> Result<Self, String> {
+        Self::load(name).map_err(|e| {
+            for problem in &e.0 {
+                eprintln!("{}", problem);
+            }
+            String::from("Invalid configuration")
+        })
+    }
+
+    /// Build the configuration from the path and contents of a config file,
+    /// if there is one, and `env`, which looks up environment variables
+    pub fn from_sources<E>(
+        name: &str,
+        file: Option<(&str, &str)>,
+        env: E,
+    ) -> Result<Self, ConfigError>
+    where
+        E: Fn(&str) -> Option<String>,
+    {
+        let config_file = file.map(|(path, _)| String::from(path));
+        let mut file: ConfigFile = match file {
+            Some((path, contents)) => {
+                toml::from_str(contents).map_err(|e| {
+                    ConfigError(vec![format!(
+                        "Failed to parse {}: {}",
+                        path, e
+                    )])
+                })?
+            }
+            None => ConfigFile::default(),
+        };
+        let service = match name {
+            "smartos_ui" => file.ui.take(),
+            "smartos_ui_executor" => file.executor.take(),
+            _ => None,
+        }
+        .unwrap_or_default();
+
+        let mut s = Sources { env, problems: Vec::new() };
+        let config = Self {
+            config_file,
+            log_file: s.get(
+                "LOG_FILE",
+                service.log_file.or(file.log_file),
+                format!("/var/log/{}.log", name),
+            ),
+            log_level: s.get_with(
+                "LOG_LEVEL",
+                service.log_level.or(file.log_level),
+                ConfigLoggingLevel::Info,
+                parse_log_level,
+            ),
+            ui_bind_http_address: s.get(
+                "UI_BIND_HTTP_ADDRESS",
+                file.ui_bind_http_address,
+                String::from("127.0.0.1:8880"),
+            ),
+            ui_bind_https_address: s.get(
+                "UI_BIND_HTTPS_ADDRESS",
+                file.ui_bind_https_address,
+                String::from("127.0.0.1:4443"),
+            ),
+            exec_bind_address: s.get(
+                "EXEC_BIND_ADDRESS",
+                file.exec_bind_address,
+                String::from("127.0.0.1:8881"),
+            ),
+            vminfo_bind_address: s.get(
+                "VMINFO_BIND_ADDRESS",
+                file.vminfo_bind_address,
+                String::from("127.0.0.1:9090"),
+            ),
+            chroot: s.get(
+                "CHROOT",
+                file.chroot,
+                String::from("/var/run/smartos_ui"),
+            ),
+            shadow_path: s.get(
+                "SHADOW_PATH",
+                file.shadow_path,
+                String::from("/etc/shadow"),
+            ),
+            usbkey_shadow_path: s.get(
+                "USBKEY_SHADOW_PATH",
+                file.usbkey_shadow_path,
+                String::from("/usbkey/shadow"),
+            ),
+            loader_conf_path: s.get(
+                "LOADER_CONF_PATH",
+                file.loader_conf_path,
+                String::from("/mnt/usbkey/boot/loader.conf"),
+            ),
+            gz_config_path: s.get(
+                "GZ_CONFIG_PATH",
+                file.gz_config_path,
+                String::from("/usbkey/config"),
+            ),
+            login_user: s.get(
+                "LOGIN_USER",
+                file.login_user,
+                String::from("root"),
+            ),
+            users_file: s.get(
+                "USERS_FILE",
+                file.users_file,
+                String::from("/usbkey/smartos_ui/users.json"),
+            ),
+            request_body_max_bytes: s.get(
+                "REQ_MAX_BYTES",
+                file.request_body_max_bytes,
+                REQ_MAX_BYTES,
+            ),
+            exec_cache_seconds: s.get(
+                "EXEC_CACHE",
+                file.exec_cache_seconds,
+                300,
+            ),
+            sysinfo_cache_seconds: s.get(
+                "SYSINFO_CACHE",
+                file.sysinfo_cache_seconds,
+                300,
+            ),
+            avail_cache_seconds: s.get(
+                "AVAIL_CACHE",
+                file.avail_cache_seconds,
+                300,
+            ),
+            skip_privilege_drop: s
+                .flag("SKIP_PRIVILEGE_DROP", file.skip_privilege_drop),
+            cert_file: s.get(
+                "CERT_FILE",
+                file.cert_file,
+                String::from("/usbkey/tls/smartos_ui_cert.pem"),
+            ),
+            key_file: s.get(
+                "KEY_FILE",
+                file.key_file,
+                String::from("/usbkey/tls/smartos_ui_key.pem"),
+            ),
             builder_brand: std::path::Path::new(
                 "/usr/lib/brand/builder/platform.xml",
             )
             .exists(),
+            metrics_enabled: s.flag("METRICS_ENABLED", file.metrics_enabled),
+            metrics_token: s
+                .optional("METRICS_TOKEN", file.metrics_token)
+                .map(Secret),
+            session_file: s.optional("SESSION_FILE", file.session_file),
+            session_hours: s.get("SESSION_HOURS", file.session_hours, 8),
+            login_max_failures: s.get(
+                "LOGIN_MAX_FAILURES",
+                file.login_max_failures,
+                5,
+            ),
+            login_lockout_seconds: s.get(
+                "LOGIN_LOCKOUT_SECONDS",
+                file.login_lockout_seconds,
+                30,
+            ),
+            login_lockout_max_seconds: s.get(
+                "LOGIN_LOCKOUT_MAX_SECONDS",
+                file.login_lockout_max_seconds,
+                3600,
+            ),
+            totp_file: s.get(
+                "TOTP_FILE",
+                file.totp_file,
+                String::from("/usbkey/smartos_ui/totp.json"),
+            ),
+            totp_required: s.flag("REQUIRE_TOTP", file.totp_required),
+            webauthn_file: s.get(
+                "WEBAUTHN_FILE",
+                file.webauthn_file,
+                String::from("/usbkey/smartos_ui/webauthn.json"),
+            ),
+            api_tokens_file: s.get(
+                "API_TOKENS_FILE",
+                file.api_tokens_file,
+                String::from("/usbkey/smartos_ui/api_tokens.json"),
+            ),
+            presets_file: s.get(
+                "PRESETS_FILE",
+                file.presets_file,
+                String::from("/usbkey/smartos_ui/presets.json"),
+            ),
+        };
+
+        for (key, var, address) in [
+            (
+                "ui_bind_http_address",
+                "UI_BIND_HTTP_ADDRESS",
+                &config.ui_bind_http_address,
+            ),
+            (
+                "ui_bind_https_address",
+                "UI_BIND_HTTPS_ADDRESS",
+                &config.ui_bind_https_address,
+            ),
+            (
+                "exec_bind_address",
+                "EXEC_BIND_ADDRESS",
+                &config.exec_bind_address,
+            ),
+            (
+                "vminfo_bind_address",
+                "VMINFO_BIND_ADDRESS",
+                &config.vminfo_bind_address,
+            ),
+        ] {
+            s.check(
+                address.parse::<SocketAddr>().is_ok(),
+                key,
+                var,
+                &format!("\"{}\" is not an IP address and port", address),
+            );
+        }
+        for (key, var, value) in [
+            ("log_file", "LOG_FILE", &config.log_file),
+            ("login_user", "LOGIN_USER", &config.login_user),
+            ("cert_file", "CERT_FILE", &config.cert_file),
+            ("key_file", "KEY_FILE", &config.key_file),
+        ] {
+            s.check(!value.is_empty(), key, var, "must not be empty");
+        }
+        s.check(
+            config.request_body_max_bytes > 0,
+            "request_body_max_bytes",
+            "REQ_MAX_BYTES",
+            "must be greater than 0",
+        );
+        for (key, var, seconds) in [
+            ("exec_cache_seconds", "EXEC_CACHE", config.exec_cache_seconds),
+            (
+                "sysinfo_cache_seconds",
+                "SYSINFO_CACHE",
+                config.sysinfo_cache_seconds,
+            ),
+            ("avail_cache_seconds", "AVAIL_CACHE", config.avail_cache_seconds),
+        ] {
+            s.check(seconds >= 0, key, var, "must not be negative");
+        }
+        s.check(
+            config.session_hours > 0,
+            "session_hours",
+            "SESSION_HOURS",
+            "must be greater than 0",
+        );
+        s.check(
+            config.login_lockout_max_seconds >= config.login_lockout_seconds,
+            "login_lockout_max_seconds",
+            "LOGIN_LOCKOUT_MAX_SECONDS",
+            "must not be less than login_lockout_seconds",
+        );
+        s.check(
+            config.login_lockout_max_seconds <= LOGIN_LOCKOUT_LIMIT_SECONDS,
+            "login_lockout_max_seconds",
+            "LOGIN_LOCKOUT_MAX_SECONDS",
+            &format!("must be at most {}", LOGIN_LOCKOUT_LIMIT_SECONDS),
+        );
+
+        if s.problems.is_empty() {
+            Ok(config)
+        } else {
+            Err(ConfigError(s.problems))
+        }
+    }
+}
+
+/// The `config` subcommands of both services
+#[derive(Debug, PartialEq, Eq)]
+pub enum Command {
+    /// Load the configuration and report any problems
+    Check,
+    /// Print the configuration that would be used, as TOML
+    Dump,
+}
+
+impl Command {
+    /// Match `config check` and `config dump`, given the arguments without
+    /// the program name
+    #[must_use]
+    pub fn parse(args: &[String]) -> Option<Self> {
+        match args {
+            [config, command] if config == "config" => match command.as_str() {
+                "check" => Some(Self::Check),
+                "dump" => Some(Self::Dump),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Run the command for the service `name`, printing the result
+    pub fn run(&self, name: &str) -> Result<(), String> {
+        let config = Config::load_or_report(name)?;
+        match self {
+            Self::Check => match &config.config_file {
+                Some(path) => println!("Configuration in {} is valid", path),
+                None => println!(
+                    "No config file found, the defaults and environment \
+                    are valid"
+                ),
+            },
+            Self::Dump => print!(
+                "{}",
+                toml::to_string(&config).map_err(|e| e.to_string())?
+            ),
         }
+        Ok(())
     }
 }
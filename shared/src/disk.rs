@@ -0,0 +1,210 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+//! Changing the disks of an existing bhyve or KVM instance. A [DiskUpdate] is
+//! the `vmadm update` payload itself, with its `add_disks`, `update_disks` and
+//! `remove_disks` lists.
+
+use crate::instance::{Brand, Disk};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Compression a disk's zvol can use
+pub const COMPRESSIONS: [&str; 5] = ["off", "lz4", "lzjb", "gzip", "zle"];
+
+/// Smallest and largest zvol block size, in bytes
+pub const BLOCK_SIZES: (u64, u64) = (512, 131_072);
+
+/// Disk models vmadm accepts for `brand`
+#[must_use]
+pub fn models(brand: &Brand) -> &'static [&'static str] {
+    match brand {
+        Brand::KVM => &["virtio", "ide", "scsi"],
+        _ => &["virtio", "ahci", "nvme"],
+    }
+}
+
+/// A disk to add
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Default, PartialEq, JsonSchema,
+)]
+pub struct NewDisk {
+    /// MiB
+    pub size: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compression: Option<String>,
+    /// Bytes, can't be changed once the disk is created
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_size: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub boot: Option<bool>,
+}
+
+/// Changes to the disk at `path`
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Default, PartialEq, JsonSchema,
+)]
+pub struct DiskChange {
+    pub path: String,
+    /// MiB, disks can only grow
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compression: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub boot: Option<bool>,
+}
+
+/// A `vmadm update` payload changing an instance's disks
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Default, PartialEq, JsonSchema,
+)]
+pub struct DiskUpdate {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub add_disks: Vec<NewDisk>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub update_disks: Vec<DiskChange>,
+    /// Paths of the disks to remove, along with their data
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub remove_disks: Vec<String>,
+}
+
+/// The short name of the disk at `path`, e.g. "disk1" for
+/// `/dev/zvol/rdsk/zones/<uuid>/disk1`
+#[must_use]
+pub fn disk_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+fn option_problems(
+    problems: &mut Vec<String>,
+    brand: &Brand,
+    model: &Option<String>,
+    compression: &Option<String>,
+) {
+    if let Some(model) = model {
+        if !models(brand).contains(&model.as_str()) {
+            problems.push(format!(
+                "{} disks can be {}, not {}",
+                brand,
+                models(brand).join(", "),
+                model
+            ));
+        }
+    }
+    if let Some(compression) = compression {
+        if !COMPRESSIONS.contains(&compression.as_str()) {
+            problems.push(format!(
+                "Compression must be one of {}",
+                COMPRESSIONS.join(", ")
+            ));
+        }
+    }
+}
+
+impl DiskUpdate {
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.add_disks.is_empty()
+            && self.update_disks.is_empty()
+            && self.remove_disks.is_empty()
+    }
+
+    /// Why this update can't be made to a `brand` instance that has `disks`,
+    /// empty if it can
+    #[must_use]
+    pub fn problems(&self, brand: &Brand, disks: &[Disk]) -> Vec<String> {
+        let mut problems = Vec::new();
+        if !brand.is_hvm() {
+            problems.push(format!("{} instances don't have disks", brand));
+            return problems;
+        }
+        let find = |path: &str| disks.iter().find(|disk| disk.path == path);
+
+        for disk in &self.add_disks {
+            if disk.size == 0 {
+                problems.push(String::from("A disk's size must be over 0"));
+            }
+            option_problems(
+                &mut problems,
+                brand,
+                &disk.model,
+                &disk.compression,
+            );
+            if let Some(block_size) = disk.block_size {
+                let (min, max) = BLOCK_SIZES;
+                if !block_size.is_power_of_two()
+                    || block_size < min
+                    || block_size > max
+                {
+                    problems.push(format!(
+                        "The block size must be a power of 2 from {} to {}",
+                        min, max
+                    ));
+                }
+            }
+        }
+        for change in &self.update_disks {
+            let Some(disk) = find(&change.path) else {
+                problems.push(format!("There is no disk {}", change.path));
+                continue;
+            };
+            if let (Some(size), Some(current)) = (change.size, disk.size) {
+                if size < current {
+                    problems.push(format!(
+                        "{} can't shrink from {} MiB to {} MiB",
+                        disk_name(&disk.path),
+                        current,
+                        size
+                    ));
+                }
+            }
+            option_problems(
+                &mut problems,
+                brand,
+                &change.model,
+                &change.compression,
+            );
+        }
+        for path in &self.remove_disks {
+            if find(path).is_none() {
+                problems.push(format!("There is no disk {}", path));
+            }
+        }
+
+        // Whichever disks are left, only one can boot
+        let mut boot = disks
+            .iter()
+            .filter(|disk| !self.remove_disks.contains(&disk.path))
+            .filter(|disk| {
+                self.update_disks
+                    .iter()
+                    .find(|change| change.path == disk.path)
+                    .and_then(|change| change.boot)
+                    .or(disk.boot)
+                    == Some(true)
+            })
+            .count();
+        boot += self
+            .add_disks
+            .iter()
+            .filter(|disk| disk.boot == Some(true))
+            .count();
+        if boot > 1 {
+            problems.push(String::from("Only one disk can be the boot disk"));
+        }
+        problems
+    }
+}
@@ -0,0 +1,198 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+//! Health checks reported by the UI's `/health` endpoint. The executor checks
+//! the programs it runs, since the UI can't see them from its chroot, and the
+//! UI adds its own checks of the executor, vminfod and TLS certificate.
+
+use std::fs::File;
+use std::os::fd::AsRawFd;
+use std::time::{Duration, Instant};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+/// A dependency that takes longer than this to respond is degraded
+pub const SLOW: Duration = Duration::from_secs(2);
+
+/// Log file systems with less than this percentage free are degraded...
+pub const DISK_LOW_PERCENT: f64 = 10.0;
+
+/// ...and with less than this are down, since the services stop logging
+pub const DISK_FULL_PERCENT: f64 = 1.0;
+
+/// Ordered from best to worst, so the overall status is the maximum
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Ok,
+    Degraded,
+    Down,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Check {
+    pub name: String,
+    pub status: Status,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Human readable details, e.g. how much disk space is free
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    /// Why the check isn't ok, if it isn't
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// The most recent error from this check, even if it has since recovered
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "time::serde::rfc3339::option"
+    )]
+    #[schemars(with = "Option<String>")]
+    pub last_error_at: Option<OffsetDateTime>,
+}
+
+impl Check {
+    #[must_use]
+    pub fn new(name: &str, status: Status) -> Self {
+        Self {
+            name: String::from(name),
+            status,
+            latency_ms: None,
+            version: None,
+            detail: None,
+            error: None,
+            last_error: None,
+            last_error_at: None,
+        }
+    }
+
+    /// An ok check that took since `started`, or a degraded one if that was
+    /// longer than [SLOW]
+    #[must_use]
+    pub fn timed(name: &str, started: Instant) -> Self {
+        let elapsed = started.elapsed();
+        let mut check = Self::new(name, Status::Ok);
+        check.latency_ms = u64::try_from(elapsed.as_millis()).ok();
+        if elapsed > SLOW {
+            check.status = Status::Degraded;
+            check.error = Some(format!(
+                "Took {}ms, more than {}ms",
+                elapsed.as_millis(),
+                SLOW.as_millis()
+            ));
+        }
+        check
+    }
+
+    #[must_use]
+    pub fn down<E: ToString>(name: &str, error: E) -> Self {
+        let mut check = Self::new(name, Status::Down);
+        check.error = Some(error.to_string());
+        check
+    }
+
+    /// Only the name, status and latency, for callers that aren't logged in
+    #[must_use]
+    pub fn summary(self) -> Self {
+        Self {
+            latency_ms: self.latency_ms,
+            ..Self::new(&self.name, self.status)
+        }
+    }
+}
+
+/// Returned by the executor's `/health` endpoint
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ExecutorHealth {
+    pub version: String,
+    pub checks: Vec<Check>,
+}
+
+/// Returned by the UI's `/health` endpoint
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct HealthReport {
+    /// The worst status of any check
+    pub status: Status,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    pub checks: Vec<Check>,
+}
+
+/// The worst status of `checks`
+pub fn overall<'a, I: IntoIterator<Item = &'a Check>>(checks: I) -> Status {
+    checks.into_iter().map(|check| check.status).max().unwrap_or(Status::Ok)
+}
+
+/// Check the free space on the file system holding `file`. Uses fstatvfs so
+/// that it works for a file opened before the UI entered its chroot.
+pub fn disk_space(name: &str, file: &File) -> Check {
+    // SAFETY: statvfs is plain old data that fstatvfs fills in
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    // SAFETY: the descriptor is open for as long as `file` is borrowed
+    if unsafe { libc::fstatvfs(file.as_raw_fd(), &mut stat) } != 0 {
+        return Check::down(name, std::io::Error::last_os_error());
+    }
+
+    let block = stat.f_frsize;
+    let total = stat.f_blocks * block;
+    let free = stat.f_bavail * block;
+    let percent =
+        if total == 0 { 100.0 } else { free as f64 * 100.0 / total as f64 };
+
+    let mut check = Check::new(name, Status::Ok);
+    check.detail = Some(format!(
+        "{} free of {} ({:.1}%)",
+        format_bytes(free),
+        format_bytes(total),
+        percent
+    ));
+    if percent < DISK_FULL_PERCENT {
+        check.status = Status::Down;
+    } else if percent < DISK_LOW_PERCENT {
+        check.status = Status::Degraded;
+    }
+    if check.status != Status::Ok {
+        check.error = Some(format!("Only {:.1}% free", percent));
+    }
+    check
+}
+
+#[must_use]
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
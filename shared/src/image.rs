@@ -34,7 +34,7 @@ pub struct ImageImportParams {
 
 /// Assumes the version 2 Manifest format created 2013-Jan-31
 /// <https://images.tritondatacenter.com/docs/>
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 pub struct Manifest {
     /// Version of the manifest format/spec. The current value is 2.
     #[serde(deserialize_with = "deserialize_into_u64")]
@@ -180,11 +180,11 @@ impl Manifest {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 pub struct User {
     pub name: String,
 }
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 pub struct Network {
     pub name: String,
     #[serde(default)]
@@ -192,7 +192,7 @@ pub struct Network {
 }
 
 /// A set of named requirements for provisioning a VM with this image
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 pub struct Requirements {
     /// Defines the minimum number of network interfaces required by this image.
     pub networks: Option<Vec<Network>>,
@@ -219,13 +219,13 @@ pub struct Requirements {
     pub bootrom: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 pub enum ImportStatus {
     Importing,
     Failed(String),
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 pub struct Image {
     pub manifest: Manifest,
     pub source: Option<Url>,
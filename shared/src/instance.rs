@@ -8,16 +8,21 @@
  * Copyright 2025 MNX Cloud, Inc.
  */
 
+use crate::nic::address_problems;
 use crate::serde_helpers::deserialize_into_u64;
 
+use std::collections::BTreeMap;
 use std::convert::TryFrom;
-use std::convert::TryInto;
 use std::fmt;
 use std::fmt::{Display, Error, Formatter};
+use std::net::IpAddr;
 use std::str::FromStr;
 
 use schemars::JsonSchema;
-use serde::{Deserialize, Serialize};
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Map, Number, Value};
+use slog::{warn, Logger};
 use uuid::{Builder as UuidBuilder, Uuid};
 
 /// Used for sending the instance json for `vmadm validate` and `vmadm create`
@@ -38,23 +43,322 @@ pub struct InstanceValidateResponse {
     pub success: bool,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// A `vmadm create` payload. The properties the UI, API and CLI work with
+/// are typed, anything else is passed through to vmadm as it is in `extra`.
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Default, PartialEq, JsonSchema,
+)]
+pub struct CreatePayload {
+    /// Generated when the instance is created if it isn't set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uuid: Option<Uuid>,
+    /// vmadm uses joyent if it isn't set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub brand: Option<Brand>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hostname: Option<String>,
+    /// For OS and LX instances, HVM instances boot from a disk's image
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image_uuid: Option<Uuid>,
+    /// MiB
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ram: Option<u64>,
+    /// MiB
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_physical_memory: Option<u64>,
+    /// GiB
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quota: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vcpus: Option<u64>,
+    /// Percent of a CPU
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_cap: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub autoboot: Option<bool>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub resolvers: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dns_domain: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delegate_dataset: Option<bool>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub disks: Vec<PayloadDisk>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub nics: Vec<PayloadNic>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub filesystems: Vec<PayloadFilesystem>,
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub customer_metadata: Map<String, Value>,
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub internal_metadata: Map<String, Value>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tags: Tags,
+    /// Other `vmadm create` properties
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Default, PartialEq, JsonSchema,
+)]
+pub struct PayloadDisk {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image_uuid: Option<Uuid>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub boot: Option<bool>,
+    /// MiB
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    /// e.g. "virtio"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Default, PartialEq, JsonSchema,
+)]
+pub struct PayloadNic {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nic_tag: Option<String>,
+    /// `ip/prefix`, `dhcp` or `addrconf`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ips: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub gateways: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub primary: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vlan_id: Option<u16>,
+    /// Addresses the instance may use besides its `ips`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_ips: Vec<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// A lofs or other mount from the global zone, for OS and LX instances
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Default, PartialEq, JsonSchema,
+)]
+pub struct PayloadFilesystem {
+    /// e.g. "lofs"
+    #[serde(rename = "type")]
+    pub fs_type: String,
+    pub source: String,
+    pub target: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub options: Vec<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl CreatePayload {
+    /// The brand vmadm will create
+    #[must_use]
+    pub fn brand(&self) -> Brand {
+        self.brand.clone().unwrap_or(Brand::Joyent)
+    }
+
+    /// The instance's UUID, which is generated first if it isn't set
+    pub fn ensure_uuid(&mut self) -> Uuid {
+        *self.uuid.get_or_insert_with(Uuid::new_v4)
+    }
+
+    /// The image the instance is created from: the boot disk's for HVM
+    /// instances
+    #[must_use]
+    pub fn image(&self) -> Option<Uuid> {
+        if !self.brand().is_hvm() {
+            return self.image_uuid;
+        }
+        self.disks
+            .iter()
+            .find(|disk| disk.boot == Some(true))
+            .or_else(|| self.disks.first())
+            .and_then(|disk| disk.image_uuid)
+    }
+
+    /// Why vmadm would refuse this payload, empty if nothing obvious is
+    /// wrong. vmadm still has the last word.
+    #[must_use]
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        let brand = self.brand();
+        if brand == Brand::Other {
+            problems.push(String::from("A brand must be chosen"));
+        }
+        if brand.is_hvm() {
+            if self.image_uuid.is_some() {
+                problems.push(format!(
+                    "{} instances boot from a disk's image_uuid, not \
+                    image_uuid",
+                    brand
+                ));
+            }
+            if self.image().is_none() {
+                problems.push(String::from(
+                    "The boot disk must have an image_uuid",
+                ));
+            }
+            if self.vcpus == Some(0) {
+                problems.push(String::from("vcpus must be greater than 0"));
+            }
+        } else {
+            if brand != Brand::Other && self.image_uuid.is_none() {
+                problems.push(String::from("An image_uuid is required"));
+            }
+            if !self.disks.is_empty() {
+                problems.push(format!("{} instances can't have disks", brand));
+            }
+        }
+        if self.delegate_dataset == Some(true)
+            && !brand.allows_delegate_dataset()
+        {
+            problems.push(format!(
+                "{} instances can't have a delegated dataset",
+                brand
+            ));
+        }
+        if self.ram == Some(0) || self.max_physical_memory == Some(0) {
+            problems.push(String::from("RAM must be greater than 0"));
+        }
+        if self.cpu_cap == Some(0) {
+            problems.push(String::from("cpu_cap must be greater than 0"));
+        }
+        for resolver in &self.resolvers {
+            if resolver.parse::<IpAddr>().is_err() {
+                problems.push(format!(
+                    "Resolver \"{}\" is not an IP address",
+                    resolver
+                ));
+            }
+        }
+        for (index, nic) in self.nics.iter().enumerate() {
+            if nic.nic_tag.as_deref().map_or(true, str::is_empty) {
+                problems.push(format!("NIC {} needs a nic_tag", index));
+            }
+            address_problems(
+                &mut problems,
+                &format!("NIC {}", index),
+                &nic.ips,
+                &nic.gateways,
+                &nic.allowed_ips,
+                nic.vlan_id,
+            );
+        }
+        if self.nics.iter().filter(|nic| nic.primary == Some(true)).count() > 1
+        {
+            problems.push(String::from("Only one NIC can be primary"));
+        }
+        if !self.filesystems.is_empty() && brand.is_hvm() {
+            problems
+                .push(format!("{} instances can't have filesystems", brand));
+        }
+        for filesystem in &self.filesystems {
+            if filesystem.source.is_empty() || filesystem.target.is_empty() {
+                problems.push(String::from(
+                    "Filesystems need a source and a target",
+                ));
+            }
+        }
+        problems
+    }
+}
+
+impl TryFrom<&CreatePayload> for InstancePayload {
+    type Error = serde_json::Error;
+
+    fn try_from(payload: &CreatePayload) -> Result<Self, Self::Error> {
+        Ok(Self { payload: serde_json::to_string(payload)? })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct Disk {
     pub boot: Option<bool>,
     pub image_uuid: Option<Uuid>,
     #[serde(default)]
     pub image_size: u64,
+    /// e.g. `/dev/zvol/rdsk/zones/<uuid>/disk0`
+    #[serde(default)]
+    pub path: String,
+    /// MiB
+    pub size: Option<u64>,
+    pub model: Option<String>,
+    pub compression: Option<String>,
+    /// Bytes
+    pub block_size: Option<u64>,
+    /// The disk's zvol
+    pub zfs_filesystem: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+impl Disk {
+    /// e.g. "disk0"
+    pub fn name(&self) -> &str {
+        crate::disk::disk_name(&self.path)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct Nic {
     pub nic_tag: Option<String>,
     pub ips: Option<Vec<String>>,
     pub gateways: Option<Vec<String>>,
     pub model: Option<String>,
     pub primary: Option<bool>,
+    #[serde(default)]
+    pub mac: String,
+    /// e.g. "net0"
+    pub interface: Option<String>,
+    pub vlan_id: Option<u16>,
+    pub allowed_ips: Option<Vec<String>>,
+}
+
+/// A tag's value, which vmadm keeps as a string, number or boolean
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, JsonSchema)]
+#[serde(untagged)]
+pub enum TagValue {
+    Bool(bool),
+    Number(Number),
+    String(String),
+}
+
+impl TagValue {
+    /// The value typed into a form: `true`, `false` and numbers keep their
+    /// type, anything else is a string
+    #[must_use]
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "true" => Self::Bool(true),
+            "false" => Self::Bool(false),
+            _ => match value.parse::<Number>() {
+                Ok(number) => Self::Number(number),
+                Err(_) => Self::String(String::from(value)),
+            },
+        }
+    }
 }
 
+impl Display for TagValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bool(value) => write!(f, "{}", value),
+            Self::Number(value) => write!(f, "{}", value),
+            Self::String(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+/// An instance's tags, in key order
+pub type Tags = BTreeMap<String, TagValue>;
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Generic {
     pub v: u8,
@@ -90,6 +394,8 @@ pub struct Generic {
     pub nics: Vec<Nic>,
     #[serde(default)]
     pub cpu_cap: u64,
+    #[serde(default)]
+    pub tags: Tags,
 
     // if started
     pub boot_timestamp: Option<String>,
@@ -234,7 +540,7 @@ pub struct LX {
     pub native: Native,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
 pub struct InstanceView {
     pub uuid: Uuid,
     pub alias: String,
@@ -249,9 +555,90 @@ pub struct InstanceView {
     #[serde(default)]
     pub cpu: f32,
     pub primary_ip: Option<String>,
+    #[serde(default)]
+    pub tags: Tags,
+    /// Only some of the instance could be read, see [Instance::Unknown]
+    #[serde(default)]
+    pub partial: bool,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// The [Generic] properties an [Unknown] instance could still be read for
+#[derive(Debug)]
+pub struct PartialGeneric {
+    pub uuid: Uuid,
+    pub alias: Option<String>,
+    pub state: String,
+    /// MiB, from `ram` or else `max_physical_memory`
+    pub ram: u64,
+    pub tags: Tags,
+}
+
+impl PartialGeneric {
+    pub fn alias(&self) -> String {
+        if let Some(alias) = &self.alias {
+            alias.clone()
+        } else {
+            self.uuid.to_string().split('-').nth(0).unwrap_or("-").to_string()
+        }
+    }
+}
+
+/// A VM whose brand isn't one of [Instance]'s, e.g. `lxd`, or which is
+/// missing a property its brand is expected to have. It serializes as the
+/// JSON it was read from.
+#[derive(Debug)]
+pub struct Unknown {
+    pub generic: PartialGeneric,
+    /// The brand as vminfod has it
+    pub brand: String,
+    /// Why the VM couldn't be read as its brand
+    pub error: String,
+    pub raw: Value,
+}
+
+impl Unknown {
+    /// What can be read of a VM, as long as it has a UUID
+    fn new(raw: Value, error: String) -> Option<Self> {
+        let text = |name: &str| {
+            raw.get(name).and_then(Value::as_str).map(String::from)
+        };
+        let generic = PartialGeneric {
+            uuid: text("uuid")?.parse().ok()?,
+            alias: text("alias"),
+            state: text("state").unwrap_or_else(|| String::from("unknown")),
+            ram: ["ram", "max_physical_memory"]
+                .iter()
+                .find_map(|name| raw.get(name)?.as_u64())
+                .unwrap_or_default(),
+            tags: raw
+                .get("tags")
+                .and_then(|tags| Tags::deserialize(tags).ok())
+                .unwrap_or_default(),
+        };
+        Some(Self {
+            generic,
+            brand: text("brand").unwrap_or_default(),
+            error,
+            raw,
+        })
+    }
+
+    /// The VM's JSON, for showing in place of its properties
+    pub fn json(&self) -> String {
+        self.raw.to_string()
+    }
+}
+
+impl Serialize for Unknown {
+    fn serialize<S: Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        self.raw.serialize(serializer)
+    }
+}
+
+#[derive(Serialize, Debug)]
 #[serde(tag = "brand")]
 pub enum Instance {
     #[serde(rename = "joyent")]
@@ -266,13 +653,136 @@ pub enum Instance {
     KVM(KVM),
     #[serde(rename = "lx")]
     LX(LX),
+    #[serde(untagged)]
+    Unknown(Unknown),
+}
+
+/// The brands [Instance] can read a VM as, before it falls back to
+/// [Instance::Unknown]
+#[derive(Deserialize)]
+#[serde(tag = "brand")]
+#[allow(clippy::upper_case_acronyms)]
+enum Branded {
+    #[serde(rename = "joyent")]
+    Joyent(Joyent),
+    #[serde(rename = "joyent-minimal")]
+    JoyentMinimal(JoyentMinimal),
+    #[serde(rename = "builder")]
+    Builder(Builder),
+    #[serde(rename = "bhyve")]
+    Bhyve(Bhyve),
+    #[serde(rename = "kvm")]
+    KVM(KVM),
+    #[serde(rename = "lx")]
+    LX(LX),
+}
+
+impl From<Branded> for Instance {
+    fn from(value: Branded) -> Self {
+        match value {
+            Branded::Joyent(i) => Instance::Joyent(i),
+            Branded::JoyentMinimal(i) => Instance::JoyentMinimal(i),
+            Branded::Builder(i) => Instance::Builder(i),
+            Branded::Bhyve(i) => Instance::Bhyve(i),
+            Branded::KVM(i) => Instance::KVM(i),
+            Branded::LX(i) => Instance::LX(i),
+        }
+    }
+}
+
+/// A VM that can't be read as its brand is an [Instance::Unknown], so one
+/// unusual VM doesn't keep the others from being listed. Only a VM without a
+/// UUID fails.
+impl<'de> Deserialize<'de> for Instance {
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        let raw = Value::deserialize(deserializer)?;
+        match Branded::deserialize(&raw) {
+            Ok(branded) => Ok(branded.into()),
+            Err(error) => {
+                let error = error.to_string();
+                Unknown::new(raw, error.clone())
+                    .map(Instance::Unknown)
+                    .ok_or_else(|| D::Error::custom(error))
+            }
+        }
+    }
 }
 
 impl Instance {
+    /// Deserializes each VM in a list individually, like
+    /// [Image::deserialize_list](crate::image::Image::deserialize_list), so a
+    /// VM that can't be read at all is left out rather than failing the list
+    pub fn deserialize_list(list: Vec<Value>, log: &Logger) -> Vec<Instance> {
+        let mut instances = Vec::new();
+        for value in list {
+            match serde_json::from_value::<Instance>(value) {
+                Ok(Instance::Unknown(unknown)) => {
+                    warn!(
+                        log,
+                        "Only partly read VM {}: {}",
+                        unknown.generic.uuid,
+                        unknown.error
+                    );
+                    instances.push(Instance::Unknown(unknown));
+                }
+                Ok(instance) => instances.push(instance),
+                Err(err) => warn!(log, "Failed to deserialize VM: {:?}", err),
+            }
+        }
+        instances
+    }
+
     pub fn is_hvm(&self) -> bool {
         matches!(self, Instance::Bhyve(_) | Instance::KVM(_))
     }
 
+    pub fn brand(&self) -> Brand {
+        match self {
+            Instance::Joyent(_) => Brand::Joyent,
+            Instance::JoyentMinimal(_) => Brand::JoyentMinimal,
+            Instance::Builder(_) => Brand::Builder,
+            Instance::Bhyve(_) => Brand::Bhyve,
+            Instance::KVM(_) => Brand::KVM,
+            Instance::LX(_) => Brand::LX,
+            Instance::Unknown(i) => i.brand.parse().unwrap_or_default(),
+        }
+    }
+
+    pub fn nics(&self) -> &[Nic] {
+        match self {
+            Instance::Joyent(i) => &i.generic.nics,
+            Instance::JoyentMinimal(i) => &i.generic.nics,
+            Instance::Builder(i) => &i.generic.nics,
+            Instance::Bhyve(i) => &i.generic.nics,
+            Instance::KVM(i) => &i.generic.nics,
+            Instance::LX(i) => &i.generic.nics,
+            Instance::Unknown(_) => &[],
+        }
+    }
+
+    pub fn tags(&self) -> &Tags {
+        match self {
+            Instance::Joyent(i) => &i.generic.tags,
+            Instance::JoyentMinimal(i) => &i.generic.tags,
+            Instance::Builder(i) => &i.generic.tags,
+            Instance::Bhyve(i) => &i.generic.tags,
+            Instance::KVM(i) => &i.generic.tags,
+            Instance::LX(i) => &i.generic.tags,
+            Instance::Unknown(i) => &i.generic.tags,
+        }
+    }
+
+    /// The HVM properties of a bhyve or KVM instance
+    pub fn hvm(&self) -> Option<&HVM> {
+        match self {
+            Instance::Bhyve(i) => Some(&i.hvm),
+            Instance::KVM(i) => Some(&i.hvm),
+            _ => None,
+        }
+    }
+
     pub fn uuid(&self) -> Uuid {
         match self {
             Instance::Joyent(i) => i.generic.uuid,
@@ -281,6 +791,7 @@ impl Instance {
             Instance::Bhyve(i) => i.generic.uuid,
             Instance::KVM(i) => i.generic.uuid,
             Instance::LX(i) => i.generic.uuid,
+            Instance::Unknown(i) => i.generic.uuid,
         }
     }
 
@@ -292,6 +803,7 @@ impl Instance {
             Instance::Bhyve(i) => &i.generic.state,
             Instance::KVM(i) => &i.generic.state,
             Instance::LX(i) => &i.generic.state,
+            Instance::Unknown(i) => &i.generic.state,
         }
     }
 
@@ -303,6 +815,7 @@ impl Instance {
             Instance::Bhyve(i) => i.generic.alias(),
             Instance::KVM(i) => i.generic.alias(),
             Instance::LX(i) => i.generic.alias(),
+            Instance::Unknown(i) => i.generic.alias(),
         }
     }
 
@@ -314,32 +827,50 @@ impl Instance {
             Instance::LX(i) => i.native.image_uuid,
             Instance::Bhyve(i) => i.hvm.get_boot_image_uuid(),
             Instance::KVM(i) => i.hvm.get_boot_image_uuid(),
+            Instance::Unknown(_) => UuidBuilder::nil().into_uuid(),
         }
     }
 }
 
-impl TryFrom<Instance> for InstanceView {
-    type Error = ();
-
-    fn try_from(value: Instance) -> Result<Self, Self::Error> {
+impl From<Instance> for InstanceView {
+    fn from(value: Instance) -> Self {
         match value {
-            Instance::Joyent(i) => i.try_into(),
-            Instance::JoyentMinimal(i) => i.try_into(),
-            Instance::Builder(i) => i.try_into(),
-            Instance::Bhyve(i) => i.try_into(),
-            Instance::KVM(i) => i.try_into(),
-            Instance::LX(i) => i.try_into(),
+            Instance::Joyent(i) => i.into(),
+            Instance::JoyentMinimal(i) => i.into(),
+            Instance::Builder(i) => i.into(),
+            Instance::Bhyve(i) => i.into(),
+            Instance::KVM(i) => i.into(),
+            Instance::LX(i) => i.into(),
+            Instance::Unknown(i) => i.into(),
         }
     }
 }
 
-impl TryFrom<KVM> for InstanceView {
-    type Error = ();
+impl From<Unknown> for InstanceView {
+    fn from(value: Unknown) -> Self {
+        let brand: Brand = value.brand.parse().unwrap_or_default();
+        InstanceView {
+            uuid: value.generic.uuid,
+            alias: value.generic.alias(),
+            hvm: brand.is_hvm(),
+            brand,
+            ram: value.generic.ram,
+            state: value.generic.state,
+            disk_usage: 0,
+            image_uuid: UuidBuilder::nil().into_uuid(),
+            cpu: 0.0,
+            primary_ip: None,
+            tags: value.generic.tags,
+            partial: true,
+        }
+    }
+}
 
-    fn try_from(value: KVM) -> Result<Self, Self::Error> {
+impl From<KVM> for InstanceView {
+    fn from(value: KVM) -> Self {
         let primary_ip = value.generic.primary_ip();
 
-        Ok(InstanceView {
+        InstanceView {
             uuid: value.generic.uuid,
             alias: value.generic.alias(),
             brand: Brand::KVM,
@@ -350,16 +881,16 @@ impl TryFrom<KVM> for InstanceView {
             image_uuid: value.hvm.get_boot_image_uuid(),
             cpu: value.hvm.get_cpus(value.generic.cpu_cap),
             primary_ip,
-        })
+            tags: value.generic.tags,
+            partial: false,
+        }
     }
 }
 
-impl TryFrom<Bhyve> for InstanceView {
-    type Error = ();
-
-    fn try_from(value: Bhyve) -> Result<Self, Self::Error> {
+impl From<Bhyve> for InstanceView {
+    fn from(value: Bhyve) -> Self {
         let primary_ip = value.generic.primary_ip();
-        Ok(InstanceView {
+        InstanceView {
             uuid: value.generic.uuid,
             alias: value.generic.alias(),
             brand: Brand::Bhyve,
@@ -370,17 +901,17 @@ impl TryFrom<Bhyve> for InstanceView {
             image_uuid: value.hvm.get_boot_image_uuid(),
             cpu: value.hvm.get_cpus(value.generic.cpu_cap),
             primary_ip,
-        })
+            tags: value.generic.tags,
+            partial: false,
+        }
     }
 }
 
-impl TryFrom<Builder> for InstanceView {
-    type Error = ();
-
-    fn try_from(value: Builder) -> Result<Self, Self::Error> {
+impl From<Builder> for InstanceView {
+    fn from(value: Builder) -> Self {
         let primary_ip = value.generic.primary_ip();
         let cpu = value.generic.get_cpus();
-        Ok(InstanceView {
+        InstanceView {
             uuid: value.generic.uuid,
             alias: value.generic.alias(),
             brand: Brand::Builder,
@@ -391,17 +922,17 @@ impl TryFrom<Builder> for InstanceView {
             image_uuid: value.native.image_uuid,
             cpu,
             primary_ip,
-        })
+            tags: value.generic.tags,
+            partial: false,
+        }
     }
 }
 
-impl TryFrom<JoyentMinimal> for InstanceView {
-    type Error = ();
-
-    fn try_from(value: JoyentMinimal) -> Result<Self, Self::Error> {
+impl From<JoyentMinimal> for InstanceView {
+    fn from(value: JoyentMinimal) -> Self {
         let primary_ip = value.generic.primary_ip();
         let cpu = value.generic.get_cpus();
-        Ok(InstanceView {
+        InstanceView {
             uuid: value.generic.uuid,
             alias: value.generic.alias(),
             brand: Brand::JoyentMinimal,
@@ -412,17 +943,17 @@ impl TryFrom<JoyentMinimal> for InstanceView {
             image_uuid: value.native.image_uuid,
             cpu,
             primary_ip,
-        })
+            tags: value.generic.tags,
+            partial: false,
+        }
     }
 }
 
-impl TryFrom<Joyent> for InstanceView {
-    type Error = ();
-
-    fn try_from(value: Joyent) -> Result<Self, Self::Error> {
+impl From<Joyent> for InstanceView {
+    fn from(value: Joyent) -> Self {
         let primary_ip = value.generic.primary_ip();
         let cpu = value.generic.get_cpus();
-        Ok(InstanceView {
+        InstanceView {
             uuid: value.generic.uuid,
             alias: value.generic.alias(),
             brand: Brand::Joyent,
@@ -433,17 +964,17 @@ impl TryFrom<Joyent> for InstanceView {
             image_uuid: value.native.image_uuid,
             cpu,
             primary_ip,
-        })
+            tags: value.generic.tags,
+            partial: false,
+        }
     }
 }
 
-impl TryFrom<LX> for InstanceView {
-    type Error = ();
-
-    fn try_from(value: LX) -> Result<Self, Self::Error> {
+impl From<LX> for InstanceView {
+    fn from(value: LX) -> Self {
         let primary_ip = value.generic.primary_ip();
         let cpu = value.generic.get_cpus();
-        Ok(InstanceView {
+        InstanceView {
             uuid: value.generic.uuid,
             alias: value.generic.alias(),
             brand: Brand::LX,
@@ -454,7 +985,9 @@ impl TryFrom<LX> for InstanceView {
             image_uuid: value.native.image_uuid,
             cpu,
             primary_ip,
-        })
+            tags: value.generic.tags,
+            partial: false,
+        }
     }
 }
 
@@ -561,7 +1094,7 @@ impl FromStr for Brand {
             "kvm" => Ok(Brand::KVM),
             "lx" => Ok(Brand::LX),
             "lxd" => Ok(Brand::LXD),
-            "other" => Ok(Brand::LXD),
+            "other" => Ok(Brand::Other),
             _ => Err(BrandError::UnknownBrand),
         }
     }
@@ -8,10 +8,19 @@
  * Copyright 2024 MNX Cloud, Inc.
  */
 
+pub mod auth;
 pub mod config;
+pub mod disk;
+pub mod health;
 pub mod http_server;
 pub mod image;
 pub mod instance;
+pub mod metrics;
+pub mod nic;
 pub mod nictag;
+pub mod preset;
+pub mod provision;
 pub mod serde_helpers;
 pub mod sysinfo;
+pub mod tag;
+pub mod tls;
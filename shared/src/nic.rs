@@ -0,0 +1,255 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+//! Changing the NICs of an existing instance. A [NicUpdate] is the
+//! `vmadm update` payload itself, with its `add_nics`, `update_nics` and
+//! `remove_nics` lists. NICs are found by their MAC address.
+
+use std::net::IpAddr;
+
+use crate::instance::{Brand, Nic};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Highest VLAN ID vmadm accepts, 0 being untagged
+pub const MAX_VLAN_ID: u16 = 4095;
+
+/// NIC models vmadm accepts for `brand`, OS and LX instances have none
+#[must_use]
+pub fn models(brand: &Brand) -> &'static [&'static str] {
+    match brand {
+        Brand::Bhyve => &["virtio", "e1000"],
+        Brand::KVM => &["virtio", "e1000", "rtl8139"],
+        _ => &[],
+    }
+}
+
+/// Whether `ip` is something vmadm accepts in a NIC's `ips`
+#[must_use]
+pub fn valid_nic_ip(ip: &str) -> bool {
+    if matches!(ip, "dhcp" | "addrconf") {
+        return true;
+    }
+    valid_address(ip)
+}
+
+/// An IP address with an optional prefix length
+fn valid_address(ip: &str) -> bool {
+    let (address, prefix) = ip.split_once('/').unwrap_or((ip, ""));
+    let max_prefix = match address.parse::<IpAddr>() {
+        Ok(IpAddr::V4(_)) => 32,
+        Ok(IpAddr::V6(_)) => 128,
+        Err(_) => return false,
+    };
+    prefix.is_empty() || prefix.parse::<u8>().map_or(false, |p| p <= max_prefix)
+}
+
+/// Problems with a NIC's addresses and VLAN, `label` being e.g. "NIC 1"
+pub(crate) fn address_problems(
+    problems: &mut Vec<String>,
+    label: &str,
+    ips: &[String],
+    gateways: &[String],
+    allowed_ips: &[String],
+    vlan_id: Option<u16>,
+) {
+    for ip in ips.iter().filter(|ip| !valid_nic_ip(ip)) {
+        problems.push(format!(
+            "{} address \"{}\" is not an IP address, dhcp or addrconf",
+            label, ip
+        ));
+    }
+    for gateway in gateways {
+        if gateway.parse::<IpAddr>().is_err() {
+            problems.push(format!(
+                "{} gateway \"{}\" is not an IP address",
+                label, gateway
+            ));
+        }
+    }
+    for ip in allowed_ips.iter().filter(|ip| !valid_address(ip)) {
+        problems.push(format!(
+            "{} allowed IP \"{}\" is not an IP address or subnet",
+            label, ip
+        ));
+    }
+    if vlan_id.map_or(false, |vlan_id| vlan_id > MAX_VLAN_ID) {
+        problems.push(format!(
+            "{} VLAN ID must be from 0 to {}",
+            label, MAX_VLAN_ID
+        ));
+    }
+}
+
+/// A NIC to add
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Default, PartialEq, JsonSchema,
+)]
+pub struct NewNic {
+    pub nic_tag: String,
+    /// `ip/prefix`, `dhcp` or `addrconf`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ips: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub gateways: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vlan_id: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub primary: Option<bool>,
+    /// Addresses the instance may use besides its `ips`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_ips: Vec<String>,
+}
+
+/// Changes to the NIC with `mac`
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Default, PartialEq, JsonSchema,
+)]
+pub struct NicChange {
+    pub mac: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ips: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gateways: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vlan_id: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub primary: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_ips: Option<Vec<String>>,
+}
+
+/// A `vmadm update` payload changing an instance's NICs
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Default, PartialEq, JsonSchema,
+)]
+pub struct NicUpdate {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub add_nics: Vec<NewNic>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub update_nics: Vec<NicChange>,
+    /// MAC addresses of the NICs to remove
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub remove_nics: Vec<String>,
+}
+
+fn model_problems(
+    problems: &mut Vec<String>,
+    label: &str,
+    brand: &Brand,
+    model: &Option<String>,
+) {
+    let Some(model) = model else {
+        return;
+    };
+    let models = models(brand);
+    if models.is_empty() {
+        problems.push(format!("{} NICs don't have a model", brand));
+    } else if !models.contains(&model.as_str()) {
+        problems.push(format!(
+            "{} {} can be {}, not {}",
+            brand,
+            label,
+            models.join(", "),
+            model
+        ));
+    }
+}
+
+impl NicUpdate {
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.add_nics.is_empty()
+            && self.update_nics.is_empty()
+            && self.remove_nics.is_empty()
+    }
+
+    /// Why this update can't be made to a `brand` instance that has `nics`,
+    /// empty if it can
+    #[must_use]
+    pub fn problems(&self, brand: &Brand, nics: &[Nic]) -> Vec<String> {
+        let mut problems = Vec::new();
+        let find = |mac: &str| nics.iter().find(|nic| nic.mac == mac);
+
+        for nic in &self.add_nics {
+            if nic.nic_tag.trim().is_empty() {
+                problems.push(String::from("A new NIC needs a nic_tag"));
+            }
+            address_problems(
+                &mut problems,
+                "The new NIC's",
+                &nic.ips,
+                &nic.gateways,
+                &nic.allowed_ips,
+                nic.vlan_id,
+            );
+            model_problems(&mut problems, "NICs", brand, &nic.model);
+        }
+        for change in &self.update_nics {
+            if find(&change.mac).is_none() {
+                problems.push(format!("There is no NIC {}", change.mac));
+                continue;
+            }
+            address_problems(
+                &mut problems,
+                &change.mac,
+                change.ips.as_deref().unwrap_or_default(),
+                change.gateways.as_deref().unwrap_or_default(),
+                change.allowed_ips.as_deref().unwrap_or_default(),
+                change.vlan_id,
+            );
+            model_problems(&mut problems, "NICs", brand, &change.model);
+        }
+        for mac in &self.remove_nics {
+            match find(mac) {
+                None => problems.push(format!("There is no NIC {}", mac)),
+                Some(nic)
+                    if nic.primary == Some(true)
+                        && nics.len() > self.remove_nics.len() =>
+                {
+                    problems.push(format!(
+                        "{} is the primary NIC, make another NIC primary \
+                        first",
+                        mac
+                    ));
+                }
+                Some(_) => {}
+            }
+        }
+
+        // Whichever NICs are left, only one can be primary
+        let mut primary = nics
+            .iter()
+            .filter(|nic| !self.remove_nics.contains(&nic.mac))
+            .filter(|nic| {
+                self.update_nics
+                    .iter()
+                    .find(|change| change.mac == nic.mac)
+                    .and_then(|change| change.primary)
+                    .or(nic.primary)
+                    == Some(true)
+            })
+            .count();
+        primary += self
+            .add_nics
+            .iter()
+            .filter(|nic| nic.primary == Some(true))
+            .count();
+        if primary > 1 {
+            problems.push(String::from("Only one NIC can be primary"));
+        }
+        problems
+    }
+}
@@ -0,0 +1,138 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+//! Provisioning presets: named sizes for new instances, like Triton's
+//! packages. The executor keeps them in `PRESETS_FILE`, and choosing one on
+//! the provisioning form fills in its values.
+
+use std::net::IpAddr;
+
+use crate::sysinfo::Sysinfo;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Longest preset name, in characters
+pub const MAX_NAME_LENGTH: usize = 64;
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, JsonSchema)]
+pub struct Preset {
+    /// Letters, digits, `.`, `_` and `-`, e.g. "small-zone"
+    pub name: String,
+    /// MiB
+    pub ram: u64,
+    /// GiB, for OS and LX instances
+    #[serde(default)]
+    pub quota: Option<u64>,
+    /// For HVM instances
+    #[serde(default)]
+    pub vcpus: Option<u64>,
+    /// Percent of a CPU, e.g. 100 for one whole CPU
+    #[serde(default)]
+    pub cpu_cap: Option<u64>,
+    #[serde(default)]
+    pub nic_tag: Option<String>,
+    #[serde(default)]
+    pub resolvers: Vec<String>,
+}
+
+/// Whether `name` can name a preset
+#[must_use]
+pub fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.chars().count() <= MAX_NAME_LENGTH
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+}
+
+impl Preset {
+    /// Why this preset can't be used on the host described by `sysinfo`,
+    /// empty if it can
+    #[must_use]
+    pub fn problems(&self, sysinfo: &Sysinfo) -> Vec<String> {
+        let mut problems = Vec::new();
+        if !valid_name(&self.name) {
+            problems.push(format!(
+                "The name must be 1 to {} letters, digits, \".\", \"_\" or \
+                \"-\"",
+                MAX_NAME_LENGTH
+            ));
+        }
+        if self.ram == 0 {
+            problems.push(String::from("RAM must be greater than 0"));
+        } else if self.ram > sysinfo.mib_of_memory {
+            problems.push(format!(
+                "{} MiB of RAM is more than the host's {} MiB",
+                self.ram, sysinfo.mib_of_memory
+            ));
+        }
+        if let Some(quota) = self.quota {
+            if quota > sysinfo.zpool_size_in_gib {
+                problems.push(format!(
+                    "A {} GiB quota is more than the host's {} GiB zpool",
+                    quota, sysinfo.zpool_size_in_gib
+                ));
+            }
+        }
+        if let Some(vcpus) = self.vcpus {
+            if vcpus == 0 {
+                problems.push(String::from("vCPUs must be greater than 0"));
+            } else if vcpus > sysinfo.cpu_count {
+                problems.push(format!(
+                    "{} vCPUs is more than the host's {} CPUs",
+                    vcpus, sysinfo.cpu_count
+                ));
+            }
+        }
+        if let Some(cpu_cap) = self.cpu_cap {
+            if cpu_cap == 0 {
+                problems
+                    .push(String::from("The CPU cap must be greater than 0"));
+            } else if cpu_cap > sysinfo.cpu_count * 100 {
+                problems.push(format!(
+                    "A CPU cap of {} is more than the host's {} CPUs allow",
+                    cpu_cap, sysinfo.cpu_count
+                ));
+            }
+        }
+        for resolver in &self.resolvers {
+            if resolver.parse::<IpAddr>().is_err() {
+                problems.push(format!(
+                    "Resolver \"{}\" is not an IP address",
+                    resolver
+                ));
+            }
+        }
+        problems
+    }
+
+    /// e.g. "1024 MiB RAM, 10 GiB quota, cap 100"
+    #[must_use]
+    pub fn summary(&self) -> String {
+        let mut parts = vec![format!("{} MiB RAM", self.ram)];
+        if let Some(quota) = self.quota {
+            parts.push(format!("{} GiB quota", quota));
+        }
+        if let Some(vcpus) = self.vcpus {
+            parts.push(format!("{} vCPUs", vcpus));
+        }
+        if let Some(cpu_cap) = self.cpu_cap {
+            parts.push(format!("cap {}", cpu_cap));
+        }
+        if let Some(nic_tag) = &self.nic_tag {
+            parts.push(format!("NIC tag {}", nic_tag));
+        }
+        if !self.resolvers.is_empty() {
+            parts.push(format!("resolvers {}", self.resolvers.join(", ")));
+        }
+        parts.join(", ")
+    }
+}
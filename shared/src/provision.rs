@@ -0,0 +1,267 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+//! Checks a [CreatePayload] against its image's requirements and what is left
+//! of the host, before `vmadm create` is asked to do something that will fail
+//! or leave a guest that won't boot.
+
+use crate::image::Manifest;
+use crate::instance::{CreatePayload, InstanceView};
+use crate::sysinfo::Sysinfo;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// RAM vmadm gives an instance that doesn't set any, in MiB
+pub const DEFAULT_RAM: u64 = 256;
+
+/// Quota vmadm gives an OS or LX instance that doesn't set one, in GiB
+pub const DEFAULT_QUOTA: u64 = 10;
+
+/// A problem with one property of a payload. `field` is the provisioning
+/// form's input for it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, JsonSchema)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    #[must_use]
+    pub fn new(field: &str, message: String) -> Self {
+        Self { field: String::from(field), message }
+    }
+}
+
+/// What the host has and what its instances have been given
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Capacity {
+    /// MiB
+    pub total_ram: u64,
+    /// MiB
+    pub provisioned_ram: u64,
+    /// MiB
+    pub total_disk: u64,
+    /// MiB
+    pub provisioned_disk: u64,
+    pub total_cpu: u64,
+    pub provisioned_cpu: f32,
+}
+
+impl Capacity {
+    /// Instances are counted for what they've been given. One without a
+    /// limit, such as an uncapped zone, or whose size couldn't be read adds
+    /// nothing rather than all of the host.
+    #[must_use]
+    pub fn new(sysinfo: &Sysinfo, instances: &[InstanceView]) -> Self {
+        Self {
+            total_ram: sysinfo.mib_of_memory,
+            provisioned_ram: instances
+                .iter()
+                .fold(0, |acc, i| acc.saturating_add(i.ram)),
+            total_disk: sysinfo.zpool_size_in_gib.saturating_mul(1024),
+            provisioned_disk: instances
+                .iter()
+                .fold(0, |acc, i| acc.saturating_add(i.disk_usage)),
+            total_cpu: sysinfo.cpu_count,
+            provisioned_cpu: instances.iter().map(|i| i.cpu).sum(),
+        }
+    }
+
+    #[must_use]
+    pub fn free_ram(&self) -> u64 {
+        self.total_ram.saturating_sub(self.provisioned_ram)
+    }
+
+    #[must_use]
+    pub fn free_disk(&self) -> u64 {
+        self.total_disk.saturating_sub(self.provisioned_disk)
+    }
+
+    #[must_use]
+    pub fn free_cpu(&self) -> f32 {
+        (self.total_cpu as f32 - self.provisioned_cpu).max(0.0)
+    }
+}
+
+/// RAM the payload gives the instance, in MiB
+fn ram(payload: &CreatePayload) -> u64 {
+    payload.ram.or(payload.max_physical_memory).unwrap_or(DEFAULT_RAM)
+}
+
+/// Disk the payload gives the instance, in MiB. An HVM disk without a size
+/// is the size of its image.
+fn disk(payload: &CreatePayload, manifest: Option<&Manifest>) -> u64 {
+    if !payload.brand().is_hvm() {
+        return payload.quota.unwrap_or(DEFAULT_QUOTA).saturating_mul(1024);
+    }
+    if let Some(Value::Number(size)) = payload.extra.get("flexible_disk_size") {
+        return size.as_u64().unwrap_or_default();
+    }
+    payload
+        .disks
+        .iter()
+        .map(|disk| match (disk.size, disk.image_uuid) {
+            (Some(size), _) => size,
+            (None, Some(_)) => {
+                manifest.and_then(|m| m.image_size).unwrap_or_default()
+            }
+            (None, None) => 0,
+        })
+        .fold(0, u64::saturating_add)
+}
+
+/// The platform stamp a `min_platform` or `max_platform` requirement asks
+/// for. They are keyed by Triton version; SmartOS is 7.0.
+fn platform(requirement: &Option<Value>) -> Option<&str> {
+    let platforms = requirement.as_ref()?.as_object()?;
+    platforms
+        .get("7.0")
+        .or_else(|| platforms.values().next())
+        .and_then(Value::as_str)
+}
+
+/// Where the payload doesn't meet the requirements in its image's manifest,
+/// on a host running the `live_image` platform
+#[must_use]
+pub fn requirement_errors(
+    payload: &CreatePayload,
+    manifest: &Manifest,
+    live_image: &str,
+) -> Vec<FieldError> {
+    let mut errors = Vec::new();
+    let Some(requirements) = &manifest.requirements else {
+        return errors;
+    };
+    let name = format!("{} {}", manifest.name, manifest.version);
+
+    if let Some(brand) = &requirements.brand {
+        if *brand != payload.brand() {
+            errors.push(FieldError::new(
+                "brand",
+                format!("{} requires the {} brand", name, brand),
+            ));
+        }
+    }
+    let ram = ram(payload);
+    if let Some(min_ram) = requirements.min_ram {
+        if ram < min_ram {
+            errors.push(FieldError::new(
+                "ram",
+                format!("{} needs at least {} MiB of RAM", name, min_ram),
+            ));
+        }
+    }
+    if let Some(max_ram) = requirements.max_ram {
+        if ram > max_ram {
+            errors.push(FieldError::new(
+                "ram",
+                format!("{} can use at most {} MiB of RAM", name, max_ram),
+            ));
+        }
+    }
+    if requirements.ssh_key == Some(true)
+        && payload
+            .customer_metadata
+            .get("root_authorized_keys")
+            .and_then(Value::as_str)
+            .map_or(true, |keys| keys.trim().is_empty())
+    {
+        errors.push(FieldError::new(
+            "root_authorized_keys",
+            format!("{} requires an SSH key", name),
+        ));
+    }
+    if let Some(networks) = &requirements.networks {
+        if payload.nics.len() < networks.len() {
+            errors.push(FieldError::new(
+                "nic_tag",
+                format!("{} needs {} NICs", name, networks.len()),
+            ));
+        }
+    }
+    // Platform stamps such as 20231102T000310Z sort by date
+    if let Some(min_platform) = platform(&requirements.min_platform) {
+        if live_image < min_platform {
+            errors.push(FieldError::new(
+                "image_uuid",
+                format!(
+                    "{} needs platform {} or later, this host runs {}",
+                    name, min_platform, live_image
+                ),
+            ));
+        }
+    }
+    if let Some(max_platform) = platform(&requirements.max_platform) {
+        if live_image > max_platform {
+            errors.push(FieldError::new(
+                "image_uuid",
+                format!(
+                    "{} needs platform {} or earlier, this host runs {}",
+                    name, max_platform, live_image
+                ),
+            ));
+        }
+    }
+    errors
+}
+
+/// Where the payload asks for more than is left of the host. Only a capped
+/// instance is checked for CPU, since an uncapped one shares what there is.
+#[must_use]
+pub fn capacity_errors(
+    payload: &CreatePayload,
+    manifest: Option<&Manifest>,
+    capacity: &Capacity,
+) -> Vec<FieldError> {
+    let mut errors = Vec::new();
+    let ram = ram(payload);
+    if ram > capacity.free_ram() {
+        errors.push(FieldError::new(
+            "ram",
+            format!(
+                "{} MiB of RAM is more than the {} MiB left",
+                ram,
+                capacity.free_ram()
+            ),
+        ));
+    }
+    let disk = disk(payload, manifest);
+    if disk > capacity.free_disk() {
+        let field = if payload.brand().is_hvm() {
+            "primary_disk_size"
+        } else {
+            "quota"
+        };
+        errors.push(FieldError::new(
+            field,
+            format!(
+                "{} GiB of disk is more than the {} GiB left",
+                disk / 1024,
+                capacity.free_disk() / 1024
+            ),
+        ));
+    }
+    if let Some(cpu_cap) = payload.cpu_cap {
+        let cpu = cpu_cap as f32 / 100.0;
+        if cpu > capacity.free_cpu() {
+            errors.push(FieldError::new(
+                "cpu_cap",
+                format!(
+                    "{} CPUs is more than the {} left",
+                    cpu,
+                    capacity.free_cpu()
+                ),
+            ));
+        }
+    }
+    errors
+}
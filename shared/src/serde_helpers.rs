@@ -122,3 +122,23 @@ where
         _ => Err(de::Error::custom("Expected either boolean or String")),
     }
 }
+
+/// Treat an empty string as [None], otherwise deserialize the string as `T`.
+/// Forms send their empty fields, e.g. a select with nothing chosen, which
+/// should be the same as leaving them out.
+pub fn deserialize_empty_as_none<'de, D, T>(
+    data: D,
+) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    let value: Option<String> = Option::deserialize(data)?;
+    match value.as_deref().map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(value) => T::deserialize(de::value::StringDeserializer::new(
+            String::from(value),
+        ))
+        .map(Some),
+    }
+}
@@ -12,6 +12,8 @@ use serde::Deserialize;
 
 #[derive(Deserialize)]
 pub struct Sysinfo {
+    #[serde(rename = "Hostname", default)]
+    pub hostname: String,
     #[serde(rename = "Live Image", default)]
     pub live_image: String,
     #[serde(rename = "CPU Count", default)]
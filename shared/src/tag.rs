@@ -0,0 +1,110 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+//! Changing the tags of an existing instance. A [TagUpdate] is the
+//! `vmadm update` payload itself, with its `set_tags` and `remove_tags`.
+
+use crate::instance::{TagValue, Tags};
+
+use std::collections::BTreeMap;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// The instance list's tag filter, `key` or `key=value`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagFilter {
+    pub key: String,
+    pub value: Option<String>,
+}
+
+impl TagFilter {
+    #[must_use]
+    pub fn parse(filter: &str) -> Self {
+        match filter.split_once('=') {
+            Some((key, value)) => Self {
+                key: String::from(key.trim()),
+                value: Some(String::from(value.trim())),
+            },
+            None => Self { key: String::from(filter.trim()), value: None },
+        }
+    }
+
+    /// Whether `tags` has the key, with the value if there is one
+    #[must_use]
+    pub fn matches(&self, tags: &Tags) -> bool {
+        match (tags.get(&self.key), &self.value) {
+            (None, _) => false,
+            (Some(_), None) => true,
+            (Some(tag), Some(value)) => tag.to_string() == *value,
+        }
+    }
+}
+
+/// Tags written as `key=value`, separated with commas, as on the
+/// provisioning form. A key without a value is an empty string.
+#[must_use]
+pub fn parse_tags(tags: &str) -> Tags {
+    tags.split(',')
+        .map(str::trim)
+        .filter(|tag| !tag.is_empty())
+        .map(|tag| {
+            let (key, value) = tag.split_once('=').unwrap_or((tag, ""));
+            (String::from(key.trim()), TagValue::parse(value.trim()))
+        })
+        .collect()
+}
+
+/// A `vmadm update` payload changing an instance's tags
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Default, PartialEq, JsonSchema,
+)]
+pub struct TagUpdate {
+    /// Tags to add, or to change the value of
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub set_tags: Tags,
+    /// Keys of the tags to remove
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub remove_tags: Vec<String>,
+}
+
+impl TagUpdate {
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.set_tags.is_empty() && self.remove_tags.is_empty()
+    }
+
+    /// Why this update can't be made to an instance with `tags`, empty if it
+    /// can
+    #[must_use]
+    pub fn problems(&self, tags: &Tags) -> Vec<String> {
+        let mut problems = Vec::new();
+        for key in self.set_tags.keys() {
+            if key.trim().is_empty() {
+                problems.push(String::from("A tag needs a key"));
+            } else if key.trim() != key {
+                problems.push(format!(
+                    "Tag key \"{}\" can't start or end with a space",
+                    key
+                ));
+            }
+            if self.remove_tags.contains(key) {
+                problems
+                    .push(format!("Tag {} can't be both set and removed", key));
+            }
+        }
+        for key in &self.remove_tags {
+            if !tags.contains_key(key) {
+                problems.push(format!("There is no tag {}", key));
+            }
+        }
+        problems
+    }
+}
@@ -0,0 +1,23 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// A certificate chain and private key for the UI's HTTPS server, already
+/// checked by the UI, for the executor to write over `CERT_FILE` and
+/// `KEY_FILE`
+#[derive(Deserialize, Serialize, Debug, JsonSchema)]
+pub struct TlsInstallRequest {
+    /// PEM certificate chain, leaf first
+    pub cert: String,
+    /// PEM PKCS #8 private key
+    pub key: String,
+}
@@ -0,0 +1,78 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+use smartos_shared::auth::{
+    constant_time_eq, ApiToken, Permission, Role, Scope,
+};
+
+use time::OffsetDateTime;
+
+#[test]
+fn test_role_permissions() {
+    assert!(Role::Viewer.can(Permission::View));
+    assert!(!Role::Viewer.can(Permission::PowerControl));
+
+    assert!(Role::Operator.can(Permission::PowerControl));
+    assert!(!Role::Operator.can(Permission::Provision));
+    assert!(!Role::Operator.can(Permission::Delete));
+
+    assert!(Role::Admin.can(Permission::Provision));
+    assert!(Role::Admin.can(Permission::Delete));
+    assert!(Role::Admin.can(Permission::ManageImages));
+    assert!(Role::Admin.can(Permission::Configure));
+    assert!(Role::Admin.can(Permission::ManageSessions));
+    assert!(!Role::Operator.can(Permission::ManageSessions));
+    assert!(Role::Admin.can(Permission::ManageLockouts));
+    assert!(!Role::Operator.can(Permission::ManageLockouts));
+}
+
+#[test]
+fn test_role_serde() {
+    let role: Role = serde_json::from_str("\"operator\"").unwrap();
+    assert_eq!(role, Role::Operator);
+    assert_eq!(serde_json::to_string(&Role::Admin).unwrap(), "\"admin\"");
+    assert!(serde_json::from_str::<Role>("\"root\"").is_err());
+}
+
+#[test]
+fn test_token_scopes() {
+    let token = ApiToken {
+        id: String::from("id"),
+        name: String::from("ci"),
+        user: String::from("olive"),
+        scopes: vec![Scope::Read, Scope::PowerControl, Scope::Delete],
+        created: OffsetDateTime::now_utc(),
+        last_used: None,
+    };
+    assert!(token.allows(Role::Operator, Permission::View));
+    assert!(token.allows(Role::Operator, Permission::PowerControl));
+    // The scope doesn't grant more than the owner's role
+    assert!(!token.allows(Role::Operator, Permission::Delete));
+    assert!(token.allows(Role::Admin, Permission::Delete));
+    assert!(!token.allows(Role::Admin, Permission::Provision));
+    // Tokens can't be given permissions outside the API
+    assert!(!token.allows(Role::Admin, Permission::ManageSessions));
+
+    for scope in Scope::ALL {
+        assert_eq!(scope.to_string().parse::<Scope>(), Ok(scope));
+        assert_eq!(
+            serde_json::to_string(&scope).unwrap(),
+            format!("\"{}\"", scope)
+        );
+    }
+}
+
+#[test]
+fn test_constant_time_eq() {
+    assert!(constant_time_eq(b"abc", b"abc"));
+    assert!(!constant_time_eq(b"abc", b"abd"));
+    assert!(!constant_time_eq(b"abc", b"ab"));
+    assert!(!constant_time_eq(b"", b"abc"));
+}
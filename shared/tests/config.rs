@@ -0,0 +1,102 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+use dropshot::ConfigLoggingLevel;
+use smartos_shared::config::{Command, Config};
+
+const FILE: &str = r#"
+log_level = "warn"
+exec_cache_seconds = 60
+session_hours = 12
+metrics_enabled = true
+
+[executor]
+log_file = "/var/log/executor.log"
+log_level = "debug"
+"#;
+
+#[test]
+fn test_sources() {
+    let no_env = |_: &str| None;
+    let config = Config::from_sources("smartos_ui", None, no_env).unwrap();
+    assert_eq!(config.log_file, "/var/log/smartos_ui.log");
+    assert_eq!(config.log_level, ConfigLoggingLevel::Info);
+    assert_eq!(config.exec_cache_seconds, 300);
+    assert_eq!(config.session_hours, 8);
+    assert!(!config.metrics_enabled);
+
+    // The file overrides the defaults, and the service's table the rest of
+    // the file
+    let file = Some(("config.toml", FILE));
+    let config = Config::from_sources("smartos_ui", file, no_env).unwrap();
+    assert_eq!(config.log_level, ConfigLoggingLevel::Warn);
+    assert_eq!(config.exec_cache_seconds, 60);
+    assert_eq!(config.session_hours, 12);
+    assert!(config.metrics_enabled);
+    let config =
+        Config::from_sources("smartos_ui_executor", file, no_env).unwrap();
+    assert_eq!(config.log_file, "/var/log/executor.log");
+    assert_eq!(config.log_level, ConfigLoggingLevel::Debug);
+
+    // The environment overrides the file
+    let env = |var: &str| match var {
+        "EXEC_CACHE" => Some(String::from("5")),
+        "LOG_LEVEL" => Some(String::from("ERROR")),
+        "METRICS_ENABLED" => Some(String::new()),
+        _ => None,
+    };
+    let config = Config::from_sources("smartos_ui", file, env).unwrap();
+    assert_eq!(config.exec_cache_seconds, 5);
+    assert_eq!(config.log_level, ConfigLoggingLevel::Error);
+    assert!(!config.metrics_enabled);
+}
+
+#[test]
+fn test_invalid() {
+    // Every problem is reported, not just the first
+    let env = |var: &str| match var {
+        "REQ_MAX_BYTES" => Some(String::from("8M")),
+        "EXEC_BIND_ADDRESS" => Some(String::from("localhost")),
+        "LOGIN_LOCKOUT_MAX_SECONDS" => Some(String::from("10")),
+        _ => None,
+    };
+    let errors = Config::from_sources("smartos_ui", None, env).unwrap_err().0;
+    assert_eq!(errors.len(), 3, "{:?}", errors);
+    assert!(errors[0].starts_with("REQ_MAX_BYTES=\"8M\""));
+    assert!(errors[1].starts_with("exec_bind_address (EXEC_BIND_ADDRESS)"));
+    assert!(errors[2].starts_with("login_lockout_max_seconds"));
+
+    let no_env = |_: &str| None;
+    for file in [
+        "exec_cach_seconds = 60",
+        "session_hours = \"12\"",
+        "session_hours = 0",
+        "login_lockout_max_seconds = 18446744073709551615",
+        "log_level = \"verbose\"",
+    ] {
+        assert!(
+            Config::from_sources("smartos_ui", Some(("c.toml", file)), no_env)
+                .is_err(),
+            "{}",
+            file
+        );
+    }
+}
+
+#[test]
+fn test_command() {
+    let args = |line: &str| {
+        line.split_whitespace().map(String::from).collect::<Vec<_>>()
+    };
+    assert_eq!(Command::parse(&args("config check")), Some(Command::Check));
+    assert_eq!(Command::parse(&args("config dump")), Some(Command::Dump));
+    assert_eq!(Command::parse(&args("config")), None);
+    assert_eq!(Command::parse(&args("version")), None);
+}
@@ -0,0 +1,74 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+use smartos_shared::disk::{disk_name, DiskUpdate};
+use smartos_shared::instance::{Brand, Disk};
+
+use serde_json::json;
+
+const PATH: &str = "/dev/zvol/rdsk/zones/bbbbbbbb-bbbb-bbbb-bbbb-bbbbbbbbbbbb";
+
+fn disks() -> Vec<Disk> {
+    serde_json::from_value(json!([
+        { "path": format!("{}/disk0", PATH), "boot": true, "size": 10240 },
+        { "path": format!("{}/disk1", PATH), "boot": false, "size": 20480 }
+    ]))
+    .unwrap()
+}
+
+#[test]
+fn test_disk_update_problems() {
+    let update: DiskUpdate = serde_json::from_value(json!({
+        "add_disks": [
+            { "size": 0, "model": "ide", "block_size": 1000, "boot": true }
+        ],
+        "update_disks": [
+            { "path": format!("{}/disk1", PATH), "size": 10240,
+              "compression": "zstd" },
+            { "path": format!("{}/disk9", PATH), "size": 10240 }
+        ],
+        "remove_disks": [format!("{}/disk8", PATH)]
+    }))
+    .unwrap();
+
+    assert_eq!(
+        update.problems(&Brand::Bhyve, &disks()),
+        vec![
+            String::from("A disk's size must be over 0"),
+            String::from("bhyve disks can be virtio, ahci, nvme, not ide"),
+            String::from(
+                "The block size must be a power of 2 from 512 to 131072"
+            ),
+            String::from("disk1 can't shrink from 20480 MiB to 10240 MiB"),
+            String::from(
+                "Compression must be one of off, lz4, lzjb, gzip, zle"
+            ),
+            format!("There is no disk {}/disk9", PATH),
+            format!("There is no disk {}/disk8", PATH),
+            String::from("Only one disk can be the boot disk"),
+        ]
+    );
+    assert_eq!(
+        update.problems(&Brand::Joyent, &[]),
+        vec![String::from("joyent instances don't have disks")]
+    );
+
+    // Moving the boot flag to another disk in the same update is fine
+    let update: DiskUpdate = serde_json::from_value(json!({
+        "update_disks": [
+            { "path": format!("{}/disk0", PATH), "boot": false },
+            { "path": format!("{}/disk1", PATH), "boot": true, "size": 30720 }
+        ],
+        "add_disks": [{ "size": 5120, "model": "nvme", "block_size": 4096 }]
+    }))
+    .unwrap();
+    assert!(update.problems(&Brand::Bhyve, &disks()).is_empty());
+    assert_eq!(disk_name(&format!("{}/disk1", PATH)), "disk1");
+}
@@ -0,0 +1,47 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+use std::fs::File;
+use std::time::Instant;
+
+use smartos_shared::health::{
+    disk_space, format_bytes, overall, Check, Status,
+};
+
+#[test]
+fn test_overall() {
+    let mut checks = vec![
+        Check::timed("executor", Instant::now()),
+        Check::new("vminfod", Status::Ok),
+    ];
+    assert_eq!(overall(&checks), Status::Ok);
+    checks.push(Check::new("tls_certificate", Status::Degraded));
+    assert_eq!(overall(&checks), Status::Degraded);
+    checks.push(Check::down("imgadm", "not found"));
+    assert_eq!(overall(&checks), Status::Down);
+    assert_eq!(overall(&[]), Status::Ok);
+
+    let mut check = Check::down("imgadm", "not found");
+    check.version = Some(String::from("3.13.2"));
+    let summary = check.summary();
+    assert_eq!(summary.status, Status::Down);
+    assert!(summary.error.is_none() && summary.version.is_none());
+}
+
+#[test]
+fn test_disk_space() {
+    let file = File::open(env!("CARGO_MANIFEST_DIR")).unwrap();
+    let check = disk_space("disk", &file);
+    assert!(check.detail.unwrap().contains(" free of "));
+
+    assert_eq!(format_bytes(512), "512 B");
+    assert_eq!(format_bytes(1536), "1.5 KiB");
+    assert_eq!(format_bytes(10 * 1024 * 1024 * 1024), "10.0 GiB");
+}
@@ -0,0 +1,147 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+use smartos_shared::instance::{Brand, CreatePayload, Instance, InstanceView};
+
+use serde_json::{json, Value};
+use slog::{o, Discard, Logger};
+use uuid::Uuid;
+
+#[test]
+fn test_create_payload_round_trip() {
+    let value = json!({
+        "brand": "bhyve",
+        "alias": "vm01",
+        "ram": 2048,
+        "vcpus": 2,
+        "disks": [{
+            "image_uuid": "22222222-2222-2222-2222-222222222222",
+            "boot": true,
+            "model": "virtio",
+            "size": 20480,
+            "compression": "lz4"
+        }],
+        "nics": [{
+            "nic_tag": "admin",
+            "ips": ["10.0.0.5/24", "addrconf"],
+            "gateways": ["10.0.0.1"],
+            "model": "virtio",
+            "allow_ip_spoofing": true
+        }],
+        "customer_metadata": { "user-script": "#!/bin/sh" },
+        "bootrom": "uefi"
+    });
+
+    let payload: CreatePayload = serde_json::from_value(value.clone()).unwrap();
+    assert_eq!(payload.brand(), Brand::Bhyve);
+    assert_eq!(
+        payload.image(),
+        Some(Uuid::parse_str("22222222-2222-2222-2222-222222222222").unwrap())
+    );
+    assert!(payload.problems().is_empty());
+
+    // Properties that aren't typed are passed on unchanged
+    assert_eq!(serde_json::to_value(&payload).unwrap(), value);
+}
+
+#[test]
+fn test_create_payload_problems() {
+    let mut payload: CreatePayload = serde_json::from_value(json!({
+        "ram": 0,
+        "delegate_dataset": true,
+        "resolvers": ["8.8.8.8", "dns"],
+        "nics": [
+            { "nic_tag": "admin", "ips": ["10.0.0.5/33"], "primary": true },
+            { "ips": ["dhcp"], "gateways": ["gw"], "primary": true }
+        ]
+    }))
+    .unwrap();
+
+    // An OS instance, the default brand
+    assert_eq!(
+        payload.problems(),
+        vec![
+            "An image_uuid is required",
+            "RAM must be greater than 0",
+            "Resolver \"dns\" is not an IP address",
+            "NIC 0 address \"10.0.0.5/33\" is not an IP address, dhcp or \
+            addrconf",
+            "NIC 1 needs a nic_tag",
+            "NIC 1 gateway \"gw\" is not an IP address",
+            "Only one NIC can be primary",
+        ]
+    );
+
+    payload.brand = Some(Brand::KVM);
+    assert!(payload.problems().contains(&String::from(
+        "kvm instances can't have a delegated dataset"
+    )));
+    assert!(payload
+        .problems()
+        .contains(&String::from("The boot disk must have an image_uuid")));
+
+    assert!(payload.uuid.is_none());
+    let uuid = payload.ensure_uuid();
+    assert_eq!(payload.ensure_uuid(), uuid);
+}
+
+fn web01() -> Value {
+    json!({
+        "uuid": "aaaaaaaa-aaaa-aaaa-aaaa-aaaaaaaaaaaa", "alias": "web01",
+        "brand": "joyent", "state": "running", "v": 1, "hvm": false,
+        "image_uuid": "11111111-1111-1111-1111-111111111111",
+        "quota": 10, "max_physical_memory": 512, "tags": { "env": "prod" },
+        "firewall_enabled": false, "autoboot": true,
+        "billing_id": "00000000-0000-0000-0000-000000000000",
+        "owner_uuid": "00000000-0000-0000-0000-000000000000",
+        "limit_priv": "default", "zfs_filesystem": "zones/web01",
+        "zonepath": "/zones/web01", "create_timestamp": "2025-01-01T00:00:00Z",
+        "last_modified": "2025-01-01T00:00:00Z",
+        "platform_buildstamp": "20250101T000000Z", "nics": []
+    })
+}
+
+#[test]
+fn test_unknown_instance() {
+    let instance: Instance = serde_json::from_value(web01()).unwrap();
+    assert!(matches!(instance, Instance::Joyent(_)));
+    assert!(!InstanceView::from(instance).partial);
+
+    // Missing a property a joyent instance should have
+    let mut vm = web01();
+    vm.as_object_mut().unwrap().remove("billing_id");
+    let instance: Instance = serde_json::from_value(vm.clone()).unwrap();
+    let Instance::Unknown(ref unknown) = instance else {
+        panic!("expected an unknown instance, got {:?}", instance);
+    };
+    assert!(unknown.error.contains("billing_id"));
+    assert_eq!(serde_json::to_value(&instance).unwrap(), vm);
+    let view = InstanceView::from(instance);
+    assert!(view.partial);
+    assert_eq!(
+        (view.alias.as_str(), view.brand, view.ram, view.state.as_str()),
+        ("web01", Brand::Joyent, 512, "running")
+    );
+    assert_eq!(view.tags.len(), 1);
+
+    // A brand there's no variant for, and a VM that can't be read at all
+    let mut lxd = web01();
+    lxd["brand"] = json!("lxd");
+    let mut broken = web01();
+    broken["uuid"] = json!("web01");
+    let log = Logger::root(Discard, o!());
+    let instances =
+        Instance::deserialize_list(vec![lxd, broken, web01()], &log);
+    assert_eq!(instances.len(), 2);
+    assert_eq!(instances[0].brand(), Brand::LXD);
+    assert!(matches!(instances[1], Instance::Joyent(_)));
+
+    assert_eq!("other".parse::<Brand>().unwrap(), Brand::Other);
+}
@@ -0,0 +1,59 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+use smartos_shared::metrics::{escape_label, header, sample, Histogram};
+
+#[test]
+fn test_sample() {
+    let mut out = String::new();
+    header(&mut out, "smartos_images", "gauge", "Installed images by type");
+    sample(&mut out, "smartos_images", &[("type", "zvol")], 2);
+    sample(&mut out, "smartos_up", &[], 1);
+    assert_eq!(
+        out,
+        "# HELP smartos_images Installed images by type\n\
+         # TYPE smartos_images gauge\n\
+         smartos_images{type=\"zvol\"} 2\n\
+         smartos_up 1\n"
+    );
+}
+
+#[test]
+fn test_escape_label() {
+    assert_eq!(escape_label("plain"), "plain");
+    assert_eq!(escape_label("a\"b"), "a\\\"b");
+    assert_eq!(escape_label("a\\b"), "a\\\\b");
+    assert_eq!(escape_label("a\nb"), "a\\nb");
+}
+
+#[test]
+fn test_histogram() {
+    let mut histogram = Histogram::default();
+    histogram.observe(0.001);
+    histogram.observe(0.2);
+    histogram.observe(1000.0);
+    assert_eq!(histogram.count(), 3);
+
+    let mut out = String::new();
+    histogram.write(&mut out, "cmd_seconds", &[("command", "vmadm list")]);
+    let lines: Vec<&str> = out.lines().collect();
+
+    // Buckets are cumulative, the +Inf bucket includes everything
+    assert!(lines.contains(
+        &"cmd_seconds_bucket{command=\"vmadm list\",le=\"0.005\"} 1"
+    ));
+    assert!(lines
+        .contains(&"cmd_seconds_bucket{command=\"vmadm list\",le=\"0.25\"} 2"));
+    assert!(lines
+        .contains(&"cmd_seconds_bucket{command=\"vmadm list\",le=\"300\"} 2"));
+    assert!(lines
+        .contains(&"cmd_seconds_bucket{command=\"vmadm list\",le=\"+Inf\"} 3"));
+    assert!(lines.contains(&"cmd_seconds_count{command=\"vmadm list\"} 3"));
+}
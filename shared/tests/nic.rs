@@ -0,0 +1,83 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+use smartos_shared::instance::{Brand, Nic};
+use smartos_shared::nic::NicUpdate;
+
+use serde_json::json;
+
+fn nics() -> Vec<Nic> {
+    serde_json::from_value(json!([
+        { "mac": "02:08:20:00:00:01", "nic_tag": "admin", "primary": true },
+        { "mac": "02:08:20:00:00:02", "nic_tag": "internal" }
+    ]))
+    .unwrap()
+}
+
+#[test]
+fn test_nic_update_problems() {
+    let update: NicUpdate = serde_json::from_value(json!({
+        "add_nics": [{
+            "nic_tag": "",
+            "ips": ["10.0.0.300/24"],
+            "gateways": ["gw"],
+            "vlan_id": 5000,
+            "model": "rtl8139",
+            "primary": true
+        }],
+        "update_nics": [
+            { "mac": "02:08:20:00:00:02", "allowed_ips": ["10.1.0.0/33"] },
+            { "mac": "02:08:20:00:00:09", "ips": ["dhcp"] }
+        ],
+        "remove_nics": ["02:08:20:00:00:01"]
+    }))
+    .unwrap();
+
+    assert_eq!(
+        update.problems(&Brand::Bhyve, &nics()),
+        vec![
+            String::from("A new NIC needs a nic_tag"),
+            String::from(
+                "The new NIC's address \"10.0.0.300/24\" is not an IP \
+                address, dhcp or addrconf"
+            ),
+            String::from("The new NIC's gateway \"gw\" is not an IP address"),
+            String::from("The new NIC's VLAN ID must be from 0 to 4095"),
+            String::from("bhyve NICs can be virtio, e1000, not rtl8139"),
+            String::from(
+                "02:08:20:00:00:02 allowed IP \"10.1.0.0/33\" is not an IP \
+                address or subnet"
+            ),
+            String::from("There is no NIC 02:08:20:00:00:09"),
+            String::from(
+                "02:08:20:00:00:01 is the primary NIC, make another NIC \
+                primary first"
+            ),
+        ]
+    );
+
+    // Moving the primary flag in one update is fine, leaving two isn't
+    let update: NicUpdate = serde_json::from_value(json!({
+        "update_nics": [
+            { "mac": "02:08:20:00:00:01", "primary": false },
+            { "mac": "02:08:20:00:00:02", "primary": true, "vlan_id": 12 }
+        ]
+    }))
+    .unwrap();
+    assert!(update.problems(&Brand::Joyent, &nics()).is_empty());
+    let update: NicUpdate = serde_json::from_value(json!({
+        "add_nics": [{ "nic_tag": "external", "primary": true }]
+    }))
+    .unwrap();
+    assert_eq!(
+        update.problems(&Brand::Joyent, &nics()),
+        vec![String::from("Only one NIC can be primary")]
+    );
+}
@@ -0,0 +1,70 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+use smartos_shared::preset::{valid_name, Preset};
+use smartos_shared::sysinfo::Sysinfo;
+
+use serde_json::json;
+
+fn sysinfo() -> Sysinfo {
+    serde_json::from_value(json!({
+        "CPU Count": 4,
+        "MiB of Memory": "8192",
+        "Zpool Size in GiB": 100,
+        "Boot Parameters": {}
+    }))
+    .unwrap()
+}
+
+fn preset() -> Preset {
+    Preset {
+        name: String::from("small-zone"),
+        ram: 1024,
+        quota: Some(10),
+        vcpus: None,
+        cpu_cap: Some(100),
+        nic_tag: None,
+        resolvers: vec![String::from("1.1.1.1")],
+    }
+}
+
+#[test]
+fn test_problems() {
+    assert!(preset().problems(&sysinfo()).is_empty());
+    assert_eq!(
+        preset().summary(),
+        "1024 MiB RAM, 10 GiB quota, cap 100, resolvers 1.1.1.1"
+    );
+
+    let too_big = Preset {
+        ram: 16384,
+        quota: Some(200),
+        vcpus: Some(8),
+        cpu_cap: Some(500),
+        resolvers: vec![String::from("dns.example.com")],
+        ..preset()
+    };
+    assert_eq!(too_big.problems(&sysinfo()).len(), 5);
+
+    let empty = Preset { ram: 0, vcpus: Some(0), ..preset() };
+    assert_eq!(
+        empty.problems(&sysinfo()),
+        ["RAM must be greater than 0", "vCPUs must be greater than 0"]
+    );
+}
+
+#[test]
+fn test_valid_name() {
+    assert!(valid_name("small-zone_1.0"));
+    assert!(!valid_name(""));
+    assert!(!valid_name("small zone"));
+    assert!(!valid_name("../users"));
+    assert!(!valid_name(&"a".repeat(65)));
+}
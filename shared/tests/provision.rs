@@ -0,0 +1,175 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+use smartos_shared::image::Manifest;
+use smartos_shared::instance::{CreatePayload, InstanceView};
+use smartos_shared::provision::{
+    capacity_errors, requirement_errors, Capacity, FieldError,
+};
+use smartos_shared::sysinfo::Sysinfo;
+
+use serde_json::json;
+
+fn manifest() -> Manifest {
+    serde_json::from_value(json!({
+        "v": 2,
+        "uuid": "11111111-1111-1111-1111-111111111111",
+        "name": "base-64",
+        "version": "24.4.0",
+        "type": "zone-dataset",
+        "os": "smartos",
+        "requirements": {
+            "brand": "joyent",
+            "ssh_key": true,
+            "min_ram": 512,
+            "max_ram": 4096,
+            "networks": [{ "name": "net0" }],
+            "min_platform": { "7.0": "20240101T000000Z" }
+        }
+    }))
+    .unwrap()
+}
+
+fn fields(errors: &[FieldError]) -> Vec<&str> {
+    errors.iter().map(|e| e.field.as_str()).collect()
+}
+
+#[test]
+fn test_requirement_errors() {
+    let mut payload: CreatePayload = serde_json::from_value(json!({
+        "brand": "lx",
+        "image_uuid": "11111111-1111-1111-1111-111111111111",
+        "ram": 256
+    }))
+    .unwrap();
+
+    let errors = requirement_errors(&payload, &manifest(), "20231102T000310Z");
+    assert_eq!(
+        fields(&errors),
+        vec!["brand", "ram", "root_authorized_keys", "nic_tag", "image_uuid"]
+    );
+    assert_eq!(
+        errors[4].message,
+        "base-64 24.4.0 needs platform 20240101T000000Z or later, this host \
+        runs 20231102T000310Z"
+    );
+
+    payload = serde_json::from_value(json!({
+        "brand": "joyent",
+        "image_uuid": "11111111-1111-1111-1111-111111111111",
+        "ram": 1024,
+        "nics": [{ "nic_tag": "admin", "ips": ["dhcp"] }],
+        "customer_metadata": { "root_authorized_keys": "ssh-ed25519 AAAA" }
+    }))
+    .unwrap();
+    assert!(requirement_errors(&payload, &manifest(), "20250101T000000Z")
+        .is_empty());
+}
+
+#[test]
+fn test_capacity_errors() {
+    let sysinfo: Sysinfo = serde_json::from_value(json!({
+        "CPU Count": 4,
+        "MiB of Memory": "8192",
+        "Zpool Size in GiB": 100,
+        "Boot Parameters": {}
+    }))
+    .unwrap();
+    let instance: InstanceView = serde_json::from_value(json!({
+        "uuid": "aaaaaaaa-aaaa-aaaa-aaaa-aaaaaaaaaaaa",
+        "alias": "web01",
+        "brand": "joyent",
+        "state": "running",
+        "ram": 6144,
+        "disk_usage": 92160,
+        "hvm": false,
+        "image_uuid": "11111111-1111-1111-1111-111111111111",
+        "cpu": 3.0,
+        "primary_ip": null
+    }))
+    .unwrap();
+    let capacity = Capacity::new(&sysinfo, &[instance]);
+    assert_eq!(capacity.free_ram(), 2048);
+    assert_eq!(capacity.free_disk(), 10240);
+
+    let payload: CreatePayload = serde_json::from_value(json!({
+        "ram": 4096,
+        "quota": 20,
+        "cpu_cap": 200
+    }))
+    .unwrap();
+    assert_eq!(
+        fields(&capacity_errors(&payload, None, &capacity)),
+        vec!["ram", "quota", "cpu_cap"]
+    );
+
+    // vmadm's defaults fit
+    assert!(
+        capacity_errors(&CreatePayload::default(), None, &capacity).is_empty()
+    );
+}
+
+#[test]
+fn test_capacity_unlimited_instances() {
+    let sysinfo: Sysinfo = serde_json::from_value(json!({
+        "CPU Count": 4,
+        "MiB of Memory": "8192",
+        "Zpool Size in GiB": 100,
+        "Boot Parameters": {}
+    }))
+    .unwrap();
+    let instances: Vec<InstanceView> = serde_json::from_value(json!([
+        {
+            // An OS zone without a CPU cap
+            "uuid": "aaaaaaaa-aaaa-aaaa-aaaa-aaaaaaaaaaaa",
+            "alias": "web01",
+            "brand": "joyent",
+            "state": "running",
+            "ram": 1024,
+            "disk_usage": 10240,
+            "hvm": false,
+            "image_uuid": "11111111-1111-1111-1111-111111111111",
+            "cpu": 0.0,
+            "primary_ip": null
+        },
+        {
+            // A brand only partly read, so its disk and CPU aren't known
+            "uuid": "bbbbbbbb-bbbb-bbbb-bbbb-bbbbbbbbbbbb",
+            "alias": "lxd01",
+            "brand": "lxd",
+            "state": "stopped",
+            "ram": 2048,
+            "hvm": false,
+            "image_uuid": "00000000-0000-0000-0000-000000000000",
+            "primary_ip": null,
+            "partial": true
+        }
+    ]))
+    .unwrap();
+    let capacity = Capacity::new(&sysinfo, &instances);
+    assert_eq!(capacity.free_ram(), 5120);
+    assert_eq!(capacity.free_disk(), 92160);
+    assert_eq!(capacity.free_cpu(), 4.0);
+
+    let payload: CreatePayload = serde_json::from_value(json!({
+        "ram": 4096,
+        "quota": 80,
+        "cpu_cap": 200
+    }))
+    .unwrap();
+    assert!(capacity_errors(&payload, None, &capacity).is_empty());
+
+    // A quota too large to be counted in MiB is more than there is
+    let payload = CreatePayload { quota: Some(u64::MAX), ..payload };
+    assert_eq!(
+        fields(&capacity_errors(&payload, None, &capacity)),
+        vec!["quota"]
+    );
+}
@@ -107,6 +107,7 @@ struct TestStructBool {
 }
 
 #[test]
+#[allow(clippy::bool_comparison)]
 fn test_deserialize_into_bool() {
     let bool_true: TestStructBool =
         serde_json::from_str("{\"value\":true}").expect("failed to parse true");
@@ -0,0 +1,42 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+use smartos_shared::instance::{TagValue, Tags};
+use smartos_shared::tag::{parse_tags, TagUpdate};
+
+use serde_json::json;
+
+#[test]
+fn test_tag_update() {
+    let tags: Tags =
+        serde_json::from_value(json!({ "env": "prod", "replicas": 3 }))
+            .unwrap();
+    assert_eq!(tags["replicas"], TagValue::parse("3"));
+
+    let update = TagUpdate {
+        set_tags: parse_tags("env=dev, backup=true, =x, owner"),
+        remove_tags: vec![String::from("env"), String::from("team")],
+    };
+    assert_eq!(
+        serde_json::to_value(&update).unwrap(),
+        json!({
+            "set_tags": { "": "x", "backup": true, "env": "dev", "owner": "" },
+            "remove_tags": ["env", "team"]
+        })
+    );
+    assert_eq!(
+        update.problems(&tags),
+        vec![
+            String::from("A tag needs a key"),
+            String::from("Tag env can't be both set and removed"),
+            String::from("There is no tag team"),
+        ]
+    );
+}
@@ -29,12 +29,14 @@ fi
 ./test/mock/vminfod &
 
 LOG_FILE=/tmp/smartos_executor.log \
+  LOG_LEVEL=debug \
   SHADOW_PATH=test/data/shadow \
   GZ_CONFIG_PATH=test/data/config \
   PATH=test/mock:$PATH \
 	./target/debug/smartos_executor &
 
 LOG_FILE=/tmp/smartos_ui.log \
+  LOG_LEVEL=debug \
   CERT_FILE="$CERT_FILE" \
   KEY_FILE="$KEY_FILE" \
 	./target/debug/smartos_ui &
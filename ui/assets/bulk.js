@@ -0,0 +1,100 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+/*
+ * Selecting instances on the instance list for bulk actions. The selection
+ * survives the list being reloaded, e.g. by live updates, but only keeps
+ * instances that are still shown.
+ */
+
+import htmx from 'htmx.org'
+import { $, $$ } from './global'
+
+const selected = new Set()
+
+// Must match confirmation_phrase() in ui/src/endpoints/bulk.rs
+const confirmationPhrase = count =>
+  count === 1 ? 'delete 1 instance' : `delete ${count} instances`
+
+const hideDeleteConfirm = () => {
+  $('#bulk-delete-confirm')?.classList.add('hidden')
+  const $confirm = $('#bulk-confirm')
+  if ($confirm) {
+    $confirm.value = ''
+  }
+}
+
+const update = () => {
+  if (!$('#bulk-actions')) {
+    selected.clear()
+    return
+  }
+
+  const $boxes = [...$$('.instance-select')]
+  const shown = new Set($boxes.map($box => $box.value))
+  for (const id of selected) {
+    if (!shown.has(id)) {
+      selected.delete(id)
+    }
+  }
+  $boxes.forEach($box => { $box.checked = selected.has($box.value) })
+
+  $('#instance-select-all').checked =
+    shown.size > 0 && selected.size === shown.size
+  $('#bulk-ids').value = [...selected].join(',')
+  $$('.bulk-count').forEach($count => { $count.textContent = selected.size })
+  $$('.bulk-phrase').forEach($phrase => {
+    $phrase.textContent = confirmationPhrase(selected.size)
+  })
+  $$('.bulk-action').forEach($button => {
+    $button.disabled = selected.size === 0
+  })
+  if (selected.size === 0) {
+    hideDeleteConfirm()
+  }
+}
+
+export const setupBulkActions = () => {
+  document.body.addEventListener('change', ({ target }) => {
+    if (target.classList.contains('instance-select')) {
+      if (target.checked) {
+        selected.add(target.value)
+      } else {
+        selected.delete(target.value)
+      }
+      update()
+    } else if (target.id === 'instance-select-all') {
+      $$('.instance-select').forEach($box => {
+        if (target.checked) {
+          selected.add($box.value)
+        } else {
+          selected.delete($box.value)
+        }
+      })
+      update()
+    }
+  })
+
+  document.body.addEventListener('click', ({ target }) => {
+    if (target.id === 'bulk-delete') {
+      $('#bulk-delete-confirm').classList.toggle('hidden')
+      $('#bulk-confirm').focus()
+    }
+  })
+
+  // Sent by the server once a bulk action has run
+  document.body.addEventListener('bulkDone', () => {
+    selected.clear()
+    update()
+  })
+
+  htmx.on('htmx:afterSettle', update)
+  update()
+}
@@ -1,3 +1,8 @@
 // Global DOM selector helpers
 export const $ = selector => document.querySelector(selector)
 export const $$ = selector => document.querySelectorAll(selector)
+
+// CSRF token for the current session, authenticated pages carry it in a meta
+// tag and it must be sent with every request that isn't a GET
+export const csrfToken = () =>
+  $('meta[name="csrf-token"]')?.getAttribute('content') || ''
@@ -0,0 +1,102 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+/*
+ * Live instance updates. The server follows vminfod's events and sends each
+ * change here, where the instance list's rows are replaced, added or removed
+ * and an open instance is reloaded when its state changes.
+ */
+
+import htmx from 'htmx.org'
+import { $ } from './global'
+
+// Reload the list, when it is shown, with its current filters and page
+const reloadList = () => {
+  const $list = $('#instance-list')
+  if ($list) {
+    htmx.ajax('GET', $list.dataset.location || '/instances', {
+      target: '#instance-list',
+      select: '#instance-list',
+      swap: 'outerHTML'
+    })
+  }
+}
+
+// Whether the list only shows instances in some state, so that a row whose
+// state changes may no longer belong in it
+const filteredByState = () => {
+  const location = $('#instance-list')?.dataset.location || ''
+  return new URLSearchParams(location.split('?')[1]).has('state')
+}
+
+// Reload the current page, keeping whichever view (e.g. JSON) is open
+const reloadInstance = () => {
+  const { pathname, search } = document.location
+  htmx.ajax('GET', pathname + search, { target: '#main', select: '#content' })
+}
+
+const onInstance = ({ data }) => {
+  const { uuid, state, row } = JSON.parse(data)
+
+  const $header = $('#instance-header')
+  if ($header && $header.dataset.instanceUuid === uuid) {
+    if ($header.dataset.instanceState !== state) {
+      reloadInstance()
+    }
+    return
+  }
+
+  // New instances are placed by the server, which sorts, filters and pages
+  // the list
+  const $row = document.getElementById(`instance-${uuid}`)
+  if (!$row || filteredByState()) {
+    reloadList()
+    return
+  }
+  $row.outerHTML = row
+  htmx.process(document.getElementById(`instance-${uuid}`))
+}
+
+const onDelete = ({ data }) => {
+  const { uuid } = JSON.parse(data)
+
+  const $header = $('#instance-header')
+  if ($header && $header.dataset.instanceUuid === uuid) {
+    htmx.ajax('GET', '/instances', { target: '#main', select: '#content' })
+    return
+  }
+
+  // Reloading brings the next instance onto the page, if there is one
+  if (document.getElementById(`instance-${uuid}`)) {
+    reloadList()
+  }
+}
+
+export const setupLiveUpdates = () => {
+  // Only authenticated pages have somewhere to show instances
+  if (!$('#main') || !window.EventSource) {
+    return
+  }
+
+  const source = new EventSource('/events/instances')
+  let disconnected = false
+  source.addEventListener('instance', onInstance)
+  source.addEventListener('delete', onDelete)
+  source.addEventListener('reload', reloadList)
+  source.addEventListener('error', () => { disconnected = true })
+
+  // The browser reconnects by itself, changes made meanwhile were missed
+  source.addEventListener('open', () => {
+    if (disconnected) {
+      disconnected = false
+      reloadList()
+    }
+  })
+}
@@ -9,10 +9,13 @@
  */
 
 import htmx from 'htmx.org'
-import { $, $$ } from './global'
+import { $, $$, csrfToken } from './global'
 import { setupProvisioningForm } from './provision'
 import { setupJSONViewer } from './json-viewer'
 import { removeMe } from './htmx-extensions'
+import { setupPasskeys } from './passkeys'
+import { setupLiveUpdates } from './live'
+import { setupBulkActions } from './bulk'
 
 import './tableFilter'
 
@@ -29,8 +32,19 @@ document.addEventListener('DOMContentLoaded', () => {
     }
   })
 
-  // Handle closing notifications
-  $('#notifications').addEventListener('click', e => {
+  setupPasskeys()
+  setupLiveUpdates()
+  setupBulkActions()
+
+  // Send the session's CSRF token with every htmx request that isn't a GET
+  htmx.on('htmx:configRequest', ({ detail }) => {
+    if (detail.verb !== 'get') {
+      detail.headers['X-CSRF-Token'] = csrfToken()
+    }
+  })
+
+  // Handle closing notifications, the login page doesn't have any
+  $('#notifications')?.addEventListener('click', e => {
     const $target = e.target.classList.contains('notification-close')
       ? e.target
       : e.target.closest('.notification-close')
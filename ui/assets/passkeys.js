@@ -0,0 +1,125 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+/*
+ * Passkey (WebAuthn) registration and login. Binary values are sent to and
+ * from the server as base64url strings.
+ */
+
+import { $, csrfToken } from './global'
+
+const toBytes = value => {
+  const base64 = value.replace(/-/g, '+').replace(/_/g, '/')
+  return Uint8Array.from(atob(base64), c => c.charCodeAt(0))
+}
+
+const toBase64url = buffer =>
+  btoa(String.fromCharCode(...new Uint8Array(buffer)))
+    .replace(/\+/g, '-')
+    .replace(/\//g, '_')
+    .replace(/=+$/, '')
+
+const post = async (path, body) => {
+  const response = await fetch(path, {
+    method: 'POST',
+    headers: {
+      'Content-Type': 'application/json',
+      'X-CSRF-Token': csrfToken()
+    },
+    body: JSON.stringify(body || {})
+  })
+  if (!response.ok) {
+    let message = `Request failed (${response.status})`
+    try {
+      message = (await response.json()).message
+    } catch (e) {}
+    throw new Error(message)
+  }
+  return response
+}
+
+const showError = message => {
+  const $error = $('#passkey-error')
+  if ($error) {
+    $error.textContent = message
+    $error.classList.remove('hidden')
+  }
+}
+
+const withIds = credentials =>
+  credentials.map(credential => ({ ...credential, id: toBytes(credential.id) }))
+
+const register = async $form => {
+  const options = await (await post('/passkeys/register/options')).json()
+  const credential = await navigator.credentials.create({
+    publicKey: {
+      ...options,
+      challenge: toBytes(options.challenge),
+      user: { ...options.user, id: toBytes(options.user.id) },
+      excludeCredentials: withIds(options.excludeCredentials)
+    }
+  })
+  await post('/passkeys/register', {
+    challenge: options.challenge,
+    name: $form.elements.name.value,
+    client_data_json: toBase64url(credential.response.clientDataJSON),
+    attestation_object: toBase64url(credential.response.attestationObject)
+  })
+  document.location.reload()
+}
+
+const login = async () => {
+  const user = $('#user') ? $('#user').value : ''
+  const options = await (
+    await post('/login/passkey/options', { user: user || null })
+  ).json()
+  const credential = await navigator.credentials.get({
+    publicKey: {
+      ...options,
+      challenge: toBytes(options.challenge),
+      allowCredentials: withIds(options.allowCredentials)
+    }
+  })
+  const { response } = credential
+  const result = await post('/login/passkey', {
+    challenge: options.challenge,
+    credential_id: toBase64url(credential.rawId),
+    client_data_json: toBase64url(response.clientDataJSON),
+    authenticator_data: toBase64url(response.authenticatorData),
+    signature: toBase64url(response.signature),
+    user_handle: response.userHandle ? toBase64url(response.userHandle) : null
+  })
+  // The redirect to the dashboard has already been followed
+  document.location = result.url
+}
+
+export const setupPasskeys = () => {
+  const run = action => {
+    if (!window.PublicKeyCredential) {
+      showError('This browser does not support passkeys')
+      return
+    }
+    action().catch(e => showError(e.message))
+  }
+
+  document.body.addEventListener('submit', e => {
+    if (e.target.matches('[data-passkey-register]')) {
+      e.preventDefault()
+      run(() => register(e.target))
+    }
+  })
+
+  document.body.addEventListener('click', e => {
+    if (e.target.closest('[data-passkey-login]')) {
+      e.preventDefault()
+      run(login)
+    }
+  })
+}
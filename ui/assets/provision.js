@@ -9,7 +9,6 @@
  */
 
 import { $ } from './global'
-import { v4 as uuidv4 } from 'uuid'
 import {
   EditorView,
   EditorState,
@@ -68,274 +67,28 @@ window.cloudInitDataOnChange = ($element) => {
   }
 }
 
-/*
- * This function willl interrogate input types for a given <form> and attempt to
- * massage them into basic JSON types.
- * A data attr named data-enctype="TYPE" can be used on any element with a name
- * attr for type hinting this is especially useful for <select> elements that
- * have no type attr
- */
-function encodeFormParameters ($targets, props = {}) {
-  $targets.forEach($element => {
-    let value = null
-    switch (($element.dataset && $element.dataset.encType) || $element.type) {
-      case 'number':
-        try {
-          value = parseInt($element.value, 10)
-        } catch (e) {
-          console.error(`Failed parsing number for "${$element.name}"`,
-            $element.name, e)
-          value = 0
-        }
-        break
-      case 'checkbox':
-        if ($element.checked) {
-          value = $element.checked
-        }
-        break
-      case 'text':
-      case 'textarea':
-      case 'hidden':
-      default:
-        value = $element.value
-        break
-    }
-    if (value || value === 0) {
-      props[$element.name] = value
-    } else {
-      delete props[$element.name]
-    }
-  })
-  return props
+// The additional properties editor's JSON, posted with the guided form
+window.additionalProperties = () => {
+  const editor = window.editors.additional
+  return editor ? editor.state.doc.toString() : ''
 }
-/*
- * Collect all of the data from inputs on the guided form, massage it as
- * needed, merge it with the additional properties tab and then write the
- * resulting object to the final properties JSON editor.
- */
-window.updateEditors = () => {
-  const editors = window.editors
-  const $form = $('#content form')
-  let final = {}
-  let additional = {}
-  const $targets = $form.querySelectorAll('[name]:not(.nicprop)')
-  const props = encodeFormParameters($targets)
-  const isHvm = (['bhyve', 'kvm'].indexOf(props.brand) !== -1)
-  const nic = {}
-  const nicTag = $('[name=nic_tag').value
-  const $ipv4Setup = $('[name=ipv4_setup')
-  const $ipv6Setup = $('[name=ipv6_setup')
-  const $cpuCap = $('[name=cpu_cap')
-
-  if (nicTag) {
-    nic.nic_tag = nicTag
-    nic.ips = []
-  }
-
-  if (nicTag && $ipv4Setup && $ipv4Setup.value === 'static') {
-    let ip = $('[name=ipv4_ip').value.trim()
-    const gateway = $('[name=ipv4_gateway').value.trim()
-    const prefix = $('[name=ipv4_prefix').value.trim()
-
-    if (ip) {
-      if (prefix) {
-        ip = `${ip}/${prefix}`
-      }
-      nic.ips.push(ip)
-    }
-
-    if (gateway) {
-      nic.gateways = [gateway]
-    }
-  } else if (nicTag && $ipv4Setup && $ipv4Setup.value === 'auto') {
-    nic.ips = ['dhcp']
-  }
 
-  if (nicTag && $ipv6Setup && $ipv6Setup.value === 'static') {
-    let ip = $('[name=ipv6_ip').value.trim()
-    const prefix = $('[name=ipv6_prefix').value.trim()
-    if (ip) {
-      if (prefix) {
-        ip = `${ip}/${prefix}`
-      }
-      nic.ips.push(ip)
-    }
-  } else if (nicTag && $ipv6Setup && $ipv6Setup.value === 'auto') {
-    nic.ips.push('addrconf')
-  }
-
-  if (Object.keys(nic).length) {
-    if (isHvm) {
-      nic.model = 'virtio'
-    }
-    props.nics = [nic]
-  }
-
-  if (props.brand === 'bhyve' && props.image_uuid) {
-    props.disks = [
-      {
-        image_uuid: props.image_uuid,
-        boot: true,
-        model: 'virtio',
-        size: props.primary_disk_size * 1024
-      }
-    ]
-    props.flexible_disk_size = props.primary_disk_size * 1024
-    delete props.image_uuid
-    delete props.primary_disk_size
-  } else if (props.brand === 'kvm' && props.image_uuid) {
-    props.disks = [
-      {
-        image_uuid: props.image_uuid,
-        boot: true,
-        model: 'virtio'
-      }
-    ]
-    delete props.image_uuid
-  } else if (props.disks) {
-    delete props.disks
-  }
-
-  if (props.resolvers) {
-    const resolvers = $('[name=resolvers').value
-      .split(',')
-      .filter(i => i)
-      .map(i => i.trim())
-    props.resolvers = resolvers
-  }
-
-  if (isHvm && props.vcpus && !props.cpu_cap) {
-    props.cpu_cap = props.vcpus * 100
-    $cpuCap.value = props.cpu_cap
-  }
-
-  if (props.user_script) {
-    if (!props.customer_metadata) {
-      props.customer_metadata = {}
-    }
-    props.customer_metadata['user-script'] = props.user_script
-  }
-  delete props.user_script
-
-  if (props.root_authorized_keys) {
-    if (!props.customer_metadata) {
-      props.customer_metadata = {}
-    }
-
-    props.customer_metadata.root_authorized_keys = props.root_authorized_keys
-
-    if (!isHvm) {
-      if (!props.internal_metadata) {
-        props.internal_metadata = {}
-      }
-      props.internal_metadata['operator-script'] = `#!/usr/bin/bash
-if ! [[ -d /root/.ssh ]]; then
-  mkdir -p /root/.ssh
-  chmod 0700 /root/.ssh
-fi
-/usr/sbin/mdata-get root_authorized_keys > /root/.ssh/authorized_keys
-/usr/sbin/mdata-delete operator-script`
-    }
-  }
-  delete props.root_authorized_keys
-
-  if (props.cloudinit_data) {
-    if (!props.customer_metadata) {
-      props.customer_metadata = {}
-    }
-    props.customer_metadata['cloud-init:user-data'] = props.cloudinit_data
-  }
-  delete props.cloudinit_data
-
-  if (props.root_pw) {
-    props.internal_metadata = {
-      root_pw: props.root_pw
-    }
-    delete props.root_pw
-  }
-
-  if (props.alias) {
-    props.hostname = props.alias
-  }
-
-  try {
-    const content = editors.additional.state.doc.toString()
-    if (content) {
-      additional = JSON.parse(content)
-      editors.additional.dispatch({
-        changes: {
-          from: 0,
-          to: editors.additional.state.doc.length,
-          insert: JSON.stringify(additional, null, 2)
-        }
-      })
-    } else {
-      editors.additional.dispatch({
-        changes: {
-          from: 0,
-          to: editors.additional.state.doc.length,
-          insert: '{}'
-        }
-      })
-    }
-    switch ($('#merge').value) {
-      case 'replace':
-        final = Object.assign({}, props, additional)
-        break
-      case 'ignore':
-        final = Object.assign({}, additional)
-        break
-      case 'extend':
-      default:
-        final = Object.assign({}, additional, props)
-        break
-    }
-  } catch (e) {
-    window.alert('Failed to serialize JSON in additional properties.')
-    console.error(e)
-    return false
-  }
-
-  /*
-   * Only update the final properties editor if the final payload has actually
-   * changed. This prevents unecessary requests to validate the payload.
-   */
-  const payload = JSON.stringify(final, null, 2)
-
-  if (payload !== editors.final.state.doc.toString()) {
-    editors.final.dispatch({
-      changes: {
-        from: 0,
-        to: editors.final.state.doc.length,
-        insert: payload
-      }
-    })
-  }
-
-  return true
-}
-
-window.getFinalEditor = () => {
-  window.updateEditors()
-  /*
-   * We need a UUID on the instance payload so we can keep track of it
-   * If the user didn't add one generate it here so we know what it is.
-   */
-  let payload = window.editors.final.state.doc.toString()
-  try {
-    const instancePayload = JSON.parse(payload)
-    if (!instancePayload.uuid) {
-      instancePayload.uuid = uuidv4()
-      payload = JSON.stringify(instancePayload)
-    }
-  } catch (e) {
-    console.error(e)
-    window.alert('Failed parsing final properties for instance.')
+// Show the payload the server built from the form in the final properties
+// editor
+document.body.addEventListener('htmx:afterSwap', ({ detail: { target } }) => {
+  const $payload = $('#final-payload')
+  const editor = window.editors.final
+  if (target.id !== 'validate-results' || !$payload || !editor) {
     return
   }
-
-  return payload
-}
+  editor.dispatch({
+    changes: {
+      from: 0,
+      to: editor.state.doc.length,
+      insert: $payload.value
+    }
+  })
+})
 
 window.finalPropertiesChanged = () => {
   const $validationResult = $('.validation')
@@ -376,7 +129,6 @@ export const setupProvisioningForm = () => {
   const $buttons = [$guidedButton, $additionalButton, $finalButton]
   const active = 'active-editor-tab'
   const inactive = 'inactive-editor-tab'
-  const updateEditors = window.updateEditors
   let editorsSetup = false
 
   function clearStyle ($tabs, $buttons) {
@@ -399,6 +151,8 @@ export const setupProvisioningForm = () => {
     const extensions = [basicSetup, json(), oneDark]
     if ($tab.dataset.readOnly) {
       extensions.push(EditorState.readOnly.of(true))
+    } else {
+      // A change means the form has to be validated again
       extensions.push(EditorView.updateListener.of(({ docChanged }) => {
         if (docChanged) {
           window.finalPropertiesChanged()
@@ -406,7 +160,9 @@ export const setupProvisioningForm = () => {
       }))
     }
 
+    // A cloned instance's properties start in the additional editor
     editors[name] = new EditorView({
+      doc: $tab.dataset.doc || '',
       extensions,
       parent
     })
@@ -428,35 +184,30 @@ export const setupProvisioningForm = () => {
   })
 
   $finalButton.addEventListener('click', () => {
-    if (updateEditors()) {
-      $validateButton.click()
-      clearStyle($tabs, $buttons)
-      $finalTab.classList.remove('hidden')
-      $finalButton.classList.remove(inactive)
-      $finalButton.classList.add(active)
-    }
+    $validateButton.click()
+    clearStyle($tabs, $buttons)
+    $finalTab.classList.remove('hidden')
+    $finalButton.classList.remove(inactive)
+    $finalButton.classList.add(active)
   })
 
   $additionalButton.addEventListener('click', () => {
-    if (updateEditors()) {
-      clearStyle($tabs, $buttons)
-      $additionalTab.classList.remove('hidden')
-      $additionalButton.classList.remove(inactive)
-      $additionalButton.classList.add(active)
-    }
+    clearStyle($tabs, $buttons)
+    $additionalTab.classList.remove('hidden')
+    $additionalButton.classList.remove(inactive)
+    $additionalButton.classList.add(active)
   })
 
   $guidedButton.addEventListener('click', () => {
-    if (updateEditors()) {
-      clearStyle($tabs, $buttons)
-      $guidedTab.classList.remove('hidden')
-      $guidedButton.classList.remove(inactive)
-      $guidedButton.classList.add(active)
-    }
+    clearStyle($tabs, $buttons)
+    $guidedTab.classList.remove('hidden')
+    $guidedButton.classList.remove(inactive)
+    $guidedButton.classList.add(active)
   })
 
   // Show/Hide major inputs and controls when an image_uuid is selected
   $guidedTab.addEventListener('change', e => {
+    window.finalPropertiesChanged()
     if (e.target.id === 'image_uuid') {
       if (e.target.value) {
         $additionalButton.classList.remove('hidden')
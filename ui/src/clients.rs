@@ -11,16 +11,31 @@
 //! HTTP Clients that are accessible from the [dropshot::RequestContext] struct
 //! provided to each endpoint handler.
 
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
 
 use smartos_shared::{
-    image::Image, image::ImageImportParams, image::Source, instance::Info,
-    instance::Instance, instance::InstancePayload,
-    instance::InstanceValidateResponse, instance::InstanceView, nictag::NicTag,
-    sysinfo::Sysinfo,
+    auth::ApiToken, auth::ApiTokenCreateRequest, auth::ApiTokenCreateResponse,
+    auth::ApiTokenDeleteRequest, auth::ApiTokenUserRequest,
+    auth::ApiTokenVerifyRequest, auth::ApiTokenVerifyResponse,
+    auth::AuthVerifyRequest, auth::AuthVerifyResponse,
+    auth::ChangePasswordRequest, auth::ChangePasswordResponse, auth::Role,
+    auth::TotpCodeRequest, auth::TotpCodeResponse, auth::TotpEnrollResponse,
+    auth::TotpStatusResponse, auth::TotpUserRequest,
+    auth::WebauthnAssertRequest, auth::WebauthnAssertResponse,
+    auth::WebauthnCredentialsResponse, auth::WebauthnDeleteRequest,
+    auth::WebauthnRegisterRequest, auth::WebauthnRegisterResponse,
+    auth::WebauthnUserRequest, disk::DiskUpdate, health::ExecutorHealth,
+    image::Image, image::ImageImportParams, image::Source,
+    instance::CreatePayload, instance::Info, instance::Instance,
+    instance::InstancePayload, instance::InstanceValidateResponse,
+    instance::InstanceView, nic::NicUpdate, nictag::NicTag, preset::Preset,
+    sysinfo::Sysinfo, tag::TagUpdate, tls::TlsInstallRequest,
 };
 
-use reqwest::{Client as HTTPClient, RequestBuilder, Response};
+use reqwest::{Client as HTTPClient, RequestBuilder, Response, StatusCode};
+use serde::Deserialize;
+use serde_json::Value;
 use slog::Logger;
 use uuid::Uuid;
 
@@ -53,6 +68,69 @@ impl fmt::Display for RequestError {
     }
 }
 
+/// Outcome of asking the executor to verify a user's password
+#[derive(Debug, PartialEq)]
+pub enum PasswordCheck {
+    /// Correct password, `totp_enabled` is set if the user must also provide
+    /// a TOTP code
+    Valid {
+        role: Role,
+        totp_enabled: bool,
+    },
+    Invalid,
+    /// Too many recent failures for the user, the executor refused to check
+    RateLimited,
+}
+
+/// An event from vminfod's `/events` stream. VMs are left as JSON so that one
+/// vminfod can't describe doesn't end the stream.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum VMInfodEvent {
+    /// Sent first, once vminfod is ready. Newer versions of vminfod include
+    /// every VM, keyed by UUID.
+    Ready {
+        #[serde(default)]
+        vms: Option<HashMap<Uuid, Value>>,
+    },
+    Create {
+        vm: Value,
+    },
+    Modify {
+        vm: Value,
+    },
+    Delete {
+        uuid: Uuid,
+    },
+    #[serde(other)]
+    Other,
+}
+
+/// A stream of newline separated JSON events from vminfod
+pub struct VMInfodEvents {
+    response: Response,
+    buffer: Vec<u8>,
+}
+
+impl VMInfodEvents {
+    /// The next event, or [None] when vminfod ends the stream
+    pub async fn next(&mut self) -> Result<Option<VMInfodEvent>, RequestError> {
+        loop {
+            if let Some(end) = self.buffer.iter().position(|&b| b == b'\n') {
+                let line: Vec<u8> = self.buffer.drain(..=end).collect();
+                if line.iter().all(u8::is_ascii_whitespace) {
+                    continue;
+                }
+                return Ok(Some(serde_json::from_slice(&line)?));
+            }
+            match self.response.chunk().await? {
+                Some(chunk) => self.buffer.extend_from_slice(&chunk),
+                None => return Ok(None),
+            }
+        }
+    }
+}
+
 /// HTTP Client for interacting with vminfod
 pub struct VMInfodClient {
     http: HTTPClient,
@@ -73,16 +151,18 @@ impl VMInfodClient {
         self.http.get(format!("{}/{path}", self.url))
     }
 
+    /// Every VM, read one at a time so a VM that can't be read doesn't fail
+    /// the list
     pub async fn get_instances(
         &self,
+        log: &Logger,
     ) -> Result<Vec<InstanceView>, reqwest::Error> {
-        let instances: Vec<Instance> =
+        let vms: Vec<Value> =
             self.get("vms").send().await?.error_for_status()?.json().await?;
-        let mut views: Vec<InstanceView> = Vec::default();
-        for i in instances {
-            views.push(i.try_into().expect("failed"))
-        }
-        Ok(views)
+        Ok(Instance::deserialize_list(vms, log)
+            .into_iter()
+            .map(InstanceView::from)
+            .collect())
     }
 
     pub async fn get_instance(
@@ -101,7 +181,7 @@ impl VMInfodClient {
         &self,
         id: &Uuid,
     ) -> Result<InstanceView, reqwest::Error> {
-        Ok(self.get_instance(id).await?.try_into().expect("failed"))
+        Ok(self.get_instance(id).await?.into())
     }
 
     pub async fn get_instance_json(
@@ -116,6 +196,12 @@ impl VMInfodClient {
             .await
     }
 
+    /// Follow vminfod's event stream
+    pub async fn events(&self) -> Result<VMInfodEvents, reqwest::Error> {
+        let response = self.get("events").send().await?.error_for_status()?;
+        Ok(VMInfodEvents { response, buffer: Vec::new() })
+    }
+
     pub async fn ping(&self) -> Result<bool, reqwest::Error> {
         Ok(self
             .get("ping")
@@ -156,6 +242,17 @@ impl ExecutorClient {
         self.get("sysinfo").send().await?.error_for_status()?.json().await
     }
 
+    pub async fn get_health(&self) -> Result<ExecutorHealth, reqwest::Error> {
+        self.get("health").send().await?.error_for_status()?.json().await
+    }
+
+    /// The complete `sysinfo` output, rather than the fields the UI uses
+    pub async fn get_sysinfo_json(
+        &self,
+    ) -> Result<serde_json::Value, reqwest::Error> {
+        self.get("sysinfo").send().await?.error_for_status()?.json().await
+    }
+
     pub async fn get_images(
         &self,
         log: &Logger,
@@ -222,17 +319,17 @@ impl ExecutorClient {
 
     pub async fn provision(
         &self,
-        payload: InstancePayload,
+        payload: &CreatePayload,
     ) -> Result<Response, RequestError> {
-        let req = serde_json::to_string(&payload)?;
+        let req = serde_json::to_string(&InstancePayload::try_from(payload)?)?;
         Ok(self.post("provision").body(req).send().await?)
     }
 
     pub async fn validate_create(
         &self,
-        payload: InstancePayload,
+        payload: &CreatePayload,
     ) -> Result<InstanceValidateResponse, RequestError> {
-        let req = serde_json::to_string(&payload)?;
+        let req = serde_json::to_string(&InstancePayload::try_from(payload)?)?;
         Ok(self
             .post("validate/create")
             .body(req)
@@ -267,6 +364,64 @@ impl ExecutorClient {
             .await
     }
 
+    /// Change an HVM instance's disks. A failed update's response has vmadm's
+    /// error.
+    pub async fn update_disks(
+        &self,
+        id: &Uuid,
+        update: &DiskUpdate,
+    ) -> Result<Response, RequestError> {
+        let req = serde_json::to_string(update)?;
+        Ok(self
+            .post(format!("instance/{}/disks", id.as_hyphenated()).as_str())
+            .body(req)
+            .send()
+            .await?)
+    }
+
+    /// Change an instance's NICs. A failed update's response has vmadm's
+    /// error.
+    pub async fn update_nics(
+        &self,
+        id: &Uuid,
+        update: &NicUpdate,
+    ) -> Result<Response, RequestError> {
+        let req = serde_json::to_string(update)?;
+        Ok(self
+            .post(format!("instance/{}/nics", id.as_hyphenated()).as_str())
+            .body(req)
+            .send()
+            .await?)
+    }
+
+    /// Set or remove an instance's tags. A failed update's response has
+    /// vmadm's error.
+    pub async fn update_tags(
+        &self,
+        id: &Uuid,
+        update: &TagUpdate,
+    ) -> Result<Response, RequestError> {
+        let req = serde_json::to_string(update)?;
+        Ok(self
+            .post(format!("instance/{}/tags", id.as_hyphenated()).as_str())
+            .body(req)
+            .send()
+            .await?)
+    }
+
+    /// Bytes used by each of an instance's disks, by zvol
+    pub async fn get_disk_usage(
+        &self,
+        id: &Uuid,
+    ) -> Result<BTreeMap<String, u64>, reqwest::Error> {
+        self.get(format!("instance/{}/disk_usage", id.as_hyphenated()).as_str())
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+    }
+
     pub async fn stop_instance(
         &self,
         id: &Uuid,
@@ -279,12 +434,245 @@ impl ExecutorClient {
             .await
     }
 
+    pub async fn reboot_instance(
+        &self,
+        id: &Uuid,
+    ) -> Result<String, reqwest::Error> {
+        self.post(format!("instance/{}/reboot", id.as_hyphenated()).as_str())
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await
+    }
+
     pub async fn get_nictags(&self) -> Result<Vec<NicTag>, reqwest::Error> {
         self.get("nictag").send().await?.error_for_status()?.json().await
     }
 
-    pub async fn get_pwhash(&self) -> Result<String, reqwest::Error> {
-        self.get("pwhash").send().await?.error_for_status()?.text().await
+    pub async fn get_presets(&self) -> Result<Vec<Preset>, reqwest::Error> {
+        self.get("preset").send().await?.error_for_status()?.json().await
+    }
+
+    /// Create a preset, or replace the one with the same name
+    pub async fn save_preset(
+        &self,
+        preset: &Preset,
+    ) -> Result<(), RequestError> {
+        let req = serde_json::to_string(preset)?;
+        self.post("preset").body(req).send().await?.error_for_status()?;
+        Ok(())
+    }
+
+    /// Delete a preset, returns false if there was no such preset
+    pub async fn delete_preset(
+        &self,
+        name: &str,
+    ) -> Result<bool, reqwest::Error> {
+        let response = self.delete(&format!("preset/{}", name)).send().await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(false);
+        }
+        response.error_for_status()?;
+        Ok(true)
+    }
+
+    /// Whether `user` has TOTP enabled, and how many recovery codes remain
+    pub async fn totp_status(
+        &self,
+        user: String,
+    ) -> Result<TotpStatusResponse, RequestError> {
+        let req = serde_json::to_string(&TotpUserRequest { user })?;
+        let response = self.post("auth/totp/status").body(req).send().await?;
+        Ok(response.error_for_status()?.json().await?)
+    }
+
+    /// Generate a new, unconfirmed, TOTP secret for `user`
+    pub async fn totp_enroll(
+        &self,
+        user: String,
+    ) -> Result<TotpEnrollResponse, RequestError> {
+        let req = serde_json::to_string(&TotpUserRequest { user })?;
+        let response = self.post("auth/totp/enroll").body(req).send().await?;
+        Ok(response.error_for_status()?.json().await?)
+    }
+
+    /// Send a TOTP (or recovery) code to one of the executor's
+    /// `/auth/totp/{action}` endpoints. Returns [None] if the executor is
+    /// refusing attempts for the user.
+    async fn totp_code(
+        &self,
+        action: &str,
+        user: String,
+        code: String,
+    ) -> Result<Option<TotpCodeResponse>, RequestError> {
+        let req = serde_json::to_string(&TotpCodeRequest { user, code })?;
+        let response = self
+            .post(&format!("auth/totp/{}", action))
+            .body(req)
+            .send()
+            .await?;
+        if response.status() == StatusCode::TOO_MANY_REQUESTS {
+            return Ok(None);
+        }
+        Ok(Some(response.error_for_status()?.json().await?))
+    }
+
+    /// Complete enrollment, the response contains the recovery codes
+    pub async fn totp_confirm(
+        &self,
+        user: String,
+        code: String,
+    ) -> Result<Option<TotpCodeResponse>, RequestError> {
+        self.totp_code("confirm", user, code).await
+    }
+
+    pub async fn totp_verify(
+        &self,
+        user: String,
+        code: String,
+    ) -> Result<Option<TotpCodeResponse>, RequestError> {
+        self.totp_code("verify", user, code).await
+    }
+
+    pub async fn totp_disable(
+        &self,
+        user: String,
+        code: String,
+    ) -> Result<Option<TotpCodeResponse>, RequestError> {
+        self.totp_code("disable", user, code).await
+    }
+
+    /// The user's WebAuthn credentials and user handle
+    pub async fn webauthn_credentials(
+        &self,
+        user: String,
+    ) -> Result<WebauthnCredentialsResponse, RequestError> {
+        let req = serde_json::to_string(&WebauthnUserRequest { user })?;
+        let response =
+            self.post("auth/webauthn/credentials").body(req).send().await?;
+        Ok(response.error_for_status()?.json().await?)
+    }
+
+    pub async fn webauthn_register(
+        &self,
+        request: WebauthnRegisterRequest,
+    ) -> Result<WebauthnRegisterResponse, RequestError> {
+        let req = serde_json::to_string(&request)?;
+        let response =
+            self.post("auth/webauthn/register").body(req).send().await?;
+        Ok(response.error_for_status()?.json().await?)
+    }
+
+    /// Check a WebAuthn login. Returns [None] if the executor is refusing
+    /// attempts for the credential's user.
+    pub async fn webauthn_assert(
+        &self,
+        request: WebauthnAssertRequest,
+    ) -> Result<Option<WebauthnAssertResponse>, RequestError> {
+        let req = serde_json::to_string(&request)?;
+        let response =
+            self.post("auth/webauthn/assert").body(req).send().await?;
+        if response.status() == StatusCode::TOO_MANY_REQUESTS {
+            return Ok(None);
+        }
+        Ok(Some(response.error_for_status()?.json().await?))
+    }
+
+    /// Remove one of `user`'s credentials, returns false if there was no such
+    /// credential
+    pub async fn webauthn_delete(
+        &self,
+        user: String,
+        id: String,
+    ) -> Result<bool, RequestError> {
+        let req = serde_json::to_string(&WebauthnDeleteRequest { user, id })?;
+        let response =
+            self.post("auth/webauthn/delete").body(req).send().await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(false);
+        }
+        response.error_for_status()?;
+        Ok(true)
+    }
+
+    pub async fn api_tokens(
+        &self,
+        user: String,
+    ) -> Result<Vec<ApiToken>, RequestError> {
+        let req = serde_json::to_string(&ApiTokenUserRequest { user })?;
+        let response = self.post("auth/tokens/list").body(req).send().await?;
+        Ok(response.error_for_status()?.json().await?)
+    }
+
+    /// Create a token, the response holds the only copy of its secret
+    pub async fn api_token_create(
+        &self,
+        request: ApiTokenCreateRequest,
+    ) -> Result<ApiTokenCreateResponse, RequestError> {
+        let req = serde_json::to_string(&request)?;
+        let response = self.post("auth/tokens/create").body(req).send().await?;
+        Ok(response.error_for_status()?.json().await?)
+    }
+
+    pub async fn api_token_verify(
+        &self,
+        secret: String,
+    ) -> Result<ApiTokenVerifyResponse, RequestError> {
+        let req = serde_json::to_string(&ApiTokenVerifyRequest { secret })?;
+        let response = self.post("auth/tokens/verify").body(req).send().await?;
+        Ok(response.error_for_status()?.json().await?)
+    }
+
+    /// Revoke one of `user`'s tokens, returns false if there was no such
+    /// token
+    pub async fn api_token_delete(
+        &self,
+        user: String,
+        id: String,
+    ) -> Result<bool, RequestError> {
+        let req = serde_json::to_string(&ApiTokenDeleteRequest { user, id })?;
+        let response = self.post("auth/tokens/delete").body(req).send().await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(false);
+        }
+        response.error_for_status()?;
+        Ok(true)
+    }
+
+    /// Change a user's password, [None] is returned if the executor is
+    /// refusing attempts for the user after too many failures
+    pub async fn change_password(
+        &self,
+        request: ChangePasswordRequest,
+    ) -> Result<Option<ChangePasswordResponse>, RequestError> {
+        let req = serde_json::to_string(&request)?;
+        let response = self.post("auth/password").body(req).send().await?;
+        if response.status() == StatusCode::TOO_MANY_REQUESTS {
+            return Ok(None);
+        }
+        Ok(Some(response.error_for_status()?.json().await?))
+    }
+
+    pub async fn verify_password(
+        &self,
+        user: String,
+        password: String,
+    ) -> Result<PasswordCheck, RequestError> {
+        let req = serde_json::to_string(&AuthVerifyRequest { user, password })?;
+        let response = self.post("auth/verify").body(req).send().await?;
+        if response.status() == StatusCode::TOO_MANY_REQUESTS {
+            return Ok(PasswordCheck::RateLimited);
+        }
+        let result: AuthVerifyResponse =
+            response.error_for_status()?.json().await?;
+        match result.role {
+            Some(role) if result.success => Ok(PasswordCheck::Valid {
+                role,
+                totp_enabled: result.totp_enabled,
+            }),
+            _ => Ok(PasswordCheck::Invalid),
+        }
     }
 
     pub async fn ping(&self) -> Result<bool, reqwest::Error> {
@@ -306,9 +694,26 @@ impl ExecutorClient {
             .await
     }
 
+    pub async fn get_metrics(&self) -> Result<String, reqwest::Error> {
+        self.get("metrics").send().await?.error_for_status()?.text().await
+    }
+
     pub async fn get_gz_config(
         &self,
     ) -> Result<Vec<(String, String)>, reqwest::Error> {
         self.get("config/gz").send().await?.error_for_status()?.json().await
     }
+
+    /// Save a new TLS certificate and key over `CERT_FILE` and `KEY_FILE`
+    pub async fn install_tls(
+        &self,
+        request: &TlsInstallRequest,
+    ) -> Result<(), reqwest::Error> {
+        self.post("config/tls")
+            .json(request)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
 }
@@ -0,0 +1,321 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+//! "Provision like this": the provisioning form filled in from an existing
+//! instance's vminfod JSON. Properties the guided form has inputs for are
+//! set on a [ProvisionQuery], everything else the new instance can share is
+//! left for the additional properties editor. Properties that identify the
+//! instance or only describe it while it runs are dropped.
+
+use std::net::Ipv4Addr;
+
+use crate::endpoints::instances::ProvisionQuery;
+use crate::nic_form::{NicForm, NicRows};
+
+use serde_json::{Map, Value};
+
+/// Properties that belong to the instance being cloned, or to its runtime
+/// state, and must not be copied
+const STRIPPED: [&str; 31] = [
+    "uuid",
+    "zonename",
+    "zonepath",
+    "zoneid",
+    "zonedid",
+    "pid",
+    "state",
+    "zone_state",
+    "exit_status",
+    "exit_timestamp",
+    "create_timestamp",
+    "last_modified",
+    "boot_timestamp",
+    "init_restarts",
+    "transition_to",
+    "transition_expire",
+    "platform_buildstamp",
+    "server_uuid",
+    "headnode_id",
+    "zfs_filesystem",
+    "snapshots",
+    "hostname",
+    "hvm",
+    "v",
+    "internal_metadata",
+    // Derived from ram
+    "max_physical_memory",
+    "max_locked_memory",
+    "max_swap",
+    "max_shm_memory",
+    "tmpfs",
+    // The boot disk's image is the clone's image_uuid, the other disks are
+    // carried empty by data_disks
+    "disks",
+];
+
+/// Properties set through the guided form's inputs
+const GUIDED: [&str; 16] = [
+    "alias",
+    "tags",
+    "brand",
+    "image_uuid",
+    "ram",
+    "quota",
+    "vcpus",
+    "cpu_cap",
+    "bootrom",
+    "dns_domain",
+    "resolvers",
+    "nics",
+    "datasets",
+    "delegate_dataset",
+    "flexible_disk_size",
+    "kernel_version",
+];
+
+/// Customer metadata keys set through the guided form's inputs
+const GUIDED_METADATA: [&str; 3] =
+    ["root_authorized_keys", "user-script", "cloud-init:user-data"];
+
+pub struct Cloned {
+    pub query: ProvisionQuery,
+    /// Properties for the additional properties editor
+    pub additional: Map<String, Value>,
+}
+
+fn string(vm: &Value, key: &str) -> String {
+    match vm.get(key) {
+        Some(Value::String(value)) => value.clone(),
+        Some(Value::Number(value)) => value.to_string(),
+        _ => String::new(),
+    }
+}
+
+fn strings(vm: &Value, key: &str) -> Vec<String> {
+    vm.get(key)
+        .and_then(Value::as_array)
+        .map(|values| {
+            values.iter().filter_map(Value::as_str).map(String::from).collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The instance's tags as the provisioning form writes them
+fn tags(vm: &Value) -> String {
+    vm.get("tags")
+        .and_then(Value::as_object)
+        .map(|tags| {
+            tags.iter()
+                .map(|(key, value)| match value.as_str() {
+                    Some(value) => format!("{}={}", key, value),
+                    // Numbers and booleans, which are written as they are
+                    None => format!("{}={}", key, value),
+                })
+                .collect::<Vec<_>>()
+                .join(",")
+        })
+        .unwrap_or_default()
+}
+
+/// The boot disk of an HVM instance, or its first disk
+fn boot_disk(vm: &Value) -> Option<&Value> {
+    let disks = vm.get("disks")?.as_array()?;
+    disks
+        .iter()
+        .find(|disk| disk.get("boot").and_then(Value::as_bool) == Some(true))
+        .or_else(|| disks.first())
+}
+
+/// An HVM instance's other disks, as empty disks of the same size and model
+fn data_disks(vm: &Value) -> Vec<Value> {
+    let Some(disks) = vm.get("disks").and_then(Value::as_array) else {
+        return Vec::new();
+    };
+    let boot = boot_disk(vm);
+    disks
+        .iter()
+        .filter(|disk| Some(*disk) != boot)
+        .filter_map(|disk| {
+            let mut data = Map::new();
+            data.insert(String::from("size"), disk.get("size")?.clone());
+            if let Some(model) = disk.get("model") {
+                data.insert(String::from("model"), model.clone());
+            }
+            Some(Value::Object(data))
+        })
+        .collect()
+}
+
+/// The instance's NICs and which of them is primary, the first if none is
+/// marked
+fn nic_rows(vm: &Value, keep_ips: bool) -> NicRows {
+    let nics = vm.get("nics").and_then(Value::as_array);
+    let Some(nics) = nics.filter(|nics| !nics.is_empty()) else {
+        return NicRows::default();
+    };
+    NicRows {
+        nics: nics.iter().map(|nic| nic_form(nic, keep_ips)).collect(),
+        primary: nics
+            .iter()
+            .position(|nic| {
+                nic.get("primary").and_then(Value::as_bool) == Some(true)
+            })
+            .unwrap_or_default(),
+    }
+}
+
+/// A NIC's addresses as `ip/prefix`, `dhcp` or `addrconf`. Older NICs only
+/// have an `ip` and `netmask`.
+fn nic_ips(nic: &Value) -> Vec<String> {
+    let ips = strings(nic, "ips");
+    if !ips.is_empty() {
+        return ips;
+    }
+    let ip = string(nic, "ip");
+    if ip.is_empty() {
+        return ips;
+    }
+    match string(nic, "netmask").parse::<Ipv4Addr>() {
+        Ok(netmask) => {
+            vec![format!("{}/{}", ip, u32::from(netmask).count_ones())]
+        }
+        Err(_) => vec![ip],
+    }
+}
+
+fn nic_form(nic: &Value, keep_ips: bool) -> NicForm {
+    let mut form = NicForm {
+        tag: string(nic, "nic_tag"),
+        ipv4_gateway: strings(nic, "gateways")
+            .into_iter()
+            .next()
+            .unwrap_or_else(|| string(nic, "gateway")),
+        // 0 is untagged, which is what an empty VLAN ID means on the form
+        vlan_id: match string(nic, "vlan_id") {
+            vlan_id if vlan_id == "0" => String::new(),
+            vlan_id => vlan_id,
+        },
+        model: string(nic, "model"),
+        allowed_ips: strings(nic, "allowed_ips").join(","),
+        ..NicForm::default()
+    };
+
+    for ip in nic_ips(nic) {
+        match ip.as_str() {
+            "dhcp" => form.ipv4_setup = String::from("auto"),
+            "addrconf" => form.ipv6_setup = String::from("auto"),
+            _ => {
+                let (address, prefix) =
+                    ip.split_once('/').unwrap_or((ip.as_str(), ""));
+                let address = if keep_ips {
+                    String::from(address)
+                } else {
+                    String::new()
+                };
+                if ip.contains(':') {
+                    form.ipv6_setup = String::from("static");
+                    form.ipv6_ip = address;
+                    form.ipv6_prefix = String::from(prefix);
+                } else {
+                    form.ipv4_setup = String::from("static");
+                    form.ipv4_ip = address;
+                    form.ipv4_prefix = String::from(prefix);
+                }
+            }
+        }
+    }
+    form
+}
+
+/// The provisioning form for a new instance like `vm`, which is an
+/// instance's vminfod JSON. IP addresses are only copied with `keep_ips`,
+/// otherwise static addresses are left for the user to fill in.
+#[must_use]
+pub fn from_instance(vm: &Value, keep_ips: bool) -> Cloned {
+    let hvm = vm.get("hvm").and_then(Value::as_bool) == Some(true)
+        || matches!(string(vm, "brand").as_str(), "bhyve" | "kvm");
+    let disk = boot_disk(vm);
+
+    let mut query = ProvisionQuery {
+        alias: match string(vm, "alias") {
+            alias if alias.is_empty() => alias,
+            alias => format!("{}-clone", alias),
+        },
+        brand: string(vm, "brand"),
+        image_uuid: match string(vm, "image_uuid") {
+            image_uuid if image_uuid.is_empty() => {
+                disk.map(|d| string(d, "image_uuid")).unwrap_or_default()
+            }
+            image_uuid => image_uuid,
+        },
+        ram: match string(vm, "ram") {
+            ram if ram.is_empty() => string(vm, "max_physical_memory"),
+            ram => ram,
+        },
+        quota: string(vm, "quota"),
+        vcpus: string(vm, "vcpus"),
+        cpu_cap: string(vm, "cpu_cap"),
+        bootrom: string(vm, "bootrom"),
+        dns_domain: string(vm, "dns_domain"),
+        resolvers: strings(vm, "resolvers").join(","),
+        tags: tags(vm),
+        nics: nic_rows(vm, keep_ips),
+        ..ProvisionQuery::default()
+    };
+
+    if hvm {
+        // The form takes GiB, vmadm MiB
+        query.primary_disk_size = disk
+            .and_then(|d| d.get("size"))
+            .and_then(Value::as_u64)
+            .map(|size| size / 1024)
+            .unwrap_or_default();
+    }
+
+    let delegated = vm.get("delegate_dataset").and_then(Value::as_bool)
+        == Some(true)
+        || !strings(vm, "datasets").is_empty();
+    if delegated && !hvm {
+        // What the form's checkbox sends
+        query.delegate_dataset = String::from("on");
+    }
+
+    let metadata = vm.get("customer_metadata").unwrap_or(&Value::Null);
+    query.root_authorized_keys = string(metadata, "root_authorized_keys");
+    query.user_script = string(metadata, "user-script");
+    query.cloudinit_data = string(metadata, "cloud-init:user-data");
+    let mut metadata = metadata.as_object().cloned().unwrap_or_default();
+    metadata.retain(|key, _| !GUIDED_METADATA.contains(&key.as_str()));
+
+    let mut additional: Map<String, Value> = vm
+        .as_object()
+        .map(|properties| {
+            properties
+                .iter()
+                .filter(|(key, _)| {
+                    !STRIPPED.contains(&key.as_str())
+                        && !GUIDED.contains(&key.as_str())
+                        && key.as_str() != "customer_metadata"
+                })
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect()
+        })
+        .unwrap_or_default();
+    if !metadata.is_empty() {
+        additional
+            .insert(String::from("customer_metadata"), Value::Object(metadata));
+    }
+    let data_disks = if hvm { data_disks(vm) } else { Vec::new() };
+    if !data_disks.is_empty() {
+        additional.insert(String::from("disks"), Value::Array(data_disks));
+    }
+
+    Cloned { query, additional }
+}
@@ -0,0 +1,446 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+//! Versioned JSON API for scripts and CI, mirroring the actions available in
+//! the UI. Requests are authenticated with an API token created on the
+//! tokens page, sent as `Authorization: Bearer <token>`. Session cookies are
+//! not accepted, so these endpoints don't need CSRF protection.
+//!
+//! Every request is checked against both the token's scopes and its owner's
+//! current role, and actions are logged with the user and token ID.
+
+use crate::endpoints::instances::HostChecks;
+use crate::endpoints::{get_header, Context, PathParams};
+
+use smartos_shared::auth::{Permission, Role, Scope};
+use smartos_shared::http_server::GenericResponse;
+use smartos_shared::image::{Image, ImageImportParams};
+use smartos_shared::instance::{CreatePayload, InstanceView};
+use smartos_shared::nictag::NicTag;
+
+use dropshot::{
+    endpoint, HttpError, HttpResponseCreated, HttpResponseDeleted,
+    HttpResponseOk, HttpResponseUpdatedNoContent, Path, Query, RequestContext,
+    TypedBody,
+};
+use hyper::StatusCode;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use slog::{error, info};
+use uuid::Uuid;
+
+/// The owner of the token a request was made with
+pub struct Caller {
+    pub login: String,
+    pub role: Role,
+    pub token_id: String,
+}
+
+fn unauthorized(message: &str) -> HttpError {
+    HttpError::for_client_error(
+        None,
+        StatusCode::UNAUTHORIZED,
+        String::from(message),
+    )
+}
+
+/// Check the request's bearer token allows `permission`
+pub async fn authenticate(
+    ctx: &RequestContext<Context>,
+    permission: Permission,
+) -> Result<Caller, HttpError> {
+    let header = get_header(ctx, "Authorization").unwrap_or_default();
+    let Some(secret) = header
+        .split_once(' ')
+        .filter(|(scheme, _)| scheme.eq_ignore_ascii_case("bearer"))
+        .map(|(_, secret)| secret.trim())
+        .filter(|secret| !secret.is_empty())
+    else {
+        return Err(unauthorized("A bearer token is required"));
+    };
+
+    let verified = ctx
+        .context()
+        .executor
+        .api_token_verify(String::from(secret))
+        .await
+        .map_err(|e| {
+            error!(ctx.log, "Failed to verify API token: {}", e);
+            HttpError::for_unavail(None, String::from("Executor unavailable"))
+        })?;
+    let (Some(token), Some(role)) = (verified.token, verified.role) else {
+        return Err(unauthorized("The token is not valid"));
+    };
+    if !token.allows(role, permission) {
+        let reason = match Scope::for_permission(permission) {
+            Some(scope) if !token.scopes.contains(&scope) => {
+                format!("The token does not have the {} scope", scope)
+            }
+            _ => format!(
+                "User {} with role {} does not have the {:?} permission",
+                token.user, role, permission
+            ),
+        };
+        return Err(HttpError::for_client_error(
+            None,
+            StatusCode::FORBIDDEN,
+            reason,
+        ));
+    }
+    Ok(Caller { login: token.user, role, token_id: token.id })
+}
+
+/// Map an error from vminfod or the executor, keeping "not found" so callers
+/// can tell a missing instance or image from a failure
+fn to_api_error(e: reqwest::Error) -> HttpError {
+    match e.status() {
+        Some(status) if status == StatusCode::NOT_FOUND.as_u16() => {
+            HttpError::for_not_found(None, String::from("Not found"))
+        }
+        _ => HttpError::for_internal_error(e.to_string()),
+    }
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ProvisionResponse {
+    pub uuid: Uuid,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct ProvisionParams {
+    /// Provision even if the host doesn't have room left for the instance
+    #[serde(default)]
+    pub overcommit: bool,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct MessageResponse {
+    pub message: String,
+}
+
+#[endpoint {
+method = GET,
+path = "/api/v1/instances"
+}]
+pub async fn get_instances(
+    ctx: RequestContext<Context>,
+) -> Result<HttpResponseOk<Vec<InstanceView>>, HttpError> {
+    authenticate(&ctx, Permission::View).await?;
+    let instances = ctx
+        .context()
+        .vminfod
+        .get_instances(&ctx.log)
+        .await
+        .map_err(to_api_error)?;
+    Ok(HttpResponseOk(instances))
+}
+
+/// The instance's complete `vmadm get` properties
+#[endpoint {
+method = GET,
+path = "/api/v1/instances/{id}"
+}]
+pub async fn get_instance(
+    ctx: RequestContext<Context>,
+    path_params: Path<PathParams>,
+) -> Result<HttpResponseOk<Value>, HttpError> {
+    authenticate(&ctx, Permission::View).await?;
+    let id = path_params.into_inner().id;
+    let json = ctx
+        .context()
+        .vminfod
+        .get_instance_json(&id)
+        .await
+        .map_err(to_api_error)?;
+    let value = serde_json::from_str(&json)
+        .map_err(|e| HttpError::for_internal_error(e.to_string()))?;
+    Ok(HttpResponseOk(value))
+}
+
+/// Create an instance from a `vmadm create` payload. A UUID is generated if
+/// the payload doesn't have one. The payload must meet its image's
+/// requirements and, unless `overcommit` is set, fit in what is left of the
+/// host.
+#[endpoint {
+method = POST,
+path = "/api/v1/instances"
+}]
+pub async fn post_instance(
+    ctx: RequestContext<Context>,
+    query_params: Query<ProvisionParams>,
+    request_body: TypedBody<CreatePayload>,
+) -> Result<HttpResponseCreated<ProvisionResponse>, HttpError> {
+    let caller = authenticate(&ctx, Permission::Provision).await?;
+    let mut payload = request_body.into_inner();
+    let problems = payload.problems();
+    if !problems.is_empty() {
+        return Err(HttpError::for_bad_request(None, problems.join(". ")));
+    }
+    let overcommit = query_params.into_inner().overcommit;
+    let checks =
+        HostChecks::new(ctx.context(), &ctx.log, &payload, overcommit).await?;
+    if !checks.is_empty() {
+        let message = checks.message("Set overcommit=true to provision anyway");
+        return Err(HttpError::for_bad_request(None, message));
+    }
+    let uuid = payload.ensure_uuid();
+
+    let response = ctx
+        .context()
+        .executor
+        .provision(&payload)
+        .await
+        .map_err(|e| HttpError::for_internal_error(e.to_string()))?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let message: GenericResponse = response
+            .json()
+            .await
+            .map_err(|e| HttpError::for_internal_error(e.to_string()))?;
+        info!(
+            ctx.log,
+            "API: {} (token {}) failed to create instance {}: {}",
+            caller.login,
+            caller.token_id,
+            uuid,
+            message.message
+        );
+        return Err(if status.is_client_error() {
+            HttpError::for_bad_request(None, message.message)
+        } else {
+            HttpError::for_internal_error(message.message)
+        });
+    }
+    info!(
+        ctx.log,
+        "API: {} (token {}) created instance {}",
+        caller.login,
+        caller.token_id,
+        uuid
+    );
+    Ok(HttpResponseCreated(ProvisionResponse { uuid }))
+}
+
+#[endpoint {
+method = POST,
+path = "/api/v1/instances/{id}/start"
+}]
+pub async fn post_instance_start(
+    ctx: RequestContext<Context>,
+    path_params: Path<PathParams>,
+) -> Result<HttpResponseUpdatedNoContent, HttpError> {
+    let caller = authenticate(&ctx, Permission::PowerControl).await?;
+    let id = path_params.into_inner().id;
+    ctx.context().executor.start_instance(&id).await.map_err(to_api_error)?;
+    info!(
+        ctx.log,
+        "API: {} (token {}) started instance {}",
+        caller.login,
+        caller.token_id,
+        id
+    );
+    Ok(HttpResponseUpdatedNoContent())
+}
+
+#[endpoint {
+method = POST,
+path = "/api/v1/instances/{id}/stop"
+}]
+pub async fn post_instance_stop(
+    ctx: RequestContext<Context>,
+    path_params: Path<PathParams>,
+) -> Result<HttpResponseUpdatedNoContent, HttpError> {
+    let caller = authenticate(&ctx, Permission::PowerControl).await?;
+    let id = path_params.into_inner().id;
+    ctx.context().executor.stop_instance(&id).await.map_err(to_api_error)?;
+    info!(
+        ctx.log,
+        "API: {} (token {}) stopped instance {}",
+        caller.login,
+        caller.token_id,
+        id
+    );
+    Ok(HttpResponseUpdatedNoContent())
+}
+
+#[endpoint {
+method = DELETE,
+path = "/api/v1/instances/{id}"
+}]
+pub async fn delete_instance(
+    ctx: RequestContext<Context>,
+    path_params: Path<PathParams>,
+) -> Result<HttpResponseDeleted, HttpError> {
+    let caller = authenticate(&ctx, Permission::Delete).await?;
+    let id = path_params.into_inner().id;
+    ctx.context().executor.delete_instance(&id).await.map_err(to_api_error)?;
+    info!(
+        ctx.log,
+        "API: {} (token {}) deleted instance {}",
+        caller.login,
+        caller.token_id,
+        id
+    );
+    Ok(HttpResponseDeleted())
+}
+
+/// Installed images, in the same format as `imgadm list -j`
+#[endpoint {
+method = GET,
+path = "/api/v1/images"
+}]
+pub async fn get_images(
+    ctx: RequestContext<Context>,
+) -> Result<HttpResponseOk<Vec<Image>>, HttpError> {
+    authenticate(&ctx, Permission::View).await?;
+    let images = ctx
+        .context()
+        .executor
+        .get_images(&ctx.log)
+        .await
+        .map_err(to_api_error)?;
+    Ok(HttpResponseOk(images))
+}
+
+/// The image's complete `imgadm get` output
+#[endpoint {
+method = GET,
+path = "/api/v1/images/{id}"
+}]
+pub async fn get_image(
+    ctx: RequestContext<Context>,
+    path_params: Path<PathParams>,
+) -> Result<HttpResponseOk<Value>, HttpError> {
+    authenticate(&ctx, Permission::View).await?;
+    let id = path_params.into_inner().id;
+    let json = ctx
+        .context()
+        .executor
+        .get_image_json(&id)
+        .await
+        .map_err(to_api_error)?;
+    let value = serde_json::from_str(&json)
+        .map_err(|e| HttpError::for_internal_error(e.to_string()))?;
+    Ok(HttpResponseOk(value))
+}
+
+/// Import an image that is available from the configured image sources.
+/// The request completes once the import has.
+#[endpoint {
+method = POST,
+path = "/api/v1/images/{id}/import"
+}]
+pub async fn post_image_import(
+    ctx: RequestContext<Context>,
+    path_params: Path<PathParams>,
+) -> Result<HttpResponseOk<MessageResponse>, HttpError> {
+    let caller = authenticate(&ctx, Permission::ManageImages).await?;
+    let id = path_params.into_inner().id;
+    let available = ctx
+        .context()
+        .executor
+        .get_available_images(&ctx.log)
+        .await
+        .map_err(to_api_error)?;
+    let Some((manifest, Some(url))) = available
+        .into_iter()
+        .find(|image| image.manifest.uuid == id)
+        .map(|image| (image.manifest, image.source))
+    else {
+        return Err(HttpError::for_not_found(
+            None,
+            format!("Image {} is not available from the image sources", id),
+        ));
+    };
+    let params = ImageImportParams {
+        url,
+        name: manifest.name,
+        version: manifest.version,
+        r#type: manifest.r#type.to_string(),
+        os: manifest.os,
+    };
+
+    let response = ctx
+        .context()
+        .executor
+        .import_image(&id, &params)
+        .await
+        .map_err(|e| HttpError::for_internal_error(e.to_string()))?;
+    let status = response.status();
+    let result: GenericResponse = response
+        .json()
+        .await
+        .map_err(|e| HttpError::for_internal_error(e.to_string()))?;
+    info!(
+        ctx.log,
+        "API: {} (token {}) imported image {}: {}",
+        caller.login,
+        caller.token_id,
+        id,
+        result.message
+    );
+    if !status.is_success() {
+        return Err(HttpError::for_bad_request(None, result.message));
+    }
+    Ok(HttpResponseOk(MessageResponse { message: result.message }))
+}
+
+#[endpoint {
+method = DELETE,
+path = "/api/v1/images/{id}"
+}]
+pub async fn delete_image(
+    ctx: RequestContext<Context>,
+    path_params: Path<PathParams>,
+) -> Result<HttpResponseDeleted, HttpError> {
+    let caller = authenticate(&ctx, Permission::ManageImages).await?;
+    let id = path_params.into_inner().id;
+    ctx.context().executor.delete_image(&id).await.map_err(to_api_error)?;
+    info!(
+        ctx.log,
+        "API: {} (token {}) deleted image {}",
+        caller.login,
+        caller.token_id,
+        id
+    );
+    Ok(HttpResponseDeleted())
+}
+
+/// The host's complete `sysinfo` output
+#[endpoint {
+method = GET,
+path = "/api/v1/sysinfo"
+}]
+pub async fn get_sysinfo(
+    ctx: RequestContext<Context>,
+) -> Result<HttpResponseOk<Value>, HttpError> {
+    authenticate(&ctx, Permission::View).await?;
+    let sysinfo = ctx
+        .context()
+        .executor
+        .get_sysinfo_json()
+        .await
+        .map_err(to_api_error)?;
+    Ok(HttpResponseOk(sysinfo))
+}
+
+#[endpoint {
+method = GET,
+path = "/api/v1/nictags"
+}]
+pub async fn get_nictags(
+    ctx: RequestContext<Context>,
+) -> Result<HttpResponseOk<Vec<NicTag>>, HttpError> {
+    authenticate(&ctx, Permission::View).await?;
+    let nictags =
+        ctx.context().executor.get_nictags().await.map_err(to_api_error)?;
+    Ok(HttpResponseOk(nictags))
+}
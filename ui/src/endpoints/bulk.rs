@@ -0,0 +1,281 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+//! Start, stop, reboot or delete the instances selected on the instance list.
+//! The executor is asked about a few instances at a time, and the outcome for
+//! each is listed in one notification.
+
+use std::collections::HashMap;
+
+use crate::clients::ExecutorClient;
+use crate::endpoints::{
+    error_notification, redirect_login, require, Context, NotificationKind,
+};
+use crate::security_headers;
+use crate::session;
+
+use smartos_shared::auth::Permission;
+use smartos_shared::http_server::{to_internal_error, GenericResponse};
+
+use askama::Template;
+use dropshot::{endpoint, HttpError, Path, RequestContext, TypedBody};
+use futures::stream::{self, StreamExt};
+use hyper::{Body, Response, StatusCode};
+use reqwest::RequestBuilder;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use slog::{info, warn, Logger};
+use uuid::Uuid;
+
+/// How many instances the executor is asked about at once
+pub const CONCURRENCY: usize = 4;
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum BulkAction {
+    Start,
+    Stop,
+    Reboot,
+    Delete,
+}
+
+impl BulkAction {
+    #[must_use]
+    pub fn permission(&self) -> Permission {
+        match self {
+            Self::Start | Self::Stop | Self::Reboot => Permission::PowerControl,
+            Self::Delete => Permission::Delete,
+        }
+    }
+
+    /// The executor request that takes this action on instance `id`
+    fn request(&self, executor: &ExecutorClient, id: &Uuid) -> RequestBuilder {
+        let path = format!("instance/{}", id.as_hyphenated());
+        match self {
+            Self::Start => executor.post(&format!("{}/start", path)),
+            Self::Stop => executor.post(&format!("{}/stop", path)),
+            Self::Reboot => executor.post(&format!("{}/reboot", path)),
+            Self::Delete => executor.delete(&path),
+        }
+    }
+
+    fn past_tense(&self) -> &'static str {
+        match self {
+            Self::Start => "Started",
+            Self::Stop => "Stopped",
+            Self::Reboot => "Rebooted",
+            Self::Delete => "Deleted",
+        }
+    }
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct BulkPathParams {
+    action: BulkAction,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct BulkBody {
+    /// Comma separated instance UUIDs
+    #[serde(default)]
+    ids: String,
+    /// Must be the [confirmation_phrase] to delete instances
+    #[serde(default)]
+    confirm: String,
+}
+
+/// What has to be typed to delete `count` instances
+#[must_use]
+pub fn confirmation_phrase(count: usize) -> String {
+    if count == 1 {
+        String::from("delete 1 instance")
+    } else {
+        format!("delete {} instances", count)
+    }
+}
+
+/// Why the executor failed an action: the message in its response `body`,
+/// which is vmadm's error when vmadm failed, or else the `status`
+#[must_use]
+pub fn failure_message(status: StatusCode, body: &str) -> String {
+    serde_json::from_str::<GenericResponse>(body)
+        .ok()
+        .map(|response| String::from(response.message.trim()))
+        .filter(|message| !message.is_empty())
+        .unwrap_or_else(|| status.to_string())
+}
+
+/// The UUIDs in a comma separated list, in order and without duplicates
+pub fn parse_ids(ids: &str) -> Result<Vec<Uuid>, String> {
+    let mut parsed: Vec<Uuid> = Vec::new();
+    for id in ids.split(',').map(str::trim).filter(|id| !id.is_empty()) {
+        let uuid = Uuid::parse_str(id)
+            .map_err(|_| format!("{} is not an instance UUID", id))?;
+        if !parsed.contains(&uuid) {
+            parsed.push(uuid);
+        }
+    }
+    Ok(parsed)
+}
+
+pub struct BulkResult {
+    pub uuid: Uuid,
+    pub alias: String,
+    /// Why the action failed for this instance, if it did
+    pub error: Option<String>,
+}
+
+/// A notification listing each instance's result
+#[derive(Template)]
+#[template(path = "bulk_result.j2")]
+struct BulkResultTemplate {
+    id: String,
+    entity_id: String,
+    kind: NotificationKind,
+    subject: String,
+    message: String,
+    timeout: Option<String>,
+    redirect: Option<String>,
+    created_at: String,
+    results: Vec<BulkResult>,
+}
+
+/// Aliases of every instance, for the results. Instances that have gone are
+/// shown by UUID.
+async fn aliases(ctx: &Context, log: &Logger) -> HashMap<Uuid, String> {
+    let instances = match ctx.instances.instances() {
+        Some(instances) => instances,
+        None => ctx.vminfod.get_instances(log).await.unwrap_or_default(),
+    };
+    instances.into_iter().map(|i| (i.uuid, i.alias)).collect()
+}
+
+#[endpoint {
+method = POST,
+path = "/bulk/instances/{action}",
+content_type = "application/x-www-form-urlencoded"
+}]
+pub async fn post_action(
+    ctx: RequestContext<Context>,
+    path_params: Path<BulkPathParams>,
+    request_body: TypedBody<BulkBody>,
+) -> Result<Response<Body>, HttpError> {
+    let response = security_headers::builder();
+    let Some(user) = session::get_user(&ctx)? else {
+        return redirect_login(response, &ctx);
+    };
+    let action = path_params.into_inner().action;
+    require(&user, action.permission())?;
+
+    let body = request_body.into_inner();
+    let ids = match parse_ids(&body.ids) {
+        Ok(ids) if ids.is_empty() => {
+            return error_notification(
+                &ctx,
+                "",
+                "No instances selected",
+                "Select at least one instance",
+            )
+        }
+        Ok(ids) => ids,
+        Err(e) => return error_notification(&ctx, "", "Invalid selection", &e),
+    };
+
+    if action == BulkAction::Delete {
+        let phrase = confirmation_phrase(ids.len());
+        if body.confirm.trim() != phrase {
+            return error_notification(
+                &ctx,
+                "",
+                "Instances not deleted",
+                &format!("Type \"{}\" to confirm", phrase),
+            );
+        }
+    }
+
+    let aliases = aliases(ctx.context(), &ctx.log).await;
+    let executor = &ctx.context().executor;
+    let mut outcomes: Vec<(usize, Uuid, Option<String>)> =
+        stream::iter(ids.into_iter().enumerate())
+            .map(|(index, uuid)| async move {
+                let error = match action.request(executor, &uuid).send().await {
+                    Ok(response) if response.status().is_success() => None,
+                    Ok(response) => {
+                        let status = response.status();
+                        let body = response.text().await.unwrap_or_default();
+                        Some(failure_message(status, &body))
+                    }
+                    // The URL names the executor's address, which isn't
+                    // useful to the user
+                    Err(e) => Some(e.without_url().to_string()),
+                };
+                (index, uuid, error)
+            })
+            .buffer_unordered(CONCURRENCY)
+            .collect()
+            .await;
+    outcomes.sort_by_key(|(index, _, _)| *index);
+
+    let results: Vec<BulkResult> = outcomes
+        .into_iter()
+        .map(|(_, uuid, error)| {
+            if let Some(error) = &error {
+                warn!(ctx.log, "{:?} of {} failed: {}", action, uuid, error);
+            }
+            BulkResult {
+                uuid,
+                alias: aliases
+                    .get(&uuid)
+                    .cloned()
+                    .unwrap_or_else(|| uuid.to_string()),
+                error,
+            }
+        })
+        .collect();
+
+    let failed = results.iter().filter(|r| r.error.is_some()).count();
+    let succeeded = results.len() - failed;
+    info!(
+        ctx.log,
+        "{} {:?} {} instances", user.login, action, results.len();
+        "failed" => failed
+    );
+
+    let template = BulkResultTemplate {
+        id: ctx.request_id.clone(),
+        entity_id: String::new(),
+        kind: if failed == 0 {
+            NotificationKind::Ok
+        } else {
+            NotificationKind::Error
+        },
+        subject: format!(
+            "{} {} of {} instances",
+            action.past_tense(),
+            succeeded,
+            results.len()
+        ),
+        message: format!("{} succeeded, {} failed", succeeded, failed),
+        // Failures stay until they are read
+        timeout: (failed == 0).then(|| String::from("15s")),
+        redirect: None,
+        created_at: String::from("/instances"),
+        results,
+    };
+    let result = template.render().map_err(to_internal_error)?;
+
+    // The list itself is updated by the instance event stream, the page only
+    // has to forget the selection
+    response
+        .status(StatusCode::OK)
+        .header("HX-Trigger", "bulkDone")
+        .body(result.into())
+        .map_err(to_internal_error)
+}
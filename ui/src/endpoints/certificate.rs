@@ -0,0 +1,153 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+//! View and replace the HTTPS server's certificate
+
+use crate::endpoints::{
+    htmx_response, redirect_login, require, Context, NotificationKind,
+    NotificationTemplate,
+};
+use crate::security_headers;
+use crate::session::{self, CurrentUser};
+use crate::tls::{CertificateInfo, KeyPair};
+
+use smartos_shared::auth::Permission;
+use smartos_shared::http_server::to_internal_error;
+use smartos_shared::tls::TlsInstallRequest;
+
+use askama::Template;
+use dropshot::{endpoint, HttpError, RequestContext, TypedBody};
+use hyper::{Body, Response, StatusCode};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use slog::{info, warn};
+
+/// Certificates expiring within this many days are highlighted
+const EXPIRY_WARNING_DAYS: i32 = 30;
+
+#[derive(Template)]
+#[template(path = "certificate.j2")]
+pub struct CertificateTemplate<'a> {
+    title: &'a str,
+    user: CurrentUser,
+    cert: CertificateInfo,
+    expiry_warning_days: i32,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct CertificateRequestBody {
+    cert: String,
+    key: String,
+}
+
+#[endpoint {
+method = GET,
+path = "/config/tls"
+}]
+pub async fn get_index(
+    ctx: RequestContext<Context>,
+) -> Result<Response<Body>, HttpError> {
+    let response = security_headers::builder();
+    let Some(user) = session::get_user(&ctx)? else {
+        return redirect_login(response, &ctx);
+    };
+    require(&user, Permission::Configure)?;
+
+    let cert = CertificateInfo::parse(&ctx.context().tls.cert())
+        .map_err(to_internal_error)?;
+    let template = CertificateTemplate {
+        title: "TLS Certificate",
+        user,
+        cert,
+        expiry_warning_days: EXPIRY_WARNING_DAYS,
+    };
+    let result = template.render().map_err(to_internal_error)?;
+    htmx_response(response, "/config/tls", result.into())
+}
+
+/// Replace the certificate. It is checked, saved by the executor and then
+/// used for new connections straight away.
+#[endpoint {
+method = POST,
+path = "/config/tls",
+content_type = "application/x-www-form-urlencoded"
+}]
+pub async fn post_index(
+    ctx: RequestContext<Context>,
+    request_body: TypedBody<CertificateRequestBody>,
+) -> Result<Response<Body>, HttpError> {
+    let response = security_headers::builder();
+    let Some(user) = session::get_user(&ctx)? else {
+        return redirect_login(response, &ctx);
+    };
+    require(&user, Permission::Configure)?;
+
+    let body = request_body.into_inner();
+    let checked = KeyPair::parse(&body.cert, &body.key).and_then(|pair| {
+        let info = CertificateInfo::parse(&pair.cert)?;
+        if info.days_remaining < 0 {
+            return Err(format!(
+                "The certificate expired on {}",
+                info.not_after
+            ));
+        }
+        Ok(pair)
+    });
+
+    let (kind, subject, message, redirect) = match checked {
+        Err(message) => {
+            warn!(
+                ctx.log,
+                "{} uploaded an invalid TLS certificate: {}",
+                user.login,
+                message
+            );
+            (NotificationKind::Error, "Certificate not replaced", message, None)
+        }
+        Ok(pair) => {
+            ctx.context()
+                .executor
+                .install_tls(&TlsInstallRequest {
+                    cert: pair.cert.clone(),
+                    key: pair.key.clone(),
+                })
+                .await
+                .map_err(to_internal_error)?;
+            ctx.context().tls.replace(&pair).map_err(to_internal_error)?;
+            info!(ctx.log, "{} replaced the TLS certificate", user.login);
+            (
+                NotificationKind::Ok,
+                "Certificate replaced",
+                String::from(
+                    "New connections will use the new certificate, you may \
+                    need to reload the page",
+                ),
+                Some(String::from("/config/tls")),
+            )
+        }
+    };
+
+    let template = NotificationTemplate {
+        id: ctx.request_id,
+        entity_id: String::new(),
+        kind,
+        subject: String::from(subject),
+        message,
+        timeout: Some(String::from("8s")),
+        redirect,
+        created_at: String::from("/config/tls"),
+    };
+    let result = template.render().map_err(to_internal_error)?;
+    response
+        .status(StatusCode::OK)
+        .header("Content-Type", "text/html")
+        .body(result.into())
+        .map_err(to_internal_error)
+}
@@ -8,9 +8,11 @@
  * Copyright 2025 MNX Cloud, Inc.
  */
 
-use crate::endpoints::{htmx_response, redirect_login, Context};
-use crate::session;
+use crate::endpoints::{htmx_response, redirect_login, require, Context};
+use crate::security_headers;
+use crate::session::{self, CurrentUser};
 
+use smartos_shared::auth::Permission;
 use smartos_shared::http_server::to_internal_error;
 
 use askama::Template;
@@ -20,6 +22,7 @@ use hyper::{Body, Response};
 #[derive(Template)]
 #[template(path = "gz_config.j2")]
 pub struct GZConfigTemplate<'a> {
+    user: CurrentUser,
     title: &'a str,
     config: Vec<(String, String)>,
 }
@@ -31,10 +34,11 @@ path = "/config/gz"
 pub async fn get_gz_index(
     ctx: RequestContext<Context>,
 ) -> Result<Response<Body>, HttpError> {
-    let response = Response::builder();
-    if !session::is_valid(&ctx) {
+    let response = security_headers::builder();
+    let Some(user) = session::get_user(&ctx)? else {
         return redirect_login(response, &ctx);
-    }
+    };
+    require(&user, Permission::Configure)?;
 
     let config = ctx
         .context()
@@ -43,7 +47,8 @@ pub async fn get_gz_index(
         .await
         .map_err(to_internal_error)?;
 
-    let template = GZConfigTemplate { title: "Global Zone Config", config };
+    let template =
+        GZConfigTemplate { user, title: "Global Zone Config", config };
     let result = template.render().map_err(to_internal_error)?;
     htmx_response(response, "/config/gz", result.into())
 }
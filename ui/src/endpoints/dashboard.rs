@@ -8,9 +8,13 @@
  * Copyright 2024 MNX Cloud, Inc.
  */
 
-use crate::endpoints::{filters, htmx_response, redirect_login, Context};
-use crate::session;
+use crate::endpoints::{
+    filters, htmx_response, redirect_login, require, Context,
+};
+use crate::security_headers;
+use crate::session::{self, CurrentUser};
 
+use smartos_shared::auth::Permission;
 use smartos_shared::{http_server::to_internal_error, sysinfo::Sysinfo};
 
 use askama::Template;
@@ -20,6 +24,7 @@ use hyper::{Body, Response};
 #[derive(Template)]
 #[template(path = "dashboard.j2")]
 pub struct DashboardTemplate<'a> {
+    user: CurrentUser,
     title: &'a str,
     sysinfo: Sysinfo,
     image_count: usize,
@@ -33,10 +38,11 @@ path = "/dashboard"
 pub async fn get_index(
     ctx: RequestContext<Context>,
 ) -> Result<Response<Body>, HttpError> {
-    let response = Response::builder();
-    if !session::is_valid(&ctx) {
+    let response = security_headers::builder();
+    let Some(user) = session::get_user(&ctx)? else {
         return redirect_login(response, &ctx);
-    }
+    };
+    require(&user, Permission::View)?;
 
     let sysinfo = ctx
         .context()
@@ -56,12 +62,13 @@ pub async fn get_index(
     let instance_count = ctx
         .context()
         .vminfod
-        .get_instances()
+        .get_instances(&ctx.log)
         .await
         .map_err(to_internal_error)?
         .len();
 
     let template = DashboardTemplate {
+        user,
         title: "Dashboard",
         sysinfo,
         image_count,
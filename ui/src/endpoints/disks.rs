@@ -0,0 +1,314 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+//! Adding, resizing and removing the disks of a bhyve or KVM instance. Sizes
+//! are entered in GiB, as on the provisioning form, and sent to vmadm in MiB.
+
+use std::future::Future;
+
+use crate::clients::{ExecutorClient, RequestError};
+use crate::endpoints::{
+    apply, error_notification, get_header, get_instance, non_empty,
+    redirect_login, require, Context, InstanceUpdate, PathParams,
+};
+use crate::security_headers;
+use crate::session;
+
+use smartos_shared::auth::Permission;
+use smartos_shared::disk::{DiskChange, DiskUpdate, NewDisk};
+use smartos_shared::instance::Instance;
+use smartos_shared::serde_helpers::deserialize_into_bool;
+
+use dropshot::{endpoint, HttpError, Path, RequestContext, TypedBody};
+use hyper::{Body, Response};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use uuid::Uuid;
+
+#[derive(Deserialize, JsonSchema)]
+pub struct DiskParams {
+    id: Uuid,
+    /// e.g. "disk1"
+    disk: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct AddDiskBody {
+    /// GiB
+    #[serde(default)]
+    size: String,
+    #[serde(default)]
+    model: String,
+    #[serde(default)]
+    compression: String,
+    /// Bytes
+    #[serde(default)]
+    block_size: String,
+    #[serde(default, deserialize_with = "deserialize_into_bool")]
+    boot: bool,
+}
+
+/// Only what differs from the disk as it is gets changed
+#[derive(Deserialize, JsonSchema)]
+pub struct UpdateDiskBody {
+    /// GiB
+    #[serde(default)]
+    size: String,
+    #[serde(default)]
+    model: String,
+    #[serde(default)]
+    compression: String,
+    #[serde(default, deserialize_with = "deserialize_into_bool")]
+    boot: bool,
+}
+
+fn number(label: &str, value: &str) -> Result<Option<u64>, String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(None);
+    }
+    value
+        .parse()
+        .map(Some)
+        .map_err(|_| format!("{} must be a whole number", label))
+}
+
+impl InstanceUpdate for DiskUpdate {
+    const WHAT: &'static str = "disks";
+
+    fn problems_with(&self, instance: &Instance) -> Vec<String> {
+        let disks =
+            instance.hvm().map(|hvm| hvm.disks.as_slice()).unwrap_or(&[]);
+        self.problems(&instance.brand(), disks)
+    }
+
+    fn changes_nothing(&self) -> bool {
+        self.is_empty()
+    }
+
+    fn send(
+        &self,
+        executor: &ExecutorClient,
+        id: &Uuid,
+    ) -> impl Future<Output = Result<reqwest::Response, RequestError>> + Send
+    {
+        executor.update_disks(id, self)
+    }
+}
+
+#[endpoint {
+method = POST,
+path = "/instances/{id}/disks",
+content_type = "application/x-www-form-urlencoded"
+}]
+pub async fn post_disk(
+    ctx: RequestContext<Context>,
+    path_params: Path<PathParams>,
+    request_body: TypedBody<AddDiskBody>,
+) -> Result<Response<Body>, HttpError> {
+    let response = security_headers::builder();
+    let Some(user) = session::get_user(&ctx)? else {
+        return redirect_login(response, &ctx);
+    };
+    require(&user, Permission::Modify)?;
+    let id = path_params.into_inner().id;
+    let body = request_body.into_inner();
+
+    let (size, block_size) = match (
+        number("Size", &body.size),
+        number("Block size", &body.block_size),
+    ) {
+        (Ok(Some(size)), Ok(block_size)) => (size, block_size),
+        (Ok(None), _) => {
+            return error_notification(
+                &ctx,
+                id,
+                "Disk not added",
+                "A size is required",
+            )
+        }
+        (Err(e), _) | (_, Err(e)) => {
+            return error_notification(&ctx, id, "Disk not added", &e)
+        }
+    };
+    let Some(mib) = size.checked_mul(1024) else {
+        return error_notification(
+            &ctx,
+            id,
+            "Disk not added",
+            "Size is too large",
+        );
+    };
+    let instance = get_instance(&ctx, &id).await?;
+    let update = DiskUpdate {
+        add_disks: vec![NewDisk {
+            size: mib,
+            model: non_empty(&body.model),
+            compression: non_empty(&body.compression),
+            block_size,
+            boot: body.boot.then_some(true),
+        }],
+        ..Default::default()
+    };
+    apply(
+        &ctx,
+        &user,
+        &instance,
+        update,
+        ("Disk added", "Disk not added"),
+        format!("Added a {} GiB disk to {}", size, instance.alias()),
+    )
+    .await
+}
+
+#[endpoint {
+method = PUT,
+path = "/instances/{id}/disks/{disk}",
+content_type = "application/x-www-form-urlencoded"
+}]
+pub async fn put_disk(
+    ctx: RequestContext<Context>,
+    path_params: Path<DiskParams>,
+    request_body: TypedBody<UpdateDiskBody>,
+) -> Result<Response<Body>, HttpError> {
+    let response = security_headers::builder();
+    let Some(user) = session::get_user(&ctx)? else {
+        return redirect_login(response, &ctx);
+    };
+    require(&user, Permission::Modify)?;
+    let DiskParams { id, disk: name } = path_params.into_inner();
+    let body = request_body.into_inner();
+
+    let size = match number("Size", &body.size) {
+        Ok(size) => size,
+        Err(e) => return error_notification(&ctx, id, "Disk not changed", &e),
+    };
+    let mib = match size.map(|size| size.checked_mul(1024)) {
+        Some(None) => {
+            return error_notification(
+                &ctx,
+                id,
+                "Disk not changed",
+                "Size is too large",
+            )
+        }
+        mib => mib.flatten(),
+    };
+    let instance = get_instance(&ctx, &id).await?;
+    let Some(disk) = instance
+        .hvm()
+        .and_then(|hvm| hvm.disks.iter().find(|disk| disk.name() == name))
+    else {
+        return error_notification(
+            &ctx,
+            id,
+            "Disk not changed",
+            &format!("{} has no disk {}", instance.alias(), name),
+        );
+    };
+
+    // The form shows whole GiB, so a disk that isn't a whole number of GiB
+    // is left alone unless a different size is entered
+    let current = disk.size.unwrap_or_default();
+    let size = size.filter(|size| *size != current / 1024).and(mib);
+    let differs = |value: &str, current: &Option<String>| {
+        non_empty(value).filter(|value| Some(value) != current.as_ref())
+    };
+    let change = DiskChange {
+        path: disk.path.clone(),
+        size,
+        model: differs(&body.model, &disk.model),
+        compression: differs(&body.compression, &disk.compression),
+        boot: Some(body.boot)
+            .filter(|boot| *boot != disk.boot.unwrap_or(false)),
+    };
+    let message = format!("Changed {} of {}", name, instance.alias());
+    // A change of nothing is left out, so that the update is empty
+    let unchanged =
+        DiskChange { path: change.path.clone(), ..Default::default() };
+    let update = DiskUpdate {
+        update_disks: (change != unchanged)
+            .then_some(change)
+            .into_iter()
+            .collect(),
+        ..Default::default()
+    };
+    apply(
+        &ctx,
+        &user,
+        &instance,
+        update,
+        ("Disk changed", "Disk not changed"),
+        message,
+    )
+    .await
+}
+
+#[endpoint {
+method = DELETE,
+path = "/instances/{id}/disks/{disk}",
+}]
+pub async fn delete_disk(
+    ctx: RequestContext<Context>,
+    path_params: Path<DiskParams>,
+) -> Result<Response<Body>, HttpError> {
+    let response = security_headers::builder();
+    let Some(user) = session::get_user(&ctx)? else {
+        return redirect_login(response, &ctx);
+    };
+    require(&user, Permission::Modify)?;
+    let DiskParams { id, disk: name } = path_params.into_inner();
+
+    // The disk's name has to be typed in, since its data goes with it
+    if get_header(&ctx, "HX-Prompt").as_deref().map(str::trim)
+        != Some(name.as_str())
+    {
+        return error_notification(
+            &ctx,
+            id,
+            "Disk not removed",
+            &format!("Type {} to confirm removing it", name),
+        );
+    }
+    let instance = get_instance(&ctx, &id).await?;
+    if instance.state() != "stopped" {
+        return error_notification(
+            &ctx,
+            id,
+            "Disk not removed",
+            "Stop the instance before removing a disk",
+        );
+    }
+    let Some(disk) = instance
+        .hvm()
+        .and_then(|hvm| hvm.disks.iter().find(|disk| disk.name() == name))
+    else {
+        return error_notification(
+            &ctx,
+            id,
+            "Disk not removed",
+            &format!("{} has no disk {}", instance.alias(), name),
+        );
+    };
+    let update = DiskUpdate {
+        remove_disks: vec![disk.path.clone()],
+        ..Default::default()
+    };
+    let message = format!("Removed {} from {}", name, instance.alias());
+    apply(
+        &ctx,
+        &user,
+        &instance,
+        update,
+        ("Disk removed", "Disk not removed"),
+        message,
+    )
+    .await
+}
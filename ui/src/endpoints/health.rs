@@ -0,0 +1,172 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+//! A health report for monitoring and SMF probes. Anyone can see the status
+//! and latency of each check; versions and errors are only included for
+//! logged in users and requests with the `METRICS_TOKEN`.
+
+use std::time::{Duration, Instant};
+
+use crate::endpoints::{get_header, Context};
+use crate::session;
+use crate::tls::CertificateInfo;
+use crate::VERSION;
+
+use smartos_shared::auth::constant_time_eq;
+use smartos_shared::health::{self, Check, HealthReport, Status};
+use smartos_shared::http_server::to_internal_error;
+
+use dropshot::{endpoint, HttpError, RequestContext};
+use hyper::{Body, Response, StatusCode};
+use time::OffsetDateTime;
+use tokio::time::timeout;
+
+/// Dependencies that take longer than this are reported as down
+const TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Certificates expiring within this many days are degraded
+const CERT_EXPIRY_DAYS: i32 = 30;
+
+/// The programs the executor checks, reported as down when it can't be
+/// reached
+const EXECUTOR_CHECKS: [&str; 4] =
+    ["vmadm", "imgadm", "sysinfo", "executor_log_disk"];
+
+async fn check_executor(ctx: &Context) -> Vec<Check> {
+    let started = Instant::now();
+    let error = match timeout(TIMEOUT, ctx.executor.get_health()).await {
+        Ok(Ok(report)) => {
+            let mut check = Check::timed("executor", started);
+            check.version = Some(report.version);
+            let mut checks = vec![check];
+            checks.extend(report.checks);
+            return checks;
+        }
+        Ok(Err(e)) => e.to_string(),
+        Err(_) => format!("No response after {}s", TIMEOUT.as_secs()),
+    };
+    let mut checks = vec![Check::down("executor", error)];
+    checks.extend(
+        EXECUTOR_CHECKS
+            .iter()
+            .map(|name| Check::down(name, "Not checked, the executor is down")),
+    );
+    checks
+}
+
+async fn check_vminfod(ctx: &Context) -> Check {
+    let started = Instant::now();
+    match timeout(TIMEOUT, ctx.vminfod.ping()).await {
+        Ok(Ok(true)) => Check::timed("vminfod", started),
+        Ok(Ok(false)) => Check::down("vminfod", "Unexpected response"),
+        Ok(Err(e)) => Check::down("vminfod", e),
+        Err(_) => Check::down(
+            "vminfod",
+            format!("No response after {}s", TIMEOUT.as_secs()),
+        ),
+    }
+}
+
+fn check_certificate(ctx: &Context) -> Check {
+    let name = "tls_certificate";
+    let cert = match CertificateInfo::parse(&ctx.tls.cert()) {
+        Ok(cert) => cert,
+        Err(e) => return Check::down(name, e),
+    };
+    let mut check = Check::new(name, Status::Ok);
+    check.detail = Some(format!(
+        "Expires {}, in {} days",
+        cert.not_after, cert.days_remaining
+    ));
+    if cert.days_remaining < 0 {
+        check.status = Status::Down;
+        check.error = Some(String::from("The certificate has expired"));
+    } else if cert.days_remaining < CERT_EXPIRY_DAYS {
+        check.status = Status::Degraded;
+        check.error = Some(format!(
+            "The certificate expires in {} days",
+            cert.days_remaining
+        ));
+    }
+    check
+}
+
+/// Remember each check's error, and add the last one to every check
+fn record_errors(ctx: &Context, checks: &mut [Check]) {
+    let Ok(mut errors) = ctx.health_errors.lock() else {
+        return;
+    };
+    let now = OffsetDateTime::now_utc();
+    for check in checks {
+        if let Some(error) = &check.error {
+            errors.insert(check.name.clone(), (error.clone(), now));
+        }
+        if let Some((error, at)) = errors.get(&check.name) {
+            check.last_error = Some(error.clone());
+            check.last_error_at = Some(*at);
+        }
+    }
+}
+
+/// Whether the caller may see versions and error messages
+fn show_details(ctx: &RequestContext<Context>) -> bool {
+    if let Some(token) = &ctx.context().config.metrics_token {
+        let expected = format!("Bearer {}", token.0);
+        let provided = get_header(ctx, "Authorization").unwrap_or_default();
+        if constant_time_eq(expected.as_bytes(), provided.as_bytes()) {
+            return true;
+        }
+    }
+    session::get_user(ctx).ok().flatten().is_some()
+}
+
+/// Report the status of the UI's dependencies. Responds with a 503 when any
+/// of them is down, and a 200 when they are all ok or only degraded.
+#[endpoint {
+method = GET,
+path = "/health"
+}]
+pub async fn get_index(
+    ctx: RequestContext<Context>,
+) -> Result<Response<Body>, HttpError> {
+    let context = ctx.context();
+    let (mut checks, vminfod) =
+        tokio::join!(check_executor(context), check_vminfod(context));
+    checks.insert(1, vminfod);
+    checks.push(check_certificate(context));
+    checks.push(match &context.log_file {
+        Some(file) => health::disk_space("ui_log_disk", file),
+        None => Check::down("ui_log_disk", "The log file couldn't be opened"),
+    });
+    record_errors(context, &mut checks);
+
+    let status = health::overall(&checks);
+    let report = if show_details(&ctx) {
+        HealthReport { status, version: Some(String::from(VERSION)), checks }
+    } else {
+        HealthReport {
+            status,
+            version: None,
+            checks: checks.into_iter().map(Check::summary).collect(),
+        }
+    };
+
+    let code = if status == Status::Down {
+        StatusCode::SERVICE_UNAVAILABLE
+    } else {
+        StatusCode::OK
+    };
+    Response::builder()
+        .status(code)
+        .header("Content-Type", "application/json")
+        .header("Cache-Control", "no-store")
+        .body(serde_json::to_string(&report).map_err(to_internal_error)?.into())
+        .map_err(to_internal_error)
+}
@@ -9,11 +9,13 @@
  */
 
 use crate::endpoints::{
-    filters, htmx_response, redirect_login, AsJson, Context, NotificationKind,
-    NotificationTemplate, PathParams,
+    filters, htmx_response, redirect_login, require, AsJson, Context,
+    NotificationKind, NotificationTemplate, PathParams,
 };
-use crate::session;
+use crate::security_headers;
+use crate::session::{self, CurrentUser};
 
+use smartos_shared::auth::Permission;
 use smartos_shared::{
     http_server::to_internal_error, http_server::GenericResponse, image::Image,
     image::ImageImportParams,
@@ -29,6 +31,7 @@ use slog::error;
 #[derive(Template)]
 #[template(path = "images.j2")]
 pub struct ImagesTemplate<'a> {
+    user: CurrentUser,
     title: &'a str,
     images: Vec<Image>,
 }
@@ -40,10 +43,11 @@ path = "/images"
 pub async fn get_index(
     ctx: RequestContext<Context>,
 ) -> Result<Response<Body>, HttpError> {
-    let response = Response::builder();
-    if !session::is_valid(&ctx) {
+    let response = security_headers::builder();
+    let Some(user) = session::get_user(&ctx)? else {
         return redirect_login(response, &ctx);
-    }
+    };
+    require(&user, Permission::View)?;
 
     let images = ctx
         .context()
@@ -52,7 +56,7 @@ pub async fn get_index(
         .await
         .map_err(to_internal_error)?;
 
-    let template = ImagesTemplate { title: "Images", images };
+    let template = ImagesTemplate { user, title: "Images", images };
     let result = template.render().map_err(to_internal_error)?;
 
     htmx_response(response, "/images", result.into())
@@ -61,6 +65,7 @@ pub async fn get_index(
 #[derive(Template)]
 #[template(path = "image.j2")]
 pub struct ImageTemplate {
+    user: CurrentUser,
     id: String,
     title: String,
     image: Option<Image>,
@@ -76,10 +81,11 @@ pub async fn get_by_id(
     path_params: Path<PathParams>,
     query_params: Query<AsJson>,
 ) -> Result<Response<Body>, HttpError> {
-    let response = Response::builder();
-    if !session::is_valid(&ctx) {
+    let response = security_headers::builder();
+    let Some(user) = session::get_user(&ctx)? else {
         return redirect_login(response, &ctx);
-    }
+    };
+    require(&user, Permission::View)?;
 
     let id = path_params.into_inner().id;
     let image = match ctx.context().executor.get_image(&id).await {
@@ -115,8 +121,13 @@ pub async fn get_by_id(
         None => format!("Not Found: {}", &id),
     };
 
-    let template =
-        ImageTemplate { id: id.to_string(), title, image, json: json_string };
+    let template = ImageTemplate {
+        user,
+        id: id.to_string(),
+        title,
+        image,
+        json: json_string,
+    };
     let result = template.render().map_err(to_internal_error)?;
 
     htmx_response(response, &location, result.into())
@@ -130,10 +141,11 @@ pub async fn delete_by_id(
     ctx: RequestContext<Context>,
     path_params: Path<PathParams>,
 ) -> Result<Response<Body>, HttpError> {
-    let response = Response::builder();
-    if !session::is_valid(&ctx) {
+    let response = security_headers::builder();
+    let Some(user) = session::get_user(&ctx)? else {
         return redirect_login(response, &ctx);
-    }
+    };
+    require(&user, Permission::ManageImages)?;
     let id = path_params.into_inner().id;
     let image = ctx
         .context()
@@ -182,6 +194,7 @@ pub async fn delete_by_id(
 #[derive(Template)]
 #[template(path = "import.j2")]
 pub struct ImportTemplate {
+    user: CurrentUser,
     title: String,
     images: Vec<Image>,
 }
@@ -193,10 +206,11 @@ path = "/import",
 pub async fn get_import_index(
     ctx: RequestContext<Context>,
 ) -> Result<Response<Body>, HttpError> {
-    let response = Response::builder();
-    if !session::is_valid(&ctx) {
+    let response = security_headers::builder();
+    let Some(user) = session::get_user(&ctx)? else {
         return redirect_login(response, &ctx);
-    }
+    };
+    require(&user, Permission::ManageImages)?;
 
     let mut images = ctx
         .context()
@@ -221,7 +235,7 @@ pub async fn get_import_index(
     images.reverse();
 
     let template =
-        ImportTemplate { title: "Available Images".to_string(), images };
+        ImportTemplate { user, title: "Available Images".to_string(), images };
 
     let result = template.render().map_err(to_internal_error)?;
     htmx_response(response, "/import", result.into())
@@ -237,10 +251,11 @@ pub async fn post_import_index(
     path_params: Path<PathParams>,
     request_body: TypedBody<ImageImportParams>,
 ) -> Result<Response<Body>, HttpError> {
-    let response = Response::builder();
-    if !session::is_valid(&ctx) {
+    let response = security_headers::builder();
+    let Some(user) = session::get_user(&ctx)? else {
         return redirect_login(response, &ctx);
-    }
+    };
+    require(&user, Permission::ManageImages)?;
 
     let id = &path_params.into_inner().id;
 
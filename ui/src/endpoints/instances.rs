@@ -10,38 +10,85 @@
 
 use std::collections::BTreeMap;
 use std::str::FromStr;
+use std::time::Duration;
 
+use crate::clone;
 use crate::endpoints::{
-    filters, htmx_response, redirect_login, AsJson, Context, NotificationKind,
-    NotificationTemplate, PathParams,
+    filters, htmx_response, redirect_login, require, AsJson, Context,
+    NotificationKind, NotificationTemplate, PathParams,
 };
-use crate::session;
+use crate::instance_list::{
+    Direction, InstanceListParams, InstancePage, SortColumn, LIMITS,
+};
+use crate::live::Change;
+use crate::nic_form::{self, NicForm, NicRows};
+use crate::provision_form::{check, ProvisionBody};
+use crate::security_headers;
+use crate::session::{self, CurrentUser};
 
+use smartos_shared::auth::Permission;
+use smartos_shared::provision::{
+    capacity_errors, requirement_errors, Capacity, FieldError,
+};
+use smartos_shared::{disk, nic};
 use smartos_shared::{
-    http_server::to_bad_request, http_server::to_internal_error, image::Image,
-    image::Type as ImageType, instance::Brand, instance::Info,
-    instance::Instance, instance::InstancePayload, instance::InstanceView,
-    instance::PayloadContainer, nictag::NicTag, sysinfo::Sysinfo,
+    http_server::to_internal_error, image::Image, image::Type as ImageType,
+    instance::Brand, instance::CreatePayload, instance::Disk, instance::Info,
+    instance::Instance, instance::InstanceValidateResponse,
+    instance::InstanceView, nictag::NicTag, preset::Preset,
+    serde_helpers::deserialize_empty_as_none,
 };
 
 use askama::Template;
-use dropshot::{endpoint, HttpError, Path, Query, RequestContext, TypedBody};
+use dropshot::{endpoint, HttpError, Path, Query, RequestContext, UntypedBody};
 use http::StatusCode;
 use hyper::{Body, Response};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use serde_json::json;
-use slog::{error, info};
-use uuid::Builder as UuidBuilder;
+use serde_json::{json, Value};
+use slog::{error, info, Logger};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::time::timeout;
+use uuid::{Builder as UuidBuilder, Uuid};
 
 #[derive(Template)]
 #[template(path = "instance.j2")]
 pub struct InstanceTemplate {
+    user: CurrentUser,
     title: String,
     instance_enum: Instance,
     json: Option<String>,
     info: Option<Info>,
     image: Option<Image>,
+    /// Bytes used by each disk's zvol
+    disk_usage: BTreeMap<String, u64>,
+    disk_models: &'static [&'static str],
+    compressions: &'static [&'static str],
+    /// For adding a NIC
+    nictags: Vec<NicTag>,
+    nic_models: &'static [&'static str],
+}
+
+impl InstanceTemplate {
+    /// Whether `option` is a disk's or NIC's current `value`
+    fn selected(&self, value: &Option<String>, option: &str) -> bool {
+        value.as_deref() == Some(option)
+    }
+
+    /// A NIC's list property as the comma separated text its inputs take
+    fn joined(&self, values: &Option<Vec<String>>) -> String {
+        values.as_ref().map(|v| v.join(", ")).unwrap_or_default()
+    }
+
+    /// GiB the disk's zvol is using, if it's known
+    fn disk_used(&self, disk: &Disk) -> String {
+        disk.zfs_filesystem
+            .as_ref()
+            .and_then(|zvol| self.disk_usage.get(zvol))
+            .map_or(String::from("-"), |used| {
+                format!("{:.2}", *used as f64 / 1024.0 / 1024.0 / 1024.0)
+            })
+    }
 }
 
 #[endpoint {
@@ -53,10 +100,11 @@ pub async fn get_by_id(
     path_params: Path<PathParams>,
     query_params: Query<AsJson>,
 ) -> Result<Response<Body>, HttpError> {
-    let response = Response::builder();
-    if !session::is_valid(&ctx) {
+    let response = security_headers::builder();
+    let Some(user) = session::get_user(&ctx)? else {
         return redirect_login(response, &ctx);
-    }
+    };
+    require(&user, Permission::View)?;
     let id = path_params.into_inner().id;
 
     let instance_enum = ctx
@@ -92,6 +140,37 @@ pub async fn get_by_id(
         );
     }
 
+    let mut disk_usage = BTreeMap::new();
+    if instance_enum.is_hvm() && json_string.is_none() {
+        match ctx.context().executor.get_disk_usage(&instance_enum.uuid()).await
+        {
+            Ok(usage) => disk_usage = usage,
+            Err(error) => {
+                error!(
+                    ctx.log,
+                    "Failed getting disk usage for {}: {}",
+                    id,
+                    error.to_string()
+                );
+            }
+        }
+    }
+
+    // NIC tags are only needed for adding a NIC
+    let mut nictags = Vec::new();
+    if user.can(Permission::Modify) && json_string.is_none() {
+        match ctx.context().executor.get_nictags().await {
+            Ok(tags) => nictags = tags,
+            Err(error) => {
+                error!(
+                    ctx.log,
+                    "Failed getting NIC tags: {}",
+                    error.to_string()
+                );
+            }
+        }
+    }
+
     let image_uuid = instance_enum.image_uuid();
 
     let image = if image_uuid != UuidBuilder::nil().into_uuid() {
@@ -111,12 +190,19 @@ pub async fn get_by_id(
         None
     };
 
+    let brand = instance_enum.brand();
     let template = InstanceTemplate {
+        user,
         title,
         instance_enum,
         json: json_string,
         info,
         image,
+        disk_usage,
+        disk_models: disk::models(&brand),
+        compressions: &disk::COMPRESSIONS,
+        nictags,
+        nic_models: nic::models(&brand),
     };
     let result = template.render().map_err(to_internal_error)?;
 
@@ -131,10 +217,11 @@ pub async fn delete_by_id(
     ctx: RequestContext<Context>,
     path_params: Path<PathParams>,
 ) -> Result<Response<Body>, HttpError> {
-    let response = Response::builder();
-    if !session::is_valid(&ctx) {
+    let response = security_headers::builder();
+    let Some(user) = session::get_user(&ctx)? else {
         return redirect_login(response, &ctx);
-    }
+    };
+    require(&user, Permission::Delete)?;
 
     let id = path_params.into_inner().id;
     let instance = ctx
@@ -187,10 +274,11 @@ pub async fn start_by_id(
     ctx: RequestContext<Context>,
     path_params: Path<PathParams>,
 ) -> Result<Response<Body>, HttpError> {
-    let response = Response::builder();
-    if !session::is_valid(&ctx) {
+    let response = security_headers::builder();
+    let Some(user) = session::get_user(&ctx)? else {
         return redirect_login(response, &ctx);
-    }
+    };
+    require(&user, Permission::PowerControl)?;
 
     let id = path_params.into_inner().id;
     let template = if ctx.context().executor.start_instance(&id).await.is_ok() {
@@ -233,10 +321,11 @@ pub async fn stop_by_id(
     ctx: RequestContext<Context>,
     path_params: Path<PathParams>,
 ) -> Result<Response<Body>, HttpError> {
-    let response = Response::builder();
-    if !session::is_valid(&ctx) {
+    let response = security_headers::builder();
+    let Some(user) = session::get_user(&ctx)? else {
         return redirect_login(response, &ctx);
-    }
+    };
+    require(&user, Permission::PowerControl)?;
 
     let id = path_params.into_inner().id;
     let template = if ctx.context().executor.stop_instance(&id).await.is_ok() {
@@ -271,10 +360,32 @@ pub async fn stop_by_id(
         .map_err(to_internal_error)
 }
 
+/// The image an instance was created from as `name@version`, or its UUID if
+/// the image isn't installed
+fn image_name(images: &[Image], instance: &InstanceView) -> String {
+    if let Some(image) =
+        images.iter().find(|&i| i.manifest.uuid == instance.image_uuid)
+    {
+        format!("{}@{}", image.manifest.name, image.manifest.version)
+    } else {
+        instance.image_uuid.to_string()
+    }
+}
+
 #[derive(Template)]
 #[template(path = "instances.j2")]
 pub struct InstancesTemplate<'a> {
+    user: CurrentUser,
     image_count: usize,
+    /// Every instance on the host, whether or not it matches the filters
+    instance_count: usize,
+    /// Whether rows have checkboxes for bulk actions
+    selectable: bool,
+    brands: Vec<Brand>,
+    states: Vec<String>,
+    limits: [usize; 4],
+    params: InstanceListParams,
+    page: InstancePage,
     provisioned_ram: u64,
     total_ram: u64,
     provisioned_quota: u64,
@@ -282,13 +393,6 @@ pub struct InstancesTemplate<'a> {
     provisioned_cpu: f32,
     total_cpu: u64,
     title: &'a str,
-    instances: Vec<(InstanceView, String)>,
-}
-
-#[derive(Deserialize, Debug, JsonSchema)]
-pub struct InstanceListParams {
-    #[serde(default)]
-    pub reload: Option<bool>,
 }
 
 #[endpoint {
@@ -299,13 +403,12 @@ pub async fn get_index(
     ctx: RequestContext<Context>,
     query_params: Query<InstanceListParams>,
 ) -> Result<Response<Body>, HttpError> {
-    let response = Response::builder();
-    if !session::is_valid(&ctx) {
+    let response = security_headers::builder();
+    let Some(user) = session::get_user(&ctx)? else {
         return redirect_login(response, &ctx);
-    }
-    let mut instances: Vec<(InstanceView, String)> = Vec::new();
-
-    let Sysinfo { cpu_count, mib_of_memory, zpool_size_in_gib, .. } = ctx
+    };
+    require(&user, Permission::View)?;
+    let sysinfo = ctx
         .context()
         .executor
         .get_sysinfo()
@@ -320,99 +423,188 @@ pub async fn get_index(
         .map_err(to_internal_error)?;
     let image_count = images.len();
 
-    let mut instance_views = ctx
-        .context()
-        .vminfod
-        .get_instances()
-        .await
-        .map_err(to_internal_error)?;
+    let mut instance_views = match ctx.context().instances.instances() {
+        Some(instances) => instances,
+        None => ctx
+            .context()
+            .vminfod
+            .get_instances(&ctx.log)
+            .await
+            .map_err(to_internal_error)?,
+    };
 
-    let provisioned_ram = instance_views.iter().fold(0, |acc, i| {
-        if i.ram == 0 {
-            mib_of_memory + acc
-        } else {
-            i.ram + acc
-        }
-    });
-    let provisioned_quota = instance_views.iter().fold(0, |acc, i| {
-        if i.disk_usage == 0 {
-            (zpool_size_in_gib * 1024) + acc
-        } else {
-            i.disk_usage + acc
-        }
-    });
+    let capacity = Capacity::new(&sysinfo, &instance_views);
 
-    let provisioned_cpu = instance_views.iter().fold(0.0, |acc, i| {
-        if i.cpu == 0.0 {
-            acc + cpu_count as f32
-        } else {
-            i.cpu + acc
+    let mut brands: Vec<Brand> = Vec::new();
+    let mut states: Vec<String> = Vec::new();
+    for instance in &instance_views {
+        if !brands.contains(&instance.brand) {
+            brands.push(instance.brand.clone());
+        }
+        if !states.contains(&instance.state) {
+            states.push(instance.state.clone());
         }
-    });
-
-    for instance in instance_views.drain(..) {
-        let image_name = if let Some(image) =
-            images.iter().find(|&i| i.manifest.uuid == instance.image_uuid)
-        {
-            format!("{}@{}", image.manifest.name, image.manifest.version)
-        } else {
-            instance.image_uuid.to_string().clone()
-        };
-        instances.push((instance, image_name));
     }
+    brands.sort_by_key(|brand| brand.to_string());
+    states.sort();
+
+    let instance_count = instance_views.len();
+    let params = query_params.into_inner();
+    let page = params.apply(
+        instance_views
+            .drain(..)
+            .map(|instance| {
+                let image_name = image_name(&images, &instance);
+                (instance, image_name)
+            })
+            .collect(),
+    );
 
     let template = InstancesTemplate {
+        selectable: can_select(&user),
+        user,
         image_count,
-        provisioned_ram,
-        total_ram: mib_of_memory,
-        provisioned_quota,
-        total_quota: zpool_size_in_gib,
-        provisioned_cpu,
-        total_cpu: cpu_count,
+        instance_count,
+        brands,
+        states,
+        limits: LIMITS,
+        page,
+        provisioned_ram: capacity.provisioned_ram,
+        total_ram: capacity.total_ram,
+        provisioned_quota: capacity.provisioned_disk,
+        total_quota: sysinfo.zpool_size_in_gib,
+        provisioned_cpu: capacity.provisioned_cpu,
+        total_cpu: capacity.total_cpu,
         title: "Instances",
-        instances,
+        params: params.clone(),
     };
     let result = template.render().map_err(to_internal_error)?;
 
-    if query_params.into_inner().reload.is_some() {
-        let event_data = json!({
-            "filterTable": {
-                "selector": "#filter-instances"
+    htmx_response(response, &params.location(), result.into())
+}
+
+/// A row of the instance list, sent to browsers as the instance changes
+#[derive(Template)]
+#[template(path = "include/instance_row.j2")]
+pub struct InstanceRowTemplate {
+    instance: InstanceView,
+    image: String,
+    selectable: bool,
+}
+
+/// Whether the user can take any bulk action, and so select instances
+fn can_select(user: &CurrentUser) -> bool {
+    user.can(Permission::PowerControl) || user.can(Permission::Delete)
+}
+
+/// How often an idle event stream sends a comment, so that it isn't closed
+/// by the browser or anything in between and revoked sessions are noticed
+const KEEPALIVE: Duration = Duration::from_secs(25);
+
+/// An instance change as a server-sent event
+async fn change_event(
+    ctx: &RequestContext<Context>,
+    change: Change,
+    selectable: bool,
+) -> Result<String, HttpError> {
+    let (event, data) = match change {
+        Change::Updated(instance) => {
+            let images = ctx
+                .context()
+                .executor
+                .get_images(&ctx.log)
+                .await
+                .map_err(to_internal_error)?;
+            let uuid = instance.uuid;
+            let state = instance.state.clone();
+            let image = image_name(&images, &instance);
+            let row = InstanceRowTemplate { instance, image, selectable }
+                .render()
+                .map_err(to_internal_error)?;
+            ("instance", json!({ "uuid": uuid, "state": state, "row": row }))
+        }
+        Change::Deleted(uuid) => ("delete", json!({ "uuid": uuid })),
+    };
+    Ok(format!("event: {}\ndata: {}\n\n", event, data))
+}
+
+/// Server-sent events for instances as they are created, change or are
+/// deleted, from the model kept by [crate::live::follow]. An `instance`
+/// event has the instance's UUID, state and row of the instance list, a
+/// `delete` event only its UUID, and `reload` means changes were missed.
+#[endpoint {
+method = GET,
+path = "/events/instances",
+}]
+pub async fn get_events(
+    ctx: RequestContext<Context>,
+) -> Result<Response<Body>, HttpError> {
+    let response = security_headers::builder();
+    let user = session::get_user(&ctx)?.ok_or_else(|| {
+        HttpError::for_client_error(
+            None,
+            StatusCode::UNAUTHORIZED,
+            String::from("Your session has expired, please log in again"),
+        )
+    })?;
+    require(&user, Permission::View)?;
+    let selectable = can_select(&user);
+
+    let mut changes = ctx.context().instances.subscribe();
+    let (mut sender, body) = Body::channel();
+    tokio::spawn(async move {
+        loop {
+            let event = match timeout(KEEPALIVE, changes.recv()).await {
+                Ok(Ok(change)) => {
+                    match change_event(&ctx, change, selectable).await {
+                        Ok(event) => event,
+                        Err(e) => {
+                            error!(
+                                ctx.log,
+                                "Failed sending instance change: {}",
+                                e.internal_message
+                            );
+                            String::from("event: reload\ndata: {}\n\n")
+                        }
+                    }
+                }
+                Ok(Err(RecvError::Lagged(_))) => {
+                    String::from("event: reload\ndata: {}\n\n")
+                }
+                Ok(Err(RecvError::Closed)) => break,
+                Err(_) => String::from(": keepalive\n\n"),
+            };
+            if !session::is_active(&ctx)
+                || sender.send_data(event.into()).await.is_err()
+            {
+                break;
             }
-        })
-        .to_string();
-        response
-            .status(StatusCode::OK)
-            .header("HX-Push-Url", "/instances")
-            .header("HX-Trigger-After-Swap", event_data)
-            .header("Content-Type", "text/html")
-            .body(result.into())
-            .map_err(to_internal_error)
-    } else {
-        htmx_response(response, "/instances", result.into())
-    }
+        }
+    });
+
+    response
+        .status(StatusCode::OK)
+        .header("Content-Type", "text/event-stream")
+        .body(body)
+        .map_err(to_internal_error)
 }
 
 #[derive(Template)]
 #[template(path = "provision.j2")]
 pub struct InstanceCreateTemplate {
+    user: CurrentUser,
     title: String,
     images: BTreeMap<String, Vec<Image>>,
     selected_image: Option<Image>,
     nictags: Vec<NicTag>,
     alias: String,
+    /// `key=value`, separated with commas
+    tags: String,
     brand: Brand,
     image_uuid: String,
     ram: String,
     quota: String,
-    nic_tag: String,
-    ipv4_setup: String,
-    ipv4_ip: String,
-    ipv4_gateway: String,
-    ipv4_prefix: String,
-    ipv6_setup: String,
-    ipv6_ip: String,
-    ipv6_prefix: String,
+    nics: NicRows,
     resolvers: String,
     vcpus: String,
     primary_disk_size: u64,
@@ -425,58 +617,146 @@ pub struct InstanceCreateTemplate {
     cloudinit_data: String,
     dns_domain: String,
     builder_brand: bool,
+    cloned_from: Option<ClonedFrom>,
+    /// Initial content of the additional properties editor
+    additional: String,
+    presets: Vec<Preset>,
+    /// The preset the form was just filled in from
+    applied_preset: Option<String>,
 }
 
-#[derive(Deserialize, Serialize, Debug, JsonSchema)]
-pub struct ProvisionQuery {
-    #[serde(default)]
+impl InstanceCreateTemplate {
+    /// Values to keep in hidden inputs while no image is chosen, when the
+    /// form has no inputs for them, so that choosing an image doesn't lose
+    /// those filled in from a clone or preset
+    fn carried(&self) -> Vec<(String, String)> {
+        let primary_disk_size = match self.primary_disk_size {
+            0 => String::new(),
+            size => size.to_string(),
+        };
+        [
+            ("ram", self.ram.clone()),
+            ("quota", self.quota.clone()),
+            ("vcpus", self.vcpus.clone()),
+            ("cpu_cap", self.cpu_cap.clone()),
+            ("bootrom", self.bootrom.clone()),
+            ("primary_disk_size", primary_disk_size),
+            ("delegate_dataset", self.delegate_dataset.clone()),
+            ("root_authorized_keys", self.root_authorized_keys.clone()),
+            ("user_script", self.user_script.clone()),
+            ("cloudinit_data", self.cloudinit_data.clone()),
+            ("resolvers", self.resolvers.clone()),
+            ("dns_domain", self.dns_domain.clone()),
+        ]
+        .into_iter()
+        .filter(|(_, value)| !value.is_empty())
+        .map(|(name, value)| (String::from(name), value))
+        .chain(self.nics.carried())
+        .collect()
+    }
+
+    /// Whether any NIC has a tag, so that the instance will have a network
+    fn has_network(&self) -> bool {
+        self.nics.nics.iter().any(|nic| !nic.tag.is_empty())
+    }
+
+    /// NIC models to choose from, none for OS and LX instances
+    fn nic_models(&self) -> &'static [&'static str] {
+        nic::models(&self.brand)
+    }
+
+    fn is_model(&self, nic: &NicForm, model: &str) -> bool {
+        nic.model == model
+    }
+}
+
+/// The instance a provisioning form was filled in from
+pub struct ClonedFrom {
+    uuid: Uuid,
     alias: String,
+    keep_ips: bool,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, JsonSchema)]
+pub struct ProvisionQuery {
     #[serde(default)]
-    brand: String,
+    pub alias: String,
+    /// `key=value`, separated with commas
     #[serde(default)]
-    image_uuid: String,
+    pub tags: String,
     #[serde(default)]
-    ram: String,
+    pub brand: String,
     #[serde(default)]
-    quota: String,
+    pub image_uuid: String,
     #[serde(default)]
-    nic_tag: String,
+    pub ram: String,
     #[serde(default)]
-    ipv4_setup: String,
+    pub quota: String,
+    /// Read from the query string by [crate::nic_form::from_query]
+    #[serde(skip)]
+    pub nics: NicRows,
     #[serde(default)]
-    ipv4_ip: String,
+    pub resolvers: String,
     #[serde(default)]
-    ipv4_prefix: String,
+    pub vcpus: String,
     #[serde(default)]
-    ipv4_gateway: String,
+    pub primary_disk_size: u64,
     #[serde(default)]
-    ipv6_setup: String,
+    pub root_authorized_keys: String,
     #[serde(default)]
-    ipv6_ip: String,
+    pub delegate_dataset: String,
     #[serde(default)]
-    ipv6_prefix: String,
+    pub root_pw: String,
     #[serde(default)]
-    resolvers: String,
+    pub bootrom: String,
+    /// LX images' kernel version, from a hidden input
     #[serde(default)]
-    vcpus: String,
+    pub kernel_version: String,
     #[serde(default)]
-    primary_disk_size: u64,
+    pub cpu_cap: String,
     #[serde(default)]
-    root_authorized_keys: String,
+    pub user_script: String,
     #[serde(default)]
-    delegate_dataset: String,
+    pub cloudinit_data: String,
     #[serde(default)]
-    root_pw: String,
+    pub dns_domain: String,
+    /// Fill the form in from this instance, see [crate::clone]
+    #[serde(default, deserialize_with = "deserialize_empty_as_none")]
+    pub clone: Option<Uuid>,
+    /// Copy the cloned instance's IP addresses too
     #[serde(default)]
-    bootrom: String,
-    #[serde(default)]
-    cpu_cap: String,
-    #[serde(default)]
-    user_script: String,
-    #[serde(default)]
-    cloudinit_data: String,
-    #[serde(default)]
-    dns_domain: String,
+    pub clone_ips: bool,
+    /// Fill the form in from the preset with this name
+    #[serde(default, deserialize_with = "deserialize_empty_as_none")]
+    pub preset: Option<String>,
+}
+
+impl ProvisionQuery {
+    /// Set the values `preset` has, leaving the rest of the form alone
+    pub fn apply_preset(&mut self, preset: &Preset) {
+        self.ram = preset.ram.to_string();
+        if let Some(quota) = preset.quota {
+            self.quota = quota.to_string();
+        }
+        if let Some(vcpus) = preset.vcpus {
+            self.vcpus = vcpus.to_string();
+        }
+        if let Some(cpu_cap) = preset.cpu_cap {
+            self.cpu_cap = cpu_cap.to_string();
+        }
+        if let Some(nic_tag) = &preset.nic_tag {
+            match self.nics.nics.first_mut() {
+                Some(nic) => nic.tag = nic_tag.clone(),
+                None => self.nics.nics.push(NicForm {
+                    tag: nic_tag.clone(),
+                    ..Default::default()
+                }),
+            }
+        }
+        if !preset.resolvers.is_empty() {
+            self.resolvers = preset.resolvers.join(",");
+        }
+    }
 }
 
 #[endpoint {
@@ -487,25 +767,75 @@ pub async fn get_provision(
     ctx: RequestContext<Context>,
     query: Query<ProvisionQuery>,
 ) -> Result<Response<Body>, HttpError> {
-    let response = Response::builder();
-    if !session::is_valid(&ctx) {
+    let response = security_headers::builder();
+    let Some(user) = session::get_user(&ctx)? else {
         return redirect_login(response, &ctx);
-    }
+    };
+    require(&user, Permission::Provision)?;
     let mut selected_image = None;
+    let mut query = query.into_inner();
+    query.nics = nic_form::from_query(ctx.request.uri().query().unwrap_or(""));
+    let mut location = String::from("/provision");
+    let mut cloned_from = None;
+    let mut additional = String::new();
+
+    if let Some(id) = query.clone {
+        let json = ctx
+            .context()
+            .vminfod
+            .get_instance_json(&id)
+            .await
+            .map_err(to_internal_error)?;
+        let vm: Value =
+            serde_json::from_str(&json).map_err(to_internal_error)?;
+        let keep_ips = query.clone_ips;
+        let cloned = clone::from_instance(&vm, keep_ips);
+        if !cloned.additional.is_empty() {
+            additional = serde_json::to_string_pretty(&cloned.additional)
+                .map_err(to_internal_error)?;
+        }
+        location = if keep_ips {
+            format!("/provision?clone={}&clone_ips=true", id)
+        } else {
+            format!("/provision?clone={}", id)
+        };
+        cloned_from = Some(ClonedFrom {
+            uuid: id,
+            alias: vm
+                .get("alias")
+                .and_then(Value::as_str)
+                .map_or_else(|| id.to_string(), String::from),
+            keep_ips,
+        });
+        query = cloned.query;
+    }
+
+    // Provisioning doesn't need presets, so failing to read them isn't fatal
+    let presets =
+        ctx.context().executor.get_presets().await.unwrap_or_else(|e| {
+            error!(ctx.log, "Failed getting presets: {}", e);
+            Vec::new()
+        });
+    let mut applied_preset = None;
+    if let Some(name) = query.preset.take() {
+        let Some(preset) = presets.iter().find(|p| p.name == name) else {
+            return Err(HttpError::for_not_found(
+                None,
+                format!("No such preset {}", name),
+            ));
+        };
+        query.apply_preset(preset);
+        applied_preset = Some(name);
+    }
+
     let ProvisionQuery {
         alias,
+        tags,
         brand,
         image_uuid,
         ram,
         quota,
-        nic_tag,
-        ipv4_setup,
-        ipv4_ip,
-        ipv4_prefix,
-        ipv4_gateway,
-        ipv6_setup,
-        ipv6_ip,
-        ipv6_prefix,
+        nics,
         resolvers,
         vcpus,
         primary_disk_size,
@@ -517,7 +847,8 @@ pub async fn get_provision(
         user_script,
         cloudinit_data,
         dns_domain,
-    } = query.into_inner();
+        ..
+    } = query;
 
     let mut selected_brand = Brand::default();
 
@@ -574,23 +905,18 @@ pub async fn get_provision(
     }
 
     let template = InstanceCreateTemplate {
+        user,
         title,
         images: image_list,
         selected_image,
         nictags,
         alias,
+        tags,
         brand: selected_brand,
         image_uuid,
         ram,
         quota,
-        nic_tag,
-        ipv4_setup,
-        ipv4_ip,
-        ipv4_gateway,
-        ipv4_prefix,
-        ipv6_setup,
-        ipv6_ip,
-        ipv6_prefix,
+        nics,
         resolvers,
         vcpus,
         primary_disk_size,
@@ -603,9 +929,13 @@ pub async fn get_provision(
         cloudinit_data,
         dns_domain,
         builder_brand,
+        cloned_from,
+        additional,
+        presets,
+        applied_preset,
     };
     let result = template.render().map_err(to_internal_error)?;
-    htmx_response(response, "/provision", result.into())
+    htmx_response(response, &location, result.into())
 }
 
 struct Button {
@@ -623,6 +953,89 @@ pub struct ProvisionTemplate {
     buttons: Option<Vec<Button>>,
 }
 
+/// Why a payload can't be provisioned on this host
+#[derive(Default)]
+pub struct HostChecks {
+    /// The image's requirements that aren't met
+    pub requirements: Vec<FieldError>,
+    /// What the host doesn't have room for, unless overcommitting
+    pub capacity: Vec<FieldError>,
+}
+
+impl HostChecks {
+    /// Check `payload` against its image and, unless `overcommit`, what is
+    /// left of the host
+    pub async fn new(
+        ctx: &Context,
+        log: &Logger,
+        payload: &CreatePayload,
+        overcommit: bool,
+    ) -> Result<Self, HttpError> {
+        let sysinfo =
+            ctx.executor.get_sysinfo().await.map_err(to_internal_error)?;
+        let images =
+            ctx.executor.get_images(log).await.map_err(to_internal_error)?;
+        let mut checks = Self::default();
+
+        let manifest = payload.image().and_then(|uuid| {
+            images
+                .into_iter()
+                .map(|image| image.manifest)
+                .find(|manifest| manifest.uuid == uuid)
+        });
+        match (&manifest, payload.image()) {
+            (Some(manifest), _) => {
+                checks.requirements =
+                    requirement_errors(payload, manifest, &sysinfo.live_image);
+            }
+            (None, Some(uuid)) => checks.requirements.push(FieldError::new(
+                "image_uuid",
+                format!("Image {} is not imported", uuid),
+            )),
+            (None, None) => {}
+        }
+
+        if !overcommit {
+            let instances = match ctx.instances.instances() {
+                Some(instances) => instances,
+                None => ctx
+                    .vminfod
+                    .get_instances(log)
+                    .await
+                    .map_err(to_internal_error)?,
+            };
+            let capacity = Capacity::new(&sysinfo, &instances);
+            checks.capacity =
+                capacity_errors(payload, manifest.as_ref(), &capacity);
+        }
+        Ok(checks)
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.requirements.is_empty() && self.capacity.is_empty()
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &FieldError> {
+        self.requirements.iter().chain(self.capacity.iter())
+    }
+
+    /// All of the problems in one sentence each, followed by `hint` if
+    /// overcommitting would help
+    #[must_use]
+    pub fn message(&self, hint: &str) -> String {
+        let mut message: Vec<&str> =
+            self.errors().map(|e| e.message.as_str()).collect();
+        if !self.capacity.is_empty() {
+            message.push(hint);
+        }
+        message.join(". ")
+    }
+}
+
+/// Shown when the host doesn't have room for an instance
+const OVERCOMMIT_HINT: &str = "Choose \"Overcommit\" to provision anyway";
+
 #[endpoint {
 method = POST,
 path = "/provision",
@@ -630,22 +1043,51 @@ content_type = "application/x-www-form-urlencoded"
 }]
 pub async fn post_provision(
     ctx: RequestContext<Context>,
-    request_body: TypedBody<InstancePayload>,
+    request_body: UntypedBody,
 ) -> Result<Response<Body>, HttpError> {
-    let response = Response::builder();
-    if !session::is_valid(&ctx) {
+    let response = security_headers::builder();
+    let Some(user) = session::get_user(&ctx)? else {
         return redirect_login(response, &ctx);
-    }
-
-    let req = request_body.into_inner();
+    };
+    require(&user, Permission::Provision)?;
 
-    let PayloadContainer { uuid } =
-        serde_json::from_str(&req.payload).map_err(to_bad_request)?;
+    let checked = match ProvisionBody::parse(request_body.as_str()?)
+        .and_then(|body| Ok((check(body.payload()?)?, body.overcommit)))
+    {
+        Ok((payload, overcommit)) => {
+            let checks =
+                HostChecks::new(ctx.context(), &ctx.log, &payload, overcommit)
+                    .await?;
+            if checks.is_empty() {
+                Ok(payload)
+            } else {
+                Err(checks.message(OVERCOMMIT_HINT))
+            }
+        }
+        Err(message) => Err(message),
+    };
+    let mut payload = match checked {
+        Ok(payload) => payload,
+        Err(message) => {
+            let template = ProvisionTemplate {
+                kind: NotificationKind::Error,
+                subject: String::from("Provision failed"),
+                message,
+                buttons: None,
+            };
+            let result = template.render().map_err(to_internal_error)?;
+            return response
+                .status(StatusCode::OK)
+                .body(result.into())
+                .map_err(to_internal_error);
+        }
+    };
+    let uuid = payload.ensure_uuid();
 
     let result = ctx
         .context()
         .executor
-        .provision(req)
+        .provision(&payload)
         .await
         .map_err(to_internal_error)?;
 
@@ -706,6 +1148,10 @@ pub async fn post_provision(
 pub struct ValidateTemplate {
     success: bool,
     message: String,
+    /// Problems with the form's inputs
+    errors: Vec<FieldError>,
+    /// The payload the form built, for the final properties editor
+    payload: Option<String>,
 }
 
 #[endpoint {
@@ -715,20 +1161,47 @@ content_type = "application/x-www-form-urlencoded"
 }]
 pub async fn post_provision_validate(
     ctx: RequestContext<Context>,
-    request_body: TypedBody<InstancePayload>,
+    request_body: UntypedBody,
 ) -> Result<Response<Body>, HttpError> {
-    let response = Response::builder();
-    if !session::is_valid(&ctx) {
+    let response = security_headers::builder();
+    let Some(user) = session::get_user(&ctx)? else {
         return redirect_login(response, &ctx);
-    }
+    };
+    require(&user, Permission::Provision)?;
 
-    let req = request_body.into_inner();
-    let validation = ctx
-        .context()
-        .executor
-        .validate_create(req)
-        .await
-        .map_err(to_internal_error)?;
+    let built = ProvisionBody::parse(request_body.as_str()?)
+        .and_then(|body| Ok((body.payload()?, body.overcommit)));
+    // Shown in the final properties editor even if it can't be used
+    let payload = built
+        .as_ref()
+        .ok()
+        .and_then(|(payload, _)| serde_json::to_string_pretty(payload).ok());
+    let mut errors = Vec::new();
+    let checked = built
+        .and_then(|(payload, overcommit)| Ok((check(payload)?, overcommit)));
+    let validation = match checked {
+        Ok((payload, overcommit)) => {
+            let checks =
+                HostChecks::new(ctx.context(), &ctx.log, &payload, overcommit)
+                    .await?;
+            if checks.is_empty() {
+                ctx.context()
+                    .executor
+                    .validate_create(&payload)
+                    .await
+                    .map_err(to_internal_error)?
+            } else {
+                let message = if checks.capacity.is_empty() {
+                    String::new()
+                } else {
+                    String::from(OVERCOMMIT_HINT)
+                };
+                errors = checks.errors().cloned().collect();
+                InstanceValidateResponse { message, success: false }
+            }
+        }
+        Err(message) => InstanceValidateResponse { message, success: false },
+    };
 
     let trigger = json!({
         "validationResult": {
@@ -740,6 +1213,8 @@ pub async fn post_provision_validate(
     let template = ValidateTemplate {
         success: validation.success,
         message: validation.message,
+        errors,
+        payload,
     };
     let template_result = template.render().map_err(to_internal_error)?;
     response
@@ -0,0 +1,173 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+use crate::endpoints::{
+    htmx_response, redirect_login, require, Context, NotificationKind,
+    NotificationTemplate,
+};
+use crate::lockout::{format_duration, Subject};
+use crate::security_headers;
+use crate::session::{self, CurrentUser};
+
+use std::time::Instant;
+
+use smartos_shared::auth::Permission;
+use smartos_shared::http_server::{to_bad_request, to_internal_error};
+
+use askama::Template;
+use dropshot::{endpoint, HttpError, RequestContext, TypedBody};
+use hyper::{Body, Response, StatusCode};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use slog::info;
+
+/// A [crate::lockout::Status] as shown on the lockouts page
+pub struct LockoutView {
+    subject: String,
+    kind: &'static str,
+    value: String,
+    failures: u32,
+    /// Time left on the lockout, [None] if not locked out
+    remaining: Option<String>,
+}
+
+#[derive(Template)]
+#[template(path = "lockouts.j2")]
+pub struct LockoutsTemplate<'a> {
+    title: &'a str,
+    user: CurrentUser,
+    lockouts: Vec<LockoutView>,
+    max_failures: u32,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct ClearRequestBody {
+    /// Subject to clear (e.g. `user:root`), clears everything if empty
+    #[serde(default)]
+    pub subject: String,
+}
+
+/// Lists source IPs and usernames with recent failed logins
+#[endpoint {
+method = GET,
+path = "/lockouts"
+}]
+pub async fn get_index(
+    ctx: RequestContext<Context>,
+) -> Result<Response<Body>, HttpError> {
+    let response = security_headers::builder();
+    let Some(user) = session::get_user(&ctx)? else {
+        return redirect_login(response, &ctx);
+    };
+    require(&user, Permission::ManageLockouts)?;
+
+    let lockouts = ctx
+        .context()
+        .lockouts
+        .lock()
+        .map_err(|e| HttpError::for_internal_error(format!("{}", e)))?
+        .list(Instant::now())
+        .into_iter()
+        .map(|status| {
+            let (kind, value) = match &status.subject {
+                Subject::Ip(ip) => ("IP Address", ip.to_string()),
+                Subject::User(login) => ("User", login.clone()),
+            };
+            LockoutView {
+                subject: status.subject.to_string(),
+                kind,
+                value,
+                failures: status.failures,
+                remaining: status.remaining.map(format_duration),
+            }
+        })
+        .collect();
+
+    let template = LockoutsTemplate {
+        title: "Lockouts",
+        user,
+        lockouts,
+        max_failures: ctx.context().config.login_max_failures,
+    };
+    let result = template.render().map_err(to_internal_error)?;
+    htmx_response(response, "/lockouts", result.into())
+}
+
+/// Forget the failed logins for a subject, or for every subject
+#[endpoint {
+method = POST,
+path = "/lockouts/clear",
+content_type = "application/x-www-form-urlencoded"
+}]
+pub async fn post_clear(
+    ctx: RequestContext<Context>,
+    request_body: TypedBody<ClearRequestBody>,
+) -> Result<Response<Body>, HttpError> {
+    let response = security_headers::builder();
+    let Some(user) = session::get_user(&ctx)? else {
+        return redirect_login(response, &ctx);
+    };
+    require(&user, Permission::ManageLockouts)?;
+
+    let ClearRequestBody { subject } = request_body.into_inner();
+    let subject = if subject.is_empty() {
+        None
+    } else {
+        Some(subject.parse::<Subject>().map_err(to_bad_request)?)
+    };
+
+    let mut lockouts = ctx
+        .context()
+        .lockouts
+        .lock()
+        .map_err(|e| HttpError::for_internal_error(format!("{}", e)))?;
+    let (kind, message) = match &subject {
+        Some(subject) if lockouts.clear(subject) => {
+            info!(ctx.log, "{} cleared lockout for {}", user.login, subject);
+            (
+                NotificationKind::Ok,
+                format!("Failed logins for {} have been cleared", subject),
+            )
+        }
+        Some(subject) => (
+            NotificationKind::Error,
+            format!("No failed logins recorded for {}", subject),
+        ),
+        None => {
+            lockouts.clear_all();
+            info!(ctx.log, "{} cleared all lockouts", user.login);
+            (
+                NotificationKind::Ok,
+                String::from("All failed logins have been cleared"),
+            )
+        }
+    };
+    drop(lockouts);
+
+    let template = NotificationTemplate {
+        id: ctx.request_id,
+        entity_id: subject.map(|s| s.to_string()).unwrap_or_default(),
+        subject: if kind == NotificationKind::Ok {
+            String::from("Lockout cleared")
+        } else {
+            String::from("Lockout not cleared")
+        },
+        kind,
+        message,
+        timeout: Some(String::from("8s")),
+        redirect: Some(String::from("/lockouts")),
+        created_at: String::from("/lockouts"),
+    };
+    let result = template.render().map_err(to_internal_error)?;
+    response
+        .status(StatusCode::OK)
+        .body(result.into())
+        .map_err(to_internal_error)
+}
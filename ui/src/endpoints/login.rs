@@ -8,8 +8,21 @@
  * Copyright 2024 MNX Cloud, Inc.
  */
 
-use crate::{endpoints::Context, session};
+use crate::endpoints::passkeys::{self, json_response, RelyingParty};
+use crate::endpoints::totp::Enrollment;
+use crate::lockout::{format_duration, Subject};
+use crate::session::Ceremony;
+use crate::{
+    clients::PasswordCheck, endpoints::Context, endpoints::PingResponse,
+    session,
+};
+
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
 
+use smartos_shared::auth::{
+    Role, WebauthnAssertRequest, WebauthnAssertResponse,
+};
 use smartos_shared::http_server::to_internal_error;
 
 use askama::Template;
@@ -20,16 +33,59 @@ use dropshot::{
 use hyper::{Body, Response, StatusCode};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use serde_json::json;
+use slog::warn;
 use tokio::try_join;
 
 #[derive(Template)]
 #[template(path = "login.j2")]
 struct LoginTemplate<'a> {
     message: Option<&'a str>,
+    /// Time left on a lockout, shown below the message
+    retry_after: Option<String>,
     executor: bool,
     vminfod: bool,
 }
 
+/// Second step of a login, for users with TOTP
+#[derive(Template)]
+#[template(path = "login_totp.j2")]
+struct LoginTotpTemplate<'a> {
+    message: Option<&'a str>,
+    /// Identifies the [session::PendingLogin]
+    token: String,
+    /// Set when the user has to enroll before logging in
+    enrollment: Option<Enrollment>,
+    /// Shown once enrollment during login has been confirmed
+    recovery_codes: Vec<String>,
+}
+
+#[derive(Deserialize, Debug, JsonSchema)]
+pub struct TotpRequestBody {
+    pub token: String,
+    pub code: String,
+}
+
+#[derive(Deserialize, Debug, JsonSchema)]
+pub struct PasskeyOptionsBody {
+    /// User name from the login form, if one was entered
+    #[serde(default)]
+    pub user: Option<String>,
+}
+
+/// The response to `navigator.credentials.get()`, binary fields are
+/// base64url
+#[derive(Deserialize, Debug, JsonSchema)]
+pub struct PasskeyLoginBody {
+    pub challenge: String,
+    pub credential_id: String,
+    pub client_data_json: String,
+    pub authenticator_data: String,
+    pub signature: String,
+    #[serde(default)]
+    pub user_handle: Option<String>,
+}
+
 #[derive(Deserialize, Serialize, Debug, JsonSchema)]
 pub struct LoginRequestBody {
     pub user: String,
@@ -57,26 +113,387 @@ pub async fn post_index(
     body_param: TypedBody<LoginRequestBody>,
 ) -> Result<Response<Body>, HttpError> {
     let LoginRequestBody { user, password } = body_param.into_inner();
-    let authed = ctx
+    let ip = ctx.request.remote_addr().ip();
+    let subjects = [Subject::Ip(ip), Subject::User(user.clone())];
+
+    if let Some(remaining) = locked_out(ctx.context(), &subjects) {
+        warn!(ctx.log, "Refusing login for {} from {}: locked out", user, ip);
+        ctx.context().metrics.login_failures.fetch_add(1, Ordering::Relaxed);
+        return render_failure(
+            "Too many failed login attempts",
+            Some(remaining),
+        );
+    }
+
+    let check = ctx
         .context()
-        .validate_password(password)
+        .validate_password(user.clone(), password)
         .await
         .map_err(to_internal_error)?;
-    if user == ctx.context().config.login_user && authed {
-        try_join!(
-            ctx.context().vminfod.get_instances(),
-            ctx.context().executor.get_images(&ctx.log),
+    let (message, lockout) = match check {
+        PasswordCheck::Valid { role, totp_enabled } => {
+            if !totp_enabled && !ctx.context().config.totp_required {
+                return complete(&ctx, user, role).await;
+            }
+            // Users that must use TOTP but haven't enrolled yet do so now,
+            // before they get a session
+            let enroll_secret = if totp_enabled {
+                None
+            } else {
+                let enrolled = ctx
+                    .context()
+                    .executor
+                    .totp_enroll(user.clone())
+                    .await
+                    .map_err(to_internal_error)?;
+                Some(enrolled.secret)
+            };
+            let token = session::begin_pending(
+                &ctx,
+                user.clone(),
+                role,
+                enroll_secret.clone(),
+            )?;
+            return render_totp(&ctx, &user, token, enroll_secret, None);
+        }
+        PasswordCheck::Invalid => {
+            warn!(ctx.log, "Failed login for {} from {}", user, ip);
+            ("Invalid username or password", record_failure(&ctx, &subjects))
+        }
+        PasswordCheck::RateLimited => {
+            ("Too many failed login attempts, please try again later", None)
+        }
+    };
+    ctx.context().metrics.login_failures.fetch_add(1, Ordering::Relaxed);
+    render_failure(message, lockout)
+}
+
+/// Checks the TOTP code (or recovery code) for a login that has passed the
+/// password check. For users enrolling during login this confirms the
+/// enrollment and shows their recovery codes.
+#[endpoint {
+method = POST,
+path = "/login/totp",
+content_type = "application/x-www-form-urlencoded"
+}]
+pub async fn post_totp(
+    ctx: RequestContext<Context>,
+    body_param: TypedBody<TotpRequestBody>,
+) -> Result<Response<Body>, HttpError> {
+    let TotpRequestBody { token, code } = body_param.into_inner();
+    let ip = ctx.request.remote_addr().ip();
+
+    let Some((user, enroll_secret)) =
+        session::with_pending(&ctx, &token, false, |p| {
+            (p.login.clone(), p.enroll_secret.clone())
+        })
+    else {
+        return render_failure(
+            "Your login has expired, please sign in again",
+            None,
+        );
+    };
+    let subjects = [Subject::Ip(ip), Subject::User(user.clone())];
+    if let Some(remaining) = locked_out(ctx.context(), &subjects) {
+        warn!(ctx.log, "Refusing login for {} from {}: locked out", user, ip);
+        return render_failure(
+            "Too many failed login attempts",
+            Some(remaining),
+        );
+    }
+
+    let executor = &ctx.context().executor;
+    let result = if enroll_secret.is_some() {
+        executor.totp_confirm(user.clone(), code).await
+    } else {
+        executor.totp_verify(user.clone(), code).await
+    }
+    .map_err(to_internal_error)?;
+
+    let Some(result) = result else {
+        return render_failure(
+            "Too many failed login attempts, please try again later",
+            None,
+        );
+    };
+    if !result.success {
+        warn!(ctx.log, "Failed TOTP code for {} from {}", user, ip);
+        ctx.context().metrics.login_failures.fetch_add(1, Ordering::Relaxed);
+        if let Some(lockout) = record_failure(&ctx, &subjects) {
+            return render_failure(
+                "Too many failed login attempts",
+                Some(lockout),
+            );
+        }
+        if session::with_pending(&ctx, &token, true, |_| ()).is_none() {
+            return render_failure(
+                "Too many incorrect codes, please sign in again",
+                None,
+            );
+        }
+        return render_totp(
+            &ctx,
+            &user,
+            token,
+            enroll_secret,
+            Some("Invalid code"),
+        );
+    }
+
+    let Some(pending) = session::take_pending(&ctx, &token) else {
+        return render_failure(
+            "Your login has expired, please sign in again",
+            None,
+        );
+    };
+    if let Some(left) = result.recovery_codes_left {
+        warn!(
+            ctx.log,
+            "{} logged in with a recovery code, {} left", user, left
+        );
+    }
+    if result.recovery_codes.is_empty() {
+        return complete(&ctx, pending.login, pending.role).await;
+    }
+
+    // Enrolled during login, show the recovery codes before continuing
+    clear_lockouts(&ctx, &subjects);
+    let cookie = session::start(&ctx, pending.login, pending.role)?;
+    let template = LoginTotpTemplate {
+        message: None,
+        token: String::new(),
+        enrollment: None,
+        recovery_codes: result.recovery_codes,
+    };
+    let result = template.render().map_err(to_internal_error)?;
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("Set-Cookie", cookie)
+        .header("Content-Type", "text/html")
+        .body(result.into())
+        .map_err(to_internal_error)
+}
+
+/// Options for `navigator.credentials.get()`. When a user name has been
+/// entered their credentials are listed, which lets security keys without
+/// discoverable credentials be used. Otherwise the browser offers whichever
+/// passkeys it has for this host.
+#[endpoint {
+method = POST,
+path = "/login/passkey/options"
+}]
+pub async fn post_passkey_options(
+    ctx: RequestContext<Context>,
+    body_param: TypedBody<PasskeyOptionsBody>,
+) -> Result<Response<Body>, HttpError> {
+    let user = body_param.into_inner().user.filter(|u| !u.is_empty());
+    let rp = RelyingParty::from_request(&ctx)?;
+    let allow: Vec<_> = match &user {
+        Some(user) => ctx
+            .context()
+            .executor
+            .webauthn_credentials(user.clone())
+            .await
+            .map_err(to_internal_error)?
+            .credentials
+            .iter()
+            .filter(|c| c.rp_id == rp.id)
+            .map(|c| json!({ "type": "public-key", "id": c.id }))
+            .collect(),
+        None => vec![],
+    };
+    let challenge = session::begin_challenge(&ctx, Ceremony::Login(user))?;
+    json_response(json!({
+        "challenge": challenge,
+        "rpId": rp.id,
+        "allowCredentials": allow,
+        "userVerification": "required",
+        "timeout": passkeys::TIMEOUT,
+    }))
+}
+
+/// Log in with the response to the options from [post_passkey_options]. A
+/// verified passkey replaces both the password and TOTP code.
+#[endpoint {
+method = POST,
+path = "/login/passkey"
+}]
+pub async fn post_passkey(
+    ctx: RequestContext<Context>,
+    body_param: TypedBody<PasskeyLoginBody>,
+) -> Result<Response<Body>, HttpError> {
+    let body = body_param.into_inner();
+    let ip = ctx.request.remote_addr().ip();
+    let rp = RelyingParty::from_request(&ctx)?;
+    let refused = |message: &str| {
+        HttpError::for_client_error(
+            None,
+            StatusCode::FORBIDDEN,
+            String::from(message),
         )
+    };
+
+    let Some(Ceremony::Login(user)) =
+        session::take_challenge(&ctx, &body.challenge)
+    else {
+        return Err(refused("The login has expired, please try again"));
+    };
+    let mut subjects = vec![Subject::Ip(ip)];
+    subjects.extend(user.clone().map(Subject::User));
+    if let Some(remaining) = locked_out(ctx.context(), &subjects) {
+        warn!(ctx.log, "Refusing passkey login from {}: locked out", ip);
+        ctx.context().metrics.login_failures.fetch_add(1, Ordering::Relaxed);
+        return Err(HttpError::for_client_error(
+            None,
+            StatusCode::TOO_MANY_REQUESTS,
+            format!(
+                "Too many failed login attempts, try again in {}",
+                format_duration(remaining)
+            ),
+        ));
+    }
+
+    let result = ctx
+        .context()
+        .executor
+        .webauthn_assert(WebauthnAssertRequest {
+            user,
+            rp_id: rp.id,
+            origin: rp.origin,
+            challenge: body.challenge,
+            credential_id: body.credential_id,
+            client_data_json: body.client_data_json,
+            authenticator_data: body.authenticator_data,
+            signature: body.signature,
+            user_handle: body.user_handle,
+        })
+        .await
         .map_err(to_internal_error)?;
-        return session::create(&ctx, user);
+
+    match result {
+        Some(WebauthnAssertResponse {
+            success: true,
+            user: Some(login),
+            role: Some(role),
+        }) => complete(&ctx, login, role).await,
+        Some(_) => {
+            warn!(ctx.log, "Failed passkey login from {}", ip);
+            ctx.context()
+                .metrics
+                .login_failures
+                .fetch_add(1, Ordering::Relaxed);
+            record_failure(&ctx, &subjects);
+            Err(refused("The passkey was not accepted"))
+        }
+        None => Err(refused(
+            "Too many failed login attempts, please try again later",
+        )),
     }
+}
+
+/// Start a session for a user that has passed every check
+async fn complete(
+    ctx: &RequestContext<Context>,
+    user: String,
+    role: Role,
+) -> Result<Response<Body>, HttpError> {
+    let ip = ctx.request.remote_addr().ip();
+    clear_lockouts(ctx, &[Subject::Ip(ip), Subject::User(user.clone())]);
+    try_join!(
+        ctx.context().vminfod.get_instances(&ctx.log),
+        ctx.context().executor.get_images(&ctx.log),
+    )
+    .map_err(to_internal_error)?;
+    session::create(ctx, user, role)
+}
+
+fn clear_lockouts(ctx: &RequestContext<Context>, subjects: &[Subject]) {
+    if let Ok(mut lockouts) = ctx.context().lockouts.lock() {
+        for subject in subjects {
+            lockouts.clear(subject);
+        }
+    }
+}
+
+/// Count a failed attempt against `subjects`, returning the lockout it
+/// caused (if any)
+fn record_failure(
+    ctx: &RequestContext<Context>,
+    subjects: &[Subject],
+) -> Option<Duration> {
+    let now = Instant::now();
+    let lockout =
+        ctx.context().lockouts.lock().ok().and_then(|mut lockouts| {
+            subjects
+                .iter()
+                .filter_map(|s| lockouts.record_failure(s.clone(), now))
+                .max()
+        });
+    if let Some(lockout) = lockout {
+        let subjects: Vec<_> = subjects.iter().map(|s| s.to_string()).collect();
+        warn!(
+            ctx.log,
+            "Locked out logins for {} for {}",
+            subjects.join(" and "),
+            format_duration(lockout)
+        );
+    }
+    lockout
+}
+
+fn render_totp(
+    ctx: &RequestContext<Context>,
+    user: &str,
+    token: String,
+    enroll_secret: Option<String>,
+    message: Option<&str>,
+) -> Result<Response<Body>, HttpError> {
+    let template = LoginTotpTemplate {
+        message,
+        token,
+        enrollment: enroll_secret
+            .map(|secret| Enrollment::new(ctx, user, &secret)),
+        recovery_codes: vec![],
+    };
+    let result = template.render().map_err(to_internal_error)?;
+    Response::builder()
+        .status(if message.is_some() {
+            StatusCode::FORBIDDEN
+        } else {
+            StatusCode::OK
+        })
+        .header("Content-Type", "text/html")
+        .body(result.into())
+        .map_err(to_internal_error)
+}
+
+/// Longest remaining lockout of any of `subjects`
+fn locked_out(ctx: &Context, subjects: &[Subject]) -> Option<Duration> {
+    let lockouts = ctx.lockouts.lock().ok()?;
+    let now = Instant::now();
+    subjects.iter().filter_map(|s| lockouts.remaining(s, now)).max()
+}
+
+fn render_failure(
+    message: &str,
+    retry_after: Option<Duration>,
+) -> Result<Response<Body>, HttpError> {
     let login = LoginTemplate {
-        message: Some("Invalid username or password"),
+        message: Some(message),
+        retry_after: retry_after.map(format_duration),
         executor: true,
         vminfod: true,
     };
     let result = login.render().map_err(to_internal_error)?;
-    Ok(Response::builder().status(StatusCode::FORBIDDEN).body(result.into())?)
+    let mut response = Response::builder();
+    if let Some(retry_after) = retry_after {
+        response = response
+            .status(StatusCode::TOO_MANY_REQUESTS)
+            .header("Retry-After", retry_after.as_secs().max(1));
+    } else {
+        response = response.status(StatusCode::FORBIDDEN);
+    }
+    Ok(response.body(result.into())?)
 }
 
 /// Presents user with a login form
@@ -87,11 +504,10 @@ path = "/login"
 pub async fn get_index(
     ctx: RequestContext<Context>,
 ) -> Result<Response<Body>, HttpError> {
-    let (vminfod, executor) =
-        try_join!(ctx.context().vminfod.ping(), ctx.context().executor.ping())
-            .map_err(to_internal_error)?;
+    let PingResponse { executor, vminfod } = ctx.context().ping().await;
 
-    let login = LoginTemplate { message: None, executor, vminfod };
+    let login =
+        LoginTemplate { message: None, retry_after: None, executor, vminfod };
 
     let result = login.render().map_err(to_internal_error)?;
 
@@ -0,0 +1,191 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+//! Prometheus metrics for the host, its instances and images, and the UI and
+//! executor services. Only registered when `METRICS_ENABLED` is set, and when
+//! `METRICS_TOKEN` is set the scraper must send it as a bearer token.
+
+use std::collections::BTreeMap;
+use std::sync::atomic::Ordering;
+
+use crate::endpoints::{get_header, Context};
+
+use smartos_shared::auth::constant_time_eq;
+use smartos_shared::http_server::to_internal_error;
+use smartos_shared::metrics::{header, sample, CONTENT_TYPE};
+
+use dropshot::{endpoint, HttpError, RequestContext};
+use hyper::{Body, Response, StatusCode};
+use slog::warn;
+use time::OffsetDateTime;
+
+#[endpoint {
+method = GET,
+path = "/metrics"
+}]
+pub async fn get_index(
+    ctx: RequestContext<Context>,
+) -> Result<Response<Body>, HttpError> {
+    if let Some(token) = &ctx.context().config.metrics_token {
+        let expected = format!("Bearer {}", token.0);
+        let provided = get_header(&ctx, "Authorization").unwrap_or_default();
+        if !constant_time_eq(expected.as_bytes(), provided.as_bytes()) {
+            return Response::builder()
+                .status(StatusCode::UNAUTHORIZED)
+                .header("WWW-Authenticate", "Bearer")
+                .body(Body::empty())
+                .map_err(to_internal_error);
+        }
+    }
+
+    let mut out = String::new();
+    let executor = &ctx.context().executor;
+    let vminfod = &ctx.context().vminfod;
+
+    let name = "smartos_ui_executor_up";
+    header(&mut out, name, "gauge", "Whether the executor responded");
+    sample(&mut out, name, &[], executor.ping().await.unwrap_or(false) as u8);
+
+    let name = "smartos_ui_vminfod_up";
+    header(&mut out, name, "gauge", "Whether vminfod responded");
+    sample(&mut out, name, &[], vminfod.ping().await.unwrap_or(false) as u8);
+
+    match executor.get_sysinfo().await {
+        Ok(sysinfo) => {
+            let name = "smartos_host_info";
+            header(&mut out, name, "gauge", "Host platform information");
+            sample(&mut out, name, &[("live_image", &sysinfo.live_image)], 1);
+
+            let name = "smartos_host_cpus";
+            header(&mut out, name, "gauge", "Number of CPUs on the host");
+            sample(&mut out, name, &[], sysinfo.cpu_count);
+
+            let name = "smartos_host_memory_mib";
+            header(&mut out, name, "gauge", "Physical memory on the host");
+            sample(&mut out, name, &[], sysinfo.mib_of_memory);
+
+            let name = "smartos_host_zpool_size_gib";
+            header(&mut out, name, "gauge", "Size of the zones pool");
+            sample(&mut out, name, &[], sysinfo.zpool_size_in_gib);
+        }
+        Err(e) => warn!(ctx.log, "metrics: failed getting sysinfo: {}", e),
+    }
+
+    match vminfod.get_instances(&ctx.log).await {
+        Ok(instances) => {
+            let mut counts = BTreeMap::<(String, String), u64>::new();
+            for instance in instances.iter() {
+                let key = (instance.brand.to_string(), instance.state.clone());
+                *counts.entry(key).or_default() += 1;
+            }
+            let name = "smartos_instances";
+            header(&mut out, name, "gauge", "Instances by brand and state");
+            for ((brand, state), count) in counts.iter() {
+                sample(
+                    &mut out,
+                    name,
+                    &[("brand", brand), ("state", state)],
+                    count,
+                );
+            }
+
+            let info = "smartos_instance_info";
+            let ram = "smartos_instance_ram_mib";
+            let disk = "smartos_instance_disk_mib";
+            let cpus = "smartos_instance_cpus";
+            let mut info_out = String::new();
+            let mut ram_out = String::new();
+            let mut disk_out = String::new();
+            let mut cpus_out = String::new();
+            header(&mut info_out, info, "gauge", "Instance metadata");
+            header(&mut ram_out, ram, "gauge", "Memory allocated to instance");
+            header(
+                &mut disk_out,
+                disk,
+                "gauge",
+                "Storage allocated to instance",
+            );
+            header(&mut cpus_out, cpus, "gauge", "CPUs allocated to instance");
+            for instance in instances.iter() {
+                let uuid = instance.uuid.to_string();
+                let image_uuid = instance.image_uuid.to_string();
+                let brand = instance.brand.to_string();
+                let labels =
+                    [("uuid", uuid.as_str()), ("alias", &instance.alias)];
+                sample(
+                    &mut info_out,
+                    info,
+                    &[
+                        ("uuid", &uuid),
+                        ("alias", &instance.alias),
+                        ("brand", &brand),
+                        ("state", &instance.state),
+                        ("image_uuid", &image_uuid),
+                    ],
+                    1,
+                );
+                sample(&mut ram_out, ram, &labels, instance.ram);
+                sample(&mut disk_out, disk, &labels, instance.disk_usage);
+                sample(&mut cpus_out, cpus, &labels, instance.cpu);
+            }
+            out.push_str(&info_out);
+            out.push_str(&ram_out);
+            out.push_str(&disk_out);
+            out.push_str(&cpus_out);
+        }
+        Err(e) => warn!(ctx.log, "metrics: failed getting instances: {}", e),
+    }
+
+    match executor.get_images(&ctx.log).await {
+        Ok(images) => {
+            let mut counts = BTreeMap::<String, u64>::new();
+            for image in images.iter() {
+                *counts
+                    .entry(image.manifest.r#type.to_string())
+                    .or_default() += 1;
+            }
+            let name = "smartos_images";
+            header(&mut out, name, "gauge", "Installed images by type");
+            for (image_type, count) in counts.iter() {
+                sample(&mut out, name, &[("type", image_type)], count);
+            }
+        }
+        Err(e) => warn!(ctx.log, "metrics: failed getting images: {}", e),
+    }
+
+    let now = OffsetDateTime::now_utc();
+    let active_sessions = match ctx.context().sessions.lock() {
+        Ok(sessions) => sessions.values().filter(|s| s.expires > now).count(),
+        Err(_) => 0,
+    };
+    let name = "smartos_ui_sessions_active";
+    header(&mut out, name, "gauge", "Unexpired UI sessions");
+    sample(&mut out, name, &[], active_sessions);
+
+    let name = "smartos_ui_login_failures_total";
+    header(&mut out, name, "counter", "Failed UI login attempts");
+    sample(
+        &mut out,
+        name,
+        &[],
+        ctx.context().metrics.login_failures.load(Ordering::Relaxed),
+    );
+
+    match executor.get_metrics().await {
+        Ok(executor_metrics) => out.push_str(&executor_metrics),
+        Err(e) => warn!(ctx.log, "metrics: failed getting executor: {}", e),
+    }
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", CONTENT_TYPE)
+        .body(out.into())
+        .map_err(to_internal_error)
+}
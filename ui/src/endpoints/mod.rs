@@ -8,34 +8,69 @@
  * Copyright 2024 MNX Cloud, Inc.
  */
 
+pub mod api;
 pub mod assets;
+pub mod bulk;
+pub mod certificate;
 pub mod config;
 pub mod dashboard;
+pub mod disks;
 pub mod filters;
+pub mod health;
 pub mod images;
 pub mod instances;
+pub mod lockouts;
 pub mod login;
+pub mod metrics;
+pub mod nics;
+pub mod passkeys;
+pub mod password;
+pub mod presets;
+pub mod sessions;
+pub mod tags;
+pub mod tokens;
+pub mod totp;
 
 use std::collections::HashMap;
+use std::fs::File;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::atomic::AtomicU64;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
-use crate::clients::{ExecutorClient, VMInfodClient};
-use crate::session::{self, Session};
+use crate::clients::{
+    ExecutorClient, PasswordCheck, RequestError, VMInfodClient,
+};
+use crate::live::InstanceModel;
+use crate::lockout::{Lockouts, Policy};
+use crate::security_headers;
+use crate::session::{
+    self, Challenge, CurrentUser, PendingLogin, Session, SessionStore,
+};
+use crate::tls::Tls;
 
-use smartos_shared::{config::Config, http_server::to_internal_error};
+use smartos_shared::{
+    auth::Permission,
+    config::Config,
+    http_server::{to_internal_error, GenericResponse},
+    instance::Instance,
+};
 
 use askama::Template;
 use dropshot::{
     endpoint, http_response_see_other, http_response_temporary_redirect,
     HttpError, HttpResponseOk, HttpResponseSeeOther,
-    HttpResponseTemporaryRedirect, RequestContext,
+    HttpResponseTemporaryRedirect, Path, RequestContext,
 };
 use http::response::Builder;
+use http::uri::Authority;
 use hyper::{Body, Response, StatusCode};
-use pwhash::unix;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use tokio::try_join;
+use slog::info;
+use time::OffsetDateTime;
+use tokio::join;
 use uuid::Uuid;
 
 #[derive(Default, PartialEq)]
@@ -93,35 +128,87 @@ pub struct AsJson {
     pub json: Option<bool>,
 }
 
+/// Counters kept by the UI process for the `/metrics` endpoint
+#[derive(Default)]
+pub struct Metrics {
+    pub login_failures: AtomicU64,
+}
+
 /// Available to in each Dropshot endpoint, contains global config, and the
 /// user sessions
 pub struct Context {
     pub config: Config,
     pub sessions: Arc<Mutex<HashMap<String, Session>>>,
+    pub session_store: Option<Arc<SessionStore>>,
+    pub lockouts: Mutex<Lockouts>,
+    /// Logins waiting for a TOTP code, keyed by token
+    pub pending_logins: Mutex<HashMap<String, PendingLogin>>,
+    /// Outstanding WebAuthn challenges, keyed by challenge
+    pub webauthn_challenges: Mutex<HashMap<String, Challenge>>,
     pub executor: ExecutorClient,
     pub vminfod: VMInfodClient,
+    /// Every instance, kept current by [crate::live::follow]
+    pub instances: Arc<InstanceModel>,
+    pub metrics: Metrics,
+    pub tls: Tls,
+    /// The UI's log file, opened before entering the chroot so that `/health`
+    /// can check the space left on its file system
+    pub log_file: Option<File>,
+    /// The most recent error from each health check, by check name
+    pub health_errors: Mutex<HashMap<String, (String, OffsetDateTime)>>,
 }
 
 impl Context {
     #[must_use]
-    pub fn new(config: Config) -> Self {
+    pub fn new(
+        config: Config,
+        session_store: Option<SessionStore>,
+        tls: Tls,
+    ) -> Self {
         let map: HashMap<String, Session> = HashMap::new();
         let exec_bind_address = config.exec_bind_address.clone();
         let vminfo_bind_address = config.vminfo_bind_address.clone();
+        let log_file = File::open(&config.log_file).ok();
+        let policy = Policy {
+            max_failures: config.login_max_failures,
+            base: Duration::from_secs(config.login_lockout_seconds),
+            max: Duration::from_secs(config.login_lockout_max_seconds),
+        };
         Self {
             config,
             executor: ExecutorClient::new(exec_bind_address),
             vminfod: VMInfodClient::new(vminfo_bind_address),
+            instances: Arc::new(InstanceModel::new()),
             sessions: Arc::new(Mutex::new(map)),
+            session_store: session_store.map(Arc::new),
+            lockouts: Mutex::new(Lockouts::new(policy)),
+            pending_logins: Mutex::new(HashMap::new()),
+            webauthn_challenges: Mutex::new(HashMap::new()),
+            metrics: Metrics::default(),
+            tls,
+            log_file,
+            health_errors: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Ping the executor and vminfod, treating errors as no response
+    pub async fn ping(&self) -> PingResponse {
+        let (vminfod, executor) =
+            join!(self.vminfod.ping(), self.executor.ping());
+        PingResponse {
+            executor: executor.unwrap_or(false),
+            vminfod: vminfod.unwrap_or(false),
         }
     }
 
+    /// Check the credentials with the executor, which has access to the
+    /// shadow file and the users file
     pub async fn validate_password(
         &self,
+        user: String,
         password: String,
-    ) -> Result<bool, reqwest::Error> {
-        let hash = self.executor.get_pwhash().await?;
-        Ok(unix::verify(password, &hash))
+    ) -> Result<PasswordCheck, RequestError> {
+        self.executor.verify_password(user, password).await
     }
 }
 
@@ -135,6 +222,33 @@ pub fn get_header(
         .map(|value| String::from(value.to_str().unwrap_or_default()))
 }
 
+/// The host (and port) the UI was reached by. HTTP/2 clients send this as
+/// the `:authority` pseudo-header rather than `Host`, which ends up in the
+/// request URI.
+pub fn request_host(ctx: &RequestContext<Context>) -> Option<String> {
+    get_header(ctx, "Host")
+        .filter(|host| !host.is_empty())
+        .or_else(|| ctx.request.uri().authority().map(|a| a.to_string()))
+}
+
+/// Refuse the request unless the user's role holds `permission`
+pub fn require(
+    user: &CurrentUser,
+    permission: Permission,
+) -> Result<(), HttpError> {
+    if user.can(permission) {
+        return Ok(());
+    }
+    Err(HttpError::for_client_error(
+        None,
+        StatusCode::FORBIDDEN,
+        format!(
+            "User {} with role {} does not have the {:?} permission",
+            user.login, user.role, permission
+        ),
+    ))
+}
+
 pub fn redirect_login(
     response: Builder,
     ctx: &RequestContext<Context>,
@@ -169,25 +283,180 @@ pub fn htmx_response(
         .map_err(to_internal_error)
 }
 
-/// Redirect to HTTPS port if loaded from non-HTTPS port
-#[endpoint {
-method = GET,
-path = "/"
-}]
-pub async fn get_tls_index(
-    ctx: RequestContext<String>,
-) -> Result<HttpResponseTemporaryRedirect, HttpError> {
-    http_response_temporary_redirect(ctx.context().to_string())
+/// A notification that `subject` failed because of `message`. It's added to
+/// the notifications whatever the request targeted.
+pub fn error_notification(
+    ctx: &RequestContext<Context>,
+    entity_id: impl ToString,
+    subject: &str,
+    message: &str,
+) -> Result<Response<Body>, HttpError> {
+    let template = NotificationTemplate {
+        id: ctx.request_id.clone(),
+        entity_id: entity_id.to_string(),
+        kind: NotificationKind::Error,
+        subject: String::from(subject),
+        message: String::from(message),
+        timeout: Some(String::from("8s")),
+        redirect: None,
+        created_at: String::from(ctx.request.uri().path()),
+    };
+    let result = template.render().map_err(to_internal_error)?;
+    security_headers::builder()
+        .status(StatusCode::OK)
+        .header("HX-Retarget", "#notifications")
+        .header("HX-Reswap", "beforeend")
+        .body(result.into())
+        .map_err(to_internal_error)
+}
+
+/// The instance with `id`, as vminfod has it
+pub async fn get_instance(
+    ctx: &RequestContext<Context>,
+    id: &Uuid,
+) -> Result<Instance, HttpError> {
+    ctx.context().vminfod.get_instance(id).await.map_err(to_internal_error)
+}
+
+/// A change to an instance's disks, NICs or tags, made by vmadm
+pub trait InstanceUpdate: Serialize {
+    /// What's changed, for the log
+    const WHAT: &'static str;
+
+    /// Why this update can't be made to `instance`, empty if it can
+    fn problems_with(&self, instance: &Instance) -> Vec<String>;
+
+    /// An update that changes nothing isn't handed to vmadm
+    fn changes_nothing(&self) -> bool;
+
+    /// Hand the update to the executor
+    fn send(
+        &self,
+        executor: &ExecutorClient,
+        id: &Uuid,
+    ) -> impl Future<Output = Result<reqwest::Response, RequestError>> + Send;
+}
+
+/// Check `update` against the instance, hand it to vmadm and report how it
+/// went
+pub async fn apply<U: InstanceUpdate + Sync>(
+    ctx: &RequestContext<Context>,
+    user: &CurrentUser,
+    instance: &Instance,
+    update: U,
+    (subject, failed): (&str, &str),
+    message: String,
+) -> Result<Response<Body>, HttpError> {
+    let id = instance.uuid();
+    let problems = update.problems_with(instance);
+    if !problems.is_empty() {
+        return error_notification(ctx, id, failed, &problems.join(". "));
+    }
+    if update.changes_nothing() {
+        return error_notification(ctx, id, failed, "Nothing was changed");
+    }
+
+    let response = update
+        .send(&ctx.context().executor, &id)
+        .await
+        .map_err(to_internal_error)?;
+    if !response.status().is_success() {
+        let result: GenericResponse =
+            response.json().await.map_err(to_internal_error)?;
+        return error_notification(ctx, id, failed, &result.message);
+    }
+    info!(
+        ctx.log,
+        "{} changed the {} of {}: {}",
+        user.login,
+        U::WHAT,
+        id,
+        serde_json::to_string(&update).unwrap_or_default()
+    );
+
+    let template = NotificationTemplate {
+        id: ctx.request_id.clone(),
+        entity_id: id.to_string(),
+        kind: NotificationKind::Ok,
+        subject: String::from(subject),
+        message,
+        timeout: Some(String::from("8s")),
+        redirect: Some(format!("/instances/{}", id)),
+        created_at: format!("/instances/{}", id),
+    };
+    let result = template.render().map_err(to_internal_error)?;
+    security_headers::builder()
+        .status(StatusCode::OK)
+        .body(result.into())
+        .map_err(to_internal_error)
+}
+
+/// `value` without surrounding whitespace, if anything is left
+pub fn non_empty(value: &str) -> Option<String> {
+    let value = value.trim();
+    (!value.is_empty()).then(|| String::from(value))
+}
+
+/// Context for the HTTP server, which only redirects to HTTPS
+pub struct RedirectContext {
+    /// The HTTPS server's address, its port is used for every redirect and
+    /// its IP address when the request has no Host header
+    pub https_address: SocketAddr,
+}
+
+/// The HTTPS URL for a request to the HTTP server: the same host (without the
+/// HTTP port), path and query on the HTTPS port
+pub fn https_location(
+    host: Option<&str>,
+    https_address: &SocketAddr,
+    path_and_query: &str,
+) -> String {
+    let host = host
+        .and_then(|host| host.parse::<Authority>().ok())
+        .map(|authority| String::from(authority.host()));
+    match host {
+        Some(host) if https_address.port() == 443 => {
+            format!("https://{}{}", host, path_and_query)
+        }
+        Some(host) => format!(
+            "https://{}:{}{}",
+            host,
+            https_address.port(),
+            path_and_query
+        ),
+        None => format!("https://{}{}", https_address, path_and_query),
+    }
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct AnyPath {
+    #[allow(dead_code)]
+    path: Vec<String>,
 }
 
+/// Redirect any request to the HTTP port to the same page over HTTPS
 #[endpoint {
 method = GET,
-path = "/login"
+path = "/{path:.*}",
+unpublished = true
 }]
-pub async fn get_tls_login_index(
-    ctx: RequestContext<String>,
+pub async fn get_tls_redirect(
+    ctx: RequestContext<RedirectContext>,
+    _path_params: Path<AnyPath>,
 ) -> Result<HttpResponseTemporaryRedirect, HttpError> {
-    http_response_temporary_redirect(ctx.context().to_string())
+    let host =
+        ctx.request.headers().get("Host").and_then(|host| host.to_str().ok());
+    let path_and_query = ctx
+        .request
+        .uri()
+        .path_and_query()
+        .map(|path| path.as_str())
+        .unwrap_or("/");
+    http_response_temporary_redirect(https_location(
+        host,
+        &ctx.context().https_address,
+        path_and_query,
+    ))
 }
 
 /// Redirect a request to "/" to either "/dashboard" (if user has a valid
@@ -221,8 +490,5 @@ path = "/ping"
 pub async fn get_ping(
     ctx: RequestContext<Context>,
 ) -> Result<HttpResponseOk<PingResponse>, HttpError> {
-    let (vminfod, executor) =
-        try_join!(ctx.context().vminfod.ping(), ctx.context().executor.ping())
-            .map_err(to_internal_error)?;
-    Ok(HttpResponseOk(PingResponse { executor, vminfod }))
+    Ok(HttpResponseOk(ctx.context().ping().await))
 }
@@ -0,0 +1,303 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+//! Adding, changing and removing an instance's NICs. Which NIC is primary is
+//! only changed by its own action, never as a side effect of editing a NIC.
+
+use std::future::Future;
+
+use crate::clients::{ExecutorClient, RequestError};
+use crate::endpoints::{
+    apply, error_notification, get_instance, non_empty, redirect_login,
+    require, Context, InstanceUpdate, PathParams,
+};
+use crate::security_headers;
+use crate::session;
+
+use smartos_shared::auth::Permission;
+use smartos_shared::instance::{Instance, Nic};
+use smartos_shared::nic::{NewNic, NicChange, NicUpdate};
+
+use dropshot::{endpoint, HttpError, Path, RequestContext, TypedBody};
+use hyper::{Body, Response};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use uuid::Uuid;
+
+#[derive(Deserialize, JsonSchema)]
+pub struct NicParams {
+    id: Uuid,
+    mac: String,
+}
+
+/// The add and edit forms. Lists are comma separated.
+#[derive(Deserialize, JsonSchema)]
+pub struct NicBody {
+    /// Only used when adding a NIC
+    #[serde(default)]
+    nic_tag: String,
+    #[serde(default)]
+    ips: String,
+    #[serde(default)]
+    gateways: String,
+    #[serde(default)]
+    vlan_id: String,
+    #[serde(default)]
+    model: String,
+    #[serde(default)]
+    allowed_ips: String,
+}
+
+fn list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+impl NicBody {
+    fn vlan_id(&self) -> Result<Option<u16>, String> {
+        let value = self.vlan_id.trim();
+        if value.is_empty() {
+            return Ok(None);
+        }
+        value
+            .parse()
+            .map(Some)
+            .map_err(|_| String::from("The VLAN ID must be a whole number"))
+    }
+}
+
+fn find_nic<'a>(instance: &'a Instance, mac: &str) -> Option<&'a Nic> {
+    instance.nics().iter().find(|nic| nic.mac == mac)
+}
+
+impl InstanceUpdate for NicUpdate {
+    const WHAT: &'static str = "NICs";
+
+    fn problems_with(&self, instance: &Instance) -> Vec<String> {
+        self.problems(&instance.brand(), instance.nics())
+    }
+
+    fn changes_nothing(&self) -> bool {
+        self.is_empty()
+    }
+
+    fn send(
+        &self,
+        executor: &ExecutorClient,
+        id: &Uuid,
+    ) -> impl Future<Output = Result<reqwest::Response, RequestError>> + Send
+    {
+        executor.update_nics(id, self)
+    }
+}
+
+#[endpoint {
+method = POST,
+path = "/instances/{id}/nics",
+content_type = "application/x-www-form-urlencoded"
+}]
+pub async fn post_nic(
+    ctx: RequestContext<Context>,
+    path_params: Path<PathParams>,
+    request_body: TypedBody<NicBody>,
+) -> Result<Response<Body>, HttpError> {
+    let response = security_headers::builder();
+    let Some(user) = session::get_user(&ctx)? else {
+        return redirect_login(response, &ctx);
+    };
+    require(&user, Permission::Modify)?;
+    let id = path_params.into_inner().id;
+    let body = request_body.into_inner();
+
+    let vlan_id = match body.vlan_id() {
+        Ok(vlan_id) => vlan_id,
+        Err(e) => return error_notification(&ctx, id, "NIC not added", &e),
+    };
+    let instance = get_instance(&ctx, &id).await?;
+    let nic_tag = String::from(body.nic_tag.trim());
+    let message =
+        format!("Added a NIC tagged {} to {}", nic_tag, instance.alias());
+    let update = NicUpdate {
+        add_nics: vec![NewNic {
+            nic_tag,
+            ips: list(&body.ips),
+            gateways: list(&body.gateways),
+            vlan_id,
+            model: non_empty(&body.model),
+            // A first NIC is primary whatever it's told, any other has to be
+            // made primary explicitly
+            primary: None,
+            allowed_ips: list(&body.allowed_ips),
+        }],
+        ..Default::default()
+    };
+    apply(
+        &ctx,
+        &user,
+        &instance,
+        update,
+        ("NIC added", "NIC not added"),
+        message,
+    )
+    .await
+}
+
+#[endpoint {
+method = PUT,
+path = "/instances/{id}/nics/{mac}",
+content_type = "application/x-www-form-urlencoded"
+}]
+pub async fn put_nic(
+    ctx: RequestContext<Context>,
+    path_params: Path<NicParams>,
+    request_body: TypedBody<NicBody>,
+) -> Result<Response<Body>, HttpError> {
+    let response = security_headers::builder();
+    let Some(user) = session::get_user(&ctx)? else {
+        return redirect_login(response, &ctx);
+    };
+    require(&user, Permission::Modify)?;
+    let NicParams { id, mac } = path_params.into_inner();
+    let body = request_body.into_inner();
+
+    let vlan_id = match body.vlan_id() {
+        Ok(vlan_id) => vlan_id,
+        Err(e) => return error_notification(&ctx, id, "NIC not changed", &e),
+    };
+    let instance = get_instance(&ctx, &id).await?;
+    let Some(nic) = find_nic(&instance, &mac) else {
+        return error_notification(
+            &ctx,
+            id,
+            "NIC not changed",
+            &format!("{} has no NIC {}", instance.alias(), mac),
+        );
+    };
+
+    // Only what differs from the NIC as it is gets changed
+    let differs = |value: Vec<String>, current: &Option<Vec<String>>| {
+        Some(value)
+            .filter(|value| value != current.as_ref().unwrap_or(&Vec::new()))
+    };
+    let change = NicChange {
+        mac: mac.clone(),
+        ips: differs(list(&body.ips), &nic.ips),
+        gateways: differs(list(&body.gateways), &nic.gateways),
+        vlan_id: vlan_id.filter(|v| *v != nic.vlan_id.unwrap_or_default()),
+        model: non_empty(&body.model).filter(|m| Some(m) != nic.model.as_ref()),
+        primary: None,
+        allowed_ips: differs(list(&body.allowed_ips), &nic.allowed_ips),
+    };
+    let unchanged = NicChange { mac: mac.clone(), ..Default::default() };
+    let update = NicUpdate {
+        update_nics: (change != unchanged)
+            .then_some(change)
+            .into_iter()
+            .collect(),
+        ..Default::default()
+    };
+    let message = format!("Changed {} of {}", mac, instance.alias());
+    apply(
+        &ctx,
+        &user,
+        &instance,
+        update,
+        ("NIC changed", "NIC not changed"),
+        message,
+    )
+    .await
+}
+
+/// Make the NIC primary, which is the only way the primary NIC changes
+#[endpoint {
+method = POST,
+path = "/instances/{id}/nics/{mac}/primary",
+}]
+pub async fn post_nic_primary(
+    ctx: RequestContext<Context>,
+    path_params: Path<NicParams>,
+) -> Result<Response<Body>, HttpError> {
+    let response = security_headers::builder();
+    let Some(user) = session::get_user(&ctx)? else {
+        return redirect_login(response, &ctx);
+    };
+    require(&user, Permission::Modify)?;
+    let NicParams { id, mac } = path_params.into_inner();
+
+    let instance = get_instance(&ctx, &id).await?;
+    if find_nic(&instance, &mac).is_none() {
+        return error_notification(
+            &ctx,
+            id,
+            "Primary NIC not changed",
+            &format!("{} has no NIC {}", instance.alias(), mac),
+        );
+    }
+    let mut update_nics = vec![NicChange {
+        mac: mac.clone(),
+        primary: Some(true),
+        ..Default::default()
+    }];
+    update_nics.extend(
+        instance
+            .nics()
+            .iter()
+            .filter(|nic| nic.primary == Some(true) && nic.mac != mac)
+            .map(|nic| NicChange {
+                mac: nic.mac.clone(),
+                primary: Some(false),
+                ..Default::default()
+            }),
+    );
+    let message =
+        format!("{} is now the primary NIC of {}", mac, instance.alias());
+    apply(
+        &ctx,
+        &user,
+        &instance,
+        NicUpdate { update_nics, ..Default::default() },
+        ("Primary NIC changed", "Primary NIC not changed"),
+        message,
+    )
+    .await
+}
+
+#[endpoint {
+method = DELETE,
+path = "/instances/{id}/nics/{mac}",
+}]
+pub async fn delete_nic(
+    ctx: RequestContext<Context>,
+    path_params: Path<NicParams>,
+) -> Result<Response<Body>, HttpError> {
+    let response = security_headers::builder();
+    let Some(user) = session::get_user(&ctx)? else {
+        return redirect_login(response, &ctx);
+    };
+    require(&user, Permission::Modify)?;
+    let NicParams { id, mac } = path_params.into_inner();
+
+    let instance = get_instance(&ctx, &id).await?;
+    let message = format!("Removed {} from {}", mac, instance.alias());
+    let update = NicUpdate { remove_nics: vec![mac], ..Default::default() };
+    apply(
+        &ctx,
+        &user,
+        &instance,
+        update,
+        ("NIC removed", "NIC not removed"),
+        message,
+    )
+    .await
+}
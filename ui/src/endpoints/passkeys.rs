@@ -0,0 +1,329 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+//! Passkey (WebAuthn) registration for the logged in user. The browser side
+//! is in `assets/passkeys.js`, the endpoints it calls return JSON. Logging in
+//! with a passkey is handled by [crate::endpoints::login].
+
+use crate::endpoints::sessions::format_time;
+use crate::endpoints::{
+    htmx_response, redirect_login, request_host, require, Context,
+    NotificationKind, NotificationTemplate,
+};
+use crate::security_headers;
+use crate::session::{self, Ceremony, CurrentUser};
+
+use smartos_shared::auth::{Permission, WebauthnRegisterRequest};
+use smartos_shared::http_server::{to_bad_request, to_internal_error};
+
+use askama::Template;
+use dropshot::{
+    endpoint, HttpError, HttpResponseUpdatedNoContent, Path, RequestContext,
+    TypedBody,
+};
+use hyper::{Body, Response, StatusCode};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::json;
+use slog::info;
+
+/// Shown by browsers and authenticators as the name of the site
+const RP_NAME: &str = "SmartOS";
+
+/// Milliseconds the browser waits for the user to use their authenticator
+pub const TIMEOUT: u32 = 120_000;
+
+/// COSE algorithms accepted by the executor: ES256, EdDSA and RS256
+const ALGORITHMS: [i64; 3] = [-7, -8, -257];
+
+/// The WebAuthn relying party. Credentials are bound to the host name, so
+/// this follows whatever host the UI was reached by, and a passkey added at
+/// one address can't be used at another.
+pub struct RelyingParty {
+    /// Host name without the port
+    pub id: String,
+    /// e.g. `https://gz.example.com:4443`
+    pub origin: String,
+}
+
+impl RelyingParty {
+    pub fn from_request(
+        ctx: &RequestContext<Context>,
+    ) -> Result<Self, HttpError> {
+        let host = request_host(ctx)
+            .ok_or_else(|| to_bad_request("Missing Host header"))?
+            .to_lowercase();
+        // Strip the port, leaving IPv6 literals (which browsers won't accept
+        // as a relying party anyway) intact
+        let id = match host.rfind(':') {
+            Some(i) if !host[i..].contains(']') => &host[..i],
+            _ => host.as_str(),
+        };
+        Ok(Self { id: String::from(id), origin: format!("https://{}", host) })
+    }
+}
+
+/// The logged in user, for endpoints called by `fetch()` that can't be
+/// redirected to the login page
+fn require_user(
+    ctx: &RequestContext<Context>,
+) -> Result<CurrentUser, HttpError> {
+    let user = session::get_user(ctx)?.ok_or_else(|| {
+        HttpError::for_client_error(
+            None,
+            StatusCode::UNAUTHORIZED,
+            String::from("Your session has expired, please log in again"),
+        )
+    })?;
+    require(&user, Permission::View)?;
+    Ok(user)
+}
+
+pub fn json_response(
+    value: serde_json::Value,
+) -> Result<Response<Body>, HttpError> {
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", "application/json")
+        .body(value.to_string().into())
+        .map_err(to_internal_error)
+}
+
+/// A registered credential as shown on the passkeys page
+pub struct PasskeyView {
+    id: String,
+    name: String,
+    rp_id: String,
+    created: String,
+    last_used: Option<String>,
+}
+
+#[derive(Template)]
+#[template(path = "passkeys.j2")]
+pub struct PasskeysTemplate<'a> {
+    title: &'a str,
+    user: CurrentUser,
+    passkeys: Vec<PasskeyView>,
+    /// The host the page was loaded from, passkeys for other hosts can't be
+    /// used here
+    rp_id: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct RegisterRequestBody {
+    pub challenge: String,
+    pub name: String,
+    pub client_data_json: String,
+    pub attestation_object: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct PasskeyParams {
+    id: String,
+}
+
+#[endpoint {
+method = GET,
+path = "/passkeys"
+}]
+pub async fn get_index(
+    ctx: RequestContext<Context>,
+) -> Result<Response<Body>, HttpError> {
+    let response = security_headers::builder();
+    let Some(user) = session::get_user(&ctx)? else {
+        return redirect_login(response, &ctx);
+    };
+    require(&user, Permission::View)?;
+
+    let rp = RelyingParty::from_request(&ctx)?;
+    let credentials = ctx
+        .context()
+        .executor
+        .webauthn_credentials(user.login.clone())
+        .await
+        .map_err(to_internal_error)?;
+    let passkeys = credentials
+        .credentials
+        .into_iter()
+        .map(|c| PasskeyView {
+            id: c.id,
+            name: c.name,
+            rp_id: c.rp_id,
+            created: format_time(c.created),
+            last_used: c.last_used.map(format_time),
+        })
+        .collect();
+
+    let template =
+        PasskeysTemplate { title: "Passkeys", user, passkeys, rp_id: rp.id };
+    let result = template.render().map_err(to_internal_error)?;
+    htmx_response(response, "/passkeys", result.into())
+}
+
+/// Options for `navigator.credentials.create()`
+#[endpoint {
+method = POST,
+path = "/passkeys/register/options"
+}]
+pub async fn post_register_options(
+    ctx: RequestContext<Context>,
+) -> Result<Response<Body>, HttpError> {
+    let user = require_user(&ctx)?;
+    let rp = RelyingParty::from_request(&ctx)?;
+    let credentials = ctx
+        .context()
+        .executor
+        .webauthn_credentials(user.login.clone())
+        .await
+        .map_err(to_internal_error)?;
+
+    let challenge = session::begin_challenge(
+        &ctx,
+        Ceremony::Register {
+            login: user.login.clone(),
+            handle: credentials.handle.clone(),
+        },
+    )?;
+    // Stops the same authenticator being registered twice
+    let exclude: Vec<_> = credentials
+        .credentials
+        .iter()
+        .filter(|c| c.rp_id == rp.id)
+        .map(|c| json!({ "type": "public-key", "id": c.id }))
+        .collect();
+    let params: Vec<_> = ALGORITHMS
+        .iter()
+        .map(|alg| json!({ "type": "public-key", "alg": alg }))
+        .collect();
+    json_response(json!({
+        "challenge": challenge,
+        "rp": { "id": rp.id, "name": RP_NAME },
+        "user": {
+            "id": credentials.handle,
+            "name": user.login,
+            "displayName": user.login,
+        },
+        "pubKeyCredParams": params,
+        "excludeCredentials": exclude,
+        // Logins with a passkey skip the password, so the authenticator must
+        // verify the user with a PIN or biometrics
+        "authenticatorSelection": {
+            "residentKey": "preferred",
+            "userVerification": "required",
+        },
+        "attestation": "none",
+        "timeout": TIMEOUT,
+    }))
+}
+
+/// Store the credential created with the options from
+/// [post_register_options]
+#[endpoint {
+method = POST,
+path = "/passkeys/register"
+}]
+pub async fn post_register(
+    ctx: RequestContext<Context>,
+    request_body: TypedBody<RegisterRequestBody>,
+) -> Result<HttpResponseUpdatedNoContent, HttpError> {
+    let user = require_user(&ctx)?;
+    let rp = RelyingParty::from_request(&ctx)?;
+    let body = request_body.into_inner();
+
+    let Some(Ceremony::Register { login, handle }) =
+        session::take_challenge(&ctx, &body.challenge)
+    else {
+        return Err(to_bad_request(
+            "The request has expired, please try again",
+        ));
+    };
+    if login != user.login {
+        return Err(to_bad_request(
+            "The request has expired, please try again",
+        ));
+    }
+
+    let result = ctx
+        .context()
+        .executor
+        .webauthn_register(WebauthnRegisterRequest {
+            user: user.login.clone(),
+            handle,
+            name: body.name,
+            rp_id: rp.id,
+            origin: rp.origin,
+            challenge: body.challenge,
+            client_data_json: body.client_data_json,
+            attestation_object: body.attestation_object,
+        })
+        .await
+        .map_err(to_internal_error)?;
+    let Some(credential) = result.credential else {
+        return Err(to_bad_request(format!(
+            "The passkey was not added: {}",
+            result.error.unwrap_or_default()
+        )));
+    };
+    info!(ctx.log, "{} added passkey \"{}\"", user.login, credential.name);
+    Ok(HttpResponseUpdatedNoContent())
+}
+
+#[endpoint {
+method = DELETE,
+path = "/passkeys/credentials/{id}"
+}]
+pub async fn delete_by_id(
+    ctx: RequestContext<Context>,
+    path_params: Path<PasskeyParams>,
+) -> Result<Response<Body>, HttpError> {
+    let response = security_headers::builder();
+    let Some(user) = session::get_user(&ctx)? else {
+        return redirect_login(response, &ctx);
+    };
+    require(&user, Permission::View)?;
+
+    let id = path_params.into_inner().id;
+    let removed = ctx
+        .context()
+        .executor
+        .webauthn_delete(user.login.clone(), id.clone())
+        .await
+        .map_err(to_internal_error)?;
+
+    let (kind, subject, message) = if removed {
+        info!(ctx.log, "{} removed a passkey", user.login);
+        (
+            NotificationKind::Ok,
+            "Passkey removed",
+            "The passkey can no longer be used to log in",
+        )
+    } else {
+        (
+            NotificationKind::Error,
+            "Passkey not removed",
+            "The passkey was not found",
+        )
+    };
+    let template = NotificationTemplate {
+        id: ctx.request_id,
+        entity_id: id,
+        kind,
+        subject: String::from(subject),
+        message: String::from(message),
+        timeout: Some(String::from("8s")),
+        redirect: Some(String::from("/passkeys")),
+        created_at: String::from("/passkeys"),
+    };
+    let result = template.render().map_err(to_internal_error)?;
+    response
+        .status(StatusCode::OK)
+        .body(result.into())
+        .map_err(to_internal_error)
+}
@@ -0,0 +1,150 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+use crate::endpoints::{
+    htmx_response, redirect_login, require, Context, NotificationKind,
+    NotificationTemplate,
+};
+use crate::security_headers;
+use crate::session::{self, CurrentUser};
+
+use smartos_shared::auth::{
+    ChangePasswordRequest, ChangePasswordResponse, Permission,
+};
+use smartos_shared::http_server::to_internal_error;
+
+use askama::Template;
+use dropshot::{endpoint, HttpError, RequestContext, TypedBody};
+use hyper::{Body, Response, StatusCode};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+/// Shortest password that will be accepted
+const MIN_PASSWORD_LENGTH: usize = 8;
+
+#[derive(Template)]
+#[template(path = "password.j2")]
+pub struct PasswordTemplate<'a> {
+    title: &'a str,
+    user: CurrentUser,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct PasswordRequestBody {
+    pub current_password: String,
+    pub new_password: String,
+    pub confirm_password: String,
+}
+
+#[endpoint {
+method = GET,
+path = "/password"
+}]
+pub async fn get_index(
+    ctx: RequestContext<Context>,
+) -> Result<Response<Body>, HttpError> {
+    let response = security_headers::builder();
+    let Some(user) = session::get_user(&ctx)? else {
+        return redirect_login(response, &ctx);
+    };
+    require(&user, Permission::View)?;
+
+    let template = PasswordTemplate { title: "Change Password", user };
+    let result = template.render().map_err(to_internal_error)?;
+    htmx_response(response, "/password", result.into())
+}
+
+#[endpoint {
+method = POST,
+path = "/password",
+content_type = "application/x-www-form-urlencoded"
+}]
+pub async fn post_index(
+    ctx: RequestContext<Context>,
+    request_body: TypedBody<PasswordRequestBody>,
+) -> Result<Response<Body>, HttpError> {
+    let response = security_headers::builder();
+    let Some(user) = session::get_user(&ctx)? else {
+        return redirect_login(response, &ctx);
+    };
+    require(&user, Permission::View)?;
+    let login = user.login;
+
+    let PasswordRequestBody {
+        current_password,
+        new_password,
+        confirm_password,
+    } = request_body.into_inner();
+
+    let error = if new_password != confirm_password {
+        Some(String::from("The new passwords do not match"))
+    } else if new_password.chars().count() < MIN_PASSWORD_LENGTH {
+        Some(format!(
+            "The new password must be at least {} characters",
+            MIN_PASSWORD_LENGTH
+        ))
+    } else {
+        None
+    };
+
+    let (kind, subject, message) = if let Some(message) = error {
+        (NotificationKind::Error, "Password not changed", message)
+    } else {
+        let result = ctx
+            .context()
+            .executor
+            .change_password(ChangePasswordRequest {
+                user: login.clone(),
+                current_password,
+                new_password,
+            })
+            .await
+            .map_err(to_internal_error)?;
+        match result {
+            Some(ChangePasswordResponse { success: true, warnings }) => {
+                let mut message = format!("Password for {} changed.", login);
+                for warning in warnings {
+                    message.push(' ');
+                    message.push_str(&warning);
+                }
+                (NotificationKind::Ok, "Password changed", message)
+            }
+            Some(ChangePasswordResponse { success: false, .. }) => (
+                NotificationKind::Error,
+                "Password not changed",
+                String::from("The current password is incorrect"),
+            ),
+            None => (
+                NotificationKind::Error,
+                "Password not changed",
+                String::from(
+                    "Too many failed attempts, please try again later",
+                ),
+            ),
+        }
+    };
+
+    let template = NotificationTemplate {
+        id: ctx.request_id,
+        entity_id: login,
+        kind,
+        subject: String::from(subject),
+        message,
+        timeout: None,
+        redirect: None,
+        created_at: String::from("/password"),
+    };
+    let result = template.render().map_err(to_internal_error)?;
+    response
+        .status(StatusCode::OK)
+        .header("Content-Type", "text/html")
+        .body(result.into())
+        .map_err(to_internal_error)
+}
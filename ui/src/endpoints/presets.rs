@@ -0,0 +1,289 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+//! Managing provisioning presets. A preset is checked against the host's
+//! capacity before it is saved, and can then be chosen on the provisioning
+//! form to fill in its values.
+
+use crate::endpoints::{
+    error_notification, htmx_response, redirect_login, require, Context,
+    NotificationKind, NotificationTemplate,
+};
+use crate::security_headers;
+use crate::session::{self, CurrentUser};
+
+use smartos_shared::auth::Permission;
+use smartos_shared::http_server::to_internal_error;
+use smartos_shared::nictag::NicTag;
+use smartos_shared::preset::{valid_name, Preset};
+use smartos_shared::serde_helpers::deserialize_empty_as_none;
+use smartos_shared::sysinfo::Sysinfo;
+
+use askama::Template;
+use dropshot::{endpoint, HttpError, Path, Query, RequestContext, TypedBody};
+use hyper::{Body, Response, StatusCode};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use slog::info;
+
+#[derive(Template)]
+#[template(path = "presets.j2")]
+pub struct PresetsTemplate<'a> {
+    title: &'a str,
+    user: CurrentUser,
+    presets: Vec<Preset>,
+    nictags: Vec<NicTag>,
+    sysinfo: Sysinfo,
+    /// The preset being edited, or a new one
+    form: PresetBody,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct PresetsQuery {
+    /// Name of a preset to fill the form in with
+    #[serde(default, deserialize_with = "deserialize_empty_as_none")]
+    edit: Option<String>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct PresetParams {
+    name: String,
+}
+
+/// The preset form. Numbers are parsed by [PresetBody::preset] so that
+/// mistakes are reported alongside the capacity checks.
+#[derive(Deserialize, Default, JsonSchema)]
+pub struct PresetBody {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    ram: String,
+    #[serde(default)]
+    quota: String,
+    #[serde(default)]
+    vcpus: String,
+    #[serde(default)]
+    cpu_cap: String,
+    #[serde(default)]
+    nic_tag: String,
+    /// Comma separated
+    #[serde(default)]
+    resolvers: String,
+}
+
+fn optional_number(
+    label: &str,
+    value: &str,
+    problems: &mut Vec<String>,
+) -> Option<u64> {
+    let value = value.trim();
+    if value.is_empty() {
+        return None;
+    }
+    value
+        .parse()
+        .map_err(|_| {
+            problems.push(format!("{} must be a whole number", label));
+        })
+        .ok()
+}
+
+impl From<&Preset> for PresetBody {
+    fn from(preset: &Preset) -> Self {
+        let number = |value: Option<u64>| {
+            value.map(|v| v.to_string()).unwrap_or_default()
+        };
+        Self {
+            name: preset.name.clone(),
+            ram: preset.ram.to_string(),
+            quota: number(preset.quota),
+            vcpus: number(preset.vcpus),
+            cpu_cap: number(preset.cpu_cap),
+            nic_tag: preset.nic_tag.clone().unwrap_or_default(),
+            resolvers: preset.resolvers.join(","),
+        }
+    }
+}
+
+impl PresetBody {
+    /// The preset described by the form, or why it can't be used on the host
+    /// described by `sysinfo`
+    pub fn preset(&self, sysinfo: &Sysinfo) -> Result<Preset, Vec<String>> {
+        let mut problems = Vec::new();
+        let ram = optional_number("RAM", &self.ram, &mut problems);
+        let preset = Preset {
+            name: String::from(self.name.trim()),
+            ram: ram.unwrap_or_default(),
+            quota: optional_number("Quota", &self.quota, &mut problems),
+            vcpus: optional_number("vCPUs", &self.vcpus, &mut problems),
+            cpu_cap: optional_number("CPU cap", &self.cpu_cap, &mut problems),
+            nic_tag: Some(String::from(self.nic_tag.trim()))
+                .filter(|tag| !tag.is_empty()),
+            resolvers: self
+                .resolvers
+                .split(',')
+                .map(str::trim)
+                .filter(|r| !r.is_empty())
+                .map(String::from)
+                .collect(),
+        };
+        if problems.is_empty() {
+            problems = preset.problems(sysinfo);
+        }
+        if problems.is_empty() {
+            Ok(preset)
+        } else {
+            Err(problems)
+        }
+    }
+}
+
+#[endpoint {
+method = GET,
+path = "/presets"
+}]
+pub async fn get_index(
+    ctx: RequestContext<Context>,
+    query: Query<PresetsQuery>,
+) -> Result<Response<Body>, HttpError> {
+    let response = security_headers::builder();
+    let Some(user) = session::get_user(&ctx)? else {
+        return redirect_login(response, &ctx);
+    };
+    require(&user, Permission::Provision)?;
+
+    let executor = &ctx.context().executor;
+    let presets = executor.get_presets().await.map_err(to_internal_error)?;
+    let nictags = executor.get_nictags().await.map_err(to_internal_error)?;
+    let sysinfo = executor.get_sysinfo().await.map_err(to_internal_error)?;
+
+    let edit = query.into_inner().edit;
+    let form = edit
+        .as_ref()
+        .and_then(|name| presets.iter().find(|p| p.name == *name))
+        .map(PresetBody::from)
+        .unwrap_or_default();
+    let location = match &edit {
+        Some(name) if !form.name.is_empty() => {
+            format!("/presets?edit={}", name)
+        }
+        _ => String::from("/presets"),
+    };
+
+    let template = PresetsTemplate {
+        title: "Presets",
+        user,
+        presets,
+        nictags,
+        sysinfo,
+        form,
+    };
+    let result = template.render().map_err(to_internal_error)?;
+    htmx_response(response, &location, result.into())
+}
+
+/// Create a preset, or replace the one with the same name
+#[endpoint {
+method = POST,
+path = "/presets",
+content_type = "application/x-www-form-urlencoded"
+}]
+pub async fn post_index(
+    ctx: RequestContext<Context>,
+    request_body: TypedBody<PresetBody>,
+) -> Result<Response<Body>, HttpError> {
+    let response = security_headers::builder();
+    let Some(user) = session::get_user(&ctx)? else {
+        return redirect_login(response, &ctx);
+    };
+    require(&user, Permission::Provision)?;
+
+    let executor = &ctx.context().executor;
+    let sysinfo = executor.get_sysinfo().await.map_err(to_internal_error)?;
+    let preset = match request_body.into_inner().preset(&sysinfo) {
+        Ok(preset) => preset,
+        Err(problems) => {
+            return error_notification(
+                &ctx,
+                "",
+                "Preset not saved",
+                &problems.join(". "),
+            )
+        }
+    };
+    executor.save_preset(&preset).await.map_err(to_internal_error)?;
+    info!(ctx.log, "{} saved preset {}", user.login, preset.name);
+
+    let template = NotificationTemplate {
+        id: ctx.request_id,
+        entity_id: preset.name.clone(),
+        kind: NotificationKind::Ok,
+        subject: String::from("Preset saved"),
+        message: format!("{}: {}", preset.name, preset.summary()),
+        timeout: Some(String::from("8s")),
+        redirect: Some(String::from("/presets")),
+        created_at: String::from("/presets"),
+    };
+    let result = template.render().map_err(to_internal_error)?;
+    response
+        .status(StatusCode::OK)
+        .body(result.into())
+        .map_err(to_internal_error)
+}
+
+#[endpoint {
+method = DELETE,
+path = "/presets/{name}"
+}]
+pub async fn delete_by_name(
+    ctx: RequestContext<Context>,
+    path_params: Path<PresetParams>,
+) -> Result<Response<Body>, HttpError> {
+    let response = security_headers::builder();
+    let Some(user) = session::get_user(&ctx)? else {
+        return redirect_login(response, &ctx);
+    };
+    require(&user, Permission::Provision)?;
+
+    let name = path_params.into_inner().name;
+    // Names can't need escaping in the executor's path
+    let removed = valid_name(&name)
+        && ctx
+            .context()
+            .executor
+            .delete_preset(&name)
+            .await
+            .map_err(to_internal_error)?;
+    if !removed {
+        return error_notification(
+            &ctx,
+            "",
+            "Preset not deleted",
+            "The preset was not found",
+        );
+    }
+    info!(ctx.log, "{} deleted preset {}", user.login, name);
+
+    let template = NotificationTemplate {
+        id: ctx.request_id,
+        entity_id: name.clone(),
+        kind: NotificationKind::Ok,
+        subject: String::from("Preset deleted"),
+        message: format!("Preset {} deleted", name),
+        timeout: Some(String::from("8s")),
+        redirect: Some(String::from("/presets")),
+        created_at: String::from("/presets"),
+    };
+    let result = template.render().map_err(to_internal_error)?;
+    response
+        .status(StatusCode::OK)
+        .body(result.into())
+        .map_err(to_internal_error)
+}
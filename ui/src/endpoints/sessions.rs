@@ -0,0 +1,161 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+use crate::endpoints::{
+    htmx_response, redirect_login, require, Context, NotificationKind,
+    NotificationTemplate,
+};
+use crate::security_headers;
+use crate::session::{self, CurrentUser};
+
+use smartos_shared::auth::Permission;
+use smartos_shared::http_server::to_internal_error;
+
+use askama::Template;
+use dropshot::{endpoint, HttpError, Path, RequestContext};
+use hyper::{Body, Response, StatusCode};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use slog::info;
+use time::{macros::format_description, OffsetDateTime};
+
+/// A [session::Session] as shown on the sessions page
+pub struct SessionView {
+    handle: String,
+    login: String,
+    role: String,
+    created: String,
+    last_activity: String,
+    expires: String,
+    source_ip: String,
+    /// The Session used to make this request
+    current: bool,
+}
+
+pub(crate) fn format_time(time: OffsetDateTime) -> String {
+    let format = format_description!(
+        "[year]-[month]-[day] [hour]:[minute]:[second] UTC"
+    );
+    time.format(&format).unwrap_or_default()
+}
+
+#[derive(Template)]
+#[template(path = "sessions.j2")]
+pub struct SessionsTemplate<'a> {
+    title: &'a str,
+    user: CurrentUser,
+    sessions: Vec<SessionView>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct HandleParams {
+    handle: String,
+}
+
+/// Lists unexpired sessions. Admins see every session, other users only see
+/// their own.
+#[endpoint {
+method = GET,
+path = "/sessions"
+}]
+pub async fn get_index(
+    ctx: RequestContext<Context>,
+) -> Result<Response<Body>, HttpError> {
+    let response = security_headers::builder();
+    let Some(user) = session::get_user(&ctx)? else {
+        return redirect_login(response, &ctx);
+    };
+    require(&user, Permission::View)?;
+
+    let show_all = user.can(Permission::ManageSessions);
+    let now = OffsetDateTime::now_utc();
+    let mut sessions = {
+        let sessions = ctx
+            .context()
+            .sessions
+            .lock()
+            .map_err(|e| HttpError::for_internal_error(format!("{}", e)))?;
+        sessions
+            .values()
+            .filter(|s| s.expires > now && (show_all || s.login == user.login))
+            .map(|s| SessionView {
+                handle: s.handle.clone(),
+                login: s.login.clone(),
+                role: s.role.to_string(),
+                created: format_time(s.created),
+                last_activity: format_time(s.last_activity),
+                expires: format_time(s.expires),
+                source_ip: s.source_ip.clone(),
+                current: false,
+            })
+            .collect::<Vec<_>>()
+    };
+    for view in sessions.iter_mut() {
+        view.current = session::is_current(&ctx, &view.handle);
+    }
+    sessions.sort_by(|a, b| b.last_activity.cmp(&a.last_activity));
+
+    let template = SessionsTemplate { title: "Sessions", user, sessions };
+    let result = template.render().map_err(to_internal_error)?;
+    htmx_response(response, "/sessions", result.into())
+}
+
+/// Revoke a session. Users may revoke their own sessions, admins may revoke
+/// any session.
+#[endpoint {
+method = DELETE,
+path = "/sessions/{handle}"
+}]
+pub async fn delete_by_handle(
+    ctx: RequestContext<Context>,
+    path_params: Path<HandleParams>,
+) -> Result<Response<Body>, HttpError> {
+    let response = security_headers::builder();
+    let Some(user) = session::get_user(&ctx)? else {
+        return redirect_login(response, &ctx);
+    };
+    require(&user, Permission::View)?;
+
+    let handle = path_params.into_inner().handle;
+    let manage_all = user.can(Permission::ManageSessions);
+    let revoked =
+        session::revoke(&ctx, &handle, |s| manage_all || s.login == user.login);
+
+    let template = if let Some(login) = revoked {
+        info!(ctx.log, "{} revoked a session for {}", user.login, login);
+        NotificationTemplate {
+            id: ctx.request_id,
+            entity_id: handle,
+            kind: NotificationKind::Ok,
+            subject: String::from("Session revoked"),
+            message: format!("Session for {} has been revoked", login),
+            timeout: Some(String::from("8s")),
+            redirect: Some(String::from("/sessions")),
+            created_at: String::from("/sessions"),
+        }
+    } else {
+        NotificationTemplate {
+            id: ctx.request_id,
+            entity_id: handle,
+            kind: NotificationKind::Error,
+            subject: String::from("Session not revoked"),
+            message: String::from("The session was not found"),
+            timeout: Some(String::from("8s")),
+            redirect: Some(String::from("/sessions")),
+            created_at: String::from("/sessions"),
+        }
+    };
+
+    let result = template.render().map_err(to_internal_error)?;
+    response
+        .status(StatusCode::OK)
+        .body(result.into())
+        .map_err(to_internal_error)
+}
@@ -0,0 +1,137 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+//! Setting and removing an instance's tags. Setting a tag that exists
+//! changes its value.
+
+use std::future::Future;
+
+use crate::clients::{ExecutorClient, RequestError};
+use crate::endpoints::{
+    apply, error_notification, get_instance, redirect_login, require, Context,
+    InstanceUpdate, PathParams,
+};
+use crate::security_headers;
+use crate::session;
+
+use smartos_shared::auth::Permission;
+use smartos_shared::instance::{Instance, TagValue};
+use smartos_shared::tag::TagUpdate;
+
+use dropshot::{endpoint, HttpError, Path, Query, RequestContext, TypedBody};
+use hyper::{Body, Response};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use uuid::Uuid;
+
+#[derive(Deserialize, JsonSchema)]
+pub struct TagBody {
+    #[serde(default)]
+    key: String,
+    /// `true`, `false` and numbers are kept as such, anything else is a
+    /// string
+    #[serde(default)]
+    value: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct TagQuery {
+    key: String,
+}
+
+impl InstanceUpdate for TagUpdate {
+    const WHAT: &'static str = "tags";
+
+    fn problems_with(&self, instance: &Instance) -> Vec<String> {
+        self.problems(instance.tags())
+    }
+
+    fn changes_nothing(&self) -> bool {
+        self.is_empty()
+    }
+
+    fn send(
+        &self,
+        executor: &ExecutorClient,
+        id: &Uuid,
+    ) -> impl Future<Output = Result<reqwest::Response, RequestError>> + Send
+    {
+        executor.update_tags(id, self)
+    }
+}
+
+#[endpoint {
+method = POST,
+path = "/instances/{id}/tags",
+content_type = "application/x-www-form-urlencoded"
+}]
+pub async fn post_tag(
+    ctx: RequestContext<Context>,
+    path_params: Path<PathParams>,
+    request_body: TypedBody<TagBody>,
+) -> Result<Response<Body>, HttpError> {
+    let response = security_headers::builder();
+    let Some(user) = session::get_user(&ctx)? else {
+        return redirect_login(response, &ctx);
+    };
+    require(&user, Permission::Modify)?;
+    let id = path_params.into_inner().id;
+    let body = request_body.into_inner();
+
+    let instance = get_instance(&ctx, &id).await?;
+    let key = String::from(body.key.trim());
+    let value = TagValue::parse(body.value.trim());
+    if instance.tags().get(&key) == Some(&value) {
+        return error_notification(
+            &ctx,
+            id,
+            "Tag not set",
+            "Nothing was changed",
+        );
+    }
+    let message = format!("Set {}={} on {}", key, value, instance.alias());
+    let update = TagUpdate {
+        set_tags: [(key, value)].into_iter().collect(),
+        ..Default::default()
+    };
+    apply(&ctx, &user, &instance, update, ("Tag set", "Tag not set"), message)
+        .await
+}
+
+#[endpoint {
+method = DELETE,
+path = "/instances/{id}/tags",
+}]
+pub async fn delete_tag(
+    ctx: RequestContext<Context>,
+    path_params: Path<PathParams>,
+    query: Query<TagQuery>,
+) -> Result<Response<Body>, HttpError> {
+    let response = security_headers::builder();
+    let Some(user) = session::get_user(&ctx)? else {
+        return redirect_login(response, &ctx);
+    };
+    require(&user, Permission::Modify)?;
+    let id = path_params.into_inner().id;
+    let key = query.into_inner().key;
+
+    let instance = get_instance(&ctx, &id).await?;
+    let message = format!("Removed tag {} from {}", key, instance.alias());
+    let update = TagUpdate { remove_tags: vec![key], ..Default::default() };
+    apply(
+        &ctx,
+        &user,
+        &instance,
+        update,
+        ("Tag removed", "Tag not removed"),
+        message,
+    )
+    .await
+}
@@ -0,0 +1,273 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+//! API tokens for the logged in user. Tokens authenticate requests to the
+//! `/api/v1` endpoints in [crate::endpoints::api], with at most the scopes
+//! chosen here and never more than the user's role allows.
+
+use crate::endpoints::sessions::format_time;
+use crate::endpoints::{
+    error_notification, htmx_response, redirect_login, require, Context,
+    NotificationKind, NotificationTemplate,
+};
+use crate::security_headers;
+use crate::session::{self, CurrentUser};
+
+use smartos_shared::auth::{ApiTokenCreateRequest, Permission, Scope};
+use smartos_shared::http_server::to_internal_error;
+
+use askama::Template;
+use dropshot::{endpoint, HttpError, Path, RequestContext, TypedBody};
+use hyper::{Body, Response, StatusCode};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use slog::info;
+
+/// A token as shown on the tokens page
+pub struct TokenView {
+    id: String,
+    name: String,
+    scopes: String,
+    created: String,
+    last_used: Option<String>,
+}
+
+/// A scope the user can give their tokens
+pub struct ScopeOption {
+    value: String,
+    description: &'static str,
+}
+
+fn describe(scope: Scope) -> &'static str {
+    match scope {
+        Scope::Read => "List and view instances, images and host details",
+        Scope::PowerControl => "Start and stop instances",
+        Scope::Provision => "Create instances",
+        Scope::Delete => "Delete instances",
+        Scope::ManageImages => "Import and delete images",
+    }
+}
+
+#[derive(Template)]
+#[template(path = "tokens.j2")]
+pub struct TokensTemplate<'a> {
+    title: &'a str,
+    user: CurrentUser,
+    tokens: Vec<TokenView>,
+    scopes: Vec<ScopeOption>,
+}
+
+#[derive(Template)]
+#[template(path = "token_created.j2")]
+pub struct TokenCreatedTemplate {
+    name: String,
+    secret: String,
+}
+
+/// The create form has a checkbox for each [Scope]
+#[derive(Deserialize, JsonSchema)]
+pub struct CreateTokenBody {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    read: Option<String>,
+    #[serde(default)]
+    power_control: Option<String>,
+    #[serde(default)]
+    provision: Option<String>,
+    #[serde(default)]
+    delete: Option<String>,
+    #[serde(default)]
+    manage_images: Option<String>,
+}
+
+impl CreateTokenBody {
+    fn scopes(&self) -> Vec<Scope> {
+        [
+            (Scope::Read, &self.read),
+            (Scope::PowerControl, &self.power_control),
+            (Scope::Provision, &self.provision),
+            (Scope::Delete, &self.delete),
+            (Scope::ManageImages, &self.manage_images),
+        ]
+        .into_iter()
+        .filter(|(_, checked)| checked.is_some())
+        .map(|(scope, _)| scope)
+        .collect()
+    }
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct TokenParams {
+    id: String,
+}
+
+#[endpoint {
+method = GET,
+path = "/tokens"
+}]
+pub async fn get_index(
+    ctx: RequestContext<Context>,
+) -> Result<Response<Body>, HttpError> {
+    let response = security_headers::builder();
+    let Some(user) = session::get_user(&ctx)? else {
+        return redirect_login(response, &ctx);
+    };
+    require(&user, Permission::View)?;
+
+    let tokens = ctx
+        .context()
+        .executor
+        .api_tokens(user.login.clone())
+        .await
+        .map_err(to_internal_error)?
+        .into_iter()
+        .map(|token| TokenView {
+            id: token.id,
+            name: token.name,
+            scopes: token
+                .scopes
+                .iter()
+                .map(Scope::to_string)
+                .collect::<Vec<_>>()
+                .join(", "),
+            created: format_time(token.created),
+            last_used: token.last_used.map(format_time),
+        })
+        .collect();
+    let scopes = Scope::ALL
+        .into_iter()
+        .filter(|scope| user.can(scope.permission()))
+        .map(|scope| ScopeOption {
+            value: scope.to_string(),
+            description: describe(scope),
+        })
+        .collect();
+
+    let template = TokensTemplate { title: "API Tokens", user, tokens, scopes };
+    let result = template.render().map_err(to_internal_error)?;
+    htmx_response(response, "/tokens", result.into())
+}
+
+#[endpoint {
+method = POST,
+path = "/tokens",
+content_type = "application/x-www-form-urlencoded"
+}]
+pub async fn post_index(
+    ctx: RequestContext<Context>,
+    request_body: TypedBody<CreateTokenBody>,
+) -> Result<Response<Body>, HttpError> {
+    let response = security_headers::builder();
+    let Some(user) = session::get_user(&ctx)? else {
+        return redirect_login(response, &ctx);
+    };
+    require(&user, Permission::View)?;
+
+    let body = request_body.into_inner();
+    let scopes = body.scopes();
+    if scopes.is_empty() {
+        return error_notification(
+            &ctx,
+            "",
+            "Token not created",
+            "Choose at least one scope",
+        );
+    }
+    if let Some(scope) = scopes.iter().find(|s| !user.can(s.permission())) {
+        return error_notification(
+            &ctx,
+            "",
+            "Token not created",
+            &format!("Your role does not allow the {} scope", scope),
+        );
+    }
+
+    let created = ctx
+        .context()
+        .executor
+        .api_token_create(ApiTokenCreateRequest {
+            user: user.login.clone(),
+            name: body.name,
+            scopes,
+        })
+        .await
+        .map_err(to_internal_error)?;
+    info!(
+        ctx.log,
+        "{} created API token {} \"{}\"",
+        user.login,
+        created.token.id,
+        created.token.name
+    );
+
+    let template = TokenCreatedTemplate {
+        name: created.token.name,
+        secret: created.secret,
+    };
+    let result = template.render().map_err(to_internal_error)?;
+    response
+        .status(StatusCode::OK)
+        .body(result.into())
+        .map_err(to_internal_error)
+}
+
+#[endpoint {
+method = DELETE,
+path = "/tokens/{id}"
+}]
+pub async fn delete_by_id(
+    ctx: RequestContext<Context>,
+    path_params: Path<TokenParams>,
+) -> Result<Response<Body>, HttpError> {
+    let response = security_headers::builder();
+    let Some(user) = session::get_user(&ctx)? else {
+        return redirect_login(response, &ctx);
+    };
+    require(&user, Permission::View)?;
+
+    let id = path_params.into_inner().id;
+    let removed = ctx
+        .context()
+        .executor
+        .api_token_delete(user.login.clone(), id.clone())
+        .await
+        .map_err(to_internal_error)?;
+
+    let (kind, subject, message) = if removed {
+        info!(ctx.log, "{} revoked API token {}", user.login, id);
+        (
+            NotificationKind::Ok,
+            "Token revoked",
+            "The token can no longer be used",
+        )
+    } else {
+        (
+            NotificationKind::Error,
+            "Token not revoked",
+            "The token was not found",
+        )
+    };
+    let template = NotificationTemplate {
+        id: ctx.request_id,
+        entity_id: id,
+        kind,
+        subject: String::from(subject),
+        message: String::from(message),
+        timeout: Some(String::from("8s")),
+        redirect: Some(String::from("/tokens")),
+        created_at: String::from("/tokens"),
+    };
+    let result = template.render().map_err(to_internal_error)?;
+    response
+        .status(StatusCode::OK)
+        .body(result.into())
+        .map_err(to_internal_error)
+}
@@ -0,0 +1,314 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+//! TOTP two-factor authentication settings for the logged in user. The
+//! secret and recovery codes are stored by the executor, the UI only sees the
+//! secret once, to show it during enrollment.
+
+use crate::endpoints::{
+    error_notification, htmx_response, redirect_login, request_host, require,
+    Context, NotificationKind, NotificationTemplate,
+};
+use crate::qr::QrCode;
+use crate::security_headers;
+use crate::session::{self, CurrentUser};
+
+use smartos_shared::auth::Permission;
+use smartos_shared::http_server::to_internal_error;
+
+use askama::Template;
+use dropshot::{endpoint, HttpError, RequestContext, TypedBody};
+use hyper::{Body, Response, StatusCode};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use slog::info;
+
+/// Shown to authenticator apps as the account's issuer
+const ISSUER: &str = "SmartOS";
+
+/// What the user needs to add the secret to an authenticator app
+pub struct Enrollment {
+    /// Base32 secret, in groups of four for manual entry
+    pub secret: String,
+    /// QR code of the `otpauth://` URI as an SVG image
+    pub qr_svg: String,
+}
+
+impl Enrollment {
+    /// Build the `otpauth://` URI for `secret`. The host the UI was reached
+    /// by is included in the label so accounts for different hosts can be
+    /// told apart.
+    pub fn new(
+        ctx: &RequestContext<Context>,
+        login: &str,
+        secret: &str,
+    ) -> Self {
+        let host = request_host(ctx).unwrap_or_default();
+        let host = host.rsplit_once(':').map_or(host.as_str(), |(h, _)| h);
+        let label = if host.is_empty() {
+            format!("{}:{}", ISSUER, login)
+        } else {
+            format!("{}:{}@{}", ISSUER, login, host)
+        };
+        let uri = format!(
+            "otpauth://totp/{}?secret={}&issuer={}&algorithm=SHA1&digits=6&period=30",
+            percent_encode(&label),
+            secret,
+            percent_encode(ISSUER)
+        );
+        let qr_svg = QrCode::encode(uri.as_bytes())
+            .map(|qr| qr.to_svg())
+            .unwrap_or_default();
+        let grouped = secret
+            .as_bytes()
+            .chunks(4)
+            .map(|chunk| String::from_utf8_lossy(chunk).into_owned())
+            .collect::<Vec<_>>()
+            .join(" ");
+        Self { secret: grouped, qr_svg }
+    }
+}
+
+/// Percent encode everything except unreserved characters (RFC 3986)
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| {
+            if b.is_ascii_alphanumeric()
+                || matches!(b, b'-' | b'.' | b'_' | b'~')
+            {
+                (b as char).to_string()
+            } else {
+                format!("%{:02X}", b)
+            }
+        })
+        .collect()
+}
+
+#[derive(Template)]
+#[template(path = "totp.j2")]
+pub struct TotpTemplate<'a> {
+    title: &'a str,
+    user: CurrentUser,
+    enabled: bool,
+    recovery_codes: usize,
+    required: bool,
+}
+
+#[derive(Template)]
+#[template(path = "totp_enroll.j2")]
+pub struct TotpEnrollTemplate {
+    enrollment: Enrollment,
+}
+
+#[derive(Template)]
+#[template(path = "totp_recovery.j2")]
+pub struct TotpRecoveryTemplate {
+    recovery_codes: Vec<String>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct CodeRequestBody {
+    pub code: String,
+}
+
+#[endpoint {
+method = GET,
+path = "/2fa"
+}]
+pub async fn get_index(
+    ctx: RequestContext<Context>,
+) -> Result<Response<Body>, HttpError> {
+    let response = security_headers::builder();
+    let Some(user) = session::get_user(&ctx)? else {
+        return redirect_login(response, &ctx);
+    };
+    require(&user, Permission::View)?;
+
+    let status = ctx
+        .context()
+        .executor
+        .totp_status(user.login.clone())
+        .await
+        .map_err(to_internal_error)?;
+
+    let template = TotpTemplate {
+        title: "Two-Factor Authentication",
+        user,
+        enabled: status.enabled,
+        recovery_codes: status.recovery_codes,
+        required: ctx.context().config.totp_required,
+    };
+    let result = template.render().map_err(to_internal_error)?;
+    htmx_response(response, "/2fa", result.into())
+}
+
+/// Generate a new secret and show it, the user confirms it with a code from
+/// their app before it is used
+#[endpoint {
+method = POST,
+path = "/2fa/enroll"
+}]
+pub async fn post_enroll(
+    ctx: RequestContext<Context>,
+) -> Result<Response<Body>, HttpError> {
+    let response = security_headers::builder();
+    let Some(user) = session::get_user(&ctx)? else {
+        return redirect_login(response, &ctx);
+    };
+    require(&user, Permission::View)?;
+
+    let executor = &ctx.context().executor;
+    let status = executor
+        .totp_status(user.login.clone())
+        .await
+        .map_err(to_internal_error)?;
+    if status.enabled {
+        return error_notification(
+            &ctx,
+            "",
+            "Already enabled",
+            "Two-factor authentication is already enabled, disable it first \
+             to enroll a new device",
+        );
+    }
+    let enrolled = executor
+        .totp_enroll(user.login.clone())
+        .await
+        .map_err(to_internal_error)?;
+
+    let template = TotpEnrollTemplate {
+        enrollment: Enrollment::new(&ctx, &user.login, &enrolled.secret),
+    };
+    let result = template.render().map_err(to_internal_error)?;
+    response
+        .status(StatusCode::OK)
+        .body(result.into())
+        .map_err(to_internal_error)
+}
+
+#[endpoint {
+method = POST,
+path = "/2fa/confirm",
+content_type = "application/x-www-form-urlencoded"
+}]
+pub async fn post_confirm(
+    ctx: RequestContext<Context>,
+    request_body: TypedBody<CodeRequestBody>,
+) -> Result<Response<Body>, HttpError> {
+    let response = security_headers::builder();
+    let Some(user) = session::get_user(&ctx)? else {
+        return redirect_login(response, &ctx);
+    };
+    require(&user, Permission::View)?;
+
+    let CodeRequestBody { code } = request_body.into_inner();
+    let result = ctx
+        .context()
+        .executor
+        .totp_confirm(user.login.clone(), code)
+        .await
+        .map_err(to_internal_error)?;
+
+    match result {
+        Some(result) if result.success => {
+            info!(ctx.log, "{} enabled two-factor authentication", user.login);
+            let template =
+                TotpRecoveryTemplate { recovery_codes: result.recovery_codes };
+            let result = template.render().map_err(to_internal_error)?;
+            response
+                .status(StatusCode::OK)
+                .body(result.into())
+                .map_err(to_internal_error)
+        }
+        Some(_) => error_notification(
+            &ctx,
+            "",
+            "Invalid code",
+            "The code was not correct, check the time on your device and try \
+             again",
+        ),
+        None => error_notification(
+            &ctx,
+            "",
+            "Too many attempts",
+            "Too many incorrect codes, please try again later",
+        ),
+    }
+}
+
+#[endpoint {
+method = POST,
+path = "/2fa/disable",
+content_type = "application/x-www-form-urlencoded"
+}]
+pub async fn post_disable(
+    ctx: RequestContext<Context>,
+    request_body: TypedBody<CodeRequestBody>,
+) -> Result<Response<Body>, HttpError> {
+    let response = security_headers::builder();
+    let Some(user) = session::get_user(&ctx)? else {
+        return redirect_login(response, &ctx);
+    };
+    require(&user, Permission::View)?;
+
+    if ctx.context().config.totp_required {
+        return error_notification(
+            &ctx,
+            "",
+            "Not disabled",
+            "Two-factor authentication is required for all users",
+        );
+    }
+
+    let CodeRequestBody { code } = request_body.into_inner();
+    let result = ctx
+        .context()
+        .executor
+        .totp_disable(user.login.clone(), code)
+        .await
+        .map_err(to_internal_error)?;
+
+    let (kind, subject, message) = match result {
+        Some(result) if result.success => {
+            info!(ctx.log, "{} disabled two-factor authentication", user.login);
+            (
+                NotificationKind::Ok,
+                "Disabled",
+                "Two-factor authentication has been disabled",
+            )
+        }
+        Some(_) => (
+            NotificationKind::Error,
+            "Not disabled",
+            "The code was not correct",
+        ),
+        None => (
+            NotificationKind::Error,
+            "Not disabled",
+            "Too many incorrect codes, please try again later",
+        ),
+    };
+    let template = NotificationTemplate {
+        id: ctx.request_id,
+        entity_id: user.login,
+        redirect: (kind == NotificationKind::Ok).then(|| String::from("/2fa")),
+        kind,
+        subject: String::from(subject),
+        message: String::from(message),
+        timeout: Some(String::from("8s")),
+        created_at: String::from("/2fa"),
+    };
+    let result = template.render().map_err(to_internal_error)?;
+    response
+        .status(StatusCode::OK)
+        .body(result.into())
+        .map_err(to_internal_error)
+}
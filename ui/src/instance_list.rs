@@ -0,0 +1,298 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+//! Sorting, filtering and pagination of the instance list. The parameters
+//! come from the query string, and are written back to it so that filtered
+//! views can be bookmarked and shared.
+
+use std::cmp::Ordering;
+
+use smartos_shared::instance::{Brand, InstanceView};
+use smartos_shared::serde_helpers::{
+    deserialize_empty_as_none, deserialize_into_option_u64,
+};
+use smartos_shared::tag::TagFilter;
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+/// Instances shown per page when the query doesn't say
+pub const DEFAULT_LIMIT: usize = 50;
+
+/// The most instances shown on one page
+pub const MAX_LIMIT: usize = 500;
+
+/// The page sizes offered by the instance list
+pub const LIMITS: [usize; 4] = [25, 50, 100, 250];
+
+#[derive(
+    Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, JsonSchema,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum SortColumn {
+    #[default]
+    Alias,
+    Brand,
+    Image,
+    Ip,
+    Cpu,
+    Ram,
+    Quota,
+    State,
+}
+
+impl SortColumn {
+    #[must_use]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Alias => "alias",
+            Self::Brand => "brand",
+            Self::Image => "image",
+            Self::Ip => "ip",
+            Self::Cpu => "cpu",
+            Self::Ram => "ram",
+            Self::Quota => "quota",
+            Self::State => "state",
+        }
+    }
+}
+
+#[derive(
+    Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, JsonSchema,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    #[default]
+    Asc,
+    Desc,
+}
+
+impl Direction {
+    #[must_use]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Asc => "asc",
+            Self::Desc => "desc",
+        }
+    }
+}
+
+/// Query parameters of `GET /instances`. Forms send their empty fields, so
+/// empty values are the same as leaving a parameter out.
+#[derive(Deserialize, Debug, Clone, Default, JsonSchema)]
+pub struct InstanceListParams {
+    #[serde(default, deserialize_with = "deserialize_empty_as_none")]
+    pub sort: Option<SortColumn>,
+    #[serde(default, deserialize_with = "deserialize_empty_as_none")]
+    pub dir: Option<Direction>,
+    #[serde(default, deserialize_with = "deserialize_empty_as_none")]
+    pub brand: Option<Brand>,
+    #[serde(default, deserialize_with = "deserialize_empty_as_none")]
+    pub state: Option<String>,
+    /// Matched against the alias, UUID, primary IP address and image name
+    #[serde(default, deserialize_with = "deserialize_empty_as_none")]
+    pub search: Option<String>,
+    /// `key` for instances that have the tag, `key=value` for those where it
+    /// has that value
+    #[serde(default, deserialize_with = "deserialize_empty_as_none")]
+    pub tag: Option<String>,
+    /// Starting from 1
+    #[serde(default, deserialize_with = "deserialize_into_option_u64")]
+    pub page: Option<u64>,
+    #[serde(default, deserialize_with = "deserialize_into_option_u64")]
+    pub limit: Option<u64>,
+}
+
+/// One page of instances, each with the name of its image
+pub struct InstancePage {
+    pub instances: Vec<(InstanceView, String)>,
+    /// How many instances matched the filters, on every page
+    pub matched: usize,
+    pub page: usize,
+    pub pages: usize,
+    /// The index of the first instance on this page, starting from 1
+    pub first: usize,
+}
+
+impl InstanceListParams {
+    #[must_use]
+    pub fn column(&self) -> SortColumn {
+        self.sort.unwrap_or_default()
+    }
+
+    #[must_use]
+    pub fn direction(&self) -> Direction {
+        self.dir.unwrap_or_default()
+    }
+
+    #[must_use]
+    pub fn limit(&self) -> usize {
+        match self.limit.and_then(|limit| usize::try_from(limit).ok()) {
+            None | Some(0) => DEFAULT_LIMIT,
+            Some(limit) => limit.min(MAX_LIMIT),
+        }
+    }
+
+    #[must_use]
+    pub fn is_limit(&self, limit: &usize) -> bool {
+        self.limit() == *limit
+    }
+
+    /// Whether any filter is set, as opposed to sorting or paging
+    #[must_use]
+    pub fn is_filtered(&self) -> bool {
+        self.brand.is_some()
+            || self.state.is_some()
+            || self.search.is_some()
+            || self.tag.is_some()
+    }
+
+    fn matches(&self, instance: &InstanceView, image: &str) -> bool {
+        if self.brand.as_ref().is_some_and(|brand| *brand != instance.brand) {
+            return false;
+        }
+        if self.state.as_ref().is_some_and(|state| *state != instance.state) {
+            return false;
+        }
+        if self
+            .tag
+            .as_ref()
+            .is_some_and(|tag| !TagFilter::parse(tag).matches(&instance.tags))
+        {
+            return false;
+        }
+        let Some(search) = &self.search else {
+            return true;
+        };
+        let search = search.trim().to_lowercase();
+        instance.alias.to_lowercase().contains(&search)
+            || instance.uuid.to_string().contains(&search)
+            || instance
+                .primary_ip
+                .as_ref()
+                .is_some_and(|ip| ip.contains(&search))
+            || image.to_lowercase().contains(&search)
+    }
+
+    fn compare(
+        &self,
+        (a, a_image): &(InstanceView, String),
+        (b, b_image): &(InstanceView, String),
+    ) -> Ordering {
+        let ordering = match self.column() {
+            SortColumn::Alias => Ordering::Equal,
+            SortColumn::Brand => a.brand.to_string().cmp(&b.brand.to_string()),
+            SortColumn::Image => a_image.cmp(b_image),
+            SortColumn::Ip => a.primary_ip.cmp(&b.primary_ip),
+            SortColumn::Cpu => a.cpu.total_cmp(&b.cpu),
+            SortColumn::Ram => a.ram.cmp(&b.ram),
+            SortColumn::Quota => a.disk_usage.cmp(&b.disk_usage),
+            SortColumn::State => a.state.cmp(&b.state),
+        }
+        .then_with(|| a.alias.to_lowercase().cmp(&b.alias.to_lowercase()))
+        .then_with(|| a.uuid.cmp(&b.uuid));
+        match self.direction() {
+            Direction::Asc => ordering,
+            Direction::Desc => ordering.reverse(),
+        }
+    }
+
+    /// Filter, sort and take the requested page of `instances`. Pages past
+    /// the end show the last page.
+    #[must_use]
+    pub fn apply(
+        &self,
+        instances: Vec<(InstanceView, String)>,
+    ) -> InstancePage {
+        let mut matched: Vec<(InstanceView, String)> = instances
+            .into_iter()
+            .filter(|(instance, image)| self.matches(instance, image))
+            .collect();
+        matched.sort_by(|a, b| self.compare(a, b));
+
+        let limit = self.limit();
+        let count = matched.len();
+        let pages = count.div_ceil(limit).max(1);
+        let page = self
+            .page
+            .and_then(|page| usize::try_from(page).ok())
+            .unwrap_or(1)
+            .clamp(1, pages);
+        let start = (page - 1) * limit;
+        InstancePage {
+            instances: matched.into_iter().skip(start).take(limit).collect(),
+            matched: count,
+            page,
+            pages,
+            first: start + 1,
+        }
+    }
+
+    /// The query string for these parameters, leaving out defaults
+    #[must_use]
+    pub fn query(&self) -> String {
+        let mut query = url::form_urlencoded::Serializer::new(String::new());
+        if let Some(search) = &self.search {
+            query.append_pair("search", search);
+        }
+        if let Some(brand) = &self.brand {
+            query.append_pair("brand", &brand.to_string());
+        }
+        if let Some(state) = &self.state {
+            query.append_pair("state", state);
+        }
+        if let Some(tag) = &self.tag {
+            query.append_pair("tag", tag);
+        }
+        if self.column() != SortColumn::default() {
+            query.append_pair("sort", self.column().as_str());
+        }
+        if self.direction() != Direction::default() {
+            query.append_pair("dir", self.direction().as_str());
+        }
+        if self.limit() != DEFAULT_LIMIT {
+            query.append_pair("limit", &self.limit().to_string());
+        }
+        if let Some(page) = self.page.filter(|page| *page > 1) {
+            query.append_pair("page", &page.to_string());
+        }
+        query.finish()
+    }
+
+    /// The instance list's path with these parameters
+    #[must_use]
+    pub fn location(&self) -> String {
+        match self.query() {
+            query if query.is_empty() => String::from("/instances"),
+            query => format!("/instances?{}", query),
+        }
+    }
+
+    /// Sorted by `column`, reversing the direction if it already is, from
+    /// the first page
+    #[must_use]
+    pub fn sorted_by(&self, column: &SortColumn) -> String {
+        let dir = if self.column() == *column {
+            match self.direction() {
+                Direction::Asc => Direction::Desc,
+                Direction::Desc => Direction::Asc,
+            }
+        } else {
+            Direction::Asc
+        };
+        Self { sort: Some(*column), dir: Some(dir), page: None, ..self.clone() }
+            .location()
+    }
+
+    #[must_use]
+    pub fn at_page(&self, page: &usize) -> String {
+        Self { page: u64::try_from(*page).ok(), ..self.clone() }.location()
+    }
+}
@@ -13,6 +13,15 @@ pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 pub const GIT_COMMIT_SHORT: &str = env!("GIT_COMMIT_SHORT");
 
 pub mod clients;
+pub mod clone;
 pub mod endpoints;
+pub mod instance_list;
+pub mod live;
+pub mod lockout;
+pub mod nic_form;
 pub mod privilege;
+pub mod provision_form;
+pub mod qr;
+pub mod security_headers;
 pub mod session;
+pub mod tls;
@@ -0,0 +1,199 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+//! Every instance, kept current by following vminfod's event stream rather
+//! than polling it. Changes are broadcast to the browsers subscribed to
+//! `/events/instances`, so pages update as instances are created, change
+//! state or are deleted.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use crate::clients::{RequestError, VMInfodClient, VMInfodEvent};
+
+use smartos_shared::instance::{Instance, InstanceView};
+
+use serde_json::Value;
+use slog::{info, warn, Logger};
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+/// How long to wait before reconnecting to vminfod, doubling up to
+/// [MAX_BACKOFF] while it keeps failing
+pub const MIN_BACKOFF: Duration = Duration::from_secs(1);
+pub const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Changes kept for subscribers that fall behind, they reload the whole list
+/// if they miss any
+const CHANGE_BUFFER: usize = 256;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    /// An instance was created, or something shown about it changed
+    Updated(InstanceView),
+    Deleted(Uuid),
+}
+
+#[derive(Default)]
+struct State {
+    /// Whether vminfod has said it is ready, and its event stream is still up
+    ready: bool,
+    instances: BTreeMap<Uuid, InstanceView>,
+}
+
+pub struct InstanceModel {
+    state: RwLock<State>,
+    changes: broadcast::Sender<Change>,
+}
+
+impl Default for InstanceModel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InstanceModel {
+    #[must_use]
+    pub fn new() -> Self {
+        let (changes, _) = broadcast::channel(CHANGE_BUFFER);
+        Self { state: RwLock::new(State::default()), changes }
+    }
+
+    /// Every instance, or [None] if the model isn't following vminfod and
+    /// callers should ask it instead
+    pub fn instances(&self) -> Option<Vec<InstanceView>> {
+        let state = self.state.read().ok()?;
+        state.ready.then(|| state.instances.values().cloned().collect())
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.state.read().is_ok_and(|state| state.ready)
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Change> {
+        self.changes.subscribe()
+    }
+
+    /// Replace every instance, broadcasting how they differ from before.
+    /// Used when vminfod is ready, including after reconnecting, when
+    /// events may have been missed.
+    pub fn reset(&self, views: Vec<InstanceView>) {
+        let Ok(mut state) = self.state.write() else {
+            return;
+        };
+        let mut previous = std::mem::take(&mut state.instances);
+        for view in views {
+            if previous.remove(&view.uuid).as_ref() != Some(&view) {
+                self.send(Change::Updated(view.clone()));
+            }
+            state.instances.insert(view.uuid, view);
+        }
+        for uuid in previous.into_keys() {
+            self.send(Change::Deleted(uuid));
+        }
+        state.ready = true;
+    }
+
+    /// Add or replace an instance, broadcasting it if anything changed
+    pub fn update(&self, view: InstanceView) {
+        let Ok(mut state) = self.state.write() else {
+            return;
+        };
+        if state.ready && state.instances.get(&view.uuid) != Some(&view) {
+            state.instances.insert(view.uuid, view.clone());
+            self.send(Change::Updated(view));
+        }
+    }
+
+    pub fn delete(&self, uuid: &Uuid) {
+        let Ok(mut state) = self.state.write() else {
+            return;
+        };
+        if state.ready && state.instances.remove(uuid).is_some() {
+            self.send(Change::Deleted(*uuid));
+        }
+    }
+
+    /// Stop answering for vminfod until it is ready again. The instances
+    /// are kept, so that the next [InstanceModel::reset] only broadcasts
+    /// what changed while the stream was down.
+    pub fn disconnected(&self) {
+        if let Ok(mut state) = self.state.write() {
+            state.ready = false;
+        }
+    }
+
+    fn send(&self, change: Change) {
+        // An error only means nobody is subscribed
+        let _ = self.changes.send(change);
+    }
+}
+
+/// A VM from vminfod as an [InstanceView], if it has a UUID to describe it
+/// by
+pub fn to_view(vm: Value) -> Option<InstanceView> {
+    serde_json::from_value::<Instance>(vm).ok().map(InstanceView::from)
+}
+
+/// Apply vminfod's events to the model until the stream ends
+async fn stream(
+    client: &VMInfodClient,
+    model: &InstanceModel,
+    log: &Logger,
+) -> Result<(), RequestError> {
+    let mut events = client.events().await?;
+    while let Some(event) = events.next().await? {
+        match event {
+            VMInfodEvent::Ready { vms } => {
+                let views = match vms {
+                    Some(vms) => {
+                        vms.into_values().filter_map(to_view).collect()
+                    }
+                    None => client.get_instances(log).await?,
+                };
+                info!(log, "Following vminfod events";
+                    "instances" => views.len());
+                model.reset(views);
+            }
+            VMInfodEvent::Create { vm } | VMInfodEvent::Modify { vm } => {
+                match to_view(vm) {
+                    Some(view) => model.update(view),
+                    None => warn!(log, "Ignoring a VM vminfod sent"),
+                }
+            }
+            VMInfodEvent::Delete { uuid } => model.delete(&uuid),
+            VMInfodEvent::Other => (),
+        }
+    }
+    Ok(())
+}
+
+/// Follow vminfod's events for as long as the UI runs, reconnecting when the
+/// stream ends or fails
+pub async fn follow(
+    client: VMInfodClient,
+    model: Arc<InstanceModel>,
+    log: Logger,
+) {
+    let mut backoff = MIN_BACKOFF;
+    loop {
+        match stream(&client, &model, &log).await {
+            Ok(()) => warn!(log, "vminfod ended its event stream"),
+            Err(e) => warn!(log, "vminfod event stream failed: {}", e),
+        }
+        if model.is_ready() {
+            backoff = MIN_BACKOFF;
+        }
+        model.disconnected();
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
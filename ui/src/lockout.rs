@@ -0,0 +1,209 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+//! Tracks failed logins by source IP and by username. Once a [Subject] has
+//! reached the configured number of failures each further failure locks it
+//! out for twice as long as the previous one, up to a maximum.
+//!
+//! The executor keeps its own, more lenient, per-user limit as a backstop for
+//! callers other than the UI.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::iter;
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// What failed attempts are counted against
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Subject {
+    Ip(IpAddr),
+    User(String),
+}
+
+impl fmt::Display for Subject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ip(ip) => write!(f, "ip:{}", ip),
+            Self::User(user) => write!(f, "user:{}", user),
+        }
+    }
+}
+
+impl FromStr for Subject {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            Some(("ip", ip)) => ip
+                .parse()
+                .map(Self::Ip)
+                .map_err(|e| format!("Invalid IP address {}: {}", ip, e)),
+            Some(("user", user)) => Ok(Self::User(String::from(user))),
+            _ => Err(format!("Invalid lockout subject: {}", s)),
+        }
+    }
+}
+
+/// When and for how long subjects are locked out
+#[derive(Debug, Clone, Copy)]
+pub struct Policy {
+    /// Failures allowed before the first lockout, 0 disables lockouts
+    pub max_failures: u32,
+    /// Length of the first lockout
+    pub base: Duration,
+    /// Upper bound for a lockout. Failures are also forgotten once this long
+    /// has passed without another one.
+    pub max: Duration,
+}
+
+impl Policy {
+    /// Lockout incurred by the `failures`th consecutive failure, if any
+    pub fn lockout(&self, failures: u32) -> Option<Duration> {
+        if self.max_failures == 0 || failures < self.max_failures {
+            return None;
+        }
+        let doublings = (failures - self.max_failures).min(31);
+        Some(
+            self.base
+                .checked_mul(1 << doublings)
+                .unwrap_or(self.max)
+                .min(self.max),
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    failures: u32,
+    last_failure: Instant,
+    locked_until: Option<Instant>,
+}
+
+/// A [Subject] with failed attempts, as shown to admins
+pub struct Status {
+    pub subject: Subject,
+    pub failures: u32,
+    /// Time left on the lockout, [None] if not currently locked out
+    pub remaining: Option<Duration>,
+}
+
+pub struct Lockouts {
+    policy: Policy,
+    entries: HashMap<Subject, Entry>,
+}
+
+impl Lockouts {
+    #[must_use]
+    pub fn new(policy: Policy) -> Self {
+        Self { policy, entries: HashMap::new() }
+    }
+
+    /// Time left on the lockout for `subject`, if it is locked out
+    pub fn remaining(
+        &self,
+        subject: &Subject,
+        now: Instant,
+    ) -> Option<Duration> {
+        let until = self.entries.get(subject)?.locked_until?;
+        until.checked_duration_since(now).filter(|d| !d.is_zero())
+    }
+
+    /// Record a failed attempt, returning the lockout it caused (if any)
+    pub fn record_failure(
+        &mut self,
+        subject: Subject,
+        now: Instant,
+    ) -> Option<Duration> {
+        self.prune(now);
+        let entry = self.entries.entry(subject).or_insert(Entry {
+            failures: 0,
+            last_failure: now,
+            locked_until: None,
+        });
+        entry.failures = entry.failures.saturating_add(1);
+        entry.last_failure = now;
+        let lockout = self.policy.lockout(entry.failures);
+        if let Some(lockout) = lockout {
+            // One too long for the clock is as long as the clock allows
+            entry.locked_until = iter::successors(Some(lockout), |d| {
+                (!d.is_zero()).then(|| *d / 2)
+            })
+            .find_map(|d| now.checked_add(d));
+        }
+        lockout
+    }
+
+    /// Forget the failures for `subject`, returns false if there were none
+    pub fn clear(&mut self, subject: &Subject) -> bool {
+        self.entries.remove(subject).is_some()
+    }
+
+    pub fn clear_all(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Every subject with failures that haven't been forgotten yet
+    pub fn list(&self, now: Instant) -> Vec<Status> {
+        let mut list = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| !self.is_stale(entry, now))
+            .map(|(subject, entry)| Status {
+                subject: subject.clone(),
+                failures: entry.failures,
+                remaining: self.remaining(subject, now),
+            })
+            .collect::<Vec<_>>();
+        list.sort_by(|a, b| a.subject.cmp(&b.subject));
+        list
+    }
+
+    fn is_stale(&self, entry: &Entry, now: Instant) -> bool {
+        let locked = entry.locked_until.is_some_and(|until| until > now);
+        !locked && now.duration_since(entry.last_failure) > self.policy.max
+    }
+
+    /// Drop forgotten entries so attempts from many addresses or against many
+    /// usernames can't grow the map without bound
+    fn prune(&mut self, now: Instant) {
+        let stale = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| self.is_stale(entry, now))
+            .map(|(subject, _)| subject.clone())
+            .collect::<Vec<_>>();
+        for subject in stale {
+            self.entries.remove(&subject);
+        }
+    }
+}
+
+/// Format a duration for display, e.g. "4 minutes 10 seconds"
+pub fn format_duration(duration: Duration) -> String {
+    // Round up so that "0 seconds" is never shown for an active lockout
+    let secs = duration.as_secs() + u64::from(duration.subsec_nanos() > 0);
+    let plural = |n: u64, unit: &str| {
+        format!("{} {}{}", n, unit, if n == 1 { "" } else { "s" })
+    };
+    let (hours, minutes, seconds) = (secs / 3600, secs / 60 % 60, secs % 60);
+    let mut parts = vec![];
+    if hours > 0 {
+        parts.push(plural(hours, "hour"));
+    }
+    if minutes > 0 {
+        parts.push(plural(minutes, "minute"));
+    }
+    if seconds > 0 || parts.is_empty() {
+        parts.push(plural(seconds, "second"));
+    }
+    parts.join(" ")
+}
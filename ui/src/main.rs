@@ -12,20 +12,21 @@
 extern crate slog;
 
 use std::env;
-use std::fs;
+use std::net::SocketAddr;
 
-use smartos_shared::config::Config;
+use smartos_shared::config::{self, Config};
 
 use smartos_ui::{
-    endpoints, endpoints::Context, privilege::drop_privileges,
-    GIT_COMMIT_SHORT, VERSION,
+    clients::VMInfodClient, endpoints, endpoints::Context,
+    endpoints::RedirectContext, live, privilege::drop_privileges, session,
+    session::SessionStore, tls, tls::Tls, GIT_COMMIT_SHORT, VERSION,
 };
 
 use dropshot::{
-    ApiDescription, ConfigDropshot, ConfigLogging, ConfigTls, HandlerTaskMode,
+    ApiDescription, ConfigDropshot, ConfigLogging, HandlerTaskMode,
     HttpServerStarter,
 };
-use tokio::try_join;
+use tokio::sync::mpsc;
 
 #[tokio::main]
 async fn main() -> Result<(), String> {
@@ -52,10 +53,16 @@ async fn main() -> Result<(), String> {
         return Ok(());
     }
 
-    let config = Config::new(name);
+    // "config check" and "config dump"
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Some(command) = config::Command::parse(&args) {
+        return command.run(name);
+    }
+
+    let config = Config::load_or_report(name)?;
 
     let request_body_max_bytes = config.request_body_max_bytes;
-    let bind_https_address = config
+    let bind_https_address: SocketAddr = config
         .ui_bind_https_address
         .parse()
         .expect("Failed to parse UI_BIND_HTTPS_ADDRESS");
@@ -65,19 +72,10 @@ async fn main() -> Result<(), String> {
         .expect("Failed to parse UI_BIND_HTTP_ADDRESS");
     let chroot = config.chroot.clone();
     let skip_privilege_drop = config.skip_privilege_drop;
-
-    // Note that we need to read the TLS key and cert before dropping privileges
-    let config_tls = Some(ConfigTls::AsBytes {
-        certs: fs::read(&config.cert_file).unwrap_or_else(|_| {
-            panic!("Failed reading TLS certificate at {}", config.key_file)
-        }),
-        key: fs::read(&config.key_file).unwrap_or_else(|_| {
-            panic!("Failed reading TLS key at {}", config.key_file)
-        }),
-    });
+    let metrics_enabled = config.metrics_enabled;
 
     let config_logging = ConfigLogging::File {
-        level: dropshot::ConfigLoggingLevel::Debug,
+        level: config.log_level.clone(),
         path: config.log_file.clone().into(),
         if_exists: dropshot::ConfigLoggingIfExists::Append,
     };
@@ -88,7 +86,57 @@ async fn main() -> Result<(), String> {
 
     debug!(log, "{} CONFIG: {:#?}", name, &config);
 
-    let ctx = Context::new(config);
+    // Note that we need to read (or generate) the TLS key and cert before
+    // dropping privileges. The generated certificate names the address we
+    // listen on, unless that is every address.
+    let https_ip =
+        Some(bind_https_address.ip()).filter(|ip| !ip.is_unspecified());
+    let key_pair = tls::load_or_generate(
+        &config.cert_file,
+        &config.key_file,
+        https_ip,
+        &log,
+    )?;
+    let config_tls = Some(key_pair.server_config()?);
+    let (tls_reload, mut tls_reloads) = mpsc::unbounded_channel();
+
+    // Like the TLS files, the session file must be opened before dropping
+    // privileges
+    let session_store = config.session_file.as_ref().map(|path| {
+        SessionStore::open(path).unwrap_or_else(|e| {
+            panic!("Failed opening session file {}: {}", path, e)
+        })
+    });
+
+    let ctx = Context::new(
+        config,
+        session_store,
+        Tls::new(key_pair.cert, tls_reload),
+    );
+
+    if let Some(store) = &ctx.session_store {
+        match store.load() {
+            Ok(sessions) => {
+                info!(log, "Loaded {} sessions", sessions.len());
+                if let Ok(mut current) = ctx.sessions.lock() {
+                    *current = sessions;
+                }
+            }
+            Err(e) => warn!(log, "Failed to load sessions: {}", e),
+        }
+    }
+
+    tokio::spawn(session::sweep(
+        ctx.sessions.clone(),
+        ctx.session_store.clone(),
+        log.clone(),
+    ));
+
+    tokio::spawn(live::follow(
+        VMInfodClient::new(ctx.config.vminfo_bind_address.clone()),
+        ctx.instances.clone(),
+        log.clone(),
+    ));
 
     if skip_privilege_drop {
         info!(log, "SKIP_PRIVILEGE_DROP set, not dropping privileges")
@@ -106,11 +154,17 @@ async fn main() -> Result<(), String> {
     // /login
     api.register(endpoints::login::get_index)?;
     api.register(endpoints::login::post_index)?;
+    api.register(endpoints::login::post_totp)?;
+    api.register(endpoints::login::post_passkey_options)?;
+    api.register(endpoints::login::post_passkey)?;
     api.register(endpoints::login::get_logout)?;
 
     // /ping
     api.register(endpoints::get_ping)?;
 
+    // /health
+    api.register(endpoints::health::get_index)?;
+
     // /dashboard
     api.register(endpoints::dashboard::get_index)?;
 
@@ -130,11 +184,37 @@ async fn main() -> Result<(), String> {
     api.register(endpoints::instances::stop_by_id)?;
     api.register(endpoints::instances::start_by_id)?;
 
+    // /instances/{id}/disks
+    api.register(endpoints::disks::post_disk)?;
+    api.register(endpoints::disks::put_disk)?;
+    api.register(endpoints::disks::delete_disk)?;
+
+    // /instances/{id}/nics
+    api.register(endpoints::nics::post_nic)?;
+    api.register(endpoints::nics::put_nic)?;
+    api.register(endpoints::nics::post_nic_primary)?;
+    api.register(endpoints::nics::delete_nic)?;
+
+    // /instances/{id}/tags
+    api.register(endpoints::tags::post_tag)?;
+    api.register(endpoints::tags::delete_tag)?;
+
+    // /bulk
+    api.register(endpoints::bulk::post_action)?;
+
+    // /events
+    api.register(endpoints::instances::get_events)?;
+
     // /provision
     api.register(endpoints::instances::get_provision)?;
     api.register(endpoints::instances::post_provision)?;
     api.register(endpoints::instances::post_provision_validate)?;
 
+    // /presets
+    api.register(endpoints::presets::get_index)?;
+    api.register(endpoints::presets::post_index)?;
+    api.register(endpoints::presets::delete_by_name)?;
+
     // /images
     api.register(endpoints::images::get_index)?;
     api.register(endpoints::images::get_by_id)?;
@@ -146,6 +226,54 @@ async fn main() -> Result<(), String> {
 
     // /config
     api.register(endpoints::config::get_gz_index)?;
+    api.register(endpoints::certificate::get_index)?;
+    api.register(endpoints::certificate::post_index)?;
+
+    // /password
+    api.register(endpoints::password::get_index)?;
+    api.register(endpoints::password::post_index)?;
+
+    // /sessions
+    api.register(endpoints::sessions::get_index)?;
+    api.register(endpoints::sessions::delete_by_handle)?;
+    api.register(endpoints::lockouts::get_index)?;
+    api.register(endpoints::lockouts::post_clear)?;
+
+    // /passkeys
+    api.register(endpoints::passkeys::get_index)?;
+    api.register(endpoints::passkeys::post_register_options)?;
+    api.register(endpoints::passkeys::post_register)?;
+    api.register(endpoints::passkeys::delete_by_id)?;
+
+    // /tokens
+    api.register(endpoints::tokens::get_index)?;
+    api.register(endpoints::tokens::post_index)?;
+    api.register(endpoints::tokens::delete_by_id)?;
+
+    // /api/v1
+    api.register(endpoints::api::get_instances)?;
+    api.register(endpoints::api::get_instance)?;
+    api.register(endpoints::api::post_instance)?;
+    api.register(endpoints::api::post_instance_start)?;
+    api.register(endpoints::api::post_instance_stop)?;
+    api.register(endpoints::api::delete_instance)?;
+    api.register(endpoints::api::get_images)?;
+    api.register(endpoints::api::get_image)?;
+    api.register(endpoints::api::post_image_import)?;
+    api.register(endpoints::api::delete_image)?;
+    api.register(endpoints::api::get_sysinfo)?;
+    api.register(endpoints::api::get_nictags)?;
+
+    // /2fa
+    api.register(endpoints::totp::get_index)?;
+    api.register(endpoints::totp::post_enroll)?;
+    api.register(endpoints::totp::post_confirm)?;
+    api.register(endpoints::totp::post_disable)?;
+
+    // /metrics
+    if metrics_enabled {
+        api.register(endpoints::metrics::get_index)?;
+    }
 
     info!(log, "{} v{}", name, full_version);
 
@@ -165,24 +293,36 @@ async fn main() -> Result<(), String> {
     .start();
 
     // Register endpoints for an HTTP server that exists solely to redirect
-    // HTTP requests from http://x.x.x.x/ to https://x.x.x.x/login
+    // HTTP requests from http://x.x.x.x/path to https://x.x.x.x/path
     let mut redir = ApiDescription::new();
-    redir.register(endpoints::get_tls_index)?;
-    redir.register(endpoints::get_tls_login_index)?;
+    redir.register(endpoints::get_tls_redirect)?;
 
-    let http_server = HttpServerStarter::new(
+    let mut http_server = HttpServerStarter::new(
         &ConfigDropshot {
             bind_address: bind_http_address,
             request_body_max_bytes,
             default_handler_task_mode: HandlerTaskMode::CancelOnDisconnect,
         },
         redir,
-        format!("https://{}", &bind_https_address),
+        RedirectContext { https_address: bind_https_address },
         &log,
     )
     .map_err(|error| format!("failed to start http server: {}", error))?
     .start();
 
-    try_join!(https_server, http_server)?;
-    Ok(())
+    // Certificates uploaded on the TLS page are handed over here, as only
+    // the server itself can replace them
+    let mut https_shutdown = https_server.wait_for_shutdown();
+    loop {
+        tokio::select! {
+            Some(config_tls) = tls_reloads.recv() => {
+                match https_server.refresh_tls(&config_tls).await {
+                    Ok(()) => info!(log, "Reloaded TLS certificate"),
+                    Err(e) => error!(log, "Failed to reload TLS: {}", e),
+                }
+            }
+            result = &mut https_shutdown => return result,
+            result = &mut http_server => return result,
+        }
+    }
 }
@@ -0,0 +1,276 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+//! The provisioning form's NICs. Each NIC's inputs are named after its
+//! position, e.g. `nic1_tag`, which [ProvisionQuery] can't describe, so they
+//! are read from the query string here. `nic_count` is how many NICs the form
+//! has and `primary_nic` which of them is primary; the add and remove buttons
+//! send `add_nic` and `remove_nic`.
+//!
+//! [ProvisionQuery]: crate::endpoints::instances::ProvisionQuery
+
+use smartos_shared::instance::PayloadNic;
+
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+/// More NICs than anyone provisions with, so a crafted query can't make the
+/// form enormous
+pub const MAX_NICS: usize = 32;
+
+/// One NIC on the provisioning form, as its inputs' text
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NicForm {
+    pub tag: String,
+    pub vlan_id: String,
+    /// "auto", "static" or empty for none
+    pub ipv4_setup: String,
+    pub ipv4_ip: String,
+    pub ipv4_prefix: String,
+    pub ipv4_gateway: String,
+    /// "auto", "static" or empty for none
+    pub ipv6_setup: String,
+    pub ipv6_ip: String,
+    pub ipv6_prefix: String,
+    pub model: String,
+    /// Comma separated
+    pub allowed_ips: String,
+}
+
+impl NicForm {
+    fn field_mut(&mut self, name: &str) -> Option<&mut String> {
+        Some(match name {
+            "tag" => &mut self.tag,
+            "vlan_id" => &mut self.vlan_id,
+            "ipv4_setup" => &mut self.ipv4_setup,
+            "ipv4_ip" => &mut self.ipv4_ip,
+            "ipv4_prefix" => &mut self.ipv4_prefix,
+            "ipv4_gateway" => &mut self.ipv4_gateway,
+            "ipv6_setup" => &mut self.ipv6_setup,
+            "ipv6_ip" => &mut self.ipv6_ip,
+            "ipv6_prefix" => &mut self.ipv6_prefix,
+            "model" => &mut self.model,
+            "allowed_ips" => &mut self.allowed_ips,
+            _ => return None,
+        })
+    }
+
+    /// Each input's name, without the `nic<n>_` prefix, and value
+    #[must_use]
+    pub fn fields(&self) -> [(&'static str, &str); 11] {
+        [
+            ("tag", &self.tag),
+            ("vlan_id", &self.vlan_id),
+            ("ipv4_setup", &self.ipv4_setup),
+            ("ipv4_ip", &self.ipv4_ip),
+            ("ipv4_prefix", &self.ipv4_prefix),
+            ("ipv4_gateway", &self.ipv4_gateway),
+            ("ipv6_setup", &self.ipv6_setup),
+            ("ipv6_ip", &self.ipv6_ip),
+            ("ipv6_prefix", &self.ipv6_prefix),
+            ("model", &self.model),
+            ("allowed_ips", &self.allowed_ips),
+        ]
+    }
+}
+
+/// The NICs on a provisioning form
+#[derive(Debug, Clone, PartialEq)]
+pub struct NicRows {
+    pub nics: Vec<NicForm>,
+    /// Index of the primary NIC
+    pub primary: usize,
+}
+
+/// A form that hasn't been filled in has one NIC to choose a tag for
+impl Default for NicRows {
+    fn default() -> Self {
+        Self { nics: vec![NicForm::default()], primary: 0 }
+    }
+}
+
+impl NicRows {
+    /// The hidden inputs that keep these NICs while the form has no inputs
+    /// for them
+    #[must_use]
+    pub fn carried(&self) -> Vec<(String, String)> {
+        let mut carried = vec![
+            (String::from("nic_count"), self.nics.len().to_string()),
+            (String::from("primary_nic"), self.primary.to_string()),
+        ];
+        for (index, nic) in self.nics.iter().enumerate() {
+            carried.extend(
+                nic.fields()
+                    .into_iter()
+                    .filter(|(_, value)| !value.is_empty())
+                    .map(|(name, value)| {
+                        (format!("nic{}_{}", index, name), String::from(value))
+                    }),
+            );
+        }
+        carried
+    }
+
+    /// The NICs as `vmadm create` takes them, leaving out any without a NIC
+    /// tag. HVM instances' NICs get a model, virtio unless one was chosen.
+    pub fn payload(
+        &self,
+        hvm: bool,
+        problems: &mut Vec<String>,
+    ) -> Vec<PayloadNic> {
+        let mut nics = Vec::new();
+        for (index, nic) in self.nics.iter().enumerate() {
+            let tag = nic.tag.trim();
+            if tag.is_empty() {
+                continue;
+            }
+            let mut payload = PayloadNic {
+                nic_tag: Some(String::from(tag)),
+                ..Default::default()
+            };
+
+            match nic.ipv4_setup.as_str() {
+                "static" => {
+                    if let Some(ip) = address(&nic.ipv4_ip, &nic.ipv4_prefix) {
+                        payload.ips.push(ip);
+                    }
+                    let gateway = nic.ipv4_gateway.trim();
+                    if !gateway.is_empty() {
+                        payload.gateways.push(String::from(gateway));
+                    }
+                }
+                "auto" => payload.ips.push(String::from("dhcp")),
+                _ => {}
+            }
+            match nic.ipv6_setup.as_str() {
+                "static" => {
+                    if let Some(ip) = address(&nic.ipv6_ip, &nic.ipv6_prefix) {
+                        payload.ips.push(ip);
+                    }
+                }
+                "auto" => payload.ips.push(String::from("addrconf")),
+                _ => {}
+            }
+
+            let vlan_id = nic.vlan_id.trim();
+            if !vlan_id.is_empty() {
+                match vlan_id.parse() {
+                    Ok(vlan_id) => payload.vlan_id = Some(vlan_id),
+                    Err(_) => problems.push(format!(
+                        "NIC {} VLAN ID \"{}\" is not a number",
+                        index, vlan_id
+                    )),
+                }
+            }
+            payload.allowed_ips = nic
+                .allowed_ips
+                .split(',')
+                .map(str::trim)
+                .filter(|ip| !ip.is_empty())
+                .map(String::from)
+                .collect();
+            if hvm {
+                let model = nic.model.trim();
+                payload.model = Some(String::from(if model.is_empty() {
+                    "virtio"
+                } else {
+                    model
+                }));
+            }
+            if index == self.primary {
+                payload.primary = Some(true);
+            }
+            nics.push(payload);
+        }
+
+        // A lone NIC is primary without saying so
+        if let [nic] = nics.as_mut_slice() {
+            nic.primary = None;
+        }
+        nics
+    }
+
+    fn remove(&mut self, index: usize) {
+        if index >= self.nics.len() {
+            return;
+        }
+        self.nics.remove(index);
+        match self.primary.cmp(&index) {
+            Ordering::Equal => self.primary = 0,
+            Ordering::Greater => self.primary -= 1,
+            Ordering::Less => {}
+        }
+    }
+}
+
+/// `ip` or `ip/prefix`, if there is an address
+fn address(ip: &str, prefix: &str) -> Option<String> {
+    let (ip, prefix) = (ip.trim(), prefix.trim());
+    match (ip.is_empty(), prefix.is_empty()) {
+        (true, _) => None,
+        (false, true) => Some(String::from(ip)),
+        (false, false) => Some(format!("{}/{}", ip, prefix)),
+    }
+}
+
+/// The NICs in a provisioning form's query string, after any NIC the form
+/// asked to add or remove
+#[must_use]
+pub fn from_query(query: &str) -> NicRows {
+    let mut count = None;
+    let mut primary = 0;
+    let mut add = false;
+    let mut remove = None;
+    let mut nics = BTreeMap::<usize, NicForm>::new();
+
+    for (key, value) in url::form_urlencoded::parse(query.as_bytes()) {
+        match key.as_ref() {
+            "nic_count" => count = value.parse().ok(),
+            "primary_nic" => primary = value.parse().unwrap_or_default(),
+            "add_nic" => add = value == "true",
+            "remove_nic" => remove = value.parse().ok(),
+            key => {
+                let Some((index, name)) = key
+                    .strip_prefix("nic")
+                    .and_then(|rest| rest.split_once('_'))
+                    .and_then(|(index, name)| {
+                        index.parse::<usize>().ok().map(|i| (i, name))
+                    })
+                    // There are never more rows than that
+                    .filter(|(index, _)| *index < MAX_NICS)
+                else {
+                    continue;
+                };
+                if let Some(field) =
+                    nics.entry(index).or_default().field_mut(name)
+                {
+                    *field = value.into_owned();
+                }
+            }
+        }
+    }
+
+    // Without a count, which a form that hasn't been filled in doesn't send,
+    // there are as many NICs as there are inputs for, or one to start with
+    let count = count
+        .unwrap_or_else(|| nics.keys().next_back().map_or(1, |index| index + 1))
+        .min(MAX_NICS);
+    let mut rows = NicRows {
+        nics: (0..count).map(|i| nics.remove(&i).unwrap_or_default()).collect(),
+        primary: if primary < count { primary } else { 0 },
+    };
+    if let Some(index) = remove {
+        rows.remove(index);
+    }
+    if add && rows.nics.len() < MAX_NICS {
+        rows.nics.push(NicForm::default());
+    }
+    rows
+}
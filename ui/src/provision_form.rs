@@ -0,0 +1,318 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+//! The `vmadm create` payload built from the provisioning form. The validate
+//! and create buttons post the guided form's inputs, the additional
+//! properties editor's JSON and how the two are merged; the payload is built
+//! and checked here so the browser only previews it.
+
+use crate::endpoints::instances::ProvisionQuery;
+use crate::endpoints::non_empty;
+use crate::nic_form;
+
+use smartos_shared::instance::{Brand, CreatePayload, PayloadDisk};
+use smartos_shared::serde_helpers::deserialize_into_bool;
+use smartos_shared::tag::parse_tags;
+
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use uuid::Uuid;
+
+/// Puts the instance's `root_authorized_keys` metadata in root's
+/// authorized_keys on an OS or LX instance's first boot
+const OPERATOR_SCRIPT: &str = "#!/usr/bin/bash
+if ! [[ -d /root/.ssh ]]; then
+  mkdir -p /root/.ssh
+  chmod 0700 /root/.ssh
+fi
+/usr/sbin/mdata-get root_authorized_keys > /root/.ssh/authorized_keys
+/usr/sbin/mdata-delete operator-script";
+
+/// How the additional properties are merged with the guided form's
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Merge {
+    /// The guided form's properties take precedence
+    #[default]
+    Extend,
+    /// The additional properties take precedence
+    Replace,
+    /// Only the additional properties are used
+    Ignore,
+}
+
+/// The provisioning form as the validate and create buttons post it
+#[derive(Debug)]
+pub struct ProvisionBody {
+    /// The guided form's inputs, NICs included
+    pub form: ProvisionQuery,
+    /// The additional properties editor's JSON
+    pub additional: String,
+    pub merge: Merge,
+    /// Provision even if the host doesn't have room left for the instance
+    pub overcommit: bool,
+}
+
+/// The inputs that aren't the guided form's
+#[derive(Deserialize)]
+struct Options {
+    #[serde(default)]
+    additional: String,
+    #[serde(default)]
+    merge: Merge,
+    #[serde(default, deserialize_with = "deserialize_into_bool")]
+    overcommit: bool,
+}
+
+impl ProvisionBody {
+    /// Read the form from its urlencoded body
+    pub fn parse(body: &str) -> Result<Self, String> {
+        let invalid =
+            |e: serde_urlencoded::de::Error| format!("Invalid form: {}", e);
+        let mut form: ProvisionQuery =
+            serde_urlencoded::from_str(body).map_err(invalid)?;
+        form.nics = nic_form::from_query(body);
+        let options: Options =
+            serde_urlencoded::from_str(body).map_err(invalid)?;
+        Ok(Self {
+            form,
+            additional: options.additional,
+            merge: options.merge,
+            overcommit: options.overcommit,
+        })
+    }
+
+    /// The payload the form describes, or why it can't be built. See
+    /// [check] for whether it can be used.
+    pub fn payload(&self) -> Result<CreatePayload, String> {
+        let mut problems = Vec::new();
+        let guided = guided(&self.form, &mut problems);
+        if !problems.is_empty() {
+            return Err(problems.join(". "));
+        }
+
+        let additional = match self.additional.trim() {
+            "" => Map::new(),
+            json => match serde_json::from_str(json) {
+                Ok(Value::Object(additional)) => additional,
+                Ok(_) => {
+                    return Err(String::from(
+                        "Additional properties must be a JSON object",
+                    ))
+                }
+                Err(e) => {
+                    return Err(format!("Invalid additional properties: {}", e))
+                }
+            },
+        };
+        let guided = match serde_json::to_value(guided) {
+            Ok(Value::Object(guided)) => guided,
+            _ => Map::new(),
+        };
+
+        let mut payload: CreatePayload = serde_json::from_value(Value::Object(
+            merge(guided, additional, self.merge),
+        ))
+        .map_err(|e| format!("Invalid payload: {}", e))?;
+        fit_flexible_disk_size(&mut payload);
+        Ok(payload)
+    }
+}
+
+/// `payload`, or the problems that would make vmadm refuse it
+pub fn check(payload: CreatePayload) -> Result<CreatePayload, String> {
+    let problems = payload.problems();
+    if problems.is_empty() {
+        Ok(payload)
+    } else {
+        Err(problems.join(". "))
+    }
+}
+
+/// `value` as a number, if it isn't empty
+fn number(problems: &mut Vec<String>, name: &str, value: &str) -> Option<u64> {
+    let value = value.trim();
+    if value.is_empty() {
+        return None;
+    }
+    match value.parse() {
+        Ok(number) => Some(number),
+        Err(_) => {
+            problems.push(format!("{} must be a whole number", name));
+            None
+        }
+    }
+}
+
+/// The payload from the guided form's inputs alone
+fn guided(form: &ProvisionQuery, problems: &mut Vec<String>) -> CreatePayload {
+    let brand = non_empty(&form.brand).and_then(|brand| brand.parse().ok());
+    let hvm = brand.as_ref().map_or(false, Brand::is_hvm);
+    let image_uuid = non_empty(&form.image_uuid).and_then(|uuid| {
+        uuid.parse::<Uuid>()
+            .map_err(|_| {
+                problems.push(format!("Image \"{}\" isn't a UUID", uuid))
+            })
+            .ok()
+    });
+
+    let mut payload = CreatePayload {
+        alias: non_empty(&form.alias),
+        hostname: non_empty(&form.alias),
+        ram: number(problems, "RAM", &form.ram),
+        quota: number(problems, "Disk quota", &form.quota),
+        vcpus: number(problems, "vCPUs", &form.vcpus),
+        cpu_cap: number(problems, "CPU cap", &form.cpu_cap),
+        resolvers: form
+            .resolvers
+            .split(',')
+            .map(str::trim)
+            .filter(|resolver| !resolver.is_empty())
+            .map(String::from)
+            .collect(),
+        dns_domain: non_empty(&form.dns_domain),
+        delegate_dataset: (!form.delegate_dataset.is_empty()).then_some(true),
+        nics: form.nics.payload(hvm, problems),
+        tags: parse_tags(&form.tags),
+        ..Default::default()
+    };
+
+    // HVM instances boot from a disk made from the image
+    match (&brand, image_uuid) {
+        (Some(Brand::Bhyve), Some(image_uuid)) => {
+            let size = if form.primary_disk_size > 0 {
+                form.primary_disk_size.checked_mul(1024).or_else(|| {
+                    problems
+                        .push(String::from("Primary disk size is too large"));
+                    None
+                })
+            } else {
+                None
+            };
+            payload.disks.push(PayloadDisk {
+                image_uuid: Some(image_uuid),
+                boot: Some(true),
+                size,
+                model: Some(String::from("virtio")),
+                ..Default::default()
+            });
+            if let Some(size) = size {
+                payload
+                    .extra
+                    .insert(String::from("flexible_disk_size"), size.into());
+            }
+        }
+        (Some(Brand::KVM), Some(image_uuid)) => {
+            payload.disks.push(PayloadDisk {
+                image_uuid: Some(image_uuid),
+                boot: Some(true),
+                model: Some(String::from("virtio")),
+                ..Default::default()
+            });
+        }
+        (_, image_uuid) => payload.image_uuid = image_uuid,
+    }
+    if hvm && payload.cpu_cap.is_none() {
+        payload.cpu_cap = payload.vcpus.map(|vcpus| vcpus.saturating_mul(100));
+    }
+    payload.brand = brand;
+
+    for (name, value) in
+        [("bootrom", &form.bootrom), ("kernel_version", &form.kernel_version)]
+    {
+        if let Some(value) = non_empty(value) {
+            payload.extra.insert(String::from(name), value.into());
+        }
+    }
+
+    let mut metadata = |key: &str, value: &str| {
+        if !value.trim().is_empty() {
+            payload
+                .customer_metadata
+                .insert(String::from(key), String::from(value).into());
+        }
+    };
+    metadata("user-script", &form.user_script);
+    metadata("root_authorized_keys", &form.root_authorized_keys);
+    metadata("cloud-init:user-data", &form.cloudinit_data);
+    if !hvm && !form.root_authorized_keys.trim().is_empty() {
+        payload.internal_metadata.insert(
+            String::from("operator-script"),
+            String::from(OPERATOR_SCRIPT).into(),
+        );
+    }
+    if !form.root_pw.is_empty() {
+        payload
+            .internal_metadata
+            .insert(String::from("root_pw"), form.root_pw.clone().into());
+    }
+
+    payload
+}
+
+/// Grow a bhyve instance's `flexible_disk_size` to hold all of its disks,
+/// when the guided form set it for the boot disk and data disks were added
+fn fit_flexible_disk_size(payload: &mut CreatePayload) {
+    let Some(Value::Number(flexible)) = payload.extra.get("flexible_disk_size")
+    else {
+        return;
+    };
+    let flexible = flexible.as_u64().unwrap_or_default();
+    let disks = payload
+        .disks
+        .iter()
+        .filter_map(|disk| disk.size)
+        .fold(0, u64::saturating_add);
+    if disks > flexible {
+        payload.extra.insert(String::from("flexible_disk_size"), disks.into());
+    }
+}
+
+fn is_boot(disk: &Value) -> bool {
+    disk.get("boot").and_then(Value::as_bool) == Some(true)
+}
+
+/// The guided form's properties merged with the additional ones. Metadata
+/// and tags are merged key by key, so that the guided form's keys and others
+/// from the additional properties can be used together. Disks are merged
+/// too: the boot disk comes from the side that takes precedence if it has
+/// one, the other disks from both.
+fn merge(
+    guided: Map<String, Value>,
+    additional: Map<String, Value>,
+    merge: Merge,
+) -> Map<String, Value> {
+    let (mut low, high) = match merge {
+        Merge::Ignore => return additional,
+        Merge::Extend => (additional, guided),
+        Merge::Replace => (guided, additional),
+    };
+    for (key, value) in high {
+        match (low.get_mut(&key), value) {
+            (Some(Value::Object(low)), Value::Object(high))
+                if key == "customer_metadata" || key == "tags" =>
+            {
+                low.extend(high);
+            }
+            (Some(Value::Array(low)), Value::Array(high)) if key == "disks" => {
+                if high.iter().any(is_boot) {
+                    low.retain(|disk| !is_boot(disk));
+                }
+                low.extend(high);
+                // The boot disk first, as vmadm lists them
+                low.sort_by_key(|disk| !is_boot(disk));
+            }
+            (_, value) => {
+                low.insert(key, value);
+            }
+        }
+    }
+    low
+}
@@ -0,0 +1,522 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+//! A minimal QR code encoder (ISO/IEC 18004), used to show `otpauth://` URIs
+//! to authenticator apps during TOTP enrollment. It only supports what that
+//! needs: byte mode, error correction level M and versions 1 to 10, which
+//! hold up to 213 bytes.
+
+use std::fmt::Write;
+
+/// Largest supported version
+const MAX_VERSION: usize = 10;
+
+/// Error correction codewords per block at level M, indexed by version
+const ECC_PER_BLOCK: [usize; MAX_VERSION + 1] =
+    [0, 10, 16, 26, 18, 24, 16, 18, 22, 22, 26];
+
+/// Error correction blocks at level M, indexed by version
+const BLOCKS: [usize; MAX_VERSION + 1] = [0, 1, 1, 1, 2, 2, 4, 4, 4, 5, 5];
+
+/// Format information bits for error correction level M
+const ECC_LEVEL_M: u32 = 0;
+
+/// Light modules around the symbol required by the spec
+const QUIET_ZONE: usize = 4;
+
+pub struct QrCode {
+    version: usize,
+    size: usize,
+    modules: Vec<bool>,
+    /// Modules that are part of a function pattern and must not be masked
+    function: Vec<bool>,
+}
+
+impl QrCode {
+    /// Encode `data` with the automatically selected mask, returns [None] if
+    /// it doesn't fit in the largest supported version
+    pub fn encode(data: &[u8]) -> Option<Self> {
+        let mut qr = Self::encode_unmasked(data)?;
+        let mask = (0..8)
+            .min_by_key(|mask| {
+                qr.apply_mask(*mask);
+                qr.draw_format(*mask);
+                let penalty = qr.penalty();
+                qr.apply_mask(*mask);
+                penalty
+            })
+            .unwrap_or_default();
+        qr.apply_mask(mask);
+        qr.draw_format(mask);
+        Some(qr)
+    }
+
+    /// Encode `data` using a specific mask (0 to 7)
+    pub fn encode_with_mask(data: &[u8], mask: u8) -> Option<Self> {
+        let mut qr = Self::encode_unmasked(data)?;
+        qr.apply_mask(mask);
+        qr.draw_format(mask);
+        Some(qr)
+    }
+
+    pub fn version(&self) -> usize {
+        self.version
+    }
+
+    /// Width and height in modules, not including the quiet zone
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn is_dark(&self, x: usize, y: usize) -> bool {
+        self.modules[y * self.size + x]
+    }
+
+    /// Render as an SVG image that scales to fit its container
+    pub fn to_svg(&self) -> String {
+        let dimension = self.size + QUIET_ZONE * 2;
+        let mut path = String::new();
+        for y in 0..self.size {
+            for x in 0..self.size {
+                if self.is_dark(x, y) {
+                    let _ = write!(
+                        path,
+                        "M{},{}h1v1h-1z",
+                        x + QUIET_ZONE,
+                        y + QUIET_ZONE
+                    );
+                }
+            }
+        }
+        format!(
+            concat!(
+                r#"<svg xmlns="http://www.w3.org/2000/svg" "#,
+                r#"viewBox="0 0 {0} {0}" shape-rendering="crispEdges">"#,
+                r#"<rect width="100%" height="100%" fill="white"/>"#,
+                r#"<path d="{1}" fill="black"/></svg>"#
+            ),
+            dimension, path
+        )
+    }
+
+    fn encode_unmasked(data: &[u8]) -> Option<Self> {
+        let version = (1..=MAX_VERSION)
+            .find(|v| data_bits(*v, data.len()) <= data_codewords(*v) * 8)?;
+        let codewords =
+            add_ecc_and_interleave(version, &data_codewords_for(version, data));
+
+        let size = version * 4 + 17;
+        let mut qr = Self {
+            version,
+            size,
+            modules: vec![false; size * size],
+            function: vec![false; size * size],
+        };
+        qr.draw_function_patterns();
+        qr.draw_codewords(&codewords);
+        Some(qr)
+    }
+
+    fn set_function(&mut self, x: usize, y: usize, dark: bool) {
+        self.modules[y * self.size + x] = dark;
+        self.function[y * self.size + x] = true;
+    }
+
+    fn draw_function_patterns(&mut self) {
+        let size = self.size;
+        for i in 0..size {
+            self.set_function(6, i, i % 2 == 0);
+            self.set_function(i, 6, i % 2 == 0);
+        }
+
+        self.draw_finder(3, 3);
+        self.draw_finder(size - 4, 3);
+        self.draw_finder(3, size - 4);
+
+        let positions = alignment_positions(self.version);
+        let last = positions.len().saturating_sub(1);
+        for (i, x) in positions.iter().enumerate() {
+            for (j, y) in positions.iter().enumerate() {
+                // Skip the three that would overlap the finder patterns
+                let finder = matches!((i, j), (0, 0))
+                    || (i == 0 && j == last)
+                    || (i == last && j == 0);
+                if !finder {
+                    self.draw_alignment(*x, *y);
+                }
+            }
+        }
+
+        // Reserve the format areas, they are drawn once the mask is chosen
+        self.draw_format(0);
+        self.draw_version();
+    }
+
+    fn draw_finder(&mut self, x: usize, y: usize) {
+        for dy in -4i32..=4 {
+            for dx in -4i32..=4 {
+                let (xx, yy) = (x as i32 + dx, y as i32 + dy);
+                if (0..self.size as i32).contains(&xx)
+                    && (0..self.size as i32).contains(&yy)
+                {
+                    let distance = dx.abs().max(dy.abs());
+                    self.set_function(
+                        xx as usize,
+                        yy as usize,
+                        distance != 2 && distance != 4,
+                    );
+                }
+            }
+        }
+    }
+
+    fn draw_alignment(&mut self, x: usize, y: usize) {
+        for dy in -2i32..=2 {
+            for dx in -2i32..=2 {
+                self.set_function(
+                    (x as i32 + dx) as usize,
+                    (y as i32 + dy) as usize,
+                    dx.abs().max(dy.abs()) != 1,
+                );
+            }
+        }
+    }
+
+    fn draw_format(&mut self, mask: u8) {
+        let data = (ECC_LEVEL_M << 3) | u32::from(mask);
+        let mut remainder = data;
+        for _ in 0..10 {
+            remainder = (remainder << 1) ^ ((remainder >> 9) * 0x537);
+        }
+        let bits = ((data << 10) | remainder) ^ 0x5412;
+        let bit = |i: usize| (bits >> i) & 1 != 0;
+        let size = self.size;
+
+        // Around the top left finder
+        for i in 0..=5 {
+            self.set_function(8, i, bit(i));
+        }
+        self.set_function(8, 7, bit(6));
+        self.set_function(8, 8, bit(7));
+        self.set_function(7, 8, bit(8));
+        for i in 9..15 {
+            self.set_function(14 - i, 8, bit(i));
+        }
+
+        // Split between the other two finders
+        for i in 0..8 {
+            self.set_function(size - 1 - i, 8, bit(i));
+        }
+        for i in 8..15 {
+            self.set_function(8, size - 15 + i, bit(i));
+        }
+        // Always dark
+        self.set_function(8, size - 8, true);
+    }
+
+    fn draw_version(&mut self) {
+        if self.version < 7 {
+            return;
+        }
+        let version = self.version as u32;
+        let mut remainder = version;
+        for _ in 0..12 {
+            remainder = (remainder << 1) ^ ((remainder >> 11) * 0x1f25);
+        }
+        let bits = (version << 12) | remainder;
+        for i in 0..18 {
+            let dark = (bits >> i) & 1 != 0;
+            let a = self.size - 11 + i % 3;
+            let b = i / 3;
+            self.set_function(a, b, dark);
+            self.set_function(b, a, dark);
+        }
+    }
+
+    /// Place the codewords in the zig-zag pattern of two module wide columns,
+    /// right to left, skipping the vertical timing pattern
+    fn draw_codewords(&mut self, codewords: &[u8]) {
+        let size = self.size;
+        let total_bits = codewords.len() * 8;
+        let mut i = 0;
+        let mut right = size - 1;
+        loop {
+            if right == 6 {
+                right = 5;
+            }
+            let upward = (right + 1) & 2 == 0;
+            for vertical in 0..size {
+                let y = if upward { size - 1 - vertical } else { vertical };
+                for x in [right, right - 1] {
+                    let index = y * size + x;
+                    if !self.function[index] && i < total_bits {
+                        self.modules[index] =
+                            (codewords[i >> 3] >> (7 - (i & 7))) & 1 != 0;
+                        i += 1;
+                    }
+                }
+            }
+            if right < 2 {
+                break;
+            }
+            right -= 2;
+        }
+    }
+
+    /// XOR the data modules with mask pattern `mask`, applying it twice undoes
+    /// it
+    fn apply_mask(&mut self, mask: u8) {
+        for y in 0..self.size {
+            for x in 0..self.size {
+                let invert = match mask {
+                    0 => (x + y) % 2 == 0,
+                    1 => y % 2 == 0,
+                    2 => x % 3 == 0,
+                    3 => (x + y) % 3 == 0,
+                    4 => (x / 3 + y / 2) % 2 == 0,
+                    5 => x * y % 2 + x * y % 3 == 0,
+                    6 => (x * y % 2 + x * y % 3) % 2 == 0,
+                    _ => ((x + y) % 2 + x * y % 3) % 2 == 0,
+                };
+                let index = y * self.size + x;
+                if invert && !self.function[index] {
+                    self.modules[index] = !self.modules[index];
+                }
+            }
+        }
+    }
+
+    /// Penalty score used to choose the mask, lower is better
+    fn penalty(&self) -> usize {
+        let size = self.size;
+        let mut penalty = 0;
+
+        let rows = (0..size)
+            .map(|y| (0..size).map(|x| self.is_dark(x, y)).collect::<Vec<_>>());
+        let columns = (0..size)
+            .map(|x| (0..size).map(|y| self.is_dark(x, y)).collect::<Vec<_>>());
+        for line in rows.chain(columns) {
+            penalty += line_penalty(&line);
+        }
+
+        // 2x2 blocks of the same colour
+        for y in 0..size - 1 {
+            for x in 0..size - 1 {
+                let dark = self.is_dark(x, y);
+                if dark == self.is_dark(x + 1, y)
+                    && dark == self.is_dark(x, y + 1)
+                    && dark == self.is_dark(x + 1, y + 1)
+                {
+                    penalty += 3;
+                }
+            }
+        }
+
+        // Balance of dark and light modules, 10 points for each 5% away
+        // from half
+        let total = size * size;
+        let dark = self.modules.iter().filter(|m| **m).count();
+        let deviation = (dark * 20).abs_diff(total * 10);
+        penalty += ((deviation + total - 1) / total).saturating_sub(1) * 10;
+
+        penalty
+    }
+}
+
+/// Penalties for runs of five or more modules of the same colour, and for
+/// patterns that look like a finder, in a single row or column
+fn line_penalty(line: &[bool]) -> usize {
+    let mut penalty = 0;
+    let mut run = 1;
+    for i in 1..=line.len() {
+        if i < line.len() && line[i] == line[i - 1] {
+            run += 1;
+            continue;
+        }
+        if run >= 5 {
+            penalty += run - 2;
+        }
+        run = 1;
+    }
+
+    // Dark-light-dark-dark-dark-light-dark with four light modules (or the
+    // edge of the symbol, which is in the quiet zone) on either side
+    const FINDER: [bool; 7] = [true, false, true, true, true, false, true];
+    let light =
+        |i: isize| i < 0 || i as usize >= line.len() || !line[i as usize];
+    for start in 0..line.len().saturating_sub(6) {
+        if line[start..start + 7] == FINDER {
+            let start = start as isize;
+            let before = (start - 4..start).all(light);
+            let after = (start + 7..start + 11).all(light);
+            if before || after {
+                penalty += 40;
+            }
+        }
+    }
+    penalty
+}
+
+/// Centre coordinates of the alignment patterns for `version`
+fn alignment_positions(version: usize) -> Vec<usize> {
+    if version == 1 {
+        return vec![];
+    }
+    let count = version / 7 + 2;
+    let step = (version * 8 + count * 3 + 5) / (count * 4 - 4) * 2;
+    let mut positions = vec![6];
+    let mut position = version * 4 + 10;
+    let mut rest = vec![];
+    for _ in 0..count - 1 {
+        rest.push(position);
+        position -= step;
+    }
+    positions.extend(rest.into_iter().rev());
+    positions
+}
+
+/// Number of modules available for data and error correction codewords
+fn raw_data_modules(version: usize) -> usize {
+    let mut result = (16 * version + 128) * version + 64;
+    if version >= 2 {
+        let alignments = version / 7 + 2;
+        result -= (25 * alignments - 10) * alignments - 55;
+        if version >= 7 {
+            result -= 36;
+        }
+    }
+    result
+}
+
+fn data_codewords(version: usize) -> usize {
+    raw_data_modules(version) / 8 - ECC_PER_BLOCK[version] * BLOCKS[version]
+}
+
+fn count_bits(version: usize) -> usize {
+    if version < 10 {
+        8
+    } else {
+        16
+    }
+}
+
+/// Bits needed to hold `length` bytes in byte mode
+fn data_bits(version: usize, length: usize) -> usize {
+    4 + count_bits(version) + length * 8
+}
+
+/// Mode indicator, length, data, terminator and padding as codewords
+fn data_codewords_for(version: usize, data: &[u8]) -> Vec<u8> {
+    let capacity = data_codewords(version) * 8;
+    let mut bits: Vec<bool> = Vec::with_capacity(capacity);
+    let mut push = |value: usize, count: usize| {
+        for i in (0..count).rev() {
+            bits.push((value >> i) & 1 != 0);
+        }
+    };
+    push(0b0100, 4);
+    push(data.len(), count_bits(version));
+    for byte in data {
+        push(usize::from(*byte), 8);
+    }
+
+    let terminator = (capacity - bits.len()).min(4);
+    bits.extend(std::iter::repeat(false).take(terminator));
+    while bits.len() % 8 != 0 {
+        bits.push(false);
+    }
+
+    let mut codewords: Vec<u8> = bits
+        .chunks(8)
+        .map(|byte| byte.iter().fold(0, |acc, bit| (acc << 1) | u8::from(*bit)))
+        .collect();
+    for pad in [0xec, 0x11].into_iter().cycle() {
+        if codewords.len() * 8 >= capacity {
+            break;
+        }
+        codewords.push(pad);
+    }
+    codewords
+}
+
+/// Split the data into blocks, append each block's Reed-Solomon error
+/// correction codewords and interleave the result
+fn add_ecc_and_interleave(version: usize, data: &[u8]) -> Vec<u8> {
+    let blocks = BLOCKS[version];
+    let ecc_len = ECC_PER_BLOCK[version];
+    let raw_codewords = raw_data_modules(version) / 8;
+    let short_blocks = blocks - raw_codewords % blocks;
+    let short_len = raw_codewords / blocks;
+    let divisor = reed_solomon_divisor(ecc_len);
+
+    let mut all = Vec::with_capacity(blocks);
+    let mut offset = 0;
+    for i in 0..blocks {
+        let length = short_len - ecc_len + usize::from(i >= short_blocks);
+        let mut block = data[offset..offset + length].to_vec();
+        offset += length;
+        let ecc = reed_solomon_remainder(&block, &divisor);
+        if i < short_blocks {
+            // Placeholder so every block has the same length, skipped below
+            block.push(0);
+        }
+        block.extend(ecc);
+        all.push(block);
+    }
+
+    let mut result = Vec::with_capacity(raw_codewords);
+    for i in 0..all[0].len() {
+        for (j, block) in all.iter().enumerate() {
+            if i != short_len - ecc_len || j >= short_blocks {
+                result.push(block[i]);
+            }
+        }
+    }
+    result
+}
+
+/// Generator polynomial of the given degree, without the leading term
+fn reed_solomon_divisor(degree: usize) -> Vec<u8> {
+    let mut result = vec![0u8; degree];
+    result[degree - 1] = 1;
+    let mut root = 1u8;
+    for _ in 0..degree {
+        for j in 0..degree {
+            result[j] = gf_multiply(result[j], root);
+            if j + 1 < degree {
+                result[j] ^= result[j + 1];
+            }
+        }
+        root = gf_multiply(root, 0x02);
+    }
+    result
+}
+
+fn reed_solomon_remainder(data: &[u8], divisor: &[u8]) -> Vec<u8> {
+    let mut result = vec![0u8; divisor.len()];
+    for byte in data {
+        let factor = byte ^ result.remove(0);
+        result.push(0);
+        for (r, d) in result.iter_mut().zip(divisor) {
+            *r ^= gf_multiply(*d, factor);
+        }
+    }
+    result
+}
+
+/// Multiplication in GF(2^8) modulo x^8 + x^4 + x^3 + x^2 + 1
+fn gf_multiply(x: u8, y: u8) -> u8 {
+    let mut z: u8 = 0;
+    for i in (0..8).rev() {
+        z = (z << 1) ^ ((z >> 7) * 0x1d);
+        z ^= ((y >> i) & 1) * x;
+    }
+    z
+}
@@ -0,0 +1,52 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+//! Headers sent with every authenticated page. Dropshot doesn't have
+//! response middleware, so endpoints that require a session start their
+//! response with [builder] rather than [http::Response::builder].
+
+use http::response::Builder;
+use hyper::Response;
+
+/// Everything is served by the UI itself. Scripts and styles can't be
+/// restricted further while the templates use `hx-on`, `onchange` and
+/// `style` attributes, which need inline code and htmx's use of `eval`.
+pub const CONTENT_SECURITY_POLICY: &str = "default-src 'self'; \
+    script-src 'self' 'unsafe-inline' 'unsafe-eval'; \
+    style-src 'self' 'unsafe-inline'; \
+    img-src 'self' data:; \
+    connect-src 'self'; \
+    object-src 'none'; \
+    base-uri 'self'; \
+    form-action 'self'; \
+    frame-ancestors 'none'";
+
+/// One year. Browsers ignore this when the certificate isn't trusted or the
+/// UI is reached by IP address, so self-signed certificates still work.
+pub const STRICT_TRANSPORT_SECURITY: &str = "max-age=31536000";
+
+/// The headers added by [builder]
+pub const HEADERS: [(&str, &str); 6] = [
+    ("Content-Security-Policy", CONTENT_SECURITY_POLICY),
+    ("Strict-Transport-Security", STRICT_TRANSPORT_SECURITY),
+    ("X-Frame-Options", "DENY"),
+    ("X-Content-Type-Options", "nosniff"),
+    ("Referrer-Policy", "same-origin"),
+    // Pages contain instance details and the CSRF token, they shouldn't be
+    // kept by the browser or anything in between
+    ("Cache-Control", "no-store"),
+];
+
+/// A response builder with the security headers already set
+pub fn builder() -> Builder {
+    HEADERS.iter().fold(Response::builder(), |builder, (name, value)| {
+        builder.header(*name, *value)
+    })
+}
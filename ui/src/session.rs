@@ -15,19 +15,38 @@
 //!
 //! The Session ID is a randomly generated and sent to the user agent as a
 //! Cookie.
+//!
+//! Every Session also has a CSRF token, which is embedded in authenticated
+//! pages and must be sent back in the `X-CSRF-Token` header of any request
+//! that isn't a GET. htmx and `fetch()` calls add it from the page's
+//! `csrf-token` meta tag.
+//!
+//! If `SESSION_FILE` is set the sessions are also written to disk by a
+//! [SessionStore] so that they survive a restart of the service. Expired
+//! sessions are removed periodically by [sweep].
+
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, Write};
+use std::net::IpAddr;
+use std::os::unix::fs::OpenOptionsExt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 
 use crate::endpoints::Context;
 
+use smartos_shared::auth::{constant_time_eq, Permission, Role};
 use smartos_shared::http_server::to_internal_error;
 
 use dropshot::{
     http_response_temporary_redirect, HttpError, HttpResponseTemporaryRedirect,
     RequestContext,
 };
-use http::{HeaderValue, Response, StatusCode};
+use http::{HeaderValue, Method, Response, StatusCode};
 use hyper::Body;
 use nanoid::nanoid;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use slog::{error, info, warn, Logger};
 use time::{format_description::well_known::Rfc2822, Duration, OffsetDateTime};
 
 /// Name for cookie. Using the "__Host-" provides some additional assurance that
@@ -45,39 +64,249 @@ const SESSION_ID_LENGTH: usize = 64;
 /// Path=/ - Required for using __Host- cookie prefix
 const COOKIE_ATTRS: &str = "HttpOnly; SameSite=Strict; Secure; Path=/";
 
-/// How many hours a session/cookie is valid
-const SESSION_HOURS: u8 = 8;
+/// Number of random characters in a Session's CSRF token
+const CSRF_TOKEN_LENGTH: usize = 32;
+
+/// Header the CSRF token is sent in
+pub const CSRF_HEADER: &str = "X-CSRF-Token";
+
+/// Number of random characters in a Session's handle
+const HANDLE_LENGTH: usize = 16;
+
+/// How long a user has to complete the second step of a login
+const PENDING_LOGIN_MINUTES: u8 = 5;
 
-#[derive(Serialize)]
+/// Wrong codes allowed at the second step before the login must be started
+/// again
+const PENDING_LOGIN_ATTEMPTS: u8 = 5;
+
+/// How long the browser has to respond to a WebAuthn challenge
+const CHALLENGE_MINUTES: u8 = 5;
+
+/// How often expired sessions are removed and the [SessionStore] is written
+const SWEEP_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+#[derive(Serialize, Deserialize)]
 /// Holds active Session information that can be access in the [RequestContext]
 pub struct Session {
     /// Name of logged in user
     pub login: String,
+    /// What the user is allowed to do
+    pub role: Role,
     /// DateTime Session is no longer valid
+    #[serde(with = "time::serde::timestamp")]
     pub expires: OffsetDateTime,
+    /// DateTime the user logged in
+    #[serde(with = "time::serde::timestamp")]
+    pub created: OffsetDateTime,
+    /// DateTime of the last request made with this Session
+    #[serde(with = "time::serde::timestamp")]
+    pub last_activity: OffsetDateTime,
+    /// IP address the user logged in from
+    pub source_ip: String,
+    /// Identifies the Session on the sessions page without revealing the
+    /// Session ID
+    pub handle: String,
+    /// Must accompany every request that changes something. Sessions saved
+    /// before tokens existed are given a new one when they are loaded.
+    #[serde(default = "new_csrf_token")]
+    pub csrf_token: String,
+}
+
+fn new_csrf_token() -> String {
+    nanoid!(CSRF_TOKEN_LENGTH)
+}
+
+/// A login that passed the password check and is waiting for a TOTP code.
+/// It is identified by a random token carried in a hidden form field.
+pub struct PendingLogin {
+    pub login: String,
+    pub role: Role,
+    /// Set when the user has to enroll in TOTP before they can log in, the
+    /// secret is kept so the enrollment form can be shown again after a
+    /// wrong code
+    pub enroll_secret: Option<String>,
+    expires: OffsetDateTime,
+    source_ip: IpAddr,
+    attempts: u8,
+}
+
+/// What a WebAuthn challenge was issued for
+#[derive(Debug, Clone, PartialEq)]
+pub enum Ceremony {
+    /// Adding a credential for the logged in user, with the user handle the
+    /// authenticator was given
+    Register { login: String, handle: String },
+    /// Logging in, for the user given on the login form if any
+    Login(Option<String>),
+}
+
+/// A WebAuthn challenge that hasn't been used yet
+pub struct Challenge {
+    pub ceremony: Ceremony,
+    expires: OffsetDateTime,
+    source_ip: IpAddr,
+}
+
+/// On-disk copy of the sessions. The file is opened before privileges are
+/// dropped and the handle is kept, as the UI process can't open files outside
+/// of its chroot afterwards.
+pub struct SessionStore {
+    file: Mutex<File>,
+    /// Set when sessions have changed in a way that doesn't need to be written
+    /// immediately (e.g. last activity), the next [sweep] writes them
+    dirty: AtomicBool,
+}
+
+impl SessionStore {
+    pub fn open(path: &str) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .mode(0o600)
+            .open(path)?;
+        Ok(Self { file: Mutex::new(file), dirty: AtomicBool::new(false) })
+    }
+
+    /// Read the unexpired sessions from the file
+    pub fn load(&self) -> io::Result<HashMap<String, Session>> {
+        let mut file = self.lock()?;
+        let mut contents = String::new();
+        file.rewind()?;
+        file.read_to_string(&mut contents)?;
+        if contents.trim().is_empty() {
+            return Ok(HashMap::new());
+        }
+        let mut sessions: HashMap<String, Session> =
+            serde_json::from_str(&contents)?;
+        let now = OffsetDateTime::now_utc();
+        sessions.retain(|_, session| session.expires > now);
+        Ok(sessions)
+    }
+
+    pub fn save(&self, sessions: &HashMap<String, Session>) -> io::Result<()> {
+        let contents = serde_json::to_vec(sessions)?;
+        let mut file = self.lock()?;
+        file.set_len(0)?;
+        file.rewind()?;
+        file.write_all(&contents)?;
+        file.sync_data()?;
+        self.dirty.store(false, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn lock(&self) -> io::Result<std::sync::MutexGuard<'_, File>> {
+        self.file
+            .lock()
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("{}", e)))
+    }
 }
 
-/// Extract the Session ID from the `Cookie` header (if any)
-fn get_id(ctx: &RequestContext<Context>) -> Option<&str> {
-    let mut id = None;
-    if let Some(cookie) = ctx.request.headers().get("Cookie") {
-        if let Ok(session_id) = cookie.to_str() {
-            id = Some(session_id);
+/// Write the sessions to the [SessionStore] (if any) now
+pub fn persist(ctx: &RequestContext<Context>) {
+    let Some(store) = &ctx.context().session_store else {
+        return;
+    };
+    if let Ok(sessions) = ctx.context().sessions.lock() {
+        if let Err(e) = store.save(&sessions) {
+            error!(ctx.log, "Failed to save sessions: {}", e);
         }
     }
-    id
+}
+
+/// Periodically remove expired sessions, writing the remaining ones to the
+/// [SessionStore] if they have changed
+pub async fn sweep(
+    sessions: Arc<Mutex<HashMap<String, Session>>>,
+    store: Option<Arc<SessionStore>>,
+    log: Logger,
+) {
+    let mut interval = tokio::time::interval(SWEEP_INTERVAL);
+    loop {
+        interval.tick().await;
+        let Ok(mut sessions) = sessions.lock() else {
+            continue;
+        };
+        let before = sessions.len();
+        let now = OffsetDateTime::now_utc();
+        sessions.retain(|_, session| session.expires > now);
+        let removed = before - sessions.len();
+        if removed > 0 {
+            info!(log, "Removed {} expired sessions", removed);
+        }
+        if let Some(store) = &store {
+            if removed > 0 || store.dirty.load(Ordering::Relaxed) {
+                if let Err(e) = store.save(&sessions) {
+                    error!(log, "Failed to save sessions: {}", e);
+                }
+            }
+        }
+    }
+}
+
+/// The user that owns the current request's [Session], passed to templates so
+/// they can hide actions the user isn't permitted to take
+#[derive(Clone, Debug)]
+pub struct CurrentUser {
+    pub login: String,
+    pub role: Role,
+    /// The [Session]'s CSRF token, for the layout's meta tag
+    pub csrf_token: String,
+}
+
+impl CurrentUser {
+    pub fn can(&self, permission: Permission) -> bool {
+        self.role.can(permission)
+    }
+}
+
+/// Find the value of the cookie called `name` in a `Cookie` header
+pub fn parse_cookie<'a>(header: &'a str, name: &str) -> Option<&'a str> {
+    header.split(';').find_map(|pair| {
+        let (key, value) = pair.split_once('=')?;
+        (key.trim() == name).then(|| value.trim().trim_matches('"'))
+    })
+}
+
+/// Extract the Session ID from the `Cookie` header(s) (if any). HTTP/2
+/// clients may send each cookie in its own header. Sessions are keyed by the
+/// whole `name=value` pair, as written to the `SESSION_FILE`.
+fn get_id(ctx: &RequestContext<Context>) -> Option<String> {
+    ctx.request
+        .headers()
+        .get_all("Cookie")
+        .iter()
+        .filter_map(|header| header.to_str().ok())
+        .find_map(|header| parse_cookie(header, COOKIE_NAME))
+        .filter(|id| !id.is_empty())
+        .map(|id| format!("{}={}", COOKIE_NAME, id))
+}
+
+/// Whether the request's method can change anything
+fn is_unsafe_method(ctx: &RequestContext<Context>) -> bool {
+    !matches!(
+        *ctx.request.method(),
+        Method::GET | Method::HEAD | Method::OPTIONS
+    )
 }
 
 /// Confirms the validity of a [Session] using the Session ID provided in
-/// the `Cookie` header of the current request.
+/// the `Cookie` header of the current request, and records the activity.
 pub fn is_valid(ctx: &RequestContext<Context>) -> bool {
     if let Some(id) = get_id(ctx) {
         if let Ok(mut sessions) = ctx.context().sessions.clone().lock() {
-            if let Some(session) = sessions.get(id) {
-                if OffsetDateTime::now_utc() < session.expires {
+            if let Some(session) = sessions.get_mut(&id) {
+                let now = OffsetDateTime::now_utc();
+                if now < session.expires {
+                    session.last_activity = now;
+                    if let Some(store) = &ctx.context().session_store {
+                        store.dirty.store(true, Ordering::Relaxed);
+                    }
                     return true;
                 } else {
-                    sessions.remove(id);
+                    sessions.remove(&id);
                 }
             }
         }
@@ -85,41 +314,277 @@ pub fn is_valid(ctx: &RequestContext<Context>) -> bool {
     false
 }
 
+/// Whether the current request's [Session] still exists and hasn't expired,
+/// without recording any activity. For long-lived responses, such as event
+/// streams, that should end when the session does.
+pub fn is_active(ctx: &RequestContext<Context>) -> bool {
+    let Some(id) = get_id(ctx) else {
+        return false;
+    };
+    let Ok(sessions) = ctx.context().sessions.lock() else {
+        return false;
+    };
+    sessions
+        .get(&id)
+        .is_some_and(|session| OffsetDateTime::now_utc() < session.expires)
+}
+
+/// Whether the current request was made with the [Session] identified by
+/// `handle`
+pub fn is_current(ctx: &RequestContext<Context>, handle: &str) -> bool {
+    let Some(id) = get_id(ctx) else {
+        return false;
+    };
+    let Ok(sessions) = ctx.context().sessions.lock() else {
+        return false;
+    };
+    sessions.get(&id).is_some_and(|session| session.handle == handle)
+}
+
+/// The [CurrentUser] for the current request, if it has a valid [Session].
+/// Requests that aren't a GET must carry the Session's CSRF token, they are
+/// refused otherwise.
+pub fn get_user(
+    ctx: &RequestContext<Context>,
+) -> Result<Option<CurrentUser>, HttpError> {
+    if !is_valid(ctx) {
+        return Ok(None);
+    }
+    let Some(id) = get_id(ctx) else {
+        return Ok(None);
+    };
+    let sessions = ctx
+        .context()
+        .sessions
+        .lock()
+        .map_err(|e| HttpError::for_internal_error(format!("{}", e)))?;
+    let Some(session) = sessions.get(&id) else {
+        return Ok(None);
+    };
+    if is_unsafe_method(ctx) {
+        let token = ctx
+            .request
+            .headers()
+            .get(CSRF_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default();
+        if !constant_time_eq(token.as_bytes(), session.csrf_token.as_bytes()) {
+            warn!(
+                ctx.log,
+                "Refused {} {} by {}: missing or invalid CSRF token",
+                ctx.request.method(),
+                ctx.request.uri().path(),
+                session.login
+            );
+            return Err(HttpError::for_client_error(
+                None,
+                StatusCode::FORBIDDEN,
+                String::from(
+                    "The request could not be verified, please reload the page",
+                ),
+            ));
+        }
+    }
+    Ok(Some(CurrentUser {
+        login: session.login.clone(),
+        role: session.role,
+        csrf_token: session.csrf_token.clone(),
+    }))
+}
+
+/// Remove the [Session] identified by `handle`. Only sessions for which
+/// `allowed` returns true can be removed. Returns the login of the removed
+/// Session's owner.
+pub fn revoke<F>(
+    ctx: &RequestContext<Context>,
+    handle: &str,
+    allowed: F,
+) -> Option<String>
+where
+    F: Fn(&Session) -> bool,
+{
+    let mut sessions = ctx.context().sessions.lock().ok()?;
+    let id = sessions
+        .iter()
+        .find(|(_, session)| session.handle == handle && allowed(session))
+        .map(|(id, _)| id.clone())?;
+    let session = sessions.remove(&id)?;
+    drop(sessions);
+    persist(ctx);
+    Some(session.login)
+}
+
+/// Record a [PendingLogin] for `login`, returning its token
+pub fn begin_pending(
+    ctx: &RequestContext<Context>,
+    login: String,
+    role: Role,
+    enroll_secret: Option<String>,
+) -> Result<String, HttpError> {
+    let mut pending = ctx
+        .context()
+        .pending_logins
+        .lock()
+        .map_err(|e| HttpError::for_internal_error(format!("{}", e)))?;
+    let now = OffsetDateTime::now_utc();
+    pending.retain(|_, p| p.expires > now);
+    let token = nanoid!(SESSION_ID_LENGTH);
+    pending.insert(
+        token.clone(),
+        PendingLogin {
+            login,
+            role,
+            enroll_secret,
+            expires: now + Duration::MINUTE * PENDING_LOGIN_MINUTES,
+            source_ip: ctx.request.remote_addr().ip(),
+            attempts: 0,
+        },
+    );
+    Ok(token)
+}
+
+/// Look up an unexpired [PendingLogin] started from the same address, and
+/// pass it to `f`. If `failed` is set the attempt is counted, and the pending
+/// login is dropped after too many.
+pub fn with_pending<T, F>(
+    ctx: &RequestContext<Context>,
+    token: &str,
+    failed: bool,
+    f: F,
+) -> Option<T>
+where
+    F: FnOnce(&PendingLogin) -> T,
+{
+    let mut pending = ctx.context().pending_logins.lock().ok()?;
+    let now = OffsetDateTime::now_utc();
+    let ip = ctx.request.remote_addr().ip();
+    let entry = pending.get_mut(token)?;
+    if entry.expires <= now || entry.source_ip != ip {
+        pending.remove(token);
+        return None;
+    }
+    let result = f(entry);
+    if failed {
+        entry.attempts += 1;
+        if entry.attempts >= PENDING_LOGIN_ATTEMPTS {
+            pending.remove(token);
+        }
+    }
+    Some(result)
+}
+
+/// Remove and return a [PendingLogin] once the second step has succeeded
+pub fn take_pending(
+    ctx: &RequestContext<Context>,
+    token: &str,
+) -> Option<PendingLogin> {
+    let mut pending = ctx.context().pending_logins.lock().ok()?;
+    let entry = pending.remove(token)?;
+    if entry.expires <= OffsetDateTime::now_utc()
+        || entry.source_ip != ctx.request.remote_addr().ip()
+    {
+        return None;
+    }
+    Some(entry)
+}
+
+/// Issue a WebAuthn challenge for `ceremony`. Challenges are base64url as
+/// the browser encodes them that way in its response; nanoid's alphabet is
+/// the base64url one, and 64 characters decode to exactly 48 bytes so the
+/// browser's encoding matches the original.
+pub fn begin_challenge(
+    ctx: &RequestContext<Context>,
+    ceremony: Ceremony,
+) -> Result<String, HttpError> {
+    let mut challenges = ctx
+        .context()
+        .webauthn_challenges
+        .lock()
+        .map_err(|e| HttpError::for_internal_error(format!("{}", e)))?;
+    let now = OffsetDateTime::now_utc();
+    challenges.retain(|_, c| c.expires > now);
+    let challenge = nanoid!(64);
+    challenges.insert(
+        challenge.clone(),
+        Challenge {
+            ceremony,
+            expires: now + Duration::MINUTE * CHALLENGE_MINUTES,
+            source_ip: ctx.request.remote_addr().ip(),
+        },
+    );
+    Ok(challenge)
+}
+
+/// Remove and return the [Ceremony] for an unexpired challenge issued to the
+/// same address. Challenges can only be used once, whether or not the
+/// response to them turns out to be valid.
+pub fn take_challenge(
+    ctx: &RequestContext<Context>,
+    challenge: &str,
+) -> Option<Ceremony> {
+    let mut challenges = ctx.context().webauthn_challenges.lock().ok()?;
+    let entry = challenges.remove(challenge)?;
+    if entry.expires <= OffsetDateTime::now_utc()
+        || entry.source_ip != ctx.request.remote_addr().ip()
+    {
+        return None;
+    }
+    Some(entry.ceremony)
+}
+
 /// Create a new [Session] and send back the appropriate `Set-Cookie` header
 /// in the response.
 pub fn create(
     ctx: &RequestContext<Context>,
     username: String,
+    role: Role,
 ) -> Result<Response<Body>, HttpError> {
-    let response = Response::builder();
+    let cookie = start(ctx, username, role)?;
+    Ok(Response::builder()
+        .status(StatusCode::SEE_OTHER)
+        .header("Set-Cookie", cookie)
+        .header("Location", "/dashboard")
+        .body(Body::empty())?)
+}
+
+/// Create a new [Session], returning the value for the `Set-Cookie` header
+pub fn start(
+    ctx: &RequestContext<Context>,
+    username: String,
+    role: Role,
+) -> Result<String, HttpError> {
     if let Ok(mut sessions) = ctx.context().sessions.clone().lock() {
         let session_id = nanoid!(SESSION_ID_LENGTH);
-        let expires =
-            OffsetDateTime::now_utc() + Duration::HOUR * SESSION_HOURS;
+        let now = OffsetDateTime::now_utc();
+        let expires = now + Duration::HOUR * ctx.context().config.session_hours;
 
         sessions.insert(
             format!("{}={}", COOKIE_NAME, &session_id),
-            Session { login: username.clone(), expires },
+            Session {
+                login: username.clone(),
+                role,
+                expires,
+                created: now,
+                last_activity: now,
+                source_ip: ctx.request.remote_addr().ip().to_string(),
+                handle: nanoid!(HANDLE_LENGTH),
+                csrf_token: new_csrf_token(),
+            },
         );
+        drop(sessions);
+        persist(ctx);
+
         let expires_formatted =
             expires.format(&Rfc2822).map_err(to_internal_error)?;
 
-        return Ok(response
-            .status(StatusCode::SEE_OTHER)
-            .header(
-                "Set-Cookie",
-                format!(
-                    "{}={}; {}; Expires={}",
-                    COOKIE_NAME, &session_id, COOKIE_ATTRS, expires_formatted
-                ),
-            )
-            .header("Location", "/dashboard")
-            .body(Body::empty())?);
+        return Ok(format!(
+            "{}={}; {}; Expires={}",
+            COOKIE_NAME, &session_id, COOKIE_ATTRS, expires_formatted
+        ));
     }
-    Ok(response
-        .status(StatusCode::INTERNAL_SERVER_ERROR)
-        .header("Location", "/login")
-        .body(Body::empty())?)
+    Err(HttpError::for_internal_error(String::from(
+        "Failed to acquire the sessions lock",
+    )))
 }
 
 /// Remove the [Session] from the in-memory [std::collections::HashMap] of
@@ -131,7 +596,9 @@ pub fn destroy(
     let mut response = http_response_temporary_redirect(login_path)?;
     if let Some(session_id) = get_id(ctx) {
         if let Ok(mut sessions) = ctx.context().sessions.clone().lock() {
-            if sessions.remove(session_id).is_some() {
+            if sessions.remove(&session_id).is_some() {
+                drop(sessions);
+                persist(ctx);
                 let headers = response.headers_mut();
                 let cookie = HeaderValue::from_str(&format!(
                     "{}; {}; Max-Age=0;",
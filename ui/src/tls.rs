@@ -0,0 +1,362 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+//! The HTTPS server's certificate. A self-signed certificate is generated on
+//! first start, and admins can replace it from the UI without restarting the
+//! service: the new certificate is checked here, written out by the executor
+//! (the UI can't write to `/usbkey` once it has dropped privileges), and
+//! handed to the running server through [Tls::replace].
+
+use std::ffi::CStr;
+use std::fs::{self, DirBuilder, OpenOptions};
+use std::io::{self, Write};
+use std::net::IpAddr;
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+use std::path::Path;
+use std::sync::Mutex;
+
+use dropshot::{ConfigTls, RawTlsConfig};
+use openssl::asn1::Asn1Time;
+use openssl::bn::{BigNum, MsbOption};
+use openssl::error::ErrorStack;
+use openssl::hash::MessageDigest;
+use openssl::nid::Nid;
+use openssl::pkey::PKey;
+use openssl::rsa::Rsa;
+use openssl::x509::extension::{
+    BasicConstraints, ExtendedKeyUsage, KeyUsage, SubjectAlternativeName,
+};
+use openssl::x509::{X509NameBuilder, X509NameRef, X509};
+use slog::{info, warn, Logger};
+use tokio::sync::mpsc::UnboundedSender;
+
+/// How long a generated certificate is valid for. Some clients refuse
+/// certificates valid for longer than 825 days, even ones the user trusts.
+const SELF_SIGNED_DAYS: u32 = 825;
+
+const SELF_SIGNED_KEY_BITS: u32 = 2048;
+
+/// A certificate chain and key that the HTTPS server will accept, with the key
+/// converted to PKCS #8 which is the only format Dropshot reads
+pub struct KeyPair {
+    pub cert: String,
+    pub key: String,
+}
+
+impl KeyPair {
+    /// Check the key belongs to the leaf certificate and that the server will
+    /// accept the pair. Expiry isn't checked so the UI still starts with an
+    /// expired certificate, allowing it to be replaced.
+    pub fn parse(cert: &str, key: &str) -> Result<Self, String> {
+        let chain = X509::stack_from_pem(cert.as_bytes())
+            .map_err(|_| String::from("The certificate could not be read"))?;
+        let Some(leaf) = chain.first() else {
+            return Err(String::from("No certificate was found"));
+        };
+        let key = PKey::private_key_from_pem(key.as_bytes()).map_err(|_| {
+            String::from(
+                "The key could not be read, it must be an unencrypted PEM \
+                private key",
+            )
+        })?;
+        let matches = leaf
+            .public_key()
+            .map(|public| public.public_eq(&key))
+            .unwrap_or(false);
+        if !matches {
+            return Err(String::from(
+                "The key does not belong to the certificate",
+            ));
+        }
+
+        let mut pair = Self { cert: String::new(), key: String::new() };
+        for cert in &chain {
+            pair.cert.push_str(&pem_string(cert.to_pem())?);
+        }
+        pair.key = pem_string(key.private_key_to_pem_pkcs8())?;
+        pair.server_config()?;
+        Ok(pair)
+    }
+
+    /// Generate a self-signed certificate for `hostname` and, if given, the
+    /// address the server is listening on
+    pub fn self_signed(
+        hostname: &str,
+        ip: Option<IpAddr>,
+    ) -> Result<Self, ErrorStack> {
+        let key = PKey::from_rsa(Rsa::generate(SELF_SIGNED_KEY_BITS)?)?;
+
+        let mut name = X509NameBuilder::new()?;
+        name.append_entry_by_nid(Nid::COMMONNAME, hostname)?;
+        let name = name.build();
+
+        let mut serial = BigNum::new()?;
+        serial.rand(128, MsbOption::MAYBE_ZERO, false)?;
+
+        let mut builder = X509::builder()?;
+        builder.set_version(2)?;
+        let serial = serial.to_asn1_integer()?;
+        builder.set_serial_number(&serial)?;
+        builder.set_subject_name(&name)?;
+        builder.set_issuer_name(&name)?;
+        builder.set_pubkey(&key)?;
+        let not_before = Asn1Time::days_from_now(0)?;
+        let not_after = Asn1Time::days_from_now(SELF_SIGNED_DAYS)?;
+        builder.set_not_before(&not_before)?;
+        builder.set_not_after(&not_after)?;
+        builder
+            .append_extension(BasicConstraints::new().critical().build()?)?;
+        builder.append_extension(
+            KeyUsage::new()
+                .critical()
+                .digital_signature()
+                .key_encipherment()
+                .build()?,
+        )?;
+        builder
+            .append_extension(ExtendedKeyUsage::new().server_auth().build()?)?;
+        let mut names = SubjectAlternativeName::new();
+        names.dns(hostname);
+        if let Some(ip) = ip {
+            names.ip(&ip.to_string());
+        }
+        let names = names.build(&builder.x509v3_context(None, None))?;
+        builder.append_extension(names)?;
+        builder.sign(&key, MessageDigest::sha256())?;
+        let cert = builder.build();
+
+        Ok(Self {
+            cert: String::from_utf8_lossy(&cert.to_pem()?).into_owned(),
+            key: String::from_utf8_lossy(&key.private_key_to_pem_pkcs8()?)
+                .into_owned(),
+        })
+    }
+
+    /// The configuration for Dropshot, built here rather than by Dropshot so
+    /// a bad pair is an error rather than a panic in
+    /// [dropshot::HttpServer::refresh_tls]
+    pub fn server_config(&self) -> Result<ConfigTls, String> {
+        let config = ConfigTls::AsBytes {
+            certs: self.cert.clone().into_bytes(),
+            key: self.key.clone().into_bytes(),
+        };
+        RawTlsConfig::try_from(&config)
+            .map(ConfigTls::Dynamic)
+            .map_err(|e| e.to_string())
+    }
+}
+
+fn pem_string(pem: Result<Vec<u8>, ErrorStack>) -> Result<String, String> {
+    pem.map(|pem| String::from_utf8_lossy(&pem).into_owned())
+        .map_err(|e| e.to_string())
+}
+
+/// Details of a certificate for the certificate page
+pub struct CertificateInfo {
+    pub subject: String,
+    pub issuer: String,
+    /// DNS names and IP addresses the certificate is valid for
+    pub names: Vec<String>,
+    pub serial: String,
+    pub not_before: String,
+    pub not_after: String,
+    /// Negative once the certificate has expired
+    pub days_remaining: i32,
+    /// SHA-256 of the certificate, as colon separated hex
+    pub fingerprint: String,
+    pub self_signed: bool,
+    /// Number of intermediate certificates sent with it
+    pub intermediates: usize,
+}
+
+fn format_name(name: &X509NameRef) -> String {
+    name.entries()
+        .map(|entry| {
+            let field = entry.object().nid().short_name().unwrap_or("?");
+            let value = entry
+                .data()
+                .as_utf8()
+                .map(|value| value.to_string())
+                .unwrap_or_default();
+            format!("{}={}", field, value)
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl CertificateInfo {
+    pub fn parse(cert: &str) -> Result<Self, String> {
+        let chain =
+            X509::stack_from_pem(cert.as_bytes()).map_err(|e| e.to_string())?;
+        let Some(leaf) = chain.first() else {
+            return Err(String::from("No certificate was found"));
+        };
+        Self::describe(leaf, chain.len() - 1).map_err(|e| e.to_string())
+    }
+
+    fn describe(leaf: &X509, intermediates: usize) -> Result<Self, ErrorStack> {
+        let names = leaf
+            .subject_alt_names()
+            .map(|names| {
+                names
+                    .iter()
+                    .filter_map(|name| {
+                        if let Some(dns) = name.dnsname() {
+                            return Some(String::from(dns));
+                        }
+                        match name.ipaddress()? {
+                            [a, b, c, d] => {
+                                Some(IpAddr::from([*a, *b, *c, *d]).to_string())
+                            }
+                            bytes => <[u8; 16]>::try_from(bytes)
+                                .ok()
+                                .map(|ip| IpAddr::from(ip).to_string()),
+                        }
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let days_remaining =
+            Asn1Time::days_from_now(0)?.diff(leaf.not_after())?.days;
+        let fingerprint = leaf
+            .digest(MessageDigest::sha256())?
+            .iter()
+            .map(|byte| format!("{:02X}", byte))
+            .collect::<Vec<_>>()
+            .join(":");
+
+        Ok(Self {
+            subject: format_name(leaf.subject_name()),
+            issuer: format_name(leaf.issuer_name()),
+            names,
+            serial: leaf.serial_number().to_bn()?.to_hex_str()?.to_string(),
+            not_before: leaf.not_before().to_string(),
+            not_after: leaf.not_after().to_string(),
+            days_remaining,
+            fingerprint,
+            self_signed: leaf
+                .public_key()
+                .and_then(|key| leaf.verify(&key))
+                .unwrap_or(false),
+            intermediates,
+        })
+    }
+}
+
+/// The host's name, for the certificate generated on first start
+pub fn hostname() -> Option<String> {
+    let mut buf = [0u8; 256];
+    // SAFETY: the buffer is valid for its length, and gethostname leaves it
+    // NUL terminated when the name fits (the last byte is never written)
+    let result =
+        unsafe { libc::gethostname(buf.as_mut_ptr().cast(), buf.len() - 1) };
+    if result != 0 {
+        return None;
+    }
+    CStr::from_bytes_until_nul(&buf)
+        .ok()
+        .and_then(|name| name.to_str().ok())
+        .filter(|name| !name.is_empty())
+        .map(String::from)
+}
+
+fn write_file(path: &Path, contents: &str, mode: u32) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        DirBuilder::new().recursive(true).mode(0o755).create(dir)?;
+    }
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(mode)
+        .open(path)?;
+    file.write_all(contents.as_bytes())
+}
+
+/// Read the certificate and key, first generating a self-signed pair if
+/// either file is missing. This must run before privileges are dropped.
+pub fn load_or_generate(
+    cert_file: &str,
+    key_file: &str,
+    ip: Option<IpAddr>,
+    log: &Logger,
+) -> Result<KeyPair, String> {
+    let cert_path = Path::new(cert_file);
+    let key_path = Path::new(key_file);
+
+    if !cert_path.exists() || !key_path.exists() {
+        let hostname = hostname().unwrap_or_else(|| String::from("smartos"));
+        info!(
+            log,
+            "Generating a self-signed TLS certificate at {}", cert_file;
+            "hostname" => &hostname,
+            "ip" => ip.map(|ip| ip.to_string()),
+        );
+        let pair = KeyPair::self_signed(&hostname, ip).map_err(|e| {
+            format!("Failed to generate a TLS certificate: {}", e)
+        })?;
+        write_file(key_path, &pair.key, 0o600).map_err(|e| {
+            format!("Failed writing TLS key to {}: {}", key_file, e)
+        })?;
+        write_file(cert_path, &pair.cert, 0o644).map_err(|e| {
+            format!("Failed writing TLS certificate to {}: {}", cert_file, e)
+        })?;
+        return Ok(pair);
+    }
+
+    let cert = fs::read_to_string(cert_path).map_err(|e| {
+        format!("Failed reading TLS certificate at {}: {}", cert_file, e)
+    })?;
+    let key = fs::read_to_string(key_path).map_err(|e| {
+        format!("Failed reading TLS key at {}: {}", key_file, e)
+    })?;
+    let pair = KeyPair::parse(&cert, &key).map_err(|e| {
+        format!(
+            "Invalid TLS certificate {} or key {}: {}",
+            cert_file, key_file, e
+        )
+    })?;
+    if let Ok(info) = CertificateInfo::parse(&pair.cert) {
+        if info.days_remaining < 0 {
+            warn!(log, "The TLS certificate expired on {}", info.not_after);
+        }
+    }
+    Ok(pair)
+}
+
+/// The running server's certificate, and the way to replace it
+pub struct Tls {
+    cert: Mutex<String>,
+    reload: UnboundedSender<ConfigTls>,
+}
+
+impl Tls {
+    /// `reload` is received by the task that owns the HTTPS server
+    pub fn new(cert: String, reload: UnboundedSender<ConfigTls>) -> Self {
+        Self { cert: Mutex::new(cert), reload }
+    }
+
+    /// The PEM certificate chain being served
+    pub fn cert(&self) -> String {
+        self.cert.lock().map(|cert| cert.clone()).unwrap_or_default()
+    }
+
+    /// Start serving `pair` to new connections
+    pub fn replace(&self, pair: &KeyPair) -> Result<(), String> {
+        let config = pair.server_config()?;
+        self.reload
+            .send(config)
+            .map_err(|_| String::from("The HTTPS server is not running"))?;
+        if let Ok(mut cert) = self.cert.lock() {
+            cert.clone_from(&pair.cert);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,52 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+use smartos_shared::auth::Permission;
+use smartos_ui::endpoints::bulk::{
+    confirmation_phrase, failure_message, parse_ids, BulkAction,
+};
+
+use hyper::StatusCode;
+use uuid::Uuid;
+
+#[test]
+fn test_parse_ids() {
+    let a = Uuid::new_v4();
+    let b = Uuid::new_v4();
+    let ids = format!("{}, {},,{}", a, b, a);
+    assert_eq!(parse_ids(&ids).unwrap(), [a, b]);
+    assert!(parse_ids("").unwrap().is_empty());
+    assert!(parse_ids(&format!("{},web01", a)).is_err());
+}
+
+#[test]
+fn test_confirmation() {
+    assert_eq!(confirmation_phrase(1), "delete 1 instance");
+    assert_eq!(confirmation_phrase(20), "delete 20 instances");
+    assert_eq!(BulkAction::Reboot.permission(), Permission::PowerControl);
+    assert_eq!(BulkAction::Delete.permission(), Permission::Delete);
+}
+
+#[test]
+fn test_failure_message() {
+    let body = r#"{
+        "request_id": "1",
+        "error_code": "Internal",
+        "message": "Unable to start VM: VM is already running\n"
+    }"#;
+    assert_eq!(
+        failure_message(StatusCode::INTERNAL_SERVER_ERROR, body),
+        "Unable to start VM: VM is already running"
+    );
+    assert_eq!(
+        failure_message(StatusCode::BAD_GATEWAY, "<html>"),
+        "502 Bad Gateway"
+    );
+}
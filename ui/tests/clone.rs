@@ -0,0 +1,169 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+use smartos_ui::clone::from_instance;
+use smartos_ui::provision_form::{Merge, ProvisionBody};
+
+use serde_json::json;
+
+#[test]
+fn test_from_instance() {
+    let vm = json!({
+        "uuid": "aaaaaaaa-aaaa-aaaa-aaaa-aaaaaaaaaaaa",
+        "alias": "web01",
+        "hostname": "web01",
+        "brand": "joyent",
+        "image_uuid": "11111111-1111-1111-1111-111111111111",
+        "max_physical_memory": 512,
+        "tmpfs": 512,
+        "quota": 10,
+        "state": "running",
+        "zonepath": "/zones/aaaaaaaa-aaaa-aaaa-aaaa-aaaaaaaaaaaa",
+        "pid": 6170,
+        "create_timestamp": "2026-10-19T00:45:49.810Z",
+        "resolvers": ["1.1.1.1", "8.8.8.8"],
+        "datasets": ["zones/aaaaaaaa-aaaa-aaaa-aaaa-aaaaaaaaaaaa/data"],
+        "nics": [{
+            "nic_tag": "internal",
+            "mac": "02:08:20:aa:bb:cd",
+            "vlan_id": 12,
+            "ips": ["dhcp"],
+            "allowed_ips": ["10.1.0.0/24"]
+        }, {
+            "nic_tag": "admin",
+            "mac": "02:08:20:aa:bb:cc",
+            "ips": ["10.0.0.5/24", "addrconf"],
+            "gateways": ["10.0.0.1"],
+            "primary": true
+        }],
+        "customer_metadata": {
+            "user-script": "#!/bin/sh",
+            "role": "web"
+        },
+        "internal_metadata": { "root_pw": "secret" },
+        "cpu_shares": 100,
+        "tags": { "env": "prod" }
+    });
+
+    let cloned = from_instance(&vm, false);
+    let query = &cloned.query;
+    assert_eq!(query.alias, "web01-clone");
+    assert_eq!(query.brand, "joyent");
+    assert_eq!(query.image_uuid, "11111111-1111-1111-1111-111111111111");
+    assert_eq!(query.ram, "512");
+    assert_eq!(query.quota, "10");
+    assert_eq!(query.resolvers, "1.1.1.1,8.8.8.8");
+    assert_eq!(query.delegate_dataset, "on");
+    assert_eq!(query.user_script, "#!/bin/sh");
+    assert_eq!(query.tags, "env=prod");
+    assert_eq!(query.nics.nics.len(), 2);
+    assert_eq!(query.nics.primary, 1);
+    let internal = &query.nics.nics[0];
+    assert_eq!(internal.tag, "internal");
+    assert_eq!(internal.vlan_id, "12");
+    assert_eq!(internal.ipv4_setup, "auto");
+    assert_eq!(internal.allowed_ips, "10.1.0.0/24");
+    let admin = &query.nics.nics[1];
+    assert_eq!(admin.tag, "admin");
+    assert_eq!(admin.ipv4_setup, "static");
+    assert_eq!(admin.ipv4_ip, "");
+    assert_eq!(admin.ipv4_prefix, "24");
+    assert_eq!(admin.ipv4_gateway, "10.0.0.1");
+    assert_eq!(admin.ipv6_setup, "auto");
+
+    // Only what the form can't set is left, without identity, runtime
+    // state or secrets
+    assert_eq!(
+        serde_json::Value::Object(cloned.additional),
+        json!({
+            "cpu_shares": 100,
+            "customer_metadata": { "role": "web" }
+        })
+    );
+
+    assert_eq!(from_instance(&vm, true).query.nics.nics[1].ipv4_ip, "10.0.0.5");
+}
+
+#[test]
+fn test_from_hvm_instance() {
+    let vm = json!({
+        "brand": "bhyve",
+        "ram": 2048,
+        "vcpus": 2,
+        "disks": [
+            { "size": 51200 },
+            {
+                "image_uuid": "22222222-2222-2222-2222-222222222222",
+                "boot": true,
+                "size": 20480
+            }
+        ],
+        // Older vmadm NICs
+        "nics": [{ "nic_tag": "external", "ip": "dhcp" }]
+    });
+
+    let query = from_instance(&vm, false).query;
+    assert_eq!(query.alias, "");
+    assert_eq!(query.image_uuid, "22222222-2222-2222-2222-222222222222");
+    assert_eq!(query.ram, "2048");
+    assert_eq!(query.vcpus, "2");
+    assert_eq!(query.primary_disk_size, 20);
+    assert_eq!(query.nics.nics[0].ipv4_setup, "auto");
+}
+
+#[test]
+fn test_from_instance_with_data_disk() {
+    let vm = json!({
+        "alias": "db01",
+        "brand": "bhyve",
+        "ram": 4096,
+        "vcpus": 2,
+        "disks": [
+            {
+                "image_uuid": "22222222-2222-2222-2222-222222222222",
+                "boot": true,
+                "size": 20480,
+                "model": "virtio",
+                "path": "/dev/zvol/rdsk/zones/cccccccc/disk0"
+            },
+            {
+                "size": 51200,
+                "model": "nvme",
+                "path": "/dev/zvol/rdsk/zones/cccccccc/disk1",
+                "zfs_filesystem": "zones/cccccccc/disk1"
+            }
+        ],
+        "nics": []
+    });
+
+    // The data disk is carried empty, without what belongs to the original
+    let cloned = from_instance(&vm, false);
+    assert_eq!(cloned.query.primary_disk_size, 20);
+    assert_eq!(
+        serde_json::Value::Object(cloned.additional.clone()),
+        json!({ "disks": [{ "size": 51200, "model": "nvme" }] })
+    );
+
+    // And provisioned after the boot disk, with room for both
+    let body = ProvisionBody {
+        form: cloned.query,
+        additional: serde_json::Value::Object(cloned.additional).to_string(),
+        merge: Merge::Extend,
+        overcommit: false,
+    };
+    let payload = body.payload().unwrap();
+    assert_eq!(payload.disks.len(), 2);
+    assert_eq!(payload.disks[0].boot, Some(true));
+    assert_eq!(payload.disks[0].size, Some(20480));
+    assert_eq!(payload.disks[1].boot, None);
+    assert_eq!(payload.disks[1].size, Some(51200));
+    assert_eq!(payload.disks[1].model.as_deref(), Some("nvme"));
+    assert_eq!(payload.extra.get("flexible_disk_size"), Some(&json!(71680)));
+}
@@ -0,0 +1,138 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+use smartos_shared::instance::{Brand, InstanceView, Tags};
+use smartos_ui::instance_list::{
+    Direction, InstanceListParams, SortColumn, DEFAULT_LIMIT,
+};
+
+use serde_json::json;
+use uuid::Uuid;
+
+fn instance(alias: &str, brand: Brand, state: &str, ram: u64) -> InstanceView {
+    InstanceView {
+        uuid: Uuid::new_v4(),
+        alias: String::from(alias),
+        brand,
+        state: String::from(state),
+        ram,
+        disk_usage: 0,
+        hvm: false,
+        image_uuid: Uuid::nil(),
+        cpu: 0.0,
+        primary_ip: Some(format!("10.0.0.{}", ram / 256)),
+        tags: Tags::new(),
+        partial: false,
+    }
+}
+
+fn instances() -> Vec<(InstanceView, String)> {
+    vec![
+        (instance("web", Brand::Joyent, "running", 512), "base-64@1".into()),
+        (instance("db", Brand::Bhyve, "stopped", 4096), "ubuntu@24".into()),
+        (instance("cache", Brand::Joyent, "running", 256), "base-64@1".into()),
+    ]
+}
+
+fn aliases(params: &InstanceListParams) -> Vec<String> {
+    let page = params.apply(instances());
+    page.instances.into_iter().map(|(i, _)| i.alias).collect()
+}
+
+#[test]
+fn test_apply() {
+    let mut params = InstanceListParams::default();
+    assert_eq!(aliases(&params), ["cache", "db", "web"]);
+
+    params.sort = Some(SortColumn::Ram);
+    params.dir = Some(Direction::Desc);
+    assert_eq!(aliases(&params), ["db", "web", "cache"]);
+
+    params.brand = Some(Brand::Joyent);
+    assert_eq!(aliases(&params), ["web", "cache"]);
+
+    params.brand = None;
+    params.search = Some(String::from("UBUNTU"));
+    assert_eq!(aliases(&params), ["db"]);
+    params.search = Some(String::from("10.0.0.2"));
+    assert_eq!(aliases(&params), ["web"]);
+
+    params.search = None;
+    params.state = Some(String::from("running"));
+    params.limit = Some(1);
+    params.page = Some(2);
+    let page = params.apply(instances());
+    assert_eq!((page.matched, page.page, page.pages, page.first), (2, 2, 2, 2));
+    assert_eq!(page.instances[0].0.alias, "cache");
+
+    // Past the end is the last page
+    params.page = Some(10);
+    assert_eq!(params.apply(instances()).page, 2);
+}
+
+#[test]
+fn test_query() {
+    // Forms send empty fields
+    let params: InstanceListParams = serde_json::from_value(json!({
+        "search": "web 01",
+        "brand": "",
+        "state": "running",
+        "sort": "ram",
+        "dir": "",
+        "limit": "",
+        "page": "3",
+    }))
+    .unwrap();
+    assert_eq!(params.brand, None);
+    assert_eq!(params.limit(), DEFAULT_LIMIT);
+    assert_eq!(
+        params.location(),
+        "/instances?search=web+01&state=running&sort=ram&page=3"
+    );
+
+    // Sorting by the same column reverses it, from the first page
+    assert_eq!(
+        params.sorted_by(&SortColumn::Ram),
+        "/instances?search=web+01&state=running&sort=ram&dir=desc"
+    );
+    assert_eq!(
+        params.at_page(&1),
+        "/instances?search=web+01&state=running&sort=ram"
+    );
+    assert_eq!(InstanceListParams::default().location(), "/instances");
+}
+
+#[test]
+fn test_tag_filter() {
+    let tagged = |alias: &str, tags: serde_json::Value| {
+        let mut view = instance(alias, Brand::Joyent, "running", 512);
+        view.tags = serde_json::from_value(tags).unwrap();
+        (view, String::new())
+    };
+    let instances = vec![
+        tagged("web", json!({ "env": "prod", "replicas": 3 })),
+        tagged("db", json!({ "env": "dev", "backup": true })),
+        tagged("cache", json!({})),
+    ];
+    let aliases = |tag: &str| -> Vec<String> {
+        let params = InstanceListParams {
+            tag: Some(String::from(tag)),
+            ..Default::default()
+        };
+        let page = params.apply(instances.clone());
+        page.instances.into_iter().map(|(i, _)| i.alias).collect()
+    };
+
+    assert_eq!(aliases("env"), ["db", "web"]);
+    assert_eq!(aliases("env=prod"), ["web"]);
+    assert_eq!(aliases("replicas=3"), ["web"]);
+    assert_eq!(aliases("backup = true"), ["db"]);
+    assert!(aliases("owner").is_empty());
+}
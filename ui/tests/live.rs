@@ -0,0 +1,87 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+use smartos_shared::instance::{Brand, InstanceView, Tags};
+use smartos_ui::clients::VMInfodEvent;
+use smartos_ui::live::{Change, InstanceModel};
+
+use uuid::Uuid;
+
+fn view(alias: &str, state: &str) -> InstanceView {
+    InstanceView {
+        uuid: Uuid::new_v4(),
+        alias: String::from(alias),
+        brand: Brand::Joyent,
+        state: String::from(state),
+        ram: 256,
+        disk_usage: 0,
+        hvm: false,
+        image_uuid: Uuid::nil(),
+        cpu: 0.0,
+        primary_ip: None,
+        tags: Tags::new(),
+        partial: false,
+    }
+}
+
+#[test]
+fn test_model() {
+    let model = InstanceModel::new();
+    let mut changes = model.subscribe();
+    let web = view("web", "running");
+    let db = view("db", "stopped");
+
+    // Nothing is applied until vminfod is ready
+    model.update(web.clone());
+    assert!(model.instances().is_none());
+
+    model.reset(vec![web.clone(), db.clone()]);
+    assert_eq!(model.instances().unwrap().len(), 2);
+    assert_eq!(changes.try_recv().unwrap(), Change::Updated(web.clone()));
+    assert_eq!(changes.try_recv().unwrap(), Change::Updated(db.clone()));
+
+    // Only changes are broadcast
+    model.update(web.clone());
+    assert!(changes.try_recv().is_err());
+    let stopped = InstanceView { state: String::from("stopped"), ..web };
+    model.update(stopped.clone());
+    assert_eq!(changes.try_recv().unwrap(), Change::Updated(stopped.clone()));
+
+    // After reconnecting, only what changed meanwhile is broadcast
+    model.disconnected();
+    assert!(model.instances().is_none());
+    let cache = view("cache", "provisioning");
+    model.reset(vec![stopped, cache.clone()]);
+    assert_eq!(changes.try_recv().unwrap(), Change::Updated(cache.clone()));
+    assert_eq!(changes.try_recv().unwrap(), Change::Deleted(db.uuid));
+    assert!(changes.try_recv().is_err());
+
+    model.delete(&cache.uuid);
+    model.delete(&cache.uuid);
+    assert_eq!(changes.try_recv().unwrap(), Change::Deleted(cache.uuid));
+    assert!(changes.try_recv().is_err());
+    assert_eq!(model.instances().unwrap().len(), 1);
+}
+
+#[test]
+fn test_events() {
+    let event: VMInfodEvent =
+        serde_json::from_str(r#"{"type": "ready", "date": "now"}"#).unwrap();
+    assert!(matches!(event, VMInfodEvent::Ready { vms: None }));
+
+    let uuid = Uuid::new_v4();
+    let json = format!(r#"{{"type": "delete", "uuid": "{}"}}"#, uuid);
+    let event: VMInfodEvent = serde_json::from_str(&json).unwrap();
+    assert!(matches!(event, VMInfodEvent::Delete { uuid: u } if u == uuid));
+
+    let event: VMInfodEvent =
+        serde_json::from_str(r#"{"type": "heartbeat"}"#).unwrap();
+    assert!(matches!(event, VMInfodEvent::Other));
+}
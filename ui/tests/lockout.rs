@@ -0,0 +1,98 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+use std::time::{Duration, Instant};
+
+use smartos_ui::lockout::{format_duration, Lockouts, Policy, Subject};
+
+const POLICY: Policy = Policy {
+    max_failures: 3,
+    base: Duration::from_secs(30),
+    max: Duration::from_secs(300),
+};
+
+#[test]
+fn test_policy() {
+    assert_eq!(POLICY.lockout(2), None);
+    assert_eq!(POLICY.lockout(3), Some(Duration::from_secs(30)));
+    assert_eq!(POLICY.lockout(4), Some(Duration::from_secs(60)));
+    assert_eq!(POLICY.lockout(5), Some(Duration::from_secs(120)));
+    assert_eq!(POLICY.lockout(7), Some(Duration::from_secs(300)));
+    assert_eq!(POLICY.lockout(u32::MAX), Some(Duration::from_secs(300)));
+    let disabled = Policy { max_failures: 0, ..POLICY };
+    assert_eq!(disabled.lockout(100), None);
+}
+
+#[test]
+fn test_lockouts() {
+    let mut lockouts = Lockouts::new(POLICY);
+    let user = Subject::User(String::from("root"));
+    let ip = Subject::Ip("192.0.2.1".parse().unwrap());
+    let now = Instant::now();
+
+    assert_eq!(lockouts.record_failure(user.clone(), now), None);
+    assert_eq!(lockouts.record_failure(user.clone(), now), None);
+    assert_eq!(lockouts.remaining(&user, now), None);
+    assert_eq!(
+        lockouts.record_failure(user.clone(), now),
+        Some(Duration::from_secs(30))
+    );
+    assert_eq!(lockouts.remaining(&user, now), Some(Duration::from_secs(30)));
+    assert_eq!(lockouts.remaining(&ip, now), None);
+
+    let later = now + Duration::from_secs(31);
+    assert_eq!(lockouts.remaining(&user, later), None);
+    assert_eq!(
+        lockouts.record_failure(user.clone(), later),
+        Some(Duration::from_secs(60))
+    );
+
+    // Forgotten once the maximum lockout has passed without a failure
+    lockouts.record_failure(ip.clone(), now);
+    let list = lockouts.list(now + Duration::from_secs(301));
+    assert_eq!(list.len(), 1);
+    assert_eq!(list[0].subject, user);
+
+    assert!(lockouts.clear(&user));
+    assert!(!lockouts.clear(&user));
+    assert_eq!(lockouts.remaining(&user, later), None);
+}
+
+#[test]
+fn test_endless_lockout() {
+    let policy =
+        Policy { max_failures: 1, base: Duration::MAX, max: Duration::MAX };
+    let mut lockouts = Lockouts::new(policy);
+    let user = Subject::User(String::from("root"));
+    let now = Instant::now();
+
+    // A lockout too long to add to the clock still locks the user out
+    assert_eq!(lockouts.record_failure(user.clone(), now), Some(Duration::MAX));
+    assert!(lockouts.remaining(&user, now).is_some());
+}
+
+#[test]
+fn test_subject() {
+    for subject in ["user:root", "ip:192.0.2.1", "ip:2001:db8::1"] {
+        assert_eq!(subject.parse::<Subject>().unwrap().to_string(), subject);
+    }
+    assert!("ip:nope".parse::<Subject>().is_err());
+    assert!("root".parse::<Subject>().is_err());
+}
+
+#[test]
+fn test_format_duration() {
+    assert_eq!(format_duration(Duration::from_millis(200)), "1 second");
+    assert_eq!(
+        format_duration(Duration::from_secs(250)),
+        "4 minutes 10 seconds"
+    );
+    assert_eq!(format_duration(Duration::from_secs(3600)), "1 hour");
+}
@@ -0,0 +1,44 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+use smartos_ui::nic_form::{from_query, NicRows};
+
+#[test]
+fn test_nic_rows_from_query() {
+    // A form that hasn't been filled in has one empty NIC
+    assert_eq!(from_query("alias=web01"), NicRows::default());
+
+    let rows = from_query(
+        "nic_count=3&primary_nic=2&nic0_tag=admin&nic1_tag=internal\
+        &nic1_vlan_id=12&nic2_tag=external&nic2_allowed_ips=10.1.0.0%2F24\
+        &remove_nic=1",
+    );
+    let tags: Vec<_> = rows.nics.iter().map(|nic| nic.tag.as_str()).collect();
+    assert_eq!(tags, ["admin", "external"]);
+    // The primary NIC moved up with the removal
+    assert_eq!(rows.primary, 1);
+    assert_eq!(rows.nics[1].allowed_ips, "10.1.0.0/24");
+
+    // What's carried comes back as the same NICs
+    let query: Vec<String> = rows
+        .carried()
+        .into_iter()
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect();
+    assert_eq!(from_query(&query.join("&")), rows);
+
+    let rows = from_query("nic_count=1&nic0_tag=admin&add_nic=true");
+    assert_eq!(rows.nics.len(), 2);
+    assert_eq!(rows.nics[1].tag, "");
+
+    // Inputs for a NIC past the last one there can be are left out
+    let rows = from_query("nic18446744073709551615_tag=admin");
+    assert_eq!(rows, NicRows::default());
+}
@@ -0,0 +1,122 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+use smartos_ui::provision_form::{check, ProvisionBody};
+
+use serde_json::json;
+
+const IMAGE: &str = "22222222-2222-2222-2222-222222222222";
+
+#[test]
+fn test_bhyve_payload() {
+    let body = format!(
+        "alias=vm01&brand=bhyve&image_uuid={}&ram=2048&vcpus=2&cpu_cap=\
+        &primary_disk_size=20&bootrom=uefi&tags=env%3Dprod%2C+replicas%3D0x10\
+        &root_authorized_keys=ssh-ed25519+AAAA&nic_count=2&primary_nic=1\
+        &nic0_tag=internal&nic0_ipv4_setup=static&nic0_ipv4_ip=10.0.0.5\
+        &nic0_ipv4_prefix=24&nic0_ipv4_gateway=10.0.0.1&nic0_vlan_id=5\
+        &nic1_tag=admin&nic1_ipv4_setup=auto&nic1_ipv6_setup=auto\
+        &nic1_model=e1000&merge=extend&overcommit=true",
+        IMAGE
+    );
+    let body = ProvisionBody::parse(&body).unwrap();
+    assert!(body.overcommit);
+    let payload = check(body.payload().unwrap()).unwrap();
+
+    assert_eq!(
+        serde_json::to_value(payload).unwrap(),
+        json!({
+            "brand": "bhyve",
+            "alias": "vm01",
+            "hostname": "vm01",
+            "ram": 2048,
+            "vcpus": 2,
+            "cpu_cap": 200,
+            "disks": [{
+                "image_uuid": IMAGE,
+                "boot": true,
+                "size": 20480,
+                "model": "virtio"
+            }],
+            "flexible_disk_size": 20480,
+            "bootrom": "uefi",
+            "nics": [
+                {
+                    "nic_tag": "internal",
+                    "ips": ["10.0.0.5/24"],
+                    "gateways": ["10.0.0.1"],
+                    "model": "virtio",
+                    "vlan_id": 5
+                },
+                {
+                    "nic_tag": "admin",
+                    "ips": ["dhcp", "addrconf"],
+                    "model": "e1000",
+                    "primary": true
+                }
+            ],
+            "customer_metadata": {
+                "root_authorized_keys": "ssh-ed25519 AAAA"
+            },
+            // Only whole decimal numbers are numbers
+            "tags": { "env": "prod", "replicas": "0x10" }
+        })
+    );
+}
+
+#[test]
+fn test_merged_payload() {
+    let body = format!(
+        "brand=joyent&image_uuid={}&ram=512&tags=env%3Dprod&nic0_tag=admin\
+        &nic0_ipv4_setup=auto&additional=",
+        IMAGE
+    );
+    let additional = json!({
+        "ram": 1024,
+        "tags": { "owner": "web" },
+        "max_lwps": 4000
+    })
+    .to_string();
+    let encoded: String =
+        url::form_urlencoded::byte_serialize(additional.as_bytes()).collect();
+
+    // The guided form's properties win, metadata and tags are merged
+    let extend = ProvisionBody::parse(&format!("{}{}", body, encoded))
+        .unwrap()
+        .payload()
+        .unwrap();
+    assert_eq!(extend.ram, Some(512));
+    assert_eq!(extend.tags.len(), 2);
+    assert_eq!(extend.extra.get("max_lwps"), Some(&json!(4000)));
+    assert_eq!(extend.nics[0].primary, None);
+
+    let replace =
+        ProvisionBody::parse(&format!("{}{}&merge=replace", body, encoded))
+            .unwrap()
+            .payload()
+            .unwrap();
+    assert_eq!(replace.ram, Some(1024));
+
+    let ignore =
+        ProvisionBody::parse(&format!("{}{}&merge=ignore", body, encoded))
+            .unwrap()
+            .payload()
+            .unwrap();
+    assert_eq!(ignore.image_uuid, None);
+    assert_eq!(check(ignore).unwrap_err(), "An image_uuid is required");
+
+    let invalid = ProvisionBody::parse(&format!("{}[1]", body)).unwrap();
+    assert_eq!(
+        invalid.payload().unwrap_err(),
+        "Additional properties must be a JSON object"
+    );
+    let invalid = ProvisionBody::parse("brand=kvm&ram=lots&vcpus=2").unwrap();
+    assert_eq!(invalid.payload().unwrap_err(), "RAM must be a whole number");
+}
@@ -0,0 +1,129 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+use smartos_ui::qr::QrCode;
+
+/// "SmartOS" as version 1-M with mask 2, from an independent encoder
+const SMARTOS_MASK_2: [&str; 21] = [
+    "#######...###.#######",
+    "#.....#....#..#.....#",
+    "#.###.#.#...#.#.###.#",
+    "#.###.#.#.##..#.###.#",
+    "#.###.#.###.#.#.###.#",
+    "#.....#.#.##..#.....#",
+    "#######.#.#.#.#######",
+    "........#.#..........",
+    "#.#####..#.#..#####..",
+    ".#.###..##.#####.#..#",
+    "#.....#...#.#.##.###.",
+    "..#.#...#.#####.#####",
+    "#.....#...#.#..#.....",
+    "........#...#.....##.",
+    "#######..###.#...###.",
+    "#.....#.#.#.....#####",
+    "#.###.#.##.#.#..##.#.",
+    "#.###.#.##.#####..#..",
+    "#.###.#.#...#.#......",
+    "#.....#..#######.##..",
+    "#######.###.#....#.#.",
+];
+
+/// A TOTP URI, 117 bytes, as version 7-M with mask 5 from the same encoder.
+/// Version 7 is the first with version information, and its data is split
+/// over four blocks.
+const TOTP_URI: &str = "otpauth://totp/SmartOS%20UI:operator@headnode.\
+    example.com?secret=JBSWY3DPEHPK3PXPJBSWY3DPEHPK3PXP&issuer=SmartOS%20UI";
+const TOTP_URI_MASK_5: [&str; 45] = [
+    "#######...#..#...#..###...#######...#.#######",
+    "#.....#.####.....######.#........#.#..#.....#",
+    "#.###.#.#.#..######.........###.##.#..#.###.#",
+    "#.###.#.#..###..###.....##.#######.##.#.###.#",
+    "#.###.#......##.#.#######.....###.###.#.###.#",
+    "#.....#..#.#...##.#.#...#.###....#....#.....#",
+    "#######.#.#.#.#.#.#.#.#.#.#.#.#.#.#.#.#######",
+    "........##.#.#.#.##.#...##.##.##.##.#........",
+    "#.....#.#.###...#.#######.....##..##.##..###.",
+    "...#.#.##.###.#..##.....###..##.#.#.#####.##.",
+    ".###..##.##.##.###.....##..#..#.#######....#.",
+    "....#...#.#.##.###.##..##..#.#..#.......#####",
+    "..###.##.#.##..#..##....###.#.#.#.##.#.###...",
+    ".#..##.##..#...#.#.##.####.####..#.###...####",
+    "##.##.#..#....#..##....##.#.#..#.####..#.##..",
+    "..###...#.##.###.####.##...###.#..###.#.####.",
+    "..###.##.#.#...####..#.##.#..#.#..#...#..#..#",
+    ".##.#.....#.###.#........#...##..#..##..#.#.#",
+    "..##.##.#..###..##...##.####..#.####..##....#",
+    "...###.......####.##.#...#..##.#.#...#.####.#",
+    "#..#######....##.#########.....#...########..",
+    "##.##...#..#.#..#####...#.###########...####.",
+    ".####.#.#...#.#..#.##.#.####...######.#.#.##.",
+    "#...#...#.#.##.##..##...#####....##.#...####.",
+    ".########......##...#####...#...#...#####...#",
+    "....#........#.##.##.#.#.#.#..##.#.##.##..#.#",
+    "###..###.#..#.#####.#.##.#.#.#.#..#..#..####.",
+    "###.#..##....####.#.##..##..#.#..##....#.####",
+    "...##.###....##....##..##....###.#...#..##.#.",
+    "###.#...##.####..##...####...#####.#...#.##..",
+    "..#...#..###.##.#.##.##.##..#....#.##.#.###.#",
+    ".#..##..####.##..##..####..#..####.#..#...#..",
+    "..##..##..#.#..###..#...#.#..#.#.#..#....#...",
+    "###..#.#.#....#.......#.###.####.##.#.###.##.",
+    "....#.#####..#.#..#...####.#...##..#.#.#..##.",
+    ".####..#..#.....#.#.#.###...######.##.#.#####",
+    "#..##.##.#....##.#########..#.###.#######.###",
+    "........##..##.#.#.##...#....##.##..#...#.#.#",
+    "#######..####.##...##.#.######....#.#.#.#.##.",
+    "#.....#..#.##...##..#...#.#...#.###.#...###.#",
+    "#.###.#...######..#.#######..#.#.#.######....",
+    "#.###.#..###.#.#.###.###...#.##..#.#.....####",
+    "#.###.#..###....#.#..#####....####..#...###.#",
+    "#.....#.....####.#...#...#.##.#.######.#.##..",
+    "#######.###.#.#.##.#....##.....#.##..#..#..#.",
+];
+
+fn render(qr: &QrCode) -> Vec<String> {
+    (0..qr.size())
+        .map(|y| {
+            (0..qr.size())
+                .map(|x| if qr.is_dark(x, y) { '#' } else { '.' })
+                .collect()
+        })
+        .collect()
+}
+
+#[test]
+fn test_encode() {
+    let qr = QrCode::encode_with_mask(b"SmartOS", 2).unwrap();
+    assert_eq!(qr.version(), 1);
+    assert_eq!(render(&qr), SMARTOS_MASK_2);
+}
+
+#[test]
+fn test_encode_version_7() {
+    let qr = QrCode::encode_with_mask(TOTP_URI.as_bytes(), 5).unwrap();
+    assert_eq!(qr.version(), 7);
+    assert_eq!(render(&qr), TOTP_URI_MASK_5);
+}
+
+#[test]
+fn test_version_selection() {
+    assert_eq!(QrCode::encode(&[b'a'; 14]).unwrap().version(), 1);
+    assert_eq!(QrCode::encode(&[b'a'; 15]).unwrap().version(), 2);
+    let qr = QrCode::encode(&[b'a'; 213]).unwrap();
+    assert_eq!((qr.version(), qr.size()), (10, 57));
+    assert!(QrCode::encode(&[b'a'; 214]).is_none());
+}
+
+#[test]
+fn test_svg() {
+    let svg = QrCode::encode(b"SmartOS").unwrap().to_svg();
+    assert!(svg.starts_with("<svg "));
+    assert!(svg.contains(r#"viewBox="0 0 29 29""#));
+}
@@ -0,0 +1,26 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+use smartos_ui::session::parse_cookie;
+
+#[test]
+fn test_parse_cookie() {
+    assert_eq!(parse_cookie("__Host-ID=abc", "__Host-ID"), Some("abc"));
+    assert_eq!(
+        parse_cookie("theme=dark; __Host-ID=abc; lang=en", "__Host-ID"),
+        Some("abc")
+    );
+    assert_eq!(parse_cookie("x__Host-ID=abc", "__Host-ID"), None);
+    assert_eq!(parse_cookie("a=\"quoted\"", "a"), Some("quoted"));
+    // Values may contain "="
+    assert_eq!(parse_cookie("a=b=c", "a"), Some("b=c"));
+    assert_eq!(parse_cookie("", "a"), None);
+    assert_eq!(parse_cookie("a", "a"), None);
+}
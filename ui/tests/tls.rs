@@ -0,0 +1,35 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright 2025 MNX Cloud, Inc.
+ */
+
+use std::net::IpAddr;
+
+use smartos_ui::tls::{CertificateInfo, KeyPair};
+
+#[test]
+fn test_self_signed() {
+    let ip: IpAddr = "10.0.0.5".parse().unwrap();
+    let pair = KeyPair::self_signed("gz.example.com", Some(ip)).unwrap();
+
+    // The generated pair is accepted as an upload would be
+    let parsed = KeyPair::parse(&pair.cert, &pair.key).unwrap();
+    assert_eq!(parsed.cert, pair.cert);
+
+    let info = CertificateInfo::parse(&pair.cert).unwrap();
+    assert_eq!(info.subject, "CN=gz.example.com");
+    assert_eq!(info.names, vec!["gz.example.com", "10.0.0.5"]);
+    assert!(info.self_signed);
+    assert_eq!(info.intermediates, 0);
+    assert!(info.days_remaining >= 824);
+
+    // A key from another pair is refused
+    let other = KeyPair::self_signed("other", None).unwrap();
+    assert!(KeyPair::parse(&pair.cert, &other.key).is_err());
+    assert!(KeyPair::parse("not a certificate", &pair.key).is_err());
+}